@@ -0,0 +1,55 @@
+use agent::util::dry_run::dry_run_discovery;
+use akri_shared::akri::configuration::KubeAkriConfig;
+use clap::Arg;
+
+/// Environment variable that the Agent reads its node's name from, which is part of the Instance names of devices
+/// that are not shared
+const AGENT_NODE_NAME_LABEL: &str = "AGENT_NODE_NAME";
+
+/// This runs the discovery of a Configuration with the Discovery Handlers embedded in the Agent and prints what the
+/// Agent would create for the discovered devices, without connecting to a cluster.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    env_logger::try_init()?;
+    let matches = clap::App::new("Akri Discovery Dry Run")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Runs an embedded Discovery Handler locally and prints the devices it discovers")
+        .arg(
+            Arg::new("config")
+                .required(true)
+                .about("YAML file of the Akri Configuration to run the discovery of"),
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .about("Keep printing the discovered devices whenever they change"),
+        )
+        .arg(
+            Arg::new("node_name")
+                .long("node-name")
+                .takes_value(true)
+                .about("Node name used in the Instance names of devices that are not shared [default: AGENT_NODE_NAME or this host's name]"),
+        )
+        .get_matches();
+
+    let config_file = matches.value_of("config").unwrap();
+    let config: KubeAkriConfig = serde_yaml::from_str(&std::fs::read_to_string(config_file)?)
+        .map_err(|e| format!("{} is not an Akri Configuration: {}", config_file, e))?;
+    // Instance names are generated from the node name the same way the Agent does
+    if let Some(node_name) = matches.value_of("node_name") {
+        std::env::set_var(AGENT_NODE_NAME_LABEL, node_name);
+    } else if std::env::var(AGENT_NODE_NAME_LABEL).is_err() {
+        let hostname = std::fs::read_to_string("/etc/hostname")
+            .map(|hostname| hostname.trim().to_string())
+            .unwrap_or_else(|_| "localhost".to_string());
+        std::env::set_var(AGENT_NODE_NAME_LABEL, hostname);
+    }
+
+    dry_run_discovery(
+        &config.metadata.name,
+        &config.spec,
+        matches.is_present("watch"),
+        &mut std::io::stdout(),
+    )
+    .await
+}
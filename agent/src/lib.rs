@@ -0,0 +1,20 @@
+extern crate hyper;
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+extern crate tokio_core;
+pub mod util;
+
+use prometheus::{HistogramVec, IntGaugeVec};
+
+lazy_static! {
+    // Reports the number of Instances visible to this node, grouped by Configuration and whether it is shared
+    pub static ref INSTANCE_COUNT_METRIC: IntGaugeVec = prometheus::register_int_gauge_vec!("akri_instance_count", "Akri Instance Count", &["configuration", "is_shared"]).unwrap();
+    // Reports the time to get discovery results, grouped by Configuration
+    pub static ref DISCOVERY_RESPONSE_TIME_METRIC: HistogramVec = prometheus::register_histogram_vec!("akri_discovery_response_time", "Akri Discovery Response Time", &["configuration"]).unwrap();
+    // Reports whether each registered Discovery Handler passed its last health check (1) or not (0)
+    pub static ref DISCOVERY_HANDLER_HEALTH_METRIC: IntGaugeVec = prometheus::register_int_gauge_vec!("akri_discovery_handler_health", "Akri Discovery Handler Health", &["discovery_handler", "endpoint"]).unwrap();
+}
@@ -1,39 +1,27 @@
-extern crate hyper;
-#[macro_use]
-extern crate lazy_static;
-#[macro_use]
-extern crate log;
-#[macro_use]
-extern crate serde_derive;
-extern crate tokio_core;
-mod util;
-
-use akri_shared::akri::{metrics::run_metrics_server, API_NAMESPACE};
-use log::{info, trace};
-use prometheus::{HistogramVec, IntGaugeVec};
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
-    time::Duration,
-};
-use tokio::sync::broadcast;
 #[cfg(feature = "agent-full")]
-use util::registration::register_embedded_discovery_handlers;
-use util::{
+use agent::util::registration::register_embedded_discovery_handlers;
+use agent::util::{
     config_action,
     constants::{
+        AGENT_SHUTDOWN_CHANNEL_CAPACITY, AGENT_SHUTDOWN_DRAIN_TIMEOUT_SECS,
         NEW_DISCOVERY_HANDLER_CHANNEL_CAPACITY, SLOT_RECONCILIATION_SLOT_GRACE_PERIOD_SECS,
     },
+    discovery_handler_health::periodic_discovery_handler_health_check,
     registration::{run_registration_server, DiscoveryHandlerName},
     slot_reconciliation::periodic_slot_reconciliation,
 };
-
-lazy_static! {
-    // Reports the number of Instances visible to this node, grouped by Configuration and whether it is shared
-    pub static ref INSTANCE_COUNT_METRIC: IntGaugeVec = prometheus::register_int_gauge_vec!("akri_instance_count", "Akri Instance Count", &["configuration", "is_shared"]).unwrap();
-    // Reports the time to get discovery results, grouped by Configuration
-    pub static ref DISCOVERY_RESPONSE_TIME_METRIC: HistogramVec = prometheus::register_histogram_vec!("akri_discovery_response_time", "Akri Discovery Response Time", &["configuration"]).unwrap();
-}
+use akri_shared::{
+    akri::{metrics::run_metrics_server, API_NAMESPACE},
+    os::signal,
+};
+use futures_old::Future;
+use log::{error, info, trace};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::broadcast;
 
 /// This is the entry point for the Akri Agent.
 /// It must be built on unix systems, since the underlying libraries for the `DevicePluginService` unix socket connection are unix only.
@@ -70,26 +58,76 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
     register_embedded_discovery_handlers(discovery_handler_map_clone.clone())?;
 
     // Start registration service for registering `DiscoveryHandlers`
+    let health_check_discovery_handler_map = discovery_handler_map_clone.clone();
     tasks.push(tokio::spawn(async move {
         run_registration_server(discovery_handler_map_clone, new_discovery_handler_sender)
             .await
             .unwrap();
     }));
 
+    // Periodically check the health of registered `DiscoveryHandlers`
     tasks.push(tokio::spawn(async move {
-        let slot_grace_period = Duration::from_secs(SLOT_RECONCILIATION_SLOT_GRACE_PERIOD_SECS);
-        periodic_slot_reconciliation(slot_grace_period)
+        periodic_discovery_handler_health_check(health_check_discovery_handler_map)
             .await
             .unwrap();
     }));
 
     tasks.push(tokio::spawn(async move {
-        config_action::do_config_watch(discovery_handler_map, new_discovery_handler_sender_clone)
+        let slot_grace_period = Duration::from_secs(SLOT_RECONCILIATION_SLOT_GRACE_PERIOD_SECS);
+        periodic_slot_reconciliation(slot_grace_period)
             .await
-            .unwrap()
+            .unwrap();
     }));
 
-    futures::future::try_join_all(tasks).await?;
+    // Keep a receiver alive so a shutdown signal can be sent before `do_config_watch` subscribes
+    let (agent_shutdown_sender, _agent_shutdown_receiver): (
+        broadcast::Sender<()>,
+        broadcast::Receiver<()>,
+    ) = broadcast::channel(AGENT_SHUTDOWN_CHANNEL_CAPACITY);
+    let config_watch_agent_shutdown_sender = agent_shutdown_sender.clone();
+    let mut config_watch_task = tokio::spawn(async move {
+        config_action::do_config_watch(
+            discovery_handler_map,
+            new_discovery_handler_sender_clone,
+            config_watch_agent_shutdown_sender,
+        )
+        .await
+        .unwrap()
+    });
+
+    // `shutdown` returns a futures 0.1 future, so wait on it from a blocking thread
+    let shutdown_signal = tokio::task::spawn_blocking(|| signal::shutdown().wait());
+
+    let received_shutdown_signal = tokio::select! {
+        result = futures::future::try_join_all(tasks) => {
+            result?;
+            false
+        },
+        result = &mut config_watch_task => {
+            result?;
+            false
+        },
+        _ = shutdown_signal => true,
+    };
+    if received_shutdown_signal {
+        info!(
+            "{} Agent received shutdown signal ... stopping discovery and device plugins",
+            API_NAMESPACE
+        );
+        agent_shutdown_sender.send(())?;
+        if tokio::time::timeout(
+            Duration::from_secs(AGENT_SHUTDOWN_DRAIN_TIMEOUT_SECS),
+            config_watch_task,
+        )
+        .await
+        .is_err()
+        {
+            error!(
+                "{} Agent did not finish shutting down within {} seconds ... exiting anyway",
+                API_NAMESPACE, AGENT_SHUTDOWN_DRAIN_TIMEOUT_SECS
+            );
+        }
+    }
     info!("{} Agent end", API_NAMESPACE);
     Ok(())
 }
@@ -0,0 +1,163 @@
+use super::constants::{
+    BROKER_PROPERTIES_DIRECTORY_LABEL, BROKER_PROPERTIES_FILE_NAME,
+    DEFAULT_BROKER_PROPERTIES_DIRECTORY,
+};
+use log::{trace, warn};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+/// Returns the directory on the node in which the broker properties of each allocated slot are written, which is set
+/// by the `BROKER_PROPERTIES_DIRECTORY` environment variable. The Agent must see this directory at the same path as
+/// the node does, since kubelet bind mounts the per slot directories into broker containers by their node path.
+pub fn get_broker_properties_directory() -> PathBuf {
+    PathBuf::from(
+        std::env::var(BROKER_PROPERTIES_DIRECTORY_LABEL)
+            .unwrap_or_else(|_| DEFAULT_BROKER_PROPERTIES_DIRECTORY.to_string()),
+    )
+}
+
+/// Returns the directory in which the broker properties of a slot of an Instance are written
+pub fn get_slot_directory(base_directory: &Path, instance_name: &str, slot: &str) -> PathBuf {
+    base_directory.join(instance_name).join(slot)
+}
+
+/// Writes broker properties into a directory as `properties.json` plus one file per property named after the
+/// property. Each file is written to a temporary file and renamed into place, so that brokers never read a partially
+/// written file, and files of properties that no longer exist are removed. Nothing is rewritten if the properties
+/// have not changed. Properties whose names are not valid file names are only written to `properties.json`.
+pub fn write_broker_properties(
+    directory: &Path,
+    properties: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    let json = serde_json::to_vec_pretty(&properties.iter().collect::<BTreeMap<_, _>>())?;
+    let json_file = directory.join(BROKER_PROPERTIES_FILE_NAME);
+    if std::fs::read(&json_file).map_or(false, |existing| existing == json) {
+        trace!(
+            "write_broker_properties - properties in {:?} unchanged",
+            directory
+        );
+        return Ok(());
+    }
+    std::fs::create_dir_all(directory)?;
+    for (name, value) in properties {
+        if is_valid_file_name(name) {
+            write_atomically(directory, name, value.as_bytes())?;
+        } else {
+            warn!(
+                "write_broker_properties - property {} is not a valid file name so is only written to {}",
+                name, BROKER_PROPERTIES_FILE_NAME
+            );
+        }
+    }
+    for entry in std::fs::read_dir(directory)? {
+        let file_name = entry?.file_name().to_string_lossy().to_string();
+        if file_name != BROKER_PROPERTIES_FILE_NAME
+            && is_valid_file_name(&file_name)
+            && !properties.contains_key(&file_name)
+        {
+            std::fs::remove_file(directory.join(&file_name))?;
+        }
+    }
+    // properties.json is written last so that it is only up to date once every other file is
+    write_atomically(directory, BROKER_PROPERTIES_FILE_NAME, &json)
+}
+
+/// Removes a directory of broker properties, if it exists
+pub fn remove_broker_properties(directory: &Path) -> std::io::Result<()> {
+    match std::fs::remove_dir_all(directory) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Checks whether a property can be written to a file of its own name. Names that start with a dot are excluded so
+/// that temporary files are never mistaken for properties.
+fn is_valid_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains('/')
+        && !name.contains('\0')
+        && name != BROKER_PROPERTIES_FILE_NAME
+}
+
+fn write_atomically(directory: &Path, file_name: &str, contents: &[u8]) -> std::io::Result<()> {
+    let temporary_file = directory.join(format!(".{}.tmp", file_name));
+    std::fs::write(&temporary_file, contents)?;
+    std::fs::rename(&temporary_file, directory.join(file_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(properties: &[(&str, &str)]) -> HashMap<String, String> {
+        properties
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_write_broker_properties() {
+        let base = tempfile::tempdir().unwrap();
+        let directory = get_slot_directory(base.path(), "config-a-b494b6", "config-a-b494b6-0");
+        write_broker_properties(
+            &directory,
+            &properties(&[
+                ("OPCUA_DISCOVERY_URL", "opc.tcp://10.0.0.1:4840/"),
+                ("RESOLUTION", "800"),
+                ("INVALID/NAME", "only in json"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(directory.join("OPCUA_DISCOVERY_URL")).unwrap(),
+            "opc.tcp://10.0.0.1:4840/"
+        );
+        assert_eq!(
+            std::fs::read_to_string(directory.join("RESOLUTION")).unwrap(),
+            "800"
+        );
+        let json: HashMap<String, String> = serde_json::from_slice(
+            &std::fs::read(directory.join(BROKER_PROPERTIES_FILE_NAME)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["INVALID/NAME"], "only in json");
+        assert_eq!(json.len(), 3);
+
+        // Changed properties are rewritten and removed properties deleted
+        write_broker_properties(
+            &directory,
+            &properties(&[("OPCUA_DISCOVERY_URL", "opc.tcp://10.0.0.2:4840/")]),
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(directory.join("OPCUA_DISCOVERY_URL")).unwrap(),
+            "opc.tcp://10.0.0.2:4840/"
+        );
+        assert!(!directory.join("RESOLUTION").exists());
+        let mut file_names: Vec<String> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        file_names.sort();
+        assert_eq!(file_names, vec!["OPCUA_DISCOVERY_URL", "properties.json"]);
+
+        remove_broker_properties(&directory).unwrap();
+        assert!(!directory.exists());
+        // Removing a directory that does not exist is not an error
+        remove_broker_properties(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_is_valid_file_name() {
+        assert!(is_valid_file_name("OPCUA_DISCOVERY_URL"));
+        assert!(!is_valid_file_name(""));
+        assert!(!is_valid_file_name(".hidden"));
+        assert!(!is_valid_file_name(".."));
+        assert!(!is_valid_file_name("a/b"));
+        assert!(!is_valid_file_name(BROKER_PROPERTIES_FILE_NAME));
+    }
+}
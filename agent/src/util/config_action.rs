@@ -1,7 +1,7 @@
 use super::{
     constants::{
         DISCOVERY_OPERATOR_FINISHED_DISCOVERY_CHANNEL_CAPACITY,
-        DISCOVERY_OPERATOR_STOP_DISCOVERY_CHANNEL_CAPACITY,
+        DISCOVERY_OPERATOR_STOP_DISCOVERY_CHANNEL_CAPACITY, REMOVE_NODE_ON_SHUTDOWN_LABEL,
     },
     device_plugin_service,
     device_plugin_service::InstanceMap,
@@ -9,9 +9,14 @@ use super::{
     registration::RegisteredDiscoveryHandlerMap,
 };
 use akri_shared::{
-    akri::{configuration::KubeAkriConfig, API_CONFIGURATIONS, API_NAMESPACE, API_VERSION},
+    akri::{
+        configuration::KubeAkriConfig,
+        retry::{random_delay, MAX_INSTANCE_UPDATE_TRIES},
+        API_CONFIGURATIONS, API_NAMESPACE, API_VERSION,
+    },
     k8s,
     k8s::{try_delete_instance, KubeInterface},
+    os::env_var::{ActualEnvVarQuery, EnvVarQuery},
 };
 use futures::StreamExt;
 use kube::api::{Informer, RawApi, WatchEvent};
@@ -25,6 +30,8 @@ type ConfigMap = Arc<Mutex<HashMap<String, ConfigInfo>>>;
 /// and senders for ceasing to discover instances upon Configuration deletion.
 #[derive(Debug)]
 pub struct ConfigInfo {
+    /// Namespace of the Configuration and its Instances
+    namespace: String,
     /// Map of all of a Configuration's Instances
     instance_map: InstanceMap,
     /// Sends notification to a `DiscoveryOperator` that it should stop all discovery for its Configuration.
@@ -34,16 +41,23 @@ pub struct ConfigInfo {
     /// Receives notification that all `DiscoveryOperators` threads have completed and a Configuration's Instances
     /// can be safely deleted and the associated `DevicePluginServices` terminated.
     finished_discovery_receiver: mpsc::Receiver<()>,
+    /// Spec of the Configuration discovery was started for. Modifications of the Configuration that leave it
+    /// unchanged, such as updates to its status, do not restart discovery.
+    spec: serde_json::Value,
 }
 
 /// This handles pre-existing Configurations and invokes an internal method that watches for Configuration events.
+/// Runs until a message is received on `agent_shutdown_sender`, at which point discovery is stopped for all
+/// Configurations and their `DevicePluginServices` are terminated.
 pub async fn do_config_watch(
     discovery_handler_map: RegisteredDiscoveryHandlerMap,
     new_discovery_handler_sender: broadcast::Sender<String>,
+    agent_shutdown_sender: broadcast::Sender<()>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
     info!("do_config_watch - enter");
     let config_map: ConfigMap = Arc::new(Mutex::new(HashMap::new()));
     let kube_interface = k8s::create_kube_interface();
+    let mut agent_shutdown_receiver = agent_shutdown_sender.subscribe();
     let mut tasks = Vec::new();
 
     // Handle pre-existing configs
@@ -66,10 +80,11 @@ pub async fn do_config_watch(
     }
 
     // Watch for new configs and changes
+    let watch_config_map = config_map.clone();
     tasks.push(tokio::spawn(async move {
         watch_for_config_changes(
             &kube_interface,
-            config_map,
+            watch_config_map,
             discovery_handler_map,
             new_discovery_handler_sender,
         )
@@ -77,11 +92,34 @@ pub async fn do_config_watch(
         .unwrap();
     }));
 
-    futures::future::try_join_all(tasks).await?;
+    tokio::select! {
+        result = futures::future::try_join_all(tasks) => {
+            result?;
+        },
+        _ = agent_shutdown_receiver.recv() => {
+            info!("do_config_watch - received message to shutdown ... stopping discovery for all Configurations");
+            let node_to_remove = get_node_to_remove_on_shutdown(&ActualEnvVarQuery {});
+            handle_agent_shutdown(
+                &k8s::create_kube_interface(),
+                config_map,
+                node_to_remove.as_deref(),
+            )
+            .await?;
+        }
+    }
     info!("do_config_watch - end");
     Ok(())
 }
 
+/// Returns the name of this node if the Agent has been configured to remove it from shared Instances upon shutdown.
+fn get_node_to_remove_on_shutdown(query: &impl EnvVarQuery) -> Option<String> {
+    if query.get_env_var(REMOVE_NODE_ON_SHUTDOWN_LABEL).is_ok() {
+        query.get_env_var("AGENT_NODE_NAME").ok()
+    } else {
+        None
+    }
+}
+
 /// This watches for Configuration events
 async fn watch_for_config_changes(
     kube_interface: &impl KubeInterface,
@@ -154,6 +192,15 @@ async fn handle_config(
         }
         // If a config is updated, delete all associated instances and device plugins and then recreate them to reflect updated config
         WatchEvent::Modified(config) => {
+            if let Some(config_info) = config_map.lock().await.get(&config.metadata.name) {
+                if config_info.spec == serde_json::to_value(&config.spec)? {
+                    trace!(
+                        "handle_config - spec of modified Configuration {} is unchanged",
+                        config.metadata.name
+                    );
+                    return Ok(());
+                }
+            }
             info!(
                 "handle_config - modified Configuration {}",
                 config.metadata.name,
@@ -196,9 +243,11 @@ async fn handle_config_add(
     let (mut finished_discovery_sender, finished_discovery_receiver) =
         mpsc::channel(DISCOVERY_OPERATOR_FINISHED_DISCOVERY_CHANNEL_CAPACITY);
     let config_info = ConfigInfo {
+        namespace: config.metadata.namespace.clone().unwrap(),
         instance_map: instance_map.clone(),
         stop_discovery_sender: stop_discovery_sender.clone(),
         finished_discovery_receiver,
+        spec: serde_json::to_value(&config.spec)?,
     };
     config_map
         .lock()
@@ -232,15 +281,34 @@ async fn handle_config_delete(
     config: &KubeAkriConfig,
     config_map: ConfigMap,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    stop_discovery(&config.metadata.name, config_map.clone()).await;
+
+    // Get map of instances for the Configuration and then remove Configuration from ConfigMap
+    let instance_map: InstanceMap;
+    {
+        let mut config_map_locked = config_map.lock().await;
+        instance_map = config_map_locked
+            .get(&config.metadata.name)
+            .unwrap()
+            .instance_map
+            .clone();
+        config_map_locked.remove(&config.metadata.name);
+    }
+    delete_all_instances_in_map(kube_interface, instance_map, config).await?;
+    Ok(())
+}
+
+/// This signals a Configuration's `DiscoveryOperator` to stop discovery and waits until it has finished.
+async fn stop_discovery(config_name: &str, config_map: ConfigMap) {
     trace!(
-        "handle_config_delete - for config {} telling do_periodic_discovery to end",
-        config.metadata.name
+        "stop_discovery - for config {} telling do_periodic_discovery to end",
+        config_name
     );
     // Send message to stop observing instances' availability and waits until response is received
     if config_map
         .lock()
         .await
-        .get(&config.metadata.name)
+        .get(config_name)
         .unwrap()
         .stop_discovery_sender
         .clone()
@@ -250,35 +318,123 @@ async fn handle_config_delete(
         config_map
             .lock()
             .await
-            .get_mut(&config.metadata.name)
+            .get_mut(config_name)
             .unwrap()
             .finished_discovery_receiver
             .recv()
             .await
             .unwrap();
         trace!(
-            "handle_config_delete - for config {} received message that do_periodic_discovery ended",
-            config.metadata.name
+            "stop_discovery - for config {} received message that do_periodic_discovery ended",
+            config_name
         );
     } else {
         trace!(
-            "handle_config_delete - for config {} do_periodic_discovery receiver has been dropped",
-            config.metadata.name
+            "stop_discovery - for config {} do_periodic_discovery receiver has been dropped",
+            config_name
         );
     }
+}
 
-    // Get map of instances for the Configuration and then remove Configuration from ConfigMap
-    let instance_map: InstanceMap;
-    {
-        let mut config_map_locked = config_map.lock().await;
-        instance_map = config_map_locked
-            .get(&config.metadata.name)
-            .unwrap()
-            .instance_map
-            .clone();
-        config_map_locked.remove(&config.metadata.name);
+/// This handles Agent shutdown. For each Configuration, it stops discovery and signals every `DevicePluginService`
+/// to end, so kubelet is sent a final list of unhealthy virtual devices. Unlike `handle_config_delete`, Instances are
+/// not deleted, since other nodes may still be using them and this node's Agent is expected to come back.
+/// If `node_to_remove` is set, that node is removed from the `nodes` list and `device_usage` slots of each shared
+/// Instance, so its slots can be used by other nodes while this Agent is down.
+async fn handle_agent_shutdown(
+    kube_interface: &impl KubeInterface,
+    config_map: ConfigMap,
+    node_to_remove: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let config_names: Vec<String> = config_map.lock().await.keys().cloned().collect();
+    for config_name in config_names {
+        stop_discovery(&config_name, config_map.clone()).await;
+        let (instance_map, namespace) = {
+            let config_map_locked = config_map.lock().await;
+            let config_info = config_map_locked.get(&config_name).unwrap();
+            (
+                config_info.instance_map.clone(),
+                config_info.namespace.clone(),
+            )
+        };
+        let mut instance_map_locked = instance_map.lock().await;
+        for (instance_name, instance_info) in instance_map_locked.drain() {
+            trace!(
+                "handle_agent_shutdown - sending message to end list_and_watch for Instance {}",
+                instance_name
+            );
+            if let Err(e) = instance_info
+                .list_and_watch_message_sender
+                .send(device_plugin_service::ListAndWatchMessageKind::End)
+            {
+                trace!(
+                    "handle_agent_shutdown - list_and_watch for Instance {} no longer receiving with error {:?}",
+                    instance_name,
+                    e
+                );
+            }
+            if let Some(node_name) = node_to_remove {
+                if let Err(e) = try_remove_node_from_instance(
+                    kube_interface,
+                    &instance_name,
+                    &namespace,
+                    node_name,
+                )
+                .await
+                {
+                    error!(
+                        "handle_agent_shutdown - could not remove node {} from Instance {} with error {}",
+                        node_name, instance_name, e
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// This tries up to `MAX_INSTANCE_UPDATE_TRIES` to remove a node from a shared Instance's `nodes` list and clear any
+/// `device_usage` slots claimed by it. Unshared Instances are left alone, since only this node can see them.
+async fn try_remove_node_from_instance(
+    kube_interface: &impl KubeInterface,
+    instance_name: &str,
+    instance_namespace: &str,
+    node_name: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    for x in 0..MAX_INSTANCE_UPDATE_TRIES {
+        let mut instance = kube_interface
+            .find_instance(instance_name, instance_namespace)
+            .await?
+            .spec;
+        if !instance.shared {
+            return Ok(());
+        }
+        instance.nodes.retain(|node| node != node_name);
+        for node in instance.device_usage.values_mut() {
+            if *node == node_name {
+                node.clear();
+            }
+        }
+        match kube_interface
+            .update_instance(&instance, instance_name, instance_namespace)
+            .await
+        {
+            Ok(()) => {
+                trace!(
+                    "try_remove_node_from_instance - removed node {} from Instance {}",
+                    node_name,
+                    instance_name
+                );
+                return Ok(());
+            }
+            Err(e) => {
+                if x == (MAX_INSTANCE_UPDATE_TRIES - 1) {
+                    return Err(e);
+                }
+            }
+        }
+        random_delay().await;
     }
-    delete_all_instances_in_map(kube_interface, instance_map, config).await?;
     Ok(())
 }
 
@@ -317,10 +473,56 @@ mod config_action_tests {
     };
     use super::*;
     use akri_discovery_utils::discovery::{mock_discovery_handler, v0::Device};
-    use akri_shared::{akri::configuration::KubeAkriConfig, k8s::MockKubeInterface};
+    use akri_shared::{
+        akri::{
+            configuration::{ConfigurationStatus, KubeAkriConfig},
+            instance::KubeAkriInstance,
+        },
+        k8s::MockKubeInterface,
+        os::env_var::MockEnvVarQuery,
+    };
     use std::{collections::HashMap, fs, sync::Arc};
     use tokio::sync::{broadcast, Mutex};
 
+    // Tests that modifications to a Configuration that do not change its spec, such as status updates, do not
+    // restart discovery
+    #[tokio::test]
+    async fn test_handle_config_modified_status() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let path_to_config = "../test/yaml/config-a.yaml";
+        let config_yaml = fs::read_to_string(path_to_config).expect("Unable to read file");
+        let mut config: KubeAkriConfig = serde_yaml::from_str(&config_yaml).unwrap();
+        let (stop_discovery_sender, mut stop_discovery_receiver) = broadcast::channel(2);
+        let (_, finished_discovery_receiver) = mpsc::channel(2);
+        let mut map: HashMap<String, ConfigInfo> = HashMap::new();
+        map.insert(
+            config.metadata.name.clone(),
+            ConfigInfo {
+                namespace: config.metadata.namespace.clone().unwrap(),
+                stop_discovery_sender,
+                instance_map: Arc::new(Mutex::new(HashMap::new())),
+                finished_discovery_receiver,
+                spec: serde_json::to_value(&config.spec).unwrap(),
+            },
+        );
+        let config_map: ConfigMap = Arc::new(Mutex::new(map));
+        config.status = Some(ConfigurationStatus {
+            discovery_details_error: Some("discovery details are not valid".to_string()),
+        });
+        let (new_discovery_handler_sender, _) = broadcast::channel(2);
+        handle_config(
+            &MockKubeInterface::new(),
+            WatchEvent::Modified(config),
+            config_map.clone(),
+            Arc::new(std::sync::Mutex::new(HashMap::new())),
+            new_discovery_handler_sender,
+        )
+        .await
+        .unwrap();
+        assert!(stop_discovery_receiver.try_recv().is_err());
+        assert_eq!(config_map.lock().await.len(), 1);
+    }
+
     #[tokio::test]
     async fn test_handle_config_delete() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -344,9 +546,11 @@ mod config_action_tests {
         map.insert(
             config_name.clone(),
             ConfigInfo {
+                namespace: config.metadata.namespace.clone().unwrap(),
                 stop_discovery_sender,
                 instance_map: instance_map.clone(),
                 finished_discovery_receiver,
+                spec: serde_json::to_value(&config.spec).unwrap(),
             },
         );
         let config_map: ConfigMap = Arc::new(Mutex::new(map));
@@ -383,6 +587,105 @@ mod config_action_tests {
         assert_eq!(instance_map.lock().await.len(), 0);
     }
 
+    // Tests that upon Agent shutdown, discovery is stopped, every list_and_watch is signaled to end,
+    // and this node is removed from shared Instances without deleting them
+    #[tokio::test]
+    async fn test_handle_agent_shutdown() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let path_to_config = "../test/yaml/config-a.yaml";
+        let config_yaml = fs::read_to_string(path_to_config).expect("Unable to read file");
+        let config: KubeAkriConfig = serde_yaml::from_str(&config_yaml).unwrap();
+        let mut list_and_watch_message_receivers = Vec::new();
+        let mut visible_discovery_results = Vec::new();
+        let instance_map: InstanceMap = build_instance_map(
+            &config,
+            &mut visible_discovery_results,
+            &mut list_and_watch_message_receivers,
+            InstanceConnectivityStatus::Online,
+        )
+        .await;
+        let (stop_discovery_sender, mut stop_discovery_receiver) = broadcast::channel(2);
+        let (mut finished_discovery_sender, finished_discovery_receiver) = mpsc::channel(2);
+        let mut map: HashMap<String, ConfigInfo> = HashMap::new();
+        map.insert(
+            config.metadata.name.clone(),
+            ConfigInfo {
+                namespace: config.metadata.namespace.clone().unwrap(),
+                stop_discovery_sender,
+                instance_map: instance_map.clone(),
+                finished_discovery_receiver,
+                spec: serde_json::to_value(&config.spec).unwrap(),
+            },
+        );
+        let config_map: ConfigMap = Arc::new(Mutex::new(map));
+
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_instance().times(2).returning(move |_, _| {
+            let instance_json = fs::read_to_string("../test/json/shared-instance-update.json")
+                .expect("Unable to read file");
+            let instance: KubeAkriInstance = serde_json::from_str(&instance_json).unwrap();
+            Ok(instance)
+        });
+        mock.expect_update_instance()
+            .times(2)
+            .withf(move |instance, _, namespace| {
+                namespace == "config-a-namespace"
+                    && !instance.nodes.contains(&"node-b".to_string())
+                    && !instance.device_usage.values().any(|node| node == "node-b")
+            })
+            .returning(move |_, _, _| Ok(()));
+        mock.expect_delete_instance().times(0);
+        let handle = tokio::spawn(async move {
+            handle_agent_shutdown(&mock, config_map, Some("node-b"))
+                .await
+                .unwrap();
+            // The mock is dropped here, which checks that every expectation was met
+        });
+
+        // Assert that handle_agent_shutdown tells start_discovery to end
+        assert!(stop_discovery_receiver.recv().await.is_ok());
+        // Mimic do_periodic_discovery's response
+        finished_discovery_sender.send(()).await.unwrap();
+
+        // Assert list_and_watch is signaled to end for every instance associated with a config
+        for mut receiver in list_and_watch_message_receivers {
+            assert_eq!(
+                receiver.recv().await.unwrap(),
+                device_plugin_service::ListAndWatchMessageKind::End
+            );
+        }
+
+        // Assert that handle_agent_shutdown finishes, so the mock expectations are checked
+        tokio::time::timeout(std::time::Duration::from_secs(5), handle)
+            .await
+            .expect("handle_agent_shutdown did not finish")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_get_node_to_remove_on_shutdown() {
+        let mut mock_query = MockEnvVarQuery::new();
+        mock_query
+            .expect_get_env_var()
+            .withf(|label: &str| label == REMOVE_NODE_ON_SHUTDOWN_LABEL)
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert_eq!(get_node_to_remove_on_shutdown(&mock_query), None);
+
+        let mut mock_query = MockEnvVarQuery::new();
+        mock_query
+            .expect_get_env_var()
+            .withf(|label: &str| label == REMOVE_NODE_ON_SHUTDOWN_LABEL)
+            .returning(|_| Ok("1".to_string()));
+        mock_query
+            .expect_get_env_var()
+            .withf(|label: &str| label == "AGENT_NODE_NAME")
+            .returning(|_| Ok("node-a".to_string()));
+        assert_eq!(
+            get_node_to_remove_on_shutdown(&mock_query),
+            Some("node-a".to_string())
+        );
+    }
+
     async fn run_and_test_handle_config_add(
         discovery_handler_map: RegisteredDiscoveryHandlerMap,
         config_map: ConfigMap,
@@ -395,7 +698,7 @@ mod config_action_tests {
         mock_kube_interface
             .expect_create_instance()
             .times(1)
-            .returning(move |_, _, _, _, _| Ok(()));
+            .returning(move |_, _, _, _, _, _, _| Ok(()));
         let arc_mock_kube_interface: Arc<Box<dyn k8s::KubeInterface>> =
             Arc::new(Box::new(mock_kube_interface));
         let config_add_config = config.clone();
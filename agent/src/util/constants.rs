@@ -15,6 +15,27 @@ pub const DEVICE_PLUGIN_PATH: &str = "/var/lib/kubelet/device-plugins";
 /// Path of the Kubelet registry socket
 pub const KUBELET_SOCKET: &str = "/var/lib/kubelet/device-plugins/kubelet.sock";
 
+/// Label of environment variable that sets the directory on the node in which broker properties are written for
+/// Configurations that mount them into brokers as files
+pub const BROKER_PROPERTIES_DIRECTORY_LABEL: &str = "BROKER_PROPERTIES_DIRECTORY";
+
+/// Directory in which broker properties are written when `BROKER_PROPERTIES_DIRECTORY` is not set
+pub const DEFAULT_BROKER_PROPERTIES_DIRECTORY: &str = "/var/lib/akri-broker-properties";
+
+/// Name of the file that contains all of a broker's properties as a JSON object
+pub const BROKER_PROPERTIES_FILE_NAME: &str = "properties.json";
+
+/// Label of environment variable that sets the directory on the node in which device credentials are written for
+/// Configurations that mount them into brokers
+pub const DEVICE_CREDENTIALS_DIRECTORY_LABEL: &str = "DEVICE_CREDENTIALS_DIRECTORY";
+
+/// Directory in which device credentials are written when `DEVICE_CREDENTIALS_DIRECTORY` is not set. It is under
+/// `/run` so that credentials are kept in memory on most nodes.
+pub const DEFAULT_DEVICE_CREDENTIALS_DIRECTORY: &str = "/run/akri-device-credentials";
+
+/// Name of the file that holds a device's credential
+pub const DEVICE_CREDENTIAL_FILE_NAME: &str = "credential";
+
 /// Maximum length of time `list_and_watch` will sleep before sending kubelet another list of virtual devices
 pub const LIST_AND_WATCH_SLEEP_SECS: u64 = 60;
 
@@ -77,3 +98,26 @@ pub const DISCOVERY_OPERATOR_FINISHED_DISCOVERY_CHANNEL_CAPACITY: usize = 1;
 /// `DiscoveryHandler`. Sent once by the Agent Registration service when a `DiscoveryHandler` re-registers with a different
 /// registration request (edge case).
 pub const CLOSE_DISCOVERY_HANDLER_CONNECTION_CHANNEL_CAPACITY: usize = 1;
+
+/// Capacity of channel over which the Agent signals `do_config_watch` that a shutdown signal (SIGINT or SIGTERM) was
+/// received and all `DiscoveryOperators` and `DevicePluginServices` should be stopped. Message is only sent once.
+pub const AGENT_SHUTDOWN_CHANNEL_CAPACITY: usize = 1;
+
+/// Maximum length of time the Agent waits for discovery to stop, `DevicePluginServices` to terminate, and (optionally)
+/// this node to be removed from Instances after receiving a shutdown signal, before exiting anyway.
+pub const AGENT_SHUTDOWN_DRAIN_TIMEOUT_SECS: u64 = 20;
+
+/// Label of environment variable that, when set, tells the Agent to remove its node from the `nodes` list and
+/// `device_usage` slots of shared Instances upon shutdown.
+pub const REMOVE_NODE_ON_SHUTDOWN_LABEL: &str = "REMOVE_NODE_ON_SHUTDOWN";
+
+/// Length of time between health checks of registered external Discovery Handlers
+pub const DISCOVERY_HANDLER_HEALTH_CHECK_INTERVAL_SECS: u64 = 30;
+
+/// Length of time the Agent waits to connect to a Discovery Handler and get a response to a health check before
+/// considering it unhealthy
+pub const DISCOVERY_HANDLER_HEALTH_CHECK_TIMEOUT_SECS: u64 = 5;
+
+/// Message of the status a `DiscoveryOperator` returns when it closes its connection with a `DiscoveryHandler` that
+/// failed a health check. The connection is then handled as if the `DiscoveryHandler` dropped it.
+pub const DISCOVERY_HANDLER_UNHEALTHY_MESSAGE: &str = "discovery handler failed health check";
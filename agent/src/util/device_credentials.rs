@@ -0,0 +1,363 @@
+use super::{
+    broker_properties_files::get_slot_directory,
+    constants::{
+        DEFAULT_DEVICE_CREDENTIALS_DIRECTORY, DEVICE_CREDENTIALS_DIRECTORY_LABEL,
+        DEVICE_CREDENTIAL_FILE_NAME,
+    },
+    v1beta1::Mount,
+};
+use akri_discovery_utils::discovery::v0::Device;
+use akri_shared::{akri::configuration::DeviceCredentials, k8s::KubeInterface};
+use k8s_openapi::api::core::v1::Secret;
+use log::{error, trace, warn};
+use std::{
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tonic::{Code, Status};
+
+/// Returns the directory on the node in which the credentials of the devices of allocated slots are written, which
+/// is set by the `DEVICE_CREDENTIALS_DIRECTORY` environment variable. Like the broker properties directory, the Agent
+/// must see it at the same path as the node does.
+pub fn get_device_credentials_directory() -> PathBuf {
+    PathBuf::from(
+        std::env::var(DEVICE_CREDENTIALS_DIRECTORY_LABEL)
+            .unwrap_or_else(|_| DEFAULT_DEVICE_CREDENTIALS_DIRECTORY.to_string()),
+    )
+}
+
+/// Returns the key of a device's entry in the Secret of `DeviceCredentials`, which is the value of its `key_property`
+/// or else its id, with every character that Secret keys do not allow replaced with `_`. Returns `None` if the device
+/// does not have the key property.
+pub fn get_device_credential_key(
+    device_credentials: &DeviceCredentials,
+    device: &Device,
+) -> Option<String> {
+    let value = match &device_credentials.key_property {
+        Some(key_property) => device.properties.get(key_property)?,
+        None => &device.id,
+    };
+    let key: String = value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if key.is_empty() {
+        None
+    } else {
+        Some(key)
+    }
+}
+
+/// Returns the credential that a Secret holds for a key, if any
+pub fn get_device_credential(secret: &Secret, key: &str) -> Option<Vec<u8>> {
+    secret
+        .data
+        .as_ref()
+        .and_then(|data| data.get(key))
+        .map(|credential| credential.0.clone())
+}
+
+/// Credential of a device, resolved for a slot, and how it is given to the broker
+#[derive(Debug, Default, PartialEq)]
+pub struct ResolvedDeviceCredential {
+    /// Mount of the file that holds the credential
+    pub mount: Option<Mount>,
+    /// Name and value of the environment variable that holds the credential
+    pub env_var: Option<(String, String)>,
+}
+
+/// Looks up the credential of a device in the Secret of `DeviceCredentials` and, if found, writes it into the slot's
+/// directory under `device_credentials_directory` and/or sets it as an environment variable, as the Configuration
+/// requests. Devices without an entry get no credential. An error is returned if the Secret cannot be read or the
+/// credential cannot be delivered, so that kubelet retries the allocation.
+pub async fn resolve_device_credential(
+    device_credentials: &DeviceCredentials,
+    device: &Device,
+    namespace: &str,
+    device_credentials_directory: &Path,
+    instance_name: &str,
+    slot: &str,
+    kube_interface: Arc<impl KubeInterface>,
+) -> Result<ResolvedDeviceCredential, Status> {
+    let key = match get_device_credential_key(device_credentials, device) {
+        Some(key) => key,
+        None => {
+            warn!(
+                "resolve_device_credential - device of Instance {} does not have property {:?} ... not giving its brokers a credential",
+                instance_name, device_credentials.key_property
+            );
+            return Ok(ResolvedDeviceCredential::default());
+        }
+    };
+    let secret = kube_interface
+        .find_secret(&device_credentials.secret_name, namespace)
+        .await
+        .map_err(|e| {
+            error!(
+                "resolve_device_credential - could not get Secret {}: {}",
+                device_credentials.secret_name, e
+            );
+            Status::new(
+                Code::Unavailable,
+                format!(
+                    "Could not get Secret {} of device credentials",
+                    device_credentials.secret_name
+                ),
+            )
+        })?;
+    let credential = match get_device_credential(&secret, &key) {
+        Some(credential) => credential,
+        None => {
+            warn!(
+                "resolve_device_credential - Secret {} has no entry {} for the device of Instance {} ... not giving its brokers a credential",
+                device_credentials.secret_name, key, instance_name
+            );
+            return Ok(ResolvedDeviceCredential::default());
+        }
+    };
+    trace!(
+        "resolve_device_credential - found credential {} for Instance {}",
+        key,
+        instance_name
+    );
+
+    let mut resolved = ResolvedDeviceCredential::default();
+    if let Some(env_var) = &device_credentials.env_var {
+        let value = String::from_utf8(credential.clone()).map_err(|_| {
+            Status::new(
+                Code::InvalidArgument,
+                format!(
+                    "Credential {} in Secret {} is not valid UTF-8 so cannot be set as an environment variable",
+                    key, device_credentials.secret_name
+                ),
+            )
+        })?;
+        resolved.env_var = Some((env_var.clone(), value));
+    }
+    if let Some(mount_path) = &device_credentials.mount_path {
+        let directory = get_slot_directory(device_credentials_directory, instance_name, slot);
+        let file = write_device_credential(device_credentials_directory, &directory, &credential)
+            .map_err(|e| {
+            error!(
+                "resolve_device_credential - could not write credential of slot {}: {}",
+                slot, e
+            );
+            Status::new(
+                Code::Unknown,
+                format!("Could not write device credential of slot {}", slot),
+            )
+        })?;
+        resolved.mount = Some(Mount {
+            container_path: mount_path.clone(),
+            host_path: file.to_string_lossy().to_string(),
+            read_only: true,
+        });
+    }
+    Ok(resolved)
+}
+
+/// Removes a directory of device credentials, if it exists
+pub fn remove_device_credentials(directory: &Path) -> std::io::Result<()> {
+    match std::fs::remove_dir_all(directory) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Writes a credential into a slot's directory and returns the path of the file. The directory of all credentials is
+/// only accessible to the Agent's user, so that the credentials can only be read on the node through the slots'
+/// directories that are mounted into brokers.
+fn write_device_credential(
+    device_credentials_directory: &Path,
+    directory: &Path,
+    credential: &[u8],
+) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(device_credentials_directory)?;
+    std::fs::set_permissions(
+        device_credentials_directory,
+        std::fs::Permissions::from_mode(0o700),
+    )?;
+    std::fs::create_dir_all(directory)?;
+    let file = directory.join(DEVICE_CREDENTIAL_FILE_NAME);
+    let temporary_file = directory.join(format!(".{}.tmp", DEVICE_CREDENTIAL_FILE_NAME));
+    // A temporary file left behind by a failed write is read-only
+    if temporary_file.exists() {
+        std::fs::remove_file(&temporary_file)?;
+    }
+    std::fs::write(&temporary_file, credential)?;
+    std::fs::set_permissions(&temporary_file, std::fs::Permissions::from_mode(0o444))?;
+    std::fs::rename(&temporary_file, &file)?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use akri_shared::k8s::MockKubeInterface;
+    use k8s_openapi::ByteString;
+
+    fn device() -> Device {
+        Device {
+            id: "http://10.0.0.1:1000/onvif/device_service".to_string(),
+            properties: vec![(
+                "ONVIF_DEVICE_MAC_ADDRESS".to_string(),
+                "48:0f:cf:4e:1b:3d".to_string(),
+            )]
+            .into_iter()
+            .collect(),
+            mounts: Vec::default(),
+            device_specs: Vec::default(),
+        }
+    }
+
+    fn mock_find_secret(mock: &mut MockKubeInterface) {
+        mock.expect_find_secret()
+            .withf(|name, namespace| name == "onvif-credentials" && namespace == "default")
+            .returning(|_, _| {
+                Ok(Secret {
+                    data: Some(
+                        vec![(
+                            "48_0f_cf_4e_1b_3d".to_string(),
+                            ByteString(b"camera-password".to_vec()),
+                        )]
+                        .into_iter()
+                        .collect(),
+                    ),
+                    ..Default::default()
+                })
+            });
+    }
+
+    #[test]
+    fn test_get_device_credential_key() {
+        let mut device_credentials = DeviceCredentials {
+            secret_name: "onvif-credentials".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            get_device_credential_key(&device_credentials, &device()).unwrap(),
+            "http___10.0.0.1_1000_onvif_device_service"
+        );
+        device_credentials.key_property = Some("ONVIF_DEVICE_MAC_ADDRESS".to_string());
+        assert_eq!(
+            get_device_credential_key(&device_credentials, &device()).unwrap(),
+            "48_0f_cf_4e_1b_3d"
+        );
+        device_credentials.key_property = Some("MISSING".to_string());
+        assert_eq!(
+            get_device_credential_key(&device_credentials, &device()),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_device_credential() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let base = tempfile::tempdir().unwrap();
+        let device_credentials_directory = base.path().join("credentials");
+        let device_credentials = DeviceCredentials {
+            secret_name: "onvif-credentials".to_string(),
+            key_property: Some("ONVIF_DEVICE_MAC_ADDRESS".to_string()),
+            mount_path: Some("/etc/akri/credential".to_string()),
+            env_var: Some("DEVICE_PASSWORD".to_string()),
+        };
+        let mut mock = MockKubeInterface::new();
+        mock_find_secret(&mut mock);
+        let resolved = resolve_device_credential(
+            &device_credentials,
+            &device(),
+            "default",
+            &device_credentials_directory,
+            "onvif-camera-b494b6",
+            "onvif-camera-b494b6-0",
+            Arc::new(mock),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            resolved.env_var,
+            Some(("DEVICE_PASSWORD".to_string(), "camera-password".to_string()))
+        );
+        let mount = resolved.mount.unwrap();
+        assert_eq!(mount.container_path, "/etc/akri/credential");
+        assert!(mount.read_only);
+        assert_eq!(
+            std::fs::read_to_string(&mount.host_path).unwrap(),
+            "camera-password"
+        );
+        assert_eq!(
+            mount.host_path,
+            device_credentials_directory
+                .join("onvif-camera-b494b6")
+                .join("onvif-camera-b494b6-0")
+                .join(DEVICE_CREDENTIAL_FILE_NAME)
+                .to_string_lossy()
+        );
+        assert_eq!(
+            std::fs::metadata(&device_credentials_directory)
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777,
+            0o700
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_device_credential_no_entry() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let base = tempfile::tempdir().unwrap();
+        // Entries are keyed by device id, which the Secret has no entry for
+        let device_credentials = DeviceCredentials {
+            secret_name: "onvif-credentials".to_string(),
+            mount_path: Some("/etc/akri/credential".to_string()),
+            ..Default::default()
+        };
+        let mut mock = MockKubeInterface::new();
+        mock_find_secret(&mut mock);
+        let resolved = resolve_device_credential(
+            &device_credentials,
+            &device(),
+            "default",
+            base.path(),
+            "onvif-camera-b494b6",
+            "onvif-camera-b494b6-0",
+            Arc::new(mock),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resolved, ResolvedDeviceCredential::default());
+        assert!(!base.path().join("onvif-camera-b494b6").exists());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_device_credential_secret_error() {
+        let device_credentials = DeviceCredentials {
+            secret_name: "onvif-credentials".to_string(),
+            env_var: Some("DEVICE_PASSWORD".to_string()),
+            ..Default::default()
+        };
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_secret()
+            .returning(|_, _| Err("forbidden".into()));
+        let status = resolve_device_credential(
+            &device_credentials,
+            &device(),
+            "default",
+            Path::new("/nonexistent"),
+            "onvif-camera-b494b6",
+            "onvif-camera-b494b6-0",
+            Arc::new(mock),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+    }
+}
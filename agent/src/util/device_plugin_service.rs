@@ -1,6 +1,14 @@
+use super::broker_properties_files::{
+    get_broker_properties_directory, get_slot_directory, remove_broker_properties,
+    write_broker_properties,
+};
 use super::constants::{
     HEALTHY, KUBELET_UPDATE_CHANNEL_CAPACITY, LIST_AND_WATCH_SLEEP_SECS, UNHEALTHY,
 };
+use super::device_credentials::{
+    get_device_credentials_directory, remove_device_credentials, resolve_device_credential,
+    ResolvedDeviceCredential,
+};
 use super::v1beta1;
 use super::v1beta1::{
     device_plugin_server::DevicePlugin, AllocateRequest, AllocateResponse, DevicePluginOptions,
@@ -10,10 +18,10 @@ use super::v1beta1::{
 use akri_discovery_utils::discovery::v0::Device;
 use akri_shared::{
     akri::{
-        configuration::Configuration,
-        instance::Instance,
+        configuration::{get_property_labels_and_annotations, Configuration},
+        instance::{is_cordoned, Instance},
         retry::{random_delay, MAX_INSTANCE_UPDATE_TRIES},
-        AKRI_SLOT_ANNOTATION_NAME,
+        AKRI_DEVICE_ID_ANNOTATION_NAME, AKRI_SLOT_ANNOTATION_NAME,
     },
     k8s,
     k8s::KubeInterface,
@@ -23,7 +31,12 @@ use log::{error, info, trace};
 use mock_instant::Instant;
 #[cfg(not(test))]
 use std::time::Instant;
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
 use tokio::{
     sync::{broadcast, mpsc, Mutex},
     time::timeout,
@@ -220,6 +233,26 @@ impl DevicePlugin for DevicePluginService {
                                 .unwrap();
                             dps.server_ender_sender.clone().send(()).await.unwrap();
                             keep_looping = false;
+                            if dps.config.broker_properties_mount_path.is_some() {
+                                if let Err(e) = remove_broker_properties(
+                                    &get_broker_properties_directory().join(&dps.instance_name),
+                                ) {
+                                    error!(
+                                        "list_and_watch - could not remove broker properties of Instance {}: {}",
+                                        dps.instance_name, e
+                                    );
+                                }
+                            }
+                            if dps.config.device_credentials.is_some() {
+                                if let Err(e) = remove_device_credentials(
+                                    &get_device_credentials_directory().join(&dps.instance_name),
+                                ) {
+                                    error!(
+                                        "list_and_watch - could not remove device credentials of Instance {}: {}",
+                                        dps.instance_name, e
+                                    );
+                                }
+                            }
                         }
                     }
                     Err(_) => trace!(
@@ -280,8 +313,12 @@ impl DevicePluginService {
                 &self.instance_name,
                 request,
             );
+            let broker_properties =
+                get_all_broker_properties(&self.config.broker_properties, &self.device.properties);
             let mut akri_annotations = std::collections::HashMap::new();
-            for device_usage_id in request.devices_i_ds {
+            // The Instance as updated by the last claim, whose broker properties are current
+            let mut claimed_instance: Option<Instance> = None;
+            for device_usage_id in &request.devices_i_ds {
                 trace!(
                     "internal_allocate - for Instance {} processing request for device usage slot id {}",
                     &self.instance_name,
@@ -293,8 +330,8 @@ impl DevicePluginService {
                     device_usage_id.clone(),
                 );
 
-                if let Err(e) = try_update_instance_device_usage(
-                    &device_usage_id,
+                match try_update_instance_device_usage(
+                    device_usage_id,
                     &self.node_name,
                     &self.instance_name,
                     &self.config_namespace,
@@ -302,11 +339,14 @@ impl DevicePluginService {
                 )
                 .await
                 {
-                    trace!("internal_allocate - could not assign {} slot to {} node ... forcing list_and_watch to continue", device_usage_id, &self.node_name);
-                    self.list_and_watch_message_sender
-                        .send(ListAndWatchMessageKind::Continue)
-                        .unwrap();
-                    return Err(e);
+                    Ok(instance) => claimed_instance = Some(instance),
+                    Err(e) => {
+                        trace!("internal_allocate - could not assign {} slot to {} node ... forcing list_and_watch to continue", device_usage_id, &self.node_name);
+                        self.list_and_watch_message_sender
+                            .send(ListAndWatchMessageKind::Continue)
+                            .unwrap();
+                        return Err(e);
+                    }
                 }
 
                 trace!(
@@ -315,14 +355,49 @@ impl DevicePluginService {
                 );
             }
             // Successfully reserved device_usage_slot[s] for this node.
+            // Broker properties are only written for claimed slots, so that only slots of this node are refreshed.
+            // If they cannot be written, the claimed slots are released by slot reconciliation. The properties are taken
+            // from the Instance, like when they are refreshed, since the device may have changed since this service was
+            // created.
+            let broker_properties_mount = match &self.config.broker_properties_mount_path {
+                Some(container_path) => mount_broker_properties(
+                    &get_broker_properties_directory(),
+                    &self.instance_name,
+                    &request.devices_i_ds,
+                    container_path,
+                    claimed_instance
+                        .as_ref()
+                        .map_or(&broker_properties, |instance| &instance.broker_properties),
+                )?,
+                None => None,
+            };
+            // Only the credential of this Instance's device is given to its brokers. Like the broker properties, it is
+            // only written for claimed slots, so that slot reconciliation removes it if the slots are released.
+            let device_credential =
+                match (&self.config.device_credentials, request.devices_i_ds.last()) {
+                    (Some(device_credentials), Some(slot)) => {
+                        resolve_device_credential(
+                            device_credentials,
+                            &self.device,
+                            &self.config_namespace,
+                            &get_device_credentials_directory(),
+                            &self.instance_name,
+                            slot,
+                            kube_interface.clone(),
+                        )
+                        .await?
+                    }
+                    _ => ResolvedDeviceCredential::default(),
+                };
             // Add response to list of responses
-            let broker_properties =
-                get_all_broker_properties(&self.config.broker_properties, &self.device.properties);
-            let response = build_container_allocate_response(
+            let mut response = build_container_allocate_response(
                 broker_properties,
                 akri_annotations,
                 &self.device,
             );
+            response.mounts.extend(broker_properties_mount);
+            response.mounts.extend(device_credential.mount);
+            response.envs.extend(device_credential.env_var);
             container_responses.push(response);
         }
         trace!(
@@ -378,14 +453,14 @@ fn get_slot_value(
 
 /// This tries up to `MAX_INSTANCE_UPDATE_TRIES` to update the requested slot of the Instance with the appropriate value (either "" to clear slot or node_name).
 /// It cannot be assumed that this will successfully update Instance on first try since Device Plugins on other nodes may be simultaneously trying to update the Instance.
-/// This returns an error if slot does not need to be updated or `MAX_INSTANCE_UPDATE_TRIES` attempted.
+/// This returns the updated Instance, or an error if slot does not need to be updated or `MAX_INSTANCE_UPDATE_TRIES` attempted.
 async fn try_update_instance_device_usage(
     device_usage_id: &str,
     node_name: &str,
     instance_name: &str,
     instance_namespace: &str,
     kube_interface: Arc<impl KubeInterface>,
-) -> Result<(), Status> {
+) -> Result<Instance, Status> {
     let mut instance: Instance;
     for x in 0..MAX_INSTANCE_UPDATE_TRIES {
         // Grab latest instance
@@ -423,7 +498,7 @@ async fn try_update_instance_device_usage(
         {
             Ok(()) => {
                 if value == node_name {
-                    return Ok(());
+                    return Ok(instance);
                 } else {
                     return Err(Status::new(Code::Unknown, "Devices are in inconsistent state, updated device usage, please retry scheduling"));
                 }
@@ -437,7 +512,72 @@ async fn try_update_instance_device_usage(
         }
         random_delay().await;
     }
-    Ok(())
+    Err(Status::new(Code::Unknown, "Could not update Instance"))
+}
+
+/// Writes the broker properties of every requested slot into its directory under `broker_properties_directory` and
+/// returns the read-only Mount of the directory of the last slot at `container_path`, since a container can only have
+/// one Mount at that path and every slot of an Instance has the same properties. If the properties of a slot cannot be
+/// written, the directories of the other slots are removed again.
+fn mount_broker_properties(
+    broker_properties_directory: &Path,
+    instance_name: &str,
+    slots: &[String],
+    container_path: &str,
+    broker_properties: &HashMap<String, String>,
+) -> Result<Option<Mount>, Status> {
+    let mut directories = Vec::new();
+    for slot in slots {
+        let directory = get_slot_directory(broker_properties_directory, instance_name, slot);
+        if let Err(e) = write_broker_properties(&directory, broker_properties) {
+            error!(
+                "internal_allocate - could not write broker properties of slot {} to {:?}: {}",
+                slot, directory, e
+            );
+            directories.push(directory);
+            for directory in directories {
+                if let Err(e) = remove_broker_properties(&directory) {
+                    error!(
+                        "internal_allocate - could not remove broker properties in {:?}: {}",
+                        directory, e
+                    );
+                }
+            }
+            return Err(Status::new(
+                Code::Unknown,
+                format!("Could not write broker properties of slot {}", slot),
+            ));
+        }
+        directories.push(directory);
+    }
+    Ok(directories.pop().map(|directory| Mount {
+        container_path: container_path.to_string(),
+        host_path: directory.to_string_lossy().to_string(),
+        read_only: true,
+    }))
+}
+
+/// Rewrites the broker properties of the slots of an Instance that this node claimed with the Instance's current
+/// properties, so that brokers see changes to them, such as a shared device's new endpoint. Only slots whose
+/// properties were mounted into brokers have a directory to refresh.
+fn refresh_broker_properties(
+    broker_properties_directory: &Path,
+    instance_name: &str,
+    instance: &Instance,
+    node_name: &str,
+) {
+    for (slot, node) in &instance.device_usage {
+        let directory = get_slot_directory(broker_properties_directory, instance_name, slot);
+        if node != node_name || !directory.exists() {
+            continue;
+        }
+        if let Err(e) = write_broker_properties(&directory, &instance.broker_properties) {
+            error!(
+                "refresh_broker_properties - could not refresh broker properties of slot {}: {}",
+                slot, e
+            );
+        }
+    }
 }
 
 /// This sets the volume mounts and environment variables according to the instance's `DiscoveryHandler`.
@@ -510,6 +650,16 @@ async fn try_create_instance(
             &dps.device.properties,
         ),
     };
+    let (labels, mut annotations) = match &dps.config.property_metadata {
+        Some(property_metadata) => {
+            get_property_labels_and_annotations(property_metadata, &instance.broker_properties)
+        }
+        None => (BTreeMap::new(), BTreeMap::new()),
+    };
+    annotations.insert(
+        AKRI_DEVICE_ID_ANNOTATION_NAME.to_string(),
+        dps.device.id.clone(),
+    );
 
     // Try up to MAX_INSTANCE_UPDATE_TRIES to create or update instance, breaking on success
     for x in 0..MAX_INSTANCE_UPDATE_TRIES {
@@ -524,6 +674,25 @@ async fn try_create_instance(
                     dps.instance_name
                 );
 
+                // Never share an Instance with another device, which can happen if both are named the same way
+                if let Some(device_id) = instance_object
+                    .metadata
+                    .annotations
+                    .get(AKRI_DEVICE_ID_ANNOTATION_NAME)
+                {
+                    if device_id != &dps.device.id {
+                        error!(
+                            "try_create_instance - Instance {} represents device {} rather than {} ... returning error",
+                            dps.instance_name, device_id, dps.device.id
+                        );
+                        return Err(format!(
+                            "Instance {} represents another device",
+                            dps.instance_name
+                        )
+                        .into());
+                    }
+                }
+
                 // Check if instance's node list already contains this node, possibly due to device plugin failure and restart
                 if !instance_object.spec.nodes.contains(&dps.node_name) {
                     instance_object.spec.nodes.push(dps.node_name.clone());
@@ -562,6 +731,8 @@ async fn try_create_instance(
                         &dps.config_namespace,
                         &dps.config_name,
                         &dps.config_uid,
+                        &labels,
+                        &annotations,
                     )
                     .await
                 {
@@ -646,11 +817,22 @@ async fn build_list_and_watch_response(
         .find_instance(&dps.instance_name, &dps.config_namespace)
         .await
     {
-        Ok(kube_akri_instance) => Ok(build_virtual_devices(
-            &kube_akri_instance.spec.device_usage,
-            kube_akri_instance.spec.shared,
-            &dps.node_name,
-        )),
+        Ok(kube_akri_instance) => {
+            if dps.config.broker_properties_mount_path.is_some() {
+                refresh_broker_properties(
+                    &get_broker_properties_directory(),
+                    &dps.instance_name,
+                    &kube_akri_instance.spec,
+                    &dps.node_name,
+                );
+            }
+            Ok(build_virtual_devices(
+                &kube_akri_instance.spec.device_usage,
+                kube_akri_instance.spec.shared,
+                is_cordoned(&kube_akri_instance),
+                &dps.node_name,
+            ))
+        }
         Err(_) => {
             trace!("build_list_and_watch_response - could not find instance {} so returning unhealthy devices", dps.instance_name);
             Ok(build_unhealthy_virtual_devices(
@@ -682,9 +864,12 @@ fn build_unhealthy_virtual_devices(capacity: i32, instance_name: &str) -> Vec<v1
 
 /// This builds a list of virtual Devices, determining the health of each virtual Device as follows:
 /// Healthy if it is available to be used by this node or Unhealthy if it is already taken by another node.
+/// If the Instance is cordoned, only the virtual Devices already taken by this node are Healthy, so that no new
+/// broker Pods are scheduled to it.
 fn build_virtual_devices(
     device_usage: &HashMap<String, String>,
     shared: bool,
+    cordoned: bool,
     node_name: &str,
 ) -> Vec<v1beta1::Device> {
     let mut devices: Vec<v1beta1::Device> = Vec::new();
@@ -695,15 +880,18 @@ fn build_virtual_devices(
         }
         // Advertise the device as Unhealthy if it is
         // USED by !this_node && SHARED
-        let unhealthy = shared && !allocated_node.is_empty() && allocated_node != node_name;
+        // or if the Instance is cordoned and the device is not USED by this_node
+        let unhealthy = (shared && !allocated_node.is_empty() && allocated_node != node_name)
+            || (cordoned && allocated_node != node_name);
         let health = if unhealthy {
             UNHEALTHY.to_string()
         } else {
             HEALTHY.to_string()
         };
         trace!(
-            "build_virtual_devices - [shared = {}] device with name [{}] and health: [{}]",
+            "build_virtual_devices - [shared = {}, cordoned = {}] device with name [{}] and health: [{}]",
             shared,
+            cordoned,
             device_name,
             health
         );
@@ -767,7 +955,7 @@ mod device_plugin_service_tests {
         v1beta1::device_plugin_client::DevicePluginClient,
     };
     use super::*;
-    use akri_shared::akri::configuration::KubeAkriConfig;
+    use akri_shared::akri::configuration::{KubeAkriConfig, PropertyMetadata};
     use akri_shared::{
         akri::instance::{Instance, KubeAkriInstance},
         k8s::MockKubeInterface,
@@ -966,8 +1154,14 @@ mod device_plugin_service_tests {
     #[tokio::test]
     async fn test_try_create_instance() {
         let _ = env_logger::builder().is_test(true).try_init();
-        let (device_plugin_service, _device_plugin_service_receivers) =
+        let (mut device_plugin_service, _device_plugin_service_receivers) =
             create_device_plugin_service(InstanceConnectivityStatus::Online, false);
+        let mut property_metadata = PropertyMetadata::default();
+        property_metadata.labels.insert(
+            "DEVICE_LOCATION_INFO".to_string(),
+            "example.com/location".to_string(),
+        );
+        device_plugin_service.config.property_metadata = Some(property_metadata);
         let mut mock = MockKubeInterface::new();
         configure_find_configuration(
             &mut mock,
@@ -987,14 +1181,20 @@ mod device_plugin_service_tests {
         let instance_name = device_plugin_service.instance_name.clone();
         let config_namespace = device_plugin_service.config_namespace.clone();
         mock.expect_create_instance()
-            .withf(move |instance, name, namespace, owner_name, owner_uid| {
-                namespace == config_namespace
-                    && name == instance_name
-                    && instance.nodes.contains(&"node-a".to_string())
-                    && owner_name == config_name
-                    && owner_uid == config_uid
-            })
-            .returning(move |_, _, _, _, _| Ok(()));
+            .withf(
+                move |instance, name, namespace, owner_name, owner_uid, labels, annotations| {
+                    namespace == config_namespace
+                        && name == instance_name
+                        && instance.nodes.contains(&"node-a".to_string())
+                        && owner_name == config_name
+                        && owner_uid == config_uid
+                        && labels.get("example.com/location") == Some(&"endpoint".to_string())
+                        && annotations.len() == 1
+                        && annotations.get(AKRI_DEVICE_ID_ANNOTATION_NAME)
+                            == Some(&"n/a".to_string())
+                },
+            )
+            .returning(move |_, _, _, _, _, _, _| Ok(()));
 
         let dps = Arc::new(device_plugin_service);
         assert!(try_create_instance(dps.clone(), Arc::new(mock))
@@ -1049,6 +1249,41 @@ mod device_plugin_service_tests {
             .contains_key(&dps.instance_name));
     }
 
+    // Tests that try_create_instance does not add this node to an Instance of another device
+    #[tokio::test]
+    async fn test_try_create_instance_of_other_device() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let (device_plugin_service, _device_plugin_service_receivers) =
+            create_device_plugin_service(InstanceConnectivityStatus::Online, false);
+        let mut mock = MockKubeInterface::new();
+        configure_find_configuration(
+            &mut mock,
+            device_plugin_service.config_name.clone(),
+            device_plugin_service.config_namespace.clone(),
+        );
+        let instance_json = fs::read_to_string("../test/json/local-instance.json")
+            .expect("Unable to read file")
+            .replace("config-a-b494b6", &device_plugin_service.instance_name);
+        let mut instance: KubeAkriInstance = serde_json::from_str(&instance_json).unwrap();
+        instance.metadata.annotations.insert(
+            AKRI_DEVICE_ID_ANNOTATION_NAME.to_string(),
+            "other-device".to_string(),
+        );
+        mock.expect_find_instance()
+            .times(1)
+            .returning(move |_, _| Ok(instance.clone()));
+
+        let dps = Arc::new(device_plugin_service);
+        assert!(try_create_instance(dps.clone(), Arc::new(mock))
+            .await
+            .is_err());
+        assert!(!dps
+            .instance_map
+            .lock()
+            .await
+            .contains_key(&dps.instance_name));
+    }
+
     // Test when instance already created and already contains this node.
     // Should find the instance but not update it.
     #[tokio::test]
@@ -1132,14 +1367,16 @@ mod device_plugin_service_tests {
         let config_namespace = device_plugin_service.config_namespace.clone();
         mock.expect_create_instance()
             .times(MAX_INSTANCE_UPDATE_TRIES as usize)
-            .withf(move |instance, name, namespace, owner_name, owner_uid| {
-                namespace == config_namespace
-                    && name == instance_name
-                    && instance.nodes.contains(&"node-a".to_string())
-                    && owner_name == config_name
-                    && owner_uid == config_uid
-            })
-            .returning(move |_, _, _, _, _| Err(None.ok_or("failure")?));
+            .withf(
+                move |instance, name, namespace, owner_name, owner_uid, _, _| {
+                    namespace == config_namespace
+                        && name == instance_name
+                        && instance.nodes.contains(&"node-a".to_string())
+                        && owner_name == config_name
+                        && owner_uid == config_uid
+                },
+            )
+            .returning(move |_, _, _, _, _, _, _| Err(None.ok_or("failure")?));
 
         let dps = Arc::new(device_plugin_service);
         assert!(try_create_instance(dps.clone(), Arc::new(mock))
@@ -1225,7 +1462,7 @@ mod device_plugin_service_tests {
 
         // Test shared all healthy
         let mut devices: Vec<v1beta1::Device> =
-            build_virtual_devices(&device_usage, true, &"nodeA".to_string());
+            build_virtual_devices(&device_usage, true, false, &"nodeA".to_string());
         for device in devices {
             assert_eq!(
                 expected_devices_nodea.get(&device.id).unwrap(),
@@ -1234,7 +1471,7 @@ mod device_plugin_service_tests {
         }
 
         // Test unshared all healthy
-        devices = build_virtual_devices(&device_usage, false, &"nodeA".to_string());
+        devices = build_virtual_devices(&device_usage, false, false, &"nodeA".to_string());
         for device in devices {
             assert_eq!(
                 expected_devices_nodea.get(&device.id).unwrap(),
@@ -1243,7 +1480,7 @@ mod device_plugin_service_tests {
         }
 
         // Test shared some unhealthy (taken by another node)
-        devices = build_virtual_devices(&device_usage, true, &"nodeB".to_string());
+        devices = build_virtual_devices(&device_usage, true, false, &"nodeB".to_string());
         for device in devices {
             assert_eq!(
                 expected_devices_nodeb.get(&device.id).unwrap(),
@@ -1251,9 +1488,20 @@ mod device_plugin_service_tests {
             );
         }
 
+        // Test cordoned only healthy when taken by this node
+        devices = build_virtual_devices(&device_usage, true, true, &"nodeA".to_string());
+        for device in devices {
+            let expected_health = if device_usage[&device.id] == "nodeA" {
+                HEALTHY
+            } else {
+                UNHEALTHY
+            };
+            assert_eq!(expected_health, device.health);
+        }
+
         // Test unshared panic. A different node should never be listed under any device usage slots
         let result = std::panic::catch_unwind(|| {
-            build_virtual_devices(&device_usage, false, &"nodeB".to_string())
+            build_virtual_devices(&device_usage, false, false, &"nodeB".to_string())
         });
         assert!(result.is_err());
     }
@@ -1386,6 +1634,192 @@ mod device_plugin_service_tests {
             .is_err());
     }
 
+    // Test that the broker properties mounted into brokers are the current ones of the Instance rather than the ones of
+    // the device when the service was created
+    #[tokio::test]
+    async fn test_internal_allocate_mounts_instance_broker_properties() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let base = tempfile::tempdir().unwrap();
+        std::env::set_var(
+            super::super::constants::BROKER_PROPERTIES_DIRECTORY_LABEL,
+            base.path(),
+        );
+        let (mut device_plugin_service, _device_plugin_service_receivers) =
+            create_device_plugin_service(InstanceConnectivityStatus::Online, true);
+        device_plugin_service.config.broker_properties_mount_path =
+            Some("/etc/akri/properties".to_string());
+        let instance_name = device_plugin_service.instance_name.clone();
+        let slot = format!("{}-0", instance_name);
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_instance().times(1).returning(|_, _| {
+            let instance_json = fs::read_to_string("../test/json/local-instance.json")
+                .expect("Unable to read file");
+            let mut instance: KubeAkriInstance = serde_json::from_str(&instance_json).unwrap();
+            instance.spec.broker_properties.insert(
+                "DEVICE_LOCATION_INFO".to_string(),
+                "updated-endpoint".to_string(),
+            );
+            Ok(instance)
+        });
+        mock.expect_update_instance()
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let container_requests = vec![v1beta1::ContainerAllocateRequest {
+            devices_i_ds: vec![slot.clone()],
+        }];
+        let response = device_plugin_service
+            .internal_allocate(
+                Request::new(AllocateRequest { container_requests }),
+                Arc::new(mock),
+            )
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.container_responses[0].mounts.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(
+                get_slot_directory(base.path(), &instance_name, &slot).join("DEVICE_LOCATION_INFO")
+            )
+            .unwrap(),
+            "updated-endpoint"
+        );
+    }
+
+    // Test that broker properties are written to the slot's directory, mounted into brokers and refreshed from the
+    // Instance for slots claimed by this node
+    #[test]
+    fn test_mount_and_refresh_broker_properties() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let base = tempfile::tempdir().unwrap();
+        let properties: HashMap<String, String> = vec![(
+            "OPCUA_DISCOVERY_URL".to_string(),
+            "opc.tcp://a:4840/".to_string(),
+        )]
+        .into_iter()
+        .collect();
+        let mount = mount_broker_properties(
+            base.path(),
+            "config-a-b494b6",
+            &["config-a-b494b6-0".to_string()],
+            "/etc/akri/properties",
+            &properties,
+        )
+        .unwrap()
+        .unwrap();
+        let directory = base
+            .path()
+            .join("config-a-b494b6")
+            .join("config-a-b494b6-0");
+        assert_eq!(mount.container_path, "/etc/akri/properties");
+        assert_eq!(mount.host_path, directory.to_string_lossy());
+        assert!(mount.read_only);
+        assert_eq!(
+            std::fs::read_to_string(directory.join("OPCUA_DISCOVERY_URL")).unwrap(),
+            "opc.tcp://a:4840/"
+        );
+
+        let mut instance = Instance {
+            configuration_name: "config-a".to_string(),
+            shared: true,
+            nodes: vec!["node-a".to_string(), "node-b".to_string()],
+            device_usage: vec![
+                ("config-a-b494b6-0".to_string(), "node-a".to_string()),
+                ("config-a-b494b6-1".to_string(), "node-a".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+            broker_properties: vec![(
+                "OPCUA_DISCOVERY_URL".to_string(),
+                "opc.tcp://b:4840/".to_string(),
+            )]
+            .into_iter()
+            .collect(),
+        };
+        // Slots of other nodes are not refreshed
+        refresh_broker_properties(base.path(), "config-a-b494b6", &instance, "node-b");
+        assert_eq!(
+            std::fs::read_to_string(directory.join("OPCUA_DISCOVERY_URL")).unwrap(),
+            "opc.tcp://a:4840/"
+        );
+        refresh_broker_properties(base.path(), "config-a-b494b6", &instance, "node-a");
+        assert_eq!(
+            std::fs::read_to_string(directory.join("OPCUA_DISCOVERY_URL")).unwrap(),
+            "opc.tcp://b:4840/"
+        );
+        // Slots whose properties were never mounted are not written
+        assert!(!base
+            .path()
+            .join("config-a-b494b6")
+            .join("config-a-b494b6-1")
+            .exists());
+
+        instance
+            .device_usage
+            .insert("config-a-b494b6-0".to_string(), "node-b".to_string());
+        instance.broker_properties.clear();
+        refresh_broker_properties(base.path(), "config-a-b494b6", &instance, "node-a");
+        assert!(directory.join("OPCUA_DISCOVERY_URL").exists());
+    }
+
+    // Test that broker properties are written for every requested slot and removed again if any of them fails
+    #[test]
+    fn test_mount_broker_properties_of_several_slots() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let base = tempfile::tempdir().unwrap();
+        let properties: HashMap<String, String> =
+            vec![("ENDPOINT".to_string(), "opc.tcp://a:4840/".to_string())]
+                .into_iter()
+                .collect();
+        let slots = vec![
+            "config-a-b494b6-0".to_string(),
+            "config-a-b494b6-1".to_string(),
+        ];
+        let instance_directory = base.path().join("config-a-b494b6");
+        let mount = mount_broker_properties(
+            base.path(),
+            "config-a-b494b6",
+            &slots,
+            "/etc/akri/properties",
+            &properties,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            mount.host_path,
+            instance_directory
+                .join("config-a-b494b6-1")
+                .to_string_lossy()
+        );
+        assert!(instance_directory
+            .join("config-a-b494b6-0")
+            .join("ENDPOINT")
+            .exists());
+
+        // A file in place of the directory of the second slot keeps its properties from being written
+        std::fs::remove_dir_all(&instance_directory).unwrap();
+        std::fs::create_dir_all(&instance_directory).unwrap();
+        std::fs::write(instance_directory.join("config-a-b494b6-1"), "").unwrap();
+        assert!(mount_broker_properties(
+            base.path(),
+            "config-a-b494b6",
+            &slots,
+            "/etc/akri/properties",
+            &properties,
+        )
+        .is_err());
+        assert!(!instance_directory.join("config-a-b494b6-0").exists());
+
+        assert!(mount_broker_properties(
+            base.path(),
+            "config-a-b494b6",
+            &[],
+            "/etc/akri/properties",
+            &properties,
+        )
+        .unwrap()
+        .is_none());
+    }
+
     // Test when device_usage[id] == ""
     // internal_allocate should set device_usage[id] = m.nodeName, return
     #[tokio::test]
@@ -1486,6 +1920,44 @@ mod device_plugin_service_tests {
         );
     }
 
+    // Tests that the device credential is not resolved for a slot that is taken by another node
+    // Expected behavior: should return error without reading the Secret of device credentials
+    #[tokio::test]
+    async fn test_internal_allocate_taken_no_device_credential() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let (mut device_plugin_service, _device_plugin_service_receivers) =
+            create_device_plugin_service(InstanceConnectivityStatus::Online, true);
+        device_plugin_service.config.device_credentials =
+            Some(akri_shared::akri::configuration::DeviceCredentials {
+                secret_name: "credentials".to_string(),
+                key_property: None,
+                mount_path: None,
+                env_var: Some("DEVICE_CREDENTIAL".to_string()),
+            });
+        let device_usage_id_slot = format!("{}-0", device_plugin_service.instance_name);
+        // `find_secret` is not expected, so the mock panics if the credential is resolved
+        let mut mock = MockKubeInterface::new();
+        configure_find_instance(
+            &mut mock,
+            "../test/json/local-instance.json",
+            device_plugin_service.instance_name.clone(),
+            device_plugin_service.config_namespace.clone(),
+            "other".to_string(),
+            NodeName::ThisNode,
+        );
+        let devices_i_ds = vec![device_usage_id_slot];
+        let container_requests = vec![v1beta1::ContainerAllocateRequest { devices_i_ds }];
+        let requests = Request::new(AllocateRequest { container_requests });
+        assert_eq!(
+            device_plugin_service
+                .internal_allocate(requests, Arc::new(mock))
+                .await
+                .unwrap_err()
+                .message(),
+            "Requested device already in use"
+        );
+    }
+
     // Tests when instance does not have the requested device usage id
     // Expected behavior: should invoke list_and_watch, and return error
     #[tokio::test]
@@ -0,0 +1,250 @@
+use super::registration::DiscoveryDetails;
+use akri_shared::{
+    akri::configuration::{ConfigurationStatus, KubeAkriConfig},
+    k8s::KubeInterface,
+};
+use jsonschema::JSONSchema;
+use log::{error, trace};
+use serde_json::Value;
+
+/// Checks that a JSON Schema advertised by a Discovery Handler when registering can be used to validate discovery
+/// details.
+pub fn check_discovery_details_schema(schema: &str) -> Result<(), String> {
+    let schema = parse_discovery_details_schema(schema)?;
+    JSONSchema::compile(&schema)
+        .map_err(|e| format!("discovery details schema is not a valid JSON Schema: {}", e))?;
+    Ok(())
+}
+
+fn parse_discovery_details_schema(schema: &str) -> Result<Value, String> {
+    serde_json::from_str(schema)
+        .map_err(|e| format!("discovery details schema is not valid JSON: {}", e))
+}
+
+/// Validates the discovery details of a Configuration, which are YAML, against the JSON Schema advertised by its
+/// Discovery Handler. Returns a description of every validation error found.
+pub fn validate_discovery_details(schema: &str, discovery_details: &str) -> Result<(), String> {
+    let schema = parse_discovery_details_schema(schema)?;
+    let compiled_schema = JSONSchema::compile(&schema)
+        .map_err(|e| format!("discovery details schema is not a valid JSON Schema: {}", e))?;
+    let discovery_details: Value = serde_yaml::from_str(discovery_details)
+        .map_err(|e| format!("discovery details are not valid YAML: {}", e))?;
+    if let Err(errors) = compiled_schema.validate(&discovery_details) {
+        let errors: Vec<String> = errors.map(|e| e.to_string()).collect();
+        return Err(format!(
+            "discovery details are not valid: {}",
+            errors.join("; ")
+        ));
+    }
+    Ok(())
+}
+
+/// Validates a Configuration's discovery details against the schema advertised by a Discovery Handler, if it
+/// advertised one, and records the result in the Configuration's status. Returns whether the Discovery Handler should
+/// be used for discovery.
+pub async fn validate_and_record_discovery_details(
+    kube_interface: &dyn KubeInterface,
+    config: &KubeAkriConfig,
+    dh_details: &DiscoveryDetails,
+) -> bool {
+    let schema = match &dh_details.discovery_details_schema {
+        Some(schema) => schema,
+        None => return true,
+    };
+    let discovery_details_error =
+        validate_discovery_details(schema, &config.spec.discovery_handler.discovery_details).err();
+    let current_error = config
+        .status
+        .as_ref()
+        .and_then(|status| status.discovery_details_error.clone());
+    // Only update the status when it changes to avoid needless writes by every Agent
+    if current_error != discovery_details_error {
+        trace!(
+            "validate_and_record_discovery_details - updating status of Configuration {} with discovery details error {:?}",
+            config.metadata.name,
+            discovery_details_error
+        );
+        let status = ConfigurationStatus {
+            discovery_details_error: discovery_details_error.clone(),
+        };
+        if let Err(e) = kube_interface
+            .update_configuration_status(
+                &status,
+                &config.metadata.name,
+                config.metadata.namespace.as_ref().unwrap(),
+            )
+            .await
+        {
+            error!(
+                "validate_and_record_discovery_details - failed to update status of Configuration {} with error {}",
+                config.metadata.name, e
+            );
+        }
+    }
+    match discovery_details_error {
+        Some(e) => {
+            error!(
+                "validate_and_record_discovery_details - Configuration {} is not valid for {} discovery handler at endpoint {:?} ... not using it for discovery: {}",
+                config.metadata.name, dh_details.name, dh_details.endpoint, e
+            );
+            false
+        }
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::registration::{DiscoveryHandlerEndpoint, DiscoveryHandlerStatus};
+    use super::*;
+    use akri_discovery_utils::discovery::discovery_handler::discovery_details_schema;
+    use akri_onvif::discovery_handler::OnvifDiscoveryDetails;
+    use akri_shared::k8s::MockKubeInterface;
+    use akri_udev::discovery_handler::UdevDiscoveryDetails;
+    use tokio::sync::broadcast;
+
+    fn create_discovery_handler_details(
+        discovery_details_schema: Option<String>,
+    ) -> DiscoveryDetails {
+        let (close_discovery_handler_connection, _) = broadcast::channel(2);
+        DiscoveryDetails {
+            name: "udev".to_string(),
+            endpoint: DiscoveryHandlerEndpoint::Uds("socket.sock".to_string()),
+            shared: false,
+            close_discovery_handler_connection,
+            connectivity_status: DiscoveryHandlerStatus::Waiting,
+            discovery_details_schema,
+        }
+    }
+
+    fn create_config(
+        discovery_details: &str,
+        discovery_details_error: Option<&str>,
+    ) -> KubeAkriConfig {
+        let path_to_config = "../test/yaml/config-a.yaml";
+        let config_yaml = std::fs::read_to_string(path_to_config).expect("Unable to read file");
+        let mut config: KubeAkriConfig = serde_yaml::from_str(&config_yaml).unwrap();
+        config.spec.discovery_handler.discovery_details = discovery_details.to_string();
+        config.status = discovery_details_error.map(|e| ConfigurationStatus {
+            discovery_details_error: Some(e.to_string()),
+        });
+        config
+    }
+
+    #[test]
+    fn test_validate_discovery_details() {
+        let udev_schema = discovery_details_schema::<UdevDiscoveryDetails>();
+        assert!(
+            validate_discovery_details(&udev_schema, "udevRules:\n- KERNEL==\"video[0-9]*\"")
+                .is_ok()
+        );
+        // Typo in field name
+        let error =
+            validate_discovery_details(&udev_schema, "udevRule:\n- KERNEL==\"video[0-9]*\"")
+                .unwrap_err();
+        assert!(error.contains("udevRule"));
+        // Wrong type
+        assert!(
+            validate_discovery_details(&udev_schema, "udevRules: KERNEL==\"video[0-9]*\"").is_err()
+        );
+        // Not YAML
+        assert!(validate_discovery_details(&udev_schema, "udevRules: [").is_err());
+
+        let onvif_schema = discovery_details_schema::<OnvifDiscoveryDetails>();
+        assert!(validate_discovery_details(&onvif_schema, "{}").is_ok());
+        assert!(validate_discovery_details(
+            &onvif_schema,
+            "ipAddresses:\n  action: Exclude\n  items:\n  - 10.0.0.1"
+        )
+        .is_ok());
+        // Unknown filter action
+        assert!(validate_discovery_details(
+            &onvif_schema,
+            "ipAddresses:\n  action: Ignore\n  items:\n  - 10.0.0.1"
+        )
+        .is_err());
+        // Typo in nested field name
+        assert!(validate_discovery_details(
+            &onvif_schema,
+            "ipAddresses:\n  action: Exclude\n  item:\n  - 10.0.0.1"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_check_discovery_details_schema() {
+        assert!(
+            check_discovery_details_schema(&discovery_details_schema::<UdevDiscoveryDetails>())
+                .is_ok()
+        );
+        assert!(check_discovery_details_schema("not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_and_record_discovery_details() {
+        let udev_schema = discovery_details_schema::<UdevDiscoveryDetails>();
+        let dh_details = create_discovery_handler_details(Some(udev_schema));
+
+        // Invalid discovery details are recorded in the status
+        let mut mock_kube_interface = MockKubeInterface::new();
+        mock_kube_interface
+            .expect_update_configuration_status()
+            .times(1)
+            .withf(
+                |status: &ConfigurationStatus, name: &str, namespace: &str| {
+                    status
+                        .discovery_details_error
+                        .as_ref()
+                        .unwrap()
+                        .contains("udevRule")
+                        && name == "config-a"
+                        && namespace == "config-a-namespace"
+                },
+            )
+            .returning(|_, _, _| Ok(()));
+        let config = create_config("udevRule: []", None);
+        assert!(
+            !validate_and_record_discovery_details(&mock_kube_interface, &config, &dh_details)
+                .await
+        );
+
+        // Status is not updated if the error has already been recorded
+        let mock_kube_interface = MockKubeInterface::new();
+        let recorded_error = validate_discovery_details(
+            dh_details.discovery_details_schema.as_ref().unwrap(),
+            "udevRule: []",
+        )
+        .unwrap_err();
+        let config = create_config("udevRule: []", Some(&recorded_error));
+        assert!(
+            !validate_and_record_discovery_details(&mock_kube_interface, &config, &dh_details)
+                .await
+        );
+
+        // A recorded error is cleared once the discovery details are fixed
+        let mut mock_kube_interface = MockKubeInterface::new();
+        mock_kube_interface
+            .expect_update_configuration_status()
+            .times(1)
+            .withf(|status: &ConfigurationStatus, _: &str, _: &str| {
+                status.discovery_details_error.is_none()
+            })
+            .returning(|_, _, _| Ok(()));
+        let config = create_config("udevRules: []", Some(&recorded_error));
+        assert!(
+            validate_and_record_discovery_details(&mock_kube_interface, &config, &dh_details).await
+        );
+
+        // Discovery Handlers that do not advertise a schema are always used
+        let mock_kube_interface = MockKubeInterface::new();
+        let config = create_config("udevRule: []", None);
+        assert!(
+            validate_and_record_discovery_details(
+                &mock_kube_interface,
+                &config,
+                &create_discovery_handler_details(None)
+            )
+            .await
+        );
+    }
+}
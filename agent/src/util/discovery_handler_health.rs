@@ -0,0 +1,271 @@
+use super::super::DISCOVERY_HANDLER_HEALTH_METRIC;
+use super::{
+    constants::{
+        DISCOVERY_HANDLER_HEALTH_CHECK_INTERVAL_SECS, DISCOVERY_HANDLER_HEALTH_CHECK_TIMEOUT_SECS,
+    },
+    discovery_operator::{connect_to_network_discovery_handler, connect_to_uds_discovery_handler},
+    registration::{
+        DiscoveryHandlerEndpoint, DiscoveryHandlerStatus, RegisteredDiscoveryHandlerMap,
+    },
+};
+use akri_discovery_utils::discovery::grpc_health_v1::{
+    health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+};
+use log::{error, info, trace};
+#[cfg(test)]
+use mock_instant::Instant;
+use std::time::Duration;
+#[cfg(not(test))]
+use std::time::Instant;
+use tonic::Code;
+
+/// Periodically checks the health of all registered external Discovery Handlers using the standard gRPC health service.
+/// This catches Discovery Handlers that keep their discovery connections open but have stopped discovering devices.
+/// A Discovery Handler that fails a health check is marked `Offline`, and any open discovery connections with it are
+/// closed, so that its Instances are marked offline and it is deregistered if it does not recover within the grace
+/// period. The health of each Discovery Handler is exposed in the `akri_discovery_handler_health` metric.
+pub async fn periodic_discovery_handler_health_check(
+    discovery_handler_map: RegisteredDiscoveryHandlerMap,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    trace!("periodic_discovery_handler_health_check - start");
+    loop {
+        tokio::time::delay_for(Duration::from_secs(
+            DISCOVERY_HANDLER_HEALTH_CHECK_INTERVAL_SECS,
+        ))
+        .await;
+        let discovery_handlers: Vec<(String, DiscoveryHandlerEndpoint)> = discovery_handler_map
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|(name, discovery_handler_details_map)| {
+                discovery_handler_details_map
+                    .keys()
+                    .filter(|endpoint| **endpoint != DiscoveryHandlerEndpoint::Embedded)
+                    .map(move |endpoint| (name.clone(), endpoint.clone()))
+            })
+            .collect();
+        for (name, endpoint) in discovery_handlers {
+            let healthy = match tokio::time::timeout(
+                Duration::from_secs(DISCOVERY_HANDLER_HEALTH_CHECK_TIMEOUT_SECS),
+                check_discovery_handler_health(&endpoint),
+            )
+            .await
+            {
+                Ok(healthy) => healthy,
+                Err(_) => {
+                    trace!("periodic_discovery_handler_health_check - health check of {} discovery handler at endpoint {:?} timed out", name, endpoint);
+                    false
+                }
+            };
+            DISCOVERY_HANDLER_HEALTH_METRIC
+                .with_label_values(&[&name, &endpoint_label(&endpoint)])
+                .set(healthy as i64);
+            update_discovery_handler_health(&discovery_handler_map, &name, &endpoint, healthy);
+        }
+    }
+}
+
+/// Calls `Check` on the health service of the Discovery Handler at an endpoint and returns whether it is serving
+async fn check_discovery_handler_health(endpoint: &DiscoveryHandlerEndpoint) -> bool {
+    let channel = match endpoint {
+        DiscoveryHandlerEndpoint::Embedded => return true,
+        DiscoveryHandlerEndpoint::Uds(socket) => connect_to_uds_discovery_handler(socket)
+            .await
+            .map_err(|e| e.to_string()),
+        DiscoveryHandlerEndpoint::Network(addr) => connect_to_network_discovery_handler(addr)
+            .await
+            .map_err(|e| e.to_string()),
+    };
+    let channel = match channel {
+        Ok(channel) => channel,
+        Err(e) => {
+            trace!(
+                "check_discovery_handler_health - could not connect to discovery handler at endpoint {:?} with error {}",
+                endpoint,
+                e
+            );
+            return false;
+        }
+    };
+    let mut health_client = HealthClient::new(channel);
+    match health_client
+        .check(tonic::Request::new(HealthCheckRequest {
+            service: String::new(),
+        }))
+        .await
+    {
+        Ok(response) => response.into_inner().status == ServingStatus::Serving as i32,
+        // Discovery Handlers that do not serve the health service can only be checked through their discovery
+        // connections
+        Err(status) if status.code() == Code::Unimplemented => true,
+        Err(status) => {
+            trace!(
+                "check_discovery_handler_health - discovery handler at endpoint {:?} returned error status {}",
+                endpoint,
+                status
+            );
+            false
+        }
+    }
+}
+
+/// Updates the `DiscoveryHandlerStatus` of a Discovery Handler based on the result of a health check.
+/// If an `Active` Discovery Handler is unhealthy, it is marked `Offline` and told to close its discovery connections.
+/// If a `Waiting` Discovery Handler is unhealthy, it is marked `Offline`.
+/// If an `Offline` Discovery Handler is healthy again, it is marked `Waiting` so discovery can be retried.
+fn update_discovery_handler_health(
+    discovery_handler_map: &RegisteredDiscoveryHandlerMap,
+    name: &str,
+    endpoint: &DiscoveryHandlerEndpoint,
+    healthy: bool,
+) {
+    let mut registered_dh_map = discovery_handler_map.lock().unwrap();
+    if let Some(dh_details) = registered_dh_map
+        .get_mut(name)
+        .and_then(|discovery_handler_details_map| discovery_handler_details_map.get_mut(endpoint))
+    {
+        match (&dh_details.connectivity_status, healthy) {
+            (DiscoveryHandlerStatus::Offline(_), true) => {
+                info!("update_discovery_handler_health - {} discovery handler at endpoint {:?} is healthy again", name, endpoint);
+                dh_details.connectivity_status = DiscoveryHandlerStatus::Waiting;
+            }
+            (DiscoveryHandlerStatus::Active, false) => {
+                error!("update_discovery_handler_health - {} discovery handler at endpoint {:?} failed health check ... closing its discovery connections", name, endpoint);
+                dh_details.connectivity_status = DiscoveryHandlerStatus::Offline(Instant::now());
+                // Ignore the error returned when no connection is listening
+                dh_details.close_discovery_handler_connection.send(()).ok();
+            }
+            (DiscoveryHandlerStatus::Waiting, false) => {
+                error!(
+                    "update_discovery_handler_health - {} discovery handler at endpoint {:?} failed health check",
+                    name, endpoint
+                );
+                dh_details.connectivity_status = DiscoveryHandlerStatus::Offline(Instant::now());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the value of the `endpoint` label of a Discovery Handler's health metric
+fn endpoint_label(endpoint: &DiscoveryHandlerEndpoint) -> String {
+    match endpoint {
+        DiscoveryHandlerEndpoint::Embedded => "embedded".to_string(),
+        DiscoveryHandlerEndpoint::Uds(socket) => socket.clone(),
+        DiscoveryHandlerEndpoint::Network(addr) => addr.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::discovery_operator::tests::add_discovery_handler_to_map;
+    use super::*;
+    use std::{collections::HashMap, sync::Arc};
+
+    fn get_status(
+        discovery_handler_map: &RegisteredDiscoveryHandlerMap,
+        endpoint: &DiscoveryHandlerEndpoint,
+    ) -> DiscoveryHandlerStatus {
+        discovery_handler_map
+            .lock()
+            .unwrap()
+            .get("debugEcho")
+            .unwrap()
+            .get(endpoint)
+            .unwrap()
+            .connectivity_status
+            .clone()
+    }
+
+    fn set_status(
+        discovery_handler_map: &RegisteredDiscoveryHandlerMap,
+        endpoint: &DiscoveryHandlerEndpoint,
+        connectivity_status: DiscoveryHandlerStatus,
+    ) {
+        discovery_handler_map
+            .lock()
+            .unwrap()
+            .get_mut("debugEcho")
+            .unwrap()
+            .get_mut(endpoint)
+            .unwrap()
+            .connectivity_status = connectivity_status;
+    }
+
+    #[test]
+    fn test_update_discovery_handler_health() {
+        let discovery_handler_map: RegisteredDiscoveryHandlerMap =
+            Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let endpoint = DiscoveryHandlerEndpoint::Uds("socket.sock".to_string());
+        add_discovery_handler_to_map("debugEcho", &endpoint, false, discovery_handler_map.clone());
+        let mut close_discovery_handler_connection_receiver = discovery_handler_map
+            .lock()
+            .unwrap()
+            .get("debugEcho")
+            .unwrap()
+            .get(&endpoint)
+            .unwrap()
+            .close_discovery_handler_connection
+            .subscribe();
+
+        // Unhealthy active discovery handler is marked offline and its connections are closed
+        set_status(
+            &discovery_handler_map,
+            &endpoint,
+            DiscoveryHandlerStatus::Active,
+        );
+        update_discovery_handler_health(&discovery_handler_map, "debugEcho", &endpoint, false);
+        let offline_status = get_status(&discovery_handler_map, &endpoint);
+        assert!(matches!(offline_status, DiscoveryHandlerStatus::Offline(_)));
+        assert!(close_discovery_handler_connection_receiver
+            .try_recv()
+            .is_ok());
+
+        // Still unhealthy discovery handler keeps the time it went offline
+        update_discovery_handler_health(&discovery_handler_map, "debugEcho", &endpoint, false);
+        assert_eq!(
+            get_status(&discovery_handler_map, &endpoint),
+            offline_status
+        );
+        assert!(close_discovery_handler_connection_receiver
+            .try_recv()
+            .is_err());
+
+        // Discovery handler that is healthy again is marked waiting
+        update_discovery_handler_health(&discovery_handler_map, "debugEcho", &endpoint, true);
+        assert_eq!(
+            get_status(&discovery_handler_map, &endpoint),
+            DiscoveryHandlerStatus::Waiting
+        );
+
+        // Unhealthy waiting discovery handler is marked offline without closing connections
+        update_discovery_handler_health(&discovery_handler_map, "debugEcho", &endpoint, false);
+        assert!(matches!(
+            get_status(&discovery_handler_map, &endpoint),
+            DiscoveryHandlerStatus::Offline(_)
+        ));
+        assert!(close_discovery_handler_connection_receiver
+            .try_recv()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_discovery_handler_health() {
+        let (endpoint_dir, endpoint) =
+            akri_discovery_utils::discovery::mock_discovery_handler::get_mock_discovery_handler_dir_and_endpoint(
+                "mock.sock",
+            );
+        let dh_endpoint = DiscoveryHandlerEndpoint::Uds(endpoint.clone());
+        // Not running
+        assert!(!check_discovery_handler_health(&dh_endpoint).await);
+        let _dh_server_thread_handle =
+            akri_discovery_utils::discovery::mock_discovery_handler::run_mock_discovery_handler(
+                &endpoint_dir,
+                &endpoint,
+                false,
+                Vec::new(),
+            )
+            .await;
+        assert!(check_discovery_handler_health(&dh_endpoint).await);
+    }
+}
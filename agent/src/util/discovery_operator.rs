@@ -1,7 +1,8 @@
 use super::super::INSTANCE_COUNT_METRIC;
 use super::{
     constants::{
-        DISCOVERY_HANDLER_OFFLINE_GRACE_PERIOD_SECS, SHARED_INSTANCE_OFFLINE_GRACE_PERIOD_SECS,
+        DISCOVERY_HANDLER_OFFLINE_GRACE_PERIOD_SECS, DISCOVERY_HANDLER_UNHEALTHY_MESSAGE,
+        SHARED_INSTANCE_OFFLINE_GRACE_PERIOD_SECS,
     },
     device_plugin_builder::{DevicePluginBuilder, DevicePluginBuilderInterface},
     device_plugin_service,
@@ -13,13 +14,27 @@ use super::{
         DiscoveryDetails, DiscoveryHandlerEndpoint, DiscoveryHandlerStatus,
         RegisteredDiscoveryHandlerMap,
     },
-    streaming_extension::StreamingExt,
+    streaming_extension::{DeltaStreamingExt, StreamingExt},
 };
-use akri_discovery_utils::discovery::v0::{
-    discovery_handler_client::DiscoveryHandlerClient, Device, DiscoverRequest, DiscoverResponse,
+use akri_discovery_utils::{
+    discovery::{
+        v0::{
+            discovery_handler_client::DiscoveryHandlerClient, Device, DiscoverRequest,
+            DiscoverResponse,
+        },
+        v1,
+    },
+    security::{client_tls_config, get_tls_files, DISCOVERY_HANDLER_TLS_DOMAIN_NAME_LABEL},
 };
 use akri_shared::{
-    akri::configuration::KubeAkriConfig,
+    akri::{
+        configuration::{Configuration, KubeAkriConfig},
+        instance::{
+            get_instance_name_template_properties, render_instance_name_template, to_dns_label,
+            MAX_INSTANCE_DIGEST_LENGTH, MAX_INSTANCE_NAME_LENGTH,
+        },
+        AKRI_DEVICE_ID_ANNOTATION_NAME,
+    },
     k8s,
     os::env_var::{ActualEnvVarQuery, EnvVarQuery},
 };
@@ -27,25 +42,30 @@ use blake2::{
     digest::{Update, VariableOutput},
     VarBlake2b,
 };
-use log::{error, trace};
+use log::{error, trace, warn};
 #[cfg(test)]
 use mock_instant::Instant;
 #[cfg(test)]
 use mockall::{automock, predicate::*};
 #[cfg(not(test))]
 use std::time::Instant;
-use std::{collections::HashMap, convert::TryFrom, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    sync::Arc,
+};
 use tokio::sync::mpsc;
 use tonic::{
-    transport::{Endpoint, Uri},
-    Status,
+    transport::{Channel, Endpoint, Uri},
+    Code, Status,
 };
 
-/// StreamType provides a wrapper around the two different types of streams returned from embedded
-/// or embedded discovery handlers and ones running externally.
+/// StreamType provides a wrapper around the different types of streams returned from embedded
+/// discovery handlers and ones running externally, which may serve either version of the Discovery API.
 pub enum StreamType {
     Embedded(mpsc::Receiver<std::result::Result<DiscoverResponse, Status>>),
     External(tonic::Streaming<DiscoverResponse>),
+    ExternalV1(tonic::Streaming<v1::DiscoverResponse>),
 }
 
 /// A DiscoveryOperator is created for each Configuration that is applied to the cluster.
@@ -65,6 +85,9 @@ pub struct DiscoveryOperator {
     config: KubeAkriConfig,
     /// Map of Akri Instances discovered by this `DiscoveryOperator`
     instance_map: InstanceMap,
+    /// Names of the Instances of the devices discovered by this `DiscoveryOperator`, keyed by device id, so that a
+    /// device keeps its Instance name for as long as it is discovered or its Instance exists
+    instance_names: Arc<std::sync::Mutex<HashMap<String, String>>>,
 }
 
 #[cfg_attr(test, automock)]
@@ -78,6 +101,7 @@ impl DiscoveryOperator {
             discovery_handler_map,
             config,
             instance_map,
+            instance_names: Arc::new(std::sync::Mutex::new(HashMap::new())),
         }
     }
     /// Returns discovery_handler_map field. Allows the struct to be mocked.
@@ -141,27 +165,19 @@ impl DiscoveryOperator {
                 }
             }
             DiscoveryHandlerEndpoint::Uds(socket) => {
-                // Clone socket for closure which has static lifetime
-                let socket = socket.clone();
-                // We will ignore this dummy uri because UDS does not use it.
-                match Endpoint::try_from("dummy://[::]:50051")
-                    .unwrap()
-                    .connect_with_connector(tower::service_fn(move |_: Uri| {
-                        let endpoint = socket.clone();
-                        tokio::net::UnixStream::connect(endpoint)
-                    }))
-                    .await
-                {
+                match connect_to_uds_discovery_handler(socket).await {
                     Ok(channel) => {
                         trace!(
                             "get_stream - connecting to external {} discovery handler over UDS",
                             self.config.spec.discovery_handler.name
                         );
-                        let mut discovery_handler_client = DiscoveryHandlerClient::new(channel);
-                        match discovery_handler_client.discover(discover_request).await {
-                            Ok(device_update_receiver) => {
-                                Some(StreamType::External(device_update_receiver.into_inner()))
-                            }
+                        match discover_with_fallback(
+                            channel,
+                            &self.config.spec.discovery_handler.discovery_details,
+                        )
+                        .await
+                        {
+                            Ok(stream_type) => Some(stream_type),
                             Err(e) => {
                                 error!("get_stream - could not connect to DiscoveryHandler at endpoint {:?} with error {}", endpoint, e);
                                 None
@@ -175,16 +191,19 @@ impl DiscoveryOperator {
                 }
             }
             DiscoveryHandlerEndpoint::Network(addr) => {
-                match DiscoveryHandlerClient::connect(addr.clone()).await {
-                    Ok(mut discovery_handler_client) => {
+                match connect_to_network_discovery_handler(addr).await {
+                    Ok(channel) => {
                         trace!(
                             "get_stream - connecting to external {} discovery handler over network",
                             self.config.spec.discovery_handler.name
                         );
-                        match discovery_handler_client.discover(discover_request).await {
-                            Ok(device_update_receiver) => {
-                                Some(StreamType::External(device_update_receiver.into_inner()))
-                            }
+                        match discover_with_fallback(
+                            channel,
+                            &self.config.spec.discovery_handler.discovery_details,
+                        )
+                        .await
+                        {
+                            Ok(stream_type) => Some(stream_type),
                             Err(e) => {
                                 error!("get_stream - could not connect to DiscoveryHandler at endpoint {:?} with error {}", endpoint, e);
                                 None
@@ -200,7 +219,7 @@ impl DiscoveryOperator {
         }
     }
     /// Listens for new discovery responses and calls a function to handle the new discovery results.
-    /// Runs until notified to stop discovery.
+    /// Runs until notified to stop discovery or until discovery results cannot be handled.
     #[allow(dead_code)]
     pub async fn internal_do_discover<'a>(
         &'a self,
@@ -217,6 +236,7 @@ impl DiscoveryOperator {
             tokio::select! {
                 _ = stop_discovery_receiver.recv() => {
                     trace!("internal_do_discover - received message to stop discovery for endpoint {:?} serving protocol {}", dh_details.endpoint, discovery_operator.get_config().spec.discovery_handler.name);
+                    self.check_discovery_handler_health(&dh_details.endpoint)?;
                     break;
                 },
                 result = stream.get_message() => {
@@ -230,7 +250,7 @@ impl DiscoveryOperator {
                             Box::new(DevicePluginBuilder{}),
                         )
                         .await
-                        .unwrap();
+                        .map_err(handling_error_status)?;
                     } else {
                         error!("internal_do_discover - received result of type None. Should not happen.");
                         break;
@@ -242,6 +262,82 @@ impl DiscoveryOperator {
         Ok(())
     }
 
+    /// Listens for changes to discovered devices from a Discovery Handler that serves the v1 Discovery API.
+    /// Snapshots are handled like v0 discovery results, while other responses are handled by only looking at the devices
+    /// that changed. If a response is missed, changes are ignored until the next snapshot.
+    /// Runs until notified to stop discovery or until a response cannot be handled, in which case the stream is dropped
+    /// so that the Discovery Handler registers again and discovery restarts with a new snapshot.
+    pub async fn internal_do_discover_v1<'a>(
+        &'a self,
+        kube_interface: Arc<Box<dyn k8s::KubeInterface>>,
+        dh_details: &'a DiscoveryDetails,
+        stream: &'a mut dyn DeltaStreamingExt,
+    ) -> Result<(), Status> {
+        let stop_discovery_receiver: &mut tokio::sync::broadcast::Receiver<()> =
+            &mut dh_details.close_discovery_handler_connection.subscribe();
+        // Sequence number of the last applied response, which is None until a snapshot is received
+        let mut last_sequence_number: Option<u64> = None;
+        let mut visible_device_ids: HashSet<String> = HashSet::new();
+        loop {
+            // Wait for either new discovery results or a message to stop discovery
+            tokio::select! {
+                _ = stop_discovery_receiver.recv() => {
+                    trace!("internal_do_discover_v1 - received message to stop discovery for endpoint {:?} serving protocol {}", dh_details.endpoint, self.config.spec.discovery_handler.name);
+                    self.check_discovery_handler_health(&dh_details.endpoint)?;
+                    break;
+                },
+                result = stream.get_message() => {
+                    let message = result?;
+                    if let Some(response) = message {
+                        trace!("internal_do_discover_v1 - got discovery response {} with snapshot {}", response.sequence_number, response.snapshot);
+                        if response.snapshot {
+                            last_sequence_number = Some(response.sequence_number);
+                            visible_device_ids = response.added.iter().map(|device| device.id.clone()).collect();
+                            self.handle_discovery_results(
+                                kube_interface.clone(),
+                                response.added,
+                                dh_details.shared,
+                                Box::new(DevicePluginBuilder{}),
+                            )
+                            .await
+                            .map_err(handling_error_status)?;
+                        } else if last_sequence_number.map(|n| n + 1) == Some(response.sequence_number) {
+                            last_sequence_number = Some(response.sequence_number);
+                            for device in response.added.iter().chain(response.updated.iter()) {
+                                visible_device_ids.insert(device.id.clone());
+                            }
+                            for id in &response.removed {
+                                visible_device_ids.remove(id);
+                            }
+                            INSTANCE_COUNT_METRIC
+                                .with_label_values(&[&self.config.metadata.name, &dh_details.shared.to_string()])
+                                .set(visible_device_ids.len() as i64);
+                            let mut changed_devices = response.added;
+                            changed_devices.extend(response.updated);
+                            self.handle_discovery_deltas(
+                                kube_interface.clone(),
+                                changed_devices,
+                                response.removed,
+                                dh_details.shared,
+                                Box::new(DevicePluginBuilder{}),
+                            )
+                            .await
+                            .map_err(handling_error_status)?;
+                        } else {
+                            error!("internal_do_discover_v1 - got discovery response {} after {:?} ... ignoring changes until next snapshot", response.sequence_number, last_sequence_number);
+                            last_sequence_number = None;
+                        }
+                    } else {
+                        error!("internal_do_discover_v1 - received result of type None. Should not happen.");
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Sets the connectivity status of a discovery handler. If a discovery handler goes offline, mark_offline_or_deregister_discovery_handler should be used.
     pub fn set_discovery_handler_connectivity_status(
         &self,
@@ -333,6 +429,7 @@ impl DiscoveryOperator {
     /// For each new device, it creates a DevicePluginService.
     /// For each previously visible device that was no longer discovered, it calls a function that updates the InstanceConnectivityStatus
     /// of the instance or deletes it if it is a local device.
+    /// The broker properties of the Instances of shared devices that are still visible are refreshed.
     pub async fn handle_discovery_results(
         &self,
         kube_interface: Arc<Box<dyn k8s::KubeInterface>>,
@@ -345,25 +442,47 @@ impl DiscoveryOperator {
             self.config.metadata.name,
             discovery_results
         );
+        let instance_map = self.instance_map.lock().await.clone();
+        let instance_names = self
+            .get_instance_names(
+                kube_interface.clone(),
+                &discovery_results,
+                shared,
+                &instance_map,
+            )
+            .await;
         let currently_visible_instances: HashMap<String, Device> = discovery_results
             .iter()
             .map(|discovery_result| {
-                let id = generate_instance_digest(&discovery_result.id, shared);
-                let instance_name = get_device_instance_name(&id, &self.config.metadata.name);
-                (instance_name, discovery_result.clone())
+                (
+                    instance_names[&discovery_result.id].clone(),
+                    discovery_result.clone(),
+                )
             })
             .collect();
         INSTANCE_COUNT_METRIC
             .with_label_values(&[&self.config.metadata.name, &shared.to_string()])
             .set(currently_visible_instances.len() as i64);
-        // Update the connectivity status of instances and return list of visible instances that don't have Instance CRs
-        let instance_map = self.instance_map.lock().await.clone();
         // Find all visible instances that do not have Instance CRDs yet
-        let new_discovery_results: Vec<Device> = currently_visible_instances
+        let new_discovery_results: Vec<(String, Device)> = currently_visible_instances
             .iter()
             .filter(|(name, _)| !instance_map.contains_key(*name))
-            .map(|(_, p)| p.clone())
+            .map(|(name, p)| (name.clone(), p.clone()))
             .collect();
+        // Refresh the broker properties of Instances of shared devices that may have been rediscovered with new ones
+        if shared && self.config.spec.broker_properties_mount_path.is_some() {
+            for (instance_name, device) in &currently_visible_instances {
+                if let Some(instance_info) = instance_map.get(instance_name) {
+                    self.update_instance_broker_properties(
+                        kube_interface.clone(),
+                        instance_name,
+                        device,
+                        instance_info,
+                    )
+                    .await;
+                }
+            }
+        }
         self.update_instance_connectivity_status(
             kube_interface,
             currently_visible_instances,
@@ -373,9 +492,7 @@ impl DiscoveryOperator {
 
         // If there are newly visible instances associated with a Config, make a device plugin and Instance CR for them
         if !new_discovery_results.is_empty() {
-            for discovery_result in new_discovery_results {
-                let id = generate_instance_digest(&discovery_result.id, shared);
-                let instance_name = get_device_instance_name(&id, &self.config.metadata.name);
+            for (instance_name, discovery_result) in new_discovery_results {
                 trace!(
                     "handle_discovery_results - new instance {} came online",
                     instance_name
@@ -398,6 +515,210 @@ impl DiscoveryOperator {
         Ok(())
     }
 
+    /// Takes in the devices that were added or updated and the ids of the devices that were removed since the last
+    /// discovery response. For each changed device without an Instance, it creates a DevicePluginService.
+    /// Instances of changed devices are marked Online and Instances of removed devices are marked Offline or deleted
+    /// in the same way as in `update_instance_connectivity_status`. Like `handle_discovery_results`, every other
+    /// Offline Instance is checked too, so that it is deleted once its grace period has elapsed.
+    pub async fn handle_discovery_deltas(
+        &self,
+        kube_interface: Arc<Box<dyn k8s::KubeInterface>>,
+        changed_devices: Vec<Device>,
+        removed_device_ids: Vec<String>,
+        shared: bool,
+        device_plugin_builder: Box<dyn DevicePluginBuilderInterface>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        trace!(
+            "handle_discovery_deltas - for config {} with changed devices {:?} and removed devices {:?}",
+            self.config.metadata.name,
+            changed_devices,
+            removed_device_ids
+        );
+        let instance_map = self.instance_map.lock().await.clone();
+        let instance_names = self
+            .get_instance_names(
+                kube_interface.clone(),
+                &changed_devices,
+                shared,
+                &instance_map,
+            )
+            .await;
+        let changed_instances: HashSet<String> = instance_names.values().cloned().collect();
+        for device in changed_devices {
+            let instance_name = instance_names[&device.id].clone();
+            match instance_map.get(&instance_name) {
+                Some(instance_info) => {
+                    if shared && self.config.spec.broker_properties_mount_path.is_some() {
+                        self.update_instance_broker_properties(
+                            kube_interface.clone(),
+                            &instance_name,
+                            &device,
+                            instance_info,
+                        )
+                        .await;
+                    }
+                    self.mark_instance_visible(&instance_name, instance_info.clone())
+                        .await
+                }
+                None => {
+                    trace!(
+                        "handle_discovery_deltas - new instance {} came online",
+                        instance_name
+                    );
+                    if let Err(e) = device_plugin_builder
+                        .build_device_plugin(
+                            instance_name,
+                            &self.config,
+                            shared,
+                            self.instance_map.clone(),
+                            device,
+                        )
+                        .await
+                    {
+                        error!("handle_discovery_deltas - error {} building device plugin ... trying again on next snapshot", e);
+                    }
+                }
+            }
+        }
+        for device_id in removed_device_ids {
+            // The properties of removed devices are not known, so only a device that was named by this
+            // DiscoveryOperator can be named from a template
+            let known_instance_name = self.instance_names.lock().unwrap().get(&device_id).cloned();
+            let instance_name = known_instance_name.unwrap_or_else(|| {
+                let id = generate_instance_digest(
+                    &device_id,
+                    shared,
+                    self.config.spec.instance_digest_length,
+                );
+                get_device_instance_name(&id, &self.config.metadata.name)
+            });
+            if let Some(instance_info) = instance_map.get(&instance_name) {
+                self.mark_instance_not_visible(
+                    kube_interface.clone(),
+                    &instance_name,
+                    instance_info.clone(),
+                    shared,
+                )
+                .await?;
+            }
+        }
+        // Instances that went Offline earlier are not in this response, so check whether their grace period elapsed
+        let instance_map = self.instance_map.lock().await.clone();
+        for (instance_name, instance_info) in instance_map {
+            if !changed_instances.contains(&instance_name)
+                && matches!(
+                    instance_info.connectivity_status,
+                    InstanceConnectivityStatus::Offline(_)
+                )
+            {
+                self.mark_instance_not_visible(
+                    kube_interface.clone(),
+                    &instance_name,
+                    instance_info,
+                    shared,
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the names of the Instances of the given devices, keyed by device id. Devices that were already named
+    /// keep their names and names of devices that are neither given nor have an Instance anymore are forgotten.
+    /// A new device gets the first of its candidate names (see `generate_instance_name_candidates`) that is neither
+    /// taken by another device named by this `DiscoveryOperator` nor by an Instance whose `akri.sh/device-id`
+    /// annotation records another device.
+    async fn get_instance_names(
+        &self,
+        kube_interface: Arc<Box<dyn k8s::KubeInterface>>,
+        devices: &[Device],
+        shared: bool,
+        instance_map: &HashMap<String, InstanceInfo>,
+    ) -> HashMap<String, String> {
+        let named_devices = {
+            let mut named_devices = self.instance_names.lock().unwrap();
+            named_devices.retain(|id, name| {
+                instance_map.contains_key(name) || devices.iter().any(|device| &device.id == id)
+            });
+            named_devices.clone()
+        };
+        // Device id that each name is taken by
+        let mut taken_names: HashMap<String, String> = named_devices
+            .iter()
+            .map(|(id, name)| (name.clone(), id.clone()))
+            .collect();
+        let mut instance_names = HashMap::new();
+        for device in devices {
+            if let Some(name) = named_devices.get(&device.id) {
+                instance_names.insert(device.id.clone(), name.clone());
+                continue;
+            }
+            let candidates = generate_instance_name_candidates(
+                &self.config.metadata.name,
+                &self.config.spec,
+                device,
+                shared,
+            );
+            let mut name = None;
+            for candidate in &candidates {
+                if taken_names
+                    .get(candidate)
+                    .map_or(false, |taken_by| taken_by != &device.id)
+                {
+                    continue;
+                }
+                if !instance_map.contains_key(candidate)
+                    && self
+                        .is_instance_name_taken(kube_interface.clone(), candidate, &device.id)
+                        .await
+                {
+                    continue;
+                }
+                name = Some(candidate.clone());
+                break;
+            }
+            let name = name.unwrap_or_else(|| {
+                let name = candidates.last().unwrap().clone();
+                warn!(
+                    "get_instance_names - every name of device {} is taken by another device, naming its Instance {}",
+                    device.id, name
+                );
+                name
+            });
+            taken_names.insert(name.clone(), device.id.clone());
+            instance_names.insert(device.id.clone(), name);
+        }
+        self.instance_names
+            .lock()
+            .unwrap()
+            .extend(instance_names.clone());
+        instance_names
+    }
+
+    /// Checks whether an Instance with the given name exists for a device other than the one with the given id.
+    /// Instances created before the `akri.sh/device-id` annotation was added are assumed to belong to the device.
+    async fn is_instance_name_taken(
+        &self,
+        kube_interface: Arc<Box<dyn k8s::KubeInterface>>,
+        instance_name: &str,
+        device_id: &str,
+    ) -> bool {
+        match kube_interface
+            .find_instance(
+                instance_name,
+                self.config.metadata.namespace.as_ref().unwrap(),
+            )
+            .await
+        {
+            Ok(instance) => instance
+                .metadata
+                .annotations
+                .get(AKRI_DEVICE_ID_ANNOTATION_NAME)
+                .map_or(false, |id| id != device_id),
+            Err(_) => false,
+        }
+    }
+
     /// Takes in a list of currently visible instances and either updates an Instance's InstanceConnectivityStatus or deletes an Instance.
     /// If a non-local/network based device is not longer visible it's InstanceConnectivityStatus is changed to Offline(time now).
     /// The associated DevicePluginService checks its InstanceConnectivityStatus before sending a response back to kubelet
@@ -417,100 +738,204 @@ impl DiscoveryOperator {
                 instance
             );
             if currently_visible_instances.contains_key(&instance) {
-                let connectivity_status = instance_info.connectivity_status;
-                // If instance is visible, make sure connectivity status is (updated to be) Online
-                if let InstanceConnectivityStatus::Offline(_instant) = connectivity_status {
-                    trace!(
-                        "update_instance_connectivity_status - instance {} that was temporarily offline is back online",
-                        instance
-                    );
-                    let list_and_watch_message_sender = instance_info.list_and_watch_message_sender;
+                self.mark_instance_visible(&instance, instance_info).await;
+            } else {
+                self.mark_instance_not_visible(
+                    kube_interface.clone(),
+                    &instance,
+                    instance_info,
+                    shared,
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Helpers shared between handling full lists of discovered devices and handling changes to discovered devices.
+impl DiscoveryOperator {
+    /// Returns an error if the Discovery Handler at the endpoint has been marked Offline for failing a health check,
+    /// which is why the connection with it was told to close.
+    fn check_discovery_handler_health(
+        &self,
+        endpoint: &DiscoveryHandlerEndpoint,
+    ) -> Result<(), Status> {
+        let failed_health_check = self
+            .discovery_handler_map
+            .lock()
+            .unwrap()
+            .get(&self.config.spec.discovery_handler.name)
+            .and_then(|discovery_handler_details_map| discovery_handler_details_map.get(endpoint))
+            .map(|dh_details| {
+                matches!(
+                    dh_details.connectivity_status,
+                    DiscoveryHandlerStatus::Offline(_)
+                )
+            })
+            .unwrap_or(false);
+        if failed_health_check {
+            Err(Status::unavailable(DISCOVERY_HANDLER_UNHEALTHY_MESSAGE))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Makes sure the connectivity status of a visible Instance is (updated to be) Online.
+    async fn mark_instance_visible(&self, instance: &str, instance_info: InstanceInfo) {
+        let connectivity_status = instance_info.connectivity_status;
+        if let InstanceConnectivityStatus::Offline(_instant) = connectivity_status {
+            trace!(
+                "mark_instance_visible - instance {} that was temporarily offline is back online",
+                instance
+            );
+            let list_and_watch_message_sender = instance_info.list_and_watch_message_sender;
+            let updated_instance_info = InstanceInfo {
+                connectivity_status: InstanceConnectivityStatus::Online,
+                list_and_watch_message_sender: list_and_watch_message_sender.clone(),
+            };
+            self.instance_map
+                .lock()
+                .await
+                .insert(instance.to_string(), updated_instance_info);
+            // Signal list_and_watch to update kubelet that the devices are healthy.
+            list_and_watch_message_sender
+                .send(device_plugin_service::ListAndWatchMessageKind::Continue)
+                .unwrap();
+        } else {
+            trace!("mark_instance_visible - instance {} still online", instance);
+        }
+    }
+
+    /// Updates the broker properties of the Instance of a shared device that was rediscovered with different
+    /// properties, such as a new endpoint, and signals its `DevicePluginService` to refresh the broker properties that
+    /// it mounted into brokers. Errors are logged, since the next change to the device retries the update.
+    async fn update_instance_broker_properties(
+        &self,
+        kube_interface: Arc<Box<dyn k8s::KubeInterface>>,
+        instance_name: &str,
+        device: &Device,
+        instance_info: &InstanceInfo,
+    ) {
+        let broker_properties = device_plugin_service::get_all_broker_properties(
+            &self.config.spec.broker_properties,
+            &device.properties,
+        );
+        let namespace = self.config.metadata.namespace.as_ref().unwrap();
+        let mut instance = match kube_interface.find_instance(instance_name, namespace).await {
+            Ok(instance) => instance,
+            Err(e) => {
+                error!(
+                    "update_instance_broker_properties - could not find Instance {}: {}",
+                    instance_name, e
+                );
+                return;
+            }
+        };
+        if instance.spec.broker_properties == broker_properties {
+            return;
+        }
+        trace!(
+            "update_instance_broker_properties - properties of Instance {} changed",
+            instance_name
+        );
+        instance.spec.broker_properties = broker_properties;
+        if let Err(e) = kube_interface
+            .update_instance(&instance.spec, instance_name, namespace)
+            .await
+        {
+            error!(
+                "update_instance_broker_properties - could not update Instance {}: {}",
+                instance_name, e
+            );
+            return;
+        }
+        // The DevicePluginService may already have ended, in which case there are no brokers to refresh
+        let _ = instance_info
+            .list_and_watch_message_sender
+            .send(device_plugin_service::ListAndWatchMessageKind::Continue);
+    }
+
+    /// Handles an Instance that is no longer visible:
+    /// If the instance is local, remove it
+    /// If the instance is not local
+    /// // If it has not already been labeled offline, label it
+    /// // If the instance has already been labeled offline
+    /// // remove instance from map if grace period has elapsed without the instance coming back online
+    async fn mark_instance_not_visible(
+        &self,
+        kube_interface: Arc<Box<dyn k8s::KubeInterface>>,
+        instance: &str,
+        instance_info: InstanceInfo,
+        shared: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let mut remove_instance = false;
+        match instance_info.connectivity_status {
+            InstanceConnectivityStatus::Online => {
+                if !shared {
+                    remove_instance = true;
+                } else {
+                    let sender = instance_info.list_and_watch_message_sender.clone();
                     let updated_instance_info = InstanceInfo {
-                        connectivity_status: InstanceConnectivityStatus::Online,
-                        list_and_watch_message_sender: list_and_watch_message_sender.clone(),
+                        connectivity_status: InstanceConnectivityStatus::Offline(Instant::now()),
+                        list_and_watch_message_sender: instance_info
+                            .list_and_watch_message_sender
+                            .clone(),
                     };
                     self.instance_map
                         .lock()
                         .await
-                        .insert(instance.clone(), updated_instance_info);
-                    // Signal list_and_watch to update kubelet that the devices are healthy.
-                    list_and_watch_message_sender
-                        .send(device_plugin_service::ListAndWatchMessageKind::Continue)
-                        .unwrap();
-                } else {
+                        .insert(instance.to_string(), updated_instance_info);
                     trace!(
-                        "update_instance_connectivity_status - instance {} still online",
+                        "mark_instance_not_visible - instance {} went offline ... starting timer and forcing list_and_watch to continue",
                         instance
                     );
+                    sender
+                        .send(device_plugin_service::ListAndWatchMessageKind::Continue)
+                        .unwrap();
                 }
-            } else {
-                // If the instance is not visible:
-                // // If the instance is local, remove it
-                // // If the instance is not local
-                // // // If it has not already been labeled offline, label it
-                // // // If the instance has already been labeled offline
-                // // // remove instance from map if grace period has elapsed without the instance coming back online
-                let mut remove_instance = false;
-                match instance_info.connectivity_status {
-                    InstanceConnectivityStatus::Online => {
-                        if !shared {
-                            remove_instance = true;
-                        } else {
-                            let sender = instance_info.list_and_watch_message_sender.clone();
-                            let updated_instance_info = InstanceInfo {
-                                connectivity_status: InstanceConnectivityStatus::Offline(
-                                    Instant::now(),
-                                ),
-                                list_and_watch_message_sender: instance_info
-                                    .list_and_watch_message_sender
-                                    .clone(),
-                            };
-                            self.instance_map
-                                .lock()
-                                .await
-                                .insert(instance.clone(), updated_instance_info);
-                            trace!(
-                                "update_instance_connectivity_status - instance {} went offline ... starting timer and forcing list_and_watch to continue",
-                                instance
-                            );
-                            sender
-                                .send(device_plugin_service::ListAndWatchMessageKind::Continue)
-                                .unwrap();
-                        }
-                    }
-                    InstanceConnectivityStatus::Offline(instant) => {
-                        let time_offline = instant.elapsed().as_secs();
-                        // If instance has been offline for longer than the grace period, terminate the associated device plugin
-                        if time_offline >= SHARED_INSTANCE_OFFLINE_GRACE_PERIOD_SECS {
-                            remove_instance = true;
-                        }
-                    }
-                }
-                if remove_instance {
-                    trace!("update_instance_connectivity_status - instance {} has been offline too long ... terminating device plugin", instance);
-                    device_plugin_service::terminate_device_plugin_service(
-                        &instance,
-                        self.instance_map.clone(),
-                    )
-                    .await
-                    .unwrap();
-                    k8s::try_delete_instance(
-                        (*kube_interface).as_ref(),
-                        &instance,
-                        self.config.metadata.namespace.as_ref().unwrap(),
-                    )
-                    .await
-                    .unwrap();
+            }
+            InstanceConnectivityStatus::Offline(instant) => {
+                let time_offline = instant.elapsed().as_secs();
+                // If instance has been offline for longer than the grace period, terminate the associated device plugin
+                if time_offline >= SHARED_INSTANCE_OFFLINE_GRACE_PERIOD_SECS {
+                    remove_instance = true;
                 }
             }
         }
+        if remove_instance {
+            trace!("mark_instance_not_visible - instance {} has been offline too long ... terminating device plugin", instance);
+            device_plugin_service::terminate_device_plugin_service(
+                instance,
+                self.instance_map.clone(),
+            )
+            .await
+            .unwrap();
+            k8s::try_delete_instance(
+                (*kube_interface).as_ref(),
+                instance,
+                self.config.metadata.namespace.as_ref().unwrap(),
+            )
+            .await?;
+        }
         Ok(())
     }
 }
 
+/// Returns the status with which discovery over a stream ends when its discovery results could not be handled
+fn handling_error_status(e: Box<dyn std::error::Error + Send + Sync + 'static>) -> Status {
+    error!(
+        "handling_error_status - failed to handle discovery results: {}",
+        e
+    );
+    Status::internal(format!("failed to handle discovery results: {}", e))
+}
+
 pub mod start_discovery {
-    use super::super::registration::{
-        DiscoveryDetails, DiscoveryHandlerEndpoint, DiscoveryHandlerStatus,
+    use super::super::{
+        constants::DISCOVERY_HANDLER_UNHEALTHY_MESSAGE,
+        discovery_details_validation::validate_and_record_discovery_details,
+        registration::{DiscoveryDetails, DiscoveryHandlerEndpoint, DiscoveryHandlerStatus},
     };
     // Use this `mockall` macro to automate importing a mock type in test mode, or a real type otherwise.
     #[double]
@@ -701,6 +1126,16 @@ pub mod start_discovery {
         endpoint: &'a DiscoveryHandlerEndpoint,
         dh_details: &'a DiscoveryDetails,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        // Only use the Discovery Handler if the Configuration's discovery details are valid for it
+        if !validate_and_record_discovery_details(
+            (*kube_interface).as_ref(),
+            &discovery_operator.get_config(),
+            dh_details,
+        )
+        .await
+        {
+            return Ok(());
+        }
         loop {
             let deregistered;
             match discovery_operator.get_stream(&endpoint).await {
@@ -710,74 +1145,86 @@ pub mod start_discovery {
                         &endpoint,
                         DiscoveryHandlerStatus::Active,
                     );
-                    match stream_type {
+                    let external_discovery_result = match stream_type {
                         StreamType::External(mut stream) => {
-                            match discovery_operator
+                            discovery_operator
                                 .internal_do_discover(
                                     kube_interface.clone(),
                                     &dh_details,
                                     &mut stream,
                                 )
                                 .await
-                            {
-                                Ok(_) => {
-                                    discovery_operator.set_discovery_handler_connectivity_status(
-                                        &endpoint,
-                                        DiscoveryHandlerStatus::Waiting,
-                                    );
-                                    break;
-                                }
-                                Err(status) => {
-                                    if status.message().contains("broken pipe") {
-                                        // Mark all associated instances as offline
-                                        error!("do_discover_on_discovery_handler - connection with Discovery Handler dropped with status {:?}. Marking all instances offline.", status);
-                                        discovery_operator
-                                            .update_instance_connectivity_status(
-                                                kube_interface.clone(),
-                                                std::collections::HashMap::new(),
-                                                dh_details.shared,
-                                            )
-                                            .await?;
-                                        deregistered = discovery_operator
-                                            .mark_offline_or_deregister_discovery_handler(&endpoint)
-                                            .await
-                                            .unwrap();
-                                    } else {
-                                        trace!("do_discover_on_discovery_handler - Discovery Handlers returned error status {}. Marking all instances offline.", status);
-                                        // TODO: Possibly mark config as invalid
-                                        // Mark all associated instances as offline by declaring no visible instances
-                                        discovery_operator
-                                            .update_instance_connectivity_status(
-                                                kube_interface.clone(),
-                                                std::collections::HashMap::new(),
-                                                dh_details.shared,
-                                            )
-                                            .await?;
-                                        discovery_operator
-                                            .set_discovery_handler_connectivity_status(
-                                                &endpoint,
-                                                DiscoveryHandlerStatus::Waiting,
-                                            );
-                                        break;
-                                    }
-                                }
-                            }
                         }
-                        StreamType::Embedded(mut stream) => {
+                        StreamType::ExternalV1(mut stream) => {
                             discovery_operator
+                                .internal_do_discover_v1(
+                                    kube_interface.clone(),
+                                    &dh_details,
+                                    &mut stream,
+                                )
+                                .await
+                        }
+                        StreamType::Embedded(mut stream) => {
+                            if let Err(status) = discovery_operator
                                 .internal_do_discover(
                                     kube_interface.clone(),
                                     &dh_details,
                                     &mut stream,
                                 )
                                 .await
-                                .unwrap();
+                            {
+                                error!("do_discover_on_discovery_handler - discovery with embedded Discovery Handler ended with status {:?}", status);
+                            }
+                            discovery_operator.set_discovery_handler_connectivity_status(
+                                &endpoint,
+                                DiscoveryHandlerStatus::Waiting,
+                            );
+                            break;
+                        }
+                    };
+                    match external_discovery_result {
+                        Ok(_) => {
                             discovery_operator.set_discovery_handler_connectivity_status(
                                 &endpoint,
                                 DiscoveryHandlerStatus::Waiting,
                             );
                             break;
                         }
+                        Err(status) => {
+                            if status.message().contains("broken pipe")
+                                || status.message() == DISCOVERY_HANDLER_UNHEALTHY_MESSAGE
+                            {
+                                // Mark all associated instances as offline
+                                error!("do_discover_on_discovery_handler - connection with Discovery Handler dropped with status {:?}. Marking all instances offline.", status);
+                                discovery_operator
+                                    .update_instance_connectivity_status(
+                                        kube_interface.clone(),
+                                        std::collections::HashMap::new(),
+                                        dh_details.shared,
+                                    )
+                                    .await?;
+                                deregistered = discovery_operator
+                                    .mark_offline_or_deregister_discovery_handler(&endpoint)
+                                    .await
+                                    .unwrap();
+                            } else {
+                                trace!("do_discover_on_discovery_handler - Discovery Handlers returned error status {}. Marking all instances offline.", status);
+                                // TODO: Possibly mark config as invalid
+                                // Mark all associated instances as offline by declaring no visible instances
+                                discovery_operator
+                                    .update_instance_connectivity_status(
+                                        kube_interface.clone(),
+                                        std::collections::HashMap::new(),
+                                        dh_details.shared,
+                                    )
+                                    .await?;
+                                discovery_operator.set_discovery_handler_connectivity_status(
+                                    &endpoint,
+                                    DiscoveryHandlerStatus::Waiting,
+                                );
+                                break;
+                            }
+                        }
                     }
                 }
                 None => {
@@ -813,19 +1260,79 @@ pub mod start_discovery {
     }
 }
 
+/// Calls discover on an external Discovery Handler, preferring the v1 Discovery API, which only sends changes to
+/// discovered devices. Falls back to the v0 Discovery API if the Discovery Handler does not serve v1.
+async fn discover_with_fallback(
+    channel: Channel,
+    discovery_details: &str,
+) -> Result<StreamType, Status> {
+    let mut discovery_handler_client =
+        v1::discovery_handler_client::DiscoveryHandlerClient::new(channel.clone());
+    let discover_request = tonic::Request::new(v1::DiscoverRequest {
+        discovery_details: discovery_details.to_string(),
+    });
+    match discovery_handler_client.discover(discover_request).await {
+        Ok(device_update_receiver) => {
+            Ok(StreamType::ExternalV1(device_update_receiver.into_inner()))
+        }
+        Err(status) if status.code() == Code::Unimplemented => {
+            trace!("discover_with_fallback - discovery handler does not serve the v1 Discovery API ... falling back to v0");
+            let mut discovery_handler_client = DiscoveryHandlerClient::new(channel);
+            let discover_request = tonic::Request::new(DiscoverRequest {
+                discovery_details: discovery_details.to_string(),
+            });
+            let device_update_receiver =
+                discovery_handler_client.discover(discover_request).await?;
+            Ok(StreamType::External(device_update_receiver.into_inner()))
+        }
+        Err(status) => Err(status),
+    }
+}
+
+/// Connects to a Discovery Handler served at a unix domain socket
+pub async fn connect_to_uds_discovery_handler(
+    socket: &str,
+) -> Result<Channel, tonic::transport::Error> {
+    // Clone socket for closure which has static lifetime
+    let socket = socket.to_string();
+    // We will ignore this dummy uri because UDS does not use it.
+    Endpoint::try_from("dummy://[::]:50051")
+        .unwrap()
+        .connect_with_connector(tower::service_fn(move |_: Uri| {
+            let endpoint = socket.clone();
+            tokio::net::UnixStream::connect(endpoint)
+        }))
+        .await
+}
+
+/// Connects to a Discovery Handler served at a network endpoint. If the Agent has been given certificates via
+/// `DISCOVERY_HANDLER_TLS_DIRECTORY`, the connection uses mTLS.
+pub async fn connect_to_network_discovery_handler(
+    addr: &str,
+) -> Result<Channel, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut endpoint = Endpoint::from_shared(addr.to_string())?;
+    if let Some(tls_files) = get_tls_files()? {
+        let domain_name = std::env::var(DISCOVERY_HANDLER_TLS_DOMAIN_NAME_LABEL).ok();
+        endpoint = endpoint.tls_config(client_tls_config(&tls_files, domain_name.as_deref()));
+    }
+    Ok(endpoint.connect().await?)
+}
+
 /// Generates an digest of an Instance's id. There should be a unique digest and Instance for each discovered device.
 /// This means that the id of non-local devices that could be visible to multiple nodes should always resolve
 /// to the same instance name (which is suffixed with this digest).
 /// However, local devices' Instances should have unique hashes even if they have the same id.
 /// To ensure this, the node's name is added to the id before it is hashed.
-pub fn generate_instance_digest(id_to_digest: &str, shared: bool) -> String {
+/// The digest is `digest_length` bytes long, each of which is two hexadecimal characters in the name.
+pub fn generate_instance_digest(id_to_digest: &str, shared: bool, digest_length: usize) -> String {
     let env_var_query = ActualEnvVarQuery {};
-    inner_generate_instance_digest(id_to_digest, shared, &env_var_query)
+    inner_generate_instance_digest(id_to_digest, shared, digest_length, &env_var_query)
 }
 
 pub fn inner_generate_instance_digest(
     id_to_digest: &str,
     shared: bool,
+    digest_length: usize,
     query: &impl EnvVarQuery,
 ) -> String {
     let mut id_to_digest = id_to_digest.to_string();
@@ -838,7 +1345,7 @@ pub fn inner_generate_instance_digest(
         );
     }
     let mut digest = String::new();
-    let mut hasher = VarBlake2b::new(3).unwrap();
+    let mut hasher = VarBlake2b::new(digest_length.max(1).min(MAX_INSTANCE_DIGEST_LENGTH)).unwrap();
     hasher.update(id_to_digest);
     hasher.finalize_variable(|var| {
         digest = var
@@ -850,6 +1357,108 @@ pub fn inner_generate_instance_digest(
     digest
 }
 
+/// Generates the candidate names of the Instance of a discovered device, from the shortest to the longest digest of its
+/// id (see `generate_instance_name`). The first candidate is the name that the device's Instance gets on every node;
+/// the longer ones are only used when it is taken by another device's Instance.
+pub fn generate_instance_name_candidates(
+    config_name: &str,
+    config: &Configuration,
+    device: &Device,
+    shared: bool,
+) -> Vec<String> {
+    let env_var_query = ActualEnvVarQuery {};
+    inner_generate_instance_name_candidates(config_name, config, device, shared, &env_var_query)
+}
+
+pub fn inner_generate_instance_name_candidates(
+    config_name: &str,
+    config: &Configuration,
+    device: &Device,
+    shared: bool,
+    query: &impl EnvVarQuery,
+) -> Vec<String> {
+    (config
+        .instance_digest_length
+        .max(1)
+        .min(MAX_INSTANCE_DIGEST_LENGTH)..=MAX_INSTANCE_DIGEST_LENGTH)
+        .map(|digest_length| {
+            generate_instance_name(config_name, config, device, shared, digest_length, query)
+        })
+        .collect()
+}
+
+/// Names the Instance of a device from the Configuration's `instanceNameTemplate` followed by a digest of the device's
+/// id, falling back to the digest alone. Since the digest is always part of the name, a device's name only depends on
+/// the device itself and not on which other devices were discovered with it. The name of the node is added before the
+/// digest of templated names of unshared devices unless the template already references it as `{{AGENT_NODE_NAME}}`.
+/// The rendered template, or the Configuration's name if there is no template, is cut so that the name fits in a
+/// DNS-1123 label with its suffix.
+fn generate_instance_name(
+    config_name: &str,
+    config: &Configuration,
+    device: &Device,
+    shared: bool,
+    digest_length: usize,
+    query: &impl EnvVarQuery,
+) -> String {
+    let digest = inner_generate_instance_digest(&device.id, shared, digest_length, query);
+    if let Some(template) = &config.instance_name_template {
+        let lookup = |property: &str| match device.properties.get(property) {
+            Some(value) => Some(value.clone()),
+            None if property == "AGENT_NODE_NAME" => query.get_env_var("AGENT_NODE_NAME").ok(),
+            None => None,
+        };
+        match render_instance_name_template(template, lookup) {
+            Some(rendered) => {
+                let references_node_name = get_instance_name_template_properties(template)
+                    .map_or(false, |properties| {
+                        properties
+                            .iter()
+                            .any(|property| property == "AGENT_NODE_NAME")
+                    });
+                let mut suffix = digest;
+                if !shared && !references_node_name {
+                    if let Some(node_name) = lookup("AGENT_NODE_NAME") {
+                        // Leave at least half of the name to the rendered template
+                        let node_label = to_dns_label(
+                            &node_name,
+                            (MAX_INSTANCE_NAME_LENGTH / 2).saturating_sub(suffix.len() + 1),
+                        );
+                        if !node_label.is_empty() {
+                            suffix = format!("{}-{}", node_label, suffix);
+                        }
+                    }
+                }
+                let name = get_device_instance_name(&rendered, config_name);
+                return format!(
+                    "{}-{}",
+                    to_dns_label(
+                        &name,
+                        MAX_INSTANCE_NAME_LENGTH.saturating_sub(suffix.len() + 1)
+                    ),
+                    suffix
+                );
+            }
+            None => warn!(
+                "generate_instance_name - device {} does not have every property referenced by instanceNameTemplate {}, naming its Instance with a digest of its id",
+                device.id, template
+            ),
+        }
+    }
+    let name = get_device_instance_name(&digest, config_name);
+    if name.len() <= MAX_INSTANCE_NAME_LENGTH {
+        return name;
+    }
+    format!(
+        "{}-{}",
+        to_dns_label(
+            config_name,
+            MAX_INSTANCE_NAME_LENGTH.saturating_sub(digest.len() + 1)
+        ),
+        digest
+    )
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::super::{
@@ -859,10 +1468,15 @@ pub mod tests {
         },
     };
     use super::*;
-    use akri_discovery_utils::discovery::mock_discovery_handler;
+    use akri_discovery_utils::discovery::{
+        mock_discovery_handler, v0::discovery_handler_server::DiscoveryHandlerServer,
+    };
     use akri_shared::{
-        akri::configuration::KubeAkriConfig, k8s::MockKubeInterface, os::env_var::MockEnvVarQuery,
+        akri::{configuration::KubeAkriConfig, instance::KubeAkriInstance},
+        k8s::MockKubeInterface,
+        os::env_var::MockEnvVarQuery,
     };
+    use futures::stream::TryStreamExt;
     use mock_instant::{Instant, MockClock};
     use mockall::Sequence;
     use std::time::Duration;
@@ -989,6 +1603,7 @@ pub mod tests {
             shared,
             close_discovery_handler_connection: close_discovery_handler_connection.clone(),
             connectivity_status: DiscoveryHandlerStatus::Waiting,
+            discovery_details_schema: None,
         }
     }
 
@@ -1001,39 +1616,318 @@ pub mod tests {
             discovery_handler_map.clone(),
         );
 
-        // Build discovery operator
+        // Build discovery operator
+        let path_to_config = "../test/yaml/config-a.yaml";
+        let config_yaml = std::fs::read_to_string(path_to_config).expect("Unable to read file");
+        let config: KubeAkriConfig = serde_yaml::from_str(&config_yaml).unwrap();
+        let discovery_operator = create_mock_discovery_operator(
+            discovery_handler_map.clone(),
+            config,
+            Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+        );
+        (discovery_operator, discovery_handler_map)
+    }
+
+    #[test]
+    fn test_generate_instance_digest() {
+        let mut mock_env_var_a = MockEnvVarQuery::new();
+        mock_env_var_a
+            .expect_get_env_var()
+            .returning(|_| Ok("node-a".to_string()));
+        let id = "video1";
+        let first_unshared_video_digest =
+            inner_generate_instance_digest(id, false, 3, &mock_env_var_a);
+        let first_shared_video_digest =
+            inner_generate_instance_digest(id, true, 3, &mock_env_var_a);
+        let mut mock_env_var_b = MockEnvVarQuery::new();
+        mock_env_var_b
+            .expect_get_env_var()
+            .returning(|_| Ok("node-b".to_string()));
+        let second_unshared_video_digest =
+            inner_generate_instance_digest(id, false, 3, &mock_env_var_b);
+        let second_shared_video_digest =
+            inner_generate_instance_digest(id, true, 3, &mock_env_var_b);
+        // unshared instances visible to different nodes should NOT have the same digest
+        assert_ne!(first_unshared_video_digest, second_unshared_video_digest);
+        // shared instances visible to different nodes should have the same digest
+        assert_eq!(first_shared_video_digest, second_shared_video_digest);
+    }
+
+    #[test]
+    fn test_generate_instance_digest_length() {
+        let mock_env_var = MockEnvVarQuery::new();
+        assert_eq!(
+            inner_generate_instance_digest("video1", true, 3, &mock_env_var).len(),
+            6
+        );
+        assert_eq!(
+            inner_generate_instance_digest("video1", true, 8, &mock_env_var).len(),
+            16
+        );
+        // Lengths are limited to the supported range
+        assert_eq!(
+            inner_generate_instance_digest("video1", true, 0, &mock_env_var).len(),
+            2
+        );
+        assert_eq!(
+            inner_generate_instance_digest("video1", true, 64, &mock_env_var).len(),
+            MAX_INSTANCE_DIGEST_LENGTH * 2
+        );
+    }
+
+    fn create_device_with_properties(id: &str, properties: &[(&str, &str)]) -> Device {
+        Device {
+            id: id.to_string(),
+            properties: properties
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            mounts: Vec::default(),
+            device_specs: Vec::default(),
+        }
+    }
+
+    fn create_config_with_template(template: Option<&str>) -> Configuration {
+        let mut config: Configuration =
+            serde_yaml::from_str("discoveryHandler:\n  name: onvif\n").unwrap();
+        config.instance_name_template = template.map(|template| template.to_string());
+        config
+    }
+
+    fn get_kube_not_found_error() -> kube::Error {
+        // Mock error thrown when instance not found
+        kube::Error::Api(kube::ErrorResponse {
+            status: "Failure".to_string(),
+            message: "instances.akri.sh \"akri-blah-901a7b\" not found".to_string(),
+            reason: "NotFound".to_string(),
+            code: k8s::ERROR_NOT_FOUND,
+        })
+    }
+
+    #[test]
+    fn test_generate_instance_name_candidates_from_template() {
+        let mut mock_env_var = MockEnvVarQuery::new();
+        mock_env_var
+            .expect_get_env_var()
+            .returning(|_| Ok("Node-A".to_string()));
+        let config = create_config_with_template(Some("cam-{{ONVIF_DEVICE_MAC_ADDRESS}}"));
+        let device = create_device_with_properties(
+            "10.0.0.1",
+            &[("ONVIF_DEVICE_MAC_ADDRESS", "AA:BB:CC:00:11:22")],
+        );
+        let digest = |digest_length: usize, shared: bool| {
+            inner_generate_instance_digest("10.0.0.1", shared, digest_length, &mock_env_var)
+        };
+        let candidates =
+            inner_generate_instance_name_candidates("onvif", &config, &device, true, &mock_env_var);
+        // Templated names always end with a digest of the device's id, from the default to the longest one
+        assert_eq!(candidates.len(), MAX_INSTANCE_DIGEST_LENGTH - 2);
+        assert_eq!(
+            candidates[0],
+            format!("onvif-cam-aa-bb-cc-00-11-22-{}", digest(3, true))
+        );
+        assert_eq!(
+            candidates[1],
+            format!("onvif-cam-aa-bb-cc-00-11-22-{}", digest(4, true))
+        );
+        assert!(candidates
+            .iter()
+            .all(|candidate| candidate.len() <= MAX_INSTANCE_NAME_LENGTH));
+
+        // Devices without the referenced property are named with a digest of their id
+        let device_without_property = create_device_with_properties("10.0.0.2", &[]);
+        assert_eq!(
+            inner_generate_instance_name_candidates(
+                "onvif",
+                &config,
+                &device_without_property,
+                true,
+                &mock_env_var
+            )[0],
+            format!(
+                "onvif-{}",
+                inner_generate_instance_digest("10.0.0.2", true, 3, &mock_env_var)
+            )
+        );
+
+        // The node's name is added to names of unshared devices, unless the template references it
+        assert_eq!(
+            inner_generate_instance_name_candidates(
+                "onvif",
+                &config,
+                &device,
+                false,
+                &mock_env_var
+            )[0],
+            format!("onvif-cam-aa-bb-cc-00-11-22-node-a-{}", digest(3, false))
+        );
+        let config = create_config_with_template(Some("{{AGENT_NODE_NAME}}-cam"));
+        assert_eq!(
+            inner_generate_instance_name_candidates(
+                "onvif",
+                &config,
+                &device,
+                false,
+                &mock_env_var
+            )[0],
+            format!("onvif-node-a-cam-{}", digest(3, false))
+        );
+    }
+
+    #[test]
+    fn test_generate_instance_name_candidates_truncates_template() {
+        let mut mock_env_var = MockEnvVarQuery::new();
+        mock_env_var
+            .expect_get_env_var()
+            .returning(|_| Ok("node-a".to_string()));
+        let config = create_config_with_template(Some("{{MODEL}}"));
+        let model = "a".repeat(100);
+        let device = create_device_with_properties("10.0.0.1", &[("MODEL", model.as_str())]);
+        for shared in &[true, false] {
+            let candidates = inner_generate_instance_name_candidates(
+                "onvif",
+                &config,
+                &device,
+                *shared,
+                &mock_env_var,
+            );
+            for (digest_length, candidate) in (3..=MAX_INSTANCE_DIGEST_LENGTH).zip(&candidates) {
+                let digest = inner_generate_instance_digest(
+                    "10.0.0.1",
+                    *shared,
+                    digest_length,
+                    &mock_env_var,
+                );
+                let suffix = if *shared {
+                    format!("-{}", digest)
+                } else {
+                    format!("-node-a-{}", digest)
+                };
+                // The rendered template is cut rather than the node's name or the digest
+                assert!(candidate.len() <= MAX_INSTANCE_NAME_LENGTH);
+                assert!(candidate.starts_with("onvif-aaa"));
+                assert!(candidate.ends_with(&suffix));
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_instance_name_candidates_truncates_config_name() {
+        let mock_env_var = MockEnvVarQuery::new();
+        let config = create_config_with_template(None);
+        let config_name = "a".repeat(100);
+        let device = create_device_with_properties("10.0.0.1", &[]);
+        let candidates = inner_generate_instance_name_candidates(
+            &config_name,
+            &config,
+            &device,
+            true,
+            &mock_env_var,
+        );
+        for (digest_length, candidate) in (3..=MAX_INSTANCE_DIGEST_LENGTH).zip(&candidates) {
+            let digest =
+                inner_generate_instance_digest("10.0.0.1", true, digest_length, &mock_env_var);
+            // The Configuration's name is cut rather than the digest
+            assert_eq!(candidate.len(), MAX_INSTANCE_NAME_LENGTH);
+            assert!(candidate.starts_with("aaa"));
+            assert!(candidate.ends_with(&format!("a-{}", digest)));
+        }
+
+        // Names that fit are not changed
+        assert_eq!(
+            inner_generate_instance_name_candidates(
+                "onvif.cameras",
+                &config,
+                &device,
+                true,
+                &mock_env_var
+            )[0],
+            format!(
+                "onvif-cameras-{}",
+                inner_generate_instance_digest("10.0.0.1", true, 3, &mock_env_var)
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_instance_names() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        std::env::set_var("AGENT_NODE_NAME", "node-a");
         let path_to_config = "../test/yaml/config-a.yaml";
         let config_yaml = std::fs::read_to_string(path_to_config).expect("Unable to read file");
-        let config: KubeAkriConfig = serde_yaml::from_str(&config_yaml).unwrap();
-        let discovery_operator = create_mock_discovery_operator(
-            discovery_handler_map.clone(),
+        let mut config: KubeAkriConfig = serde_yaml::from_str(&config_yaml).unwrap();
+        config.spec.instance_name_template = Some("{{MODEL}}".to_string());
+        let device1 = create_device_with_properties("10.0.0.1", &[("MODEL", "camera")]);
+        let device2 = create_device_with_properties("10.0.0.2", &[("MODEL", "camera")]);
+        let device1_candidates =
+            generate_instance_name_candidates("config-a", &config.spec, &device1, true);
+        let device2_candidates =
+            generate_instance_name_candidates("config-a", &config.spec, &device2, true);
+        // Devices with the same properties get different names
+        assert_ne!(device1_candidates[0], device2_candidates[0]);
+        let discovery_operator = DiscoveryOperator::new(
+            Arc::new(std::sync::Mutex::new(HashMap::new())),
             config,
             Arc::new(tokio::sync::Mutex::new(HashMap::new())),
         );
-        (discovery_operator, discovery_handler_map)
-    }
+        let create_instance = |name: &str, device_id: &str| -> KubeAkriInstance {
+            serde_json::from_value(serde_json::json!({
+                "apiVersion": "akri.sh/v0",
+                "kind": "Instance",
+                "metadata": {
+                    "name": name,
+                    "namespace": "default",
+                    "annotations": { AKRI_DEVICE_ID_ANNOTATION_NAME: device_id }
+                },
+                "spec": {
+                    "configurationName": "config-a",
+                    "shared": true,
+                    "nodes": ["node-b"],
+                    "deviceUsage": {}
+                }
+            }))
+            .unwrap()
+        };
 
-    #[test]
-    fn test_generate_instance_digest() {
-        let mut mock_env_var_a = MockEnvVarQuery::new();
-        mock_env_var_a
-            .expect_get_env_var()
-            .returning(|_| Ok("node-a".to_string()));
-        let id = "video1";
-        let first_unshared_video_digest =
-            inner_generate_instance_digest(id, false, &mock_env_var_a);
-        let first_shared_video_digest = inner_generate_instance_digest(id, true, &mock_env_var_a);
-        let mut mock_env_var_b = MockEnvVarQuery::new();
-        mock_env_var_b
-            .expect_get_env_var()
-            .returning(|_| Ok("node-b".to_string()));
-        let second_unshared_video_digest =
-            inner_generate_instance_digest(id, false, &mock_env_var_b);
-        let second_shared_video_digest = inner_generate_instance_digest(id, true, &mock_env_var_b);
-        // unshared instances visible to different nodes should NOT have the same digest
-        assert_ne!(first_unshared_video_digest, second_unshared_video_digest);
-        // shared instances visible to different nodes should have the same digest
-        assert_eq!(first_shared_video_digest, second_shared_video_digest);
+        // device1's name is taken by an Instance of another device and device2's Instance was created by another node
+        let mut mock = MockKubeInterface::new();
+        let taken_name = device1_candidates[0].clone();
+        let taken_instance = create_instance(&taken_name, "10.0.0.9");
+        mock.expect_find_instance()
+            .withf(move |name, _| name == taken_name)
+            .times(1)
+            .returning(move |_, _| Ok(taken_instance.clone()));
+        let free_name = device1_candidates[1].clone();
+        mock.expect_find_instance()
+            .withf(move |name, _| name == free_name)
+            .times(1)
+            .returning(|_, _| Err(get_kube_not_found_error()));
+        let device2_name = device2_candidates[0].clone();
+        let device2_instance = create_instance(&device2_name, "10.0.0.2");
+        mock.expect_find_instance()
+            .withf(move |name, _| name == device2_name)
+            .times(1)
+            .returning(move |_, _| Ok(device2_instance.clone()));
+        let kube_interface: Arc<Box<dyn k8s::KubeInterface>> = Arc::new(Box::new(mock));
+        let names = discovery_operator
+            .get_instance_names(
+                kube_interface,
+                &[device1.clone(), device2.clone()],
+                true,
+                &HashMap::new(),
+            )
+            .await;
+        assert_eq!(names["10.0.0.1"], device1_candidates[1]);
+        assert_eq!(names["10.0.0.2"], device2_candidates[0]);
+
+        // Devices that are already named keep their names without looking them up again
+        let kube_interface: Arc<Box<dyn k8s::KubeInterface>> =
+            Arc::new(Box::new(MockKubeInterface::new()));
+        let names = discovery_operator
+            .get_instance_names(kube_interface, &[device2, device1], true, &HashMap::new())
+            .await;
+        assert_eq!(names["10.0.0.1"], device1_candidates[1]);
+        assert_eq!(names["10.0.0.2"], device2_candidates[0]);
     }
 
     #[tokio::test]
@@ -1223,8 +2117,14 @@ pub mod tests {
         let _ = env_logger::builder().is_test(true).try_init();
         // Set node name for generating instance id
         std::env::set_var("AGENT_NODE_NAME", "node-a");
+        let mut mock_kube_interface = MockKubeInterface::new();
+        // The names of new devices are not taken by existing Instances
+        mock_kube_interface
+            .expect_find_instance()
+            .times(2)
+            .returning(|_, _| Err(get_kube_not_found_error()));
         let mock_kube_interface: Arc<Box<dyn k8s::KubeInterface>> =
-            Arc::new(Box::new(MockKubeInterface::new()));
+            Arc::new(Box::new(mock_kube_interface));
         let discovery_handler_map: RegisteredDiscoveryHandlerMap =
             Arc::new(std::sync::Mutex::new(HashMap::new()));
         let path_to_config = "../test/yaml/config-a.yaml";
@@ -1275,6 +2175,426 @@ pub mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_handle_discovery_deltas() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        // Set node name for generating instance id
+        std::env::set_var("AGENT_NODE_NAME", "node-a");
+        let path_to_config = "../test/yaml/config-a.yaml";
+        let config_yaml = std::fs::read_to_string(path_to_config).expect("Unable to read file");
+        let config: KubeAkriConfig = serde_yaml::from_str(&config_yaml).unwrap();
+        let instance_name = |id: &str| {
+            get_device_instance_name(
+                &generate_instance_digest(id, true, 3),
+                &config.metadata.name,
+            )
+        };
+        let create_device = |id: &str| Device {
+            id: id.to_string(),
+            properties: HashMap::new(),
+            mounts: Vec::default(),
+            device_specs: Vec::default(),
+        };
+        // device1 was offline and device3 is online
+        let (device1_sender, mut device1_receiver) = broadcast::channel(2);
+        let (device3_sender, mut device3_receiver) = broadcast::channel(2);
+        let mut instance_map = HashMap::new();
+        instance_map.insert(
+            instance_name("device1"),
+            InstanceInfo {
+                list_and_watch_message_sender: device1_sender,
+                connectivity_status: InstanceConnectivityStatus::Offline(Instant::now()),
+            },
+        );
+        instance_map.insert(
+            instance_name("device3"),
+            InstanceInfo {
+                list_and_watch_message_sender: device3_sender,
+                connectivity_status: InstanceConnectivityStatus::Online,
+            },
+        );
+        let instance_map: InstanceMap = Arc::new(tokio::sync::Mutex::new(instance_map));
+        let discovery_operator = DiscoveryOperator::new(
+            Arc::new(std::sync::Mutex::new(HashMap::new())),
+            config.clone(),
+            instance_map.clone(),
+        );
+        // Only the new device2 should get a device plugin and have its name looked up
+        let device2_instance_name = instance_name("device2");
+        let mut mock_kube_interface = MockKubeInterface::new();
+        let expected_instance_name = device2_instance_name.clone();
+        mock_kube_interface
+            .expect_find_instance()
+            .withf(move |name, _| name == expected_instance_name)
+            .times(1)
+            .returning(|_, _| Err(get_kube_not_found_error()));
+        let mock_kube_interface: Arc<Box<dyn k8s::KubeInterface>> =
+            Arc::new(Box::new(mock_kube_interface));
+        let mut mock_device_plugin_builder = MockDevicePluginBuilderInterface::new();
+        mock_device_plugin_builder
+            .expect_build_device_plugin()
+            .withf(move |name, _, _, _, _| name == &device2_instance_name)
+            .times(1)
+            .returning(move |_, _, _, _, _| Ok(()));
+        discovery_operator
+            .handle_discovery_deltas(
+                mock_kube_interface,
+                vec![create_device("device1"), create_device("device2")],
+                vec!["device3".to_string(), "device4".to_string()],
+                true,
+                Box::new(mock_device_plugin_builder),
+            )
+            .await
+            .unwrap();
+
+        let instance_map = instance_map.lock().await;
+        assert_eq!(
+            instance_map
+                .get(&instance_name("device1"))
+                .unwrap()
+                .connectivity_status,
+            InstanceConnectivityStatus::Online
+        );
+        assert_eq!(
+            device1_receiver.recv().await.unwrap(),
+            device_plugin_service::ListAndWatchMessageKind::Continue
+        );
+        match instance_map
+            .get(&instance_name("device3"))
+            .unwrap()
+            .connectivity_status
+        {
+            InstanceConnectivityStatus::Offline(_) => {}
+            InstanceConnectivityStatus::Online => panic!("removed device should be offline"),
+        }
+        assert_eq!(
+            device3_receiver.recv().await.unwrap(),
+            device_plugin_service::ListAndWatchMessageKind::Continue
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_discovery_deltas_deletes_expired_offline_instances() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let path_to_config = "../test/yaml/config-a.yaml";
+        let config_yaml = std::fs::read_to_string(path_to_config).expect("Unable to read file");
+        let config: KubeAkriConfig = serde_yaml::from_str(&config_yaml).unwrap();
+        let expired_instance_name = get_device_instance_name(
+            &generate_instance_digest("device1", true, 3),
+            &config.metadata.name,
+        );
+        // device1 went offline before the grace period and is in neither the changed nor the removed devices
+        let (device1_sender, _device1_receiver) = broadcast::channel(2);
+        let mut instance_map = HashMap::new();
+        instance_map.insert(
+            expired_instance_name.clone(),
+            InstanceInfo {
+                list_and_watch_message_sender: device1_sender,
+                connectivity_status: InstanceConnectivityStatus::Offline(Instant::now()),
+            },
+        );
+        MockClock::advance(Duration::from_secs(301));
+        let instance_map: InstanceMap = Arc::new(tokio::sync::Mutex::new(instance_map));
+        let mut mock_kube_interface = MockKubeInterface::new();
+        let expected_instance_name = expired_instance_name.clone();
+        mock_kube_interface
+            .expect_delete_instance()
+            .withf(move |name, _| name == expected_instance_name)
+            .times(1)
+            .returning(move |_, _| Ok(()));
+        let discovery_operator = DiscoveryOperator::new(
+            Arc::new(std::sync::Mutex::new(HashMap::new())),
+            config,
+            instance_map.clone(),
+        );
+        discovery_operator
+            .handle_discovery_deltas(
+                Arc::new(Box::new(mock_kube_interface)),
+                Vec::new(),
+                Vec::new(),
+                true,
+                Box::new(MockDevicePluginBuilderInterface::new()),
+            )
+            .await
+            .unwrap();
+        assert!(!instance_map
+            .lock()
+            .await
+            .contains_key(&expired_instance_name));
+    }
+
+    // Tests that snapshots and consecutive changes are applied and that changes after a missed response are ignored
+    #[tokio::test]
+    async fn test_internal_do_discover_v1() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let path_to_config = "../test/yaml/config-a.yaml";
+        let config_yaml = std::fs::read_to_string(path_to_config).expect("Unable to read file");
+        let config: KubeAkriConfig = serde_yaml::from_str(&config_yaml).unwrap();
+        let instance_name = get_device_instance_name(
+            &generate_instance_digest("device1", true, 3),
+            &config.metadata.name,
+        );
+        let (device1_sender, mut device1_receiver) = broadcast::channel(4);
+        let mut instance_map = HashMap::new();
+        instance_map.insert(
+            instance_name.clone(),
+            InstanceInfo {
+                list_and_watch_message_sender: device1_sender,
+                connectivity_status: InstanceConnectivityStatus::Online,
+            },
+        );
+        let instance_map: InstanceMap = Arc::new(tokio::sync::Mutex::new(instance_map));
+        let discovery_operator = DiscoveryOperator::new(
+            Arc::new(std::sync::Mutex::new(HashMap::new())),
+            config,
+            instance_map.clone(),
+        );
+        let device1 = Device {
+            id: "device1".to_string(),
+            properties: HashMap::new(),
+            mounts: Vec::default(),
+            device_specs: Vec::default(),
+        };
+        let response = |sequence_number: u64,
+                        snapshot: bool,
+                        added: Vec<Device>,
+                        removed: &[&str]|
+         -> Result<v1::DiscoverResponse, Status> {
+            Ok(v1::DiscoverResponse {
+                sequence_number,
+                snapshot,
+                added,
+                updated: Vec::new(),
+                removed: removed.iter().map(|id| id.to_string()).collect(),
+            })
+        };
+        let (mut sender, mut receiver) = mpsc::channel(4);
+        // A change before the first snapshot is ignored
+        sender
+            .send(response(3, false, Vec::new(), &["device1"]))
+            .await
+            .unwrap();
+        sender
+            .send(response(4, true, vec![device1.clone()], &[]))
+            .await
+            .unwrap();
+        // device1 is removed by the next change, but a change after a missed response is ignored
+        sender
+            .send(response(5, false, Vec::new(), &["device1"]))
+            .await
+            .unwrap();
+        sender
+            .send(response(7, false, vec![device1], &[]))
+            .await
+            .unwrap();
+        drop(sender);
+        let dh_details =
+            create_discovery_handler_details("debugEcho", DiscoveryHandlerEndpoint::Embedded, true);
+        let result = discovery_operator
+            .internal_do_discover_v1(
+                Arc::new(Box::new(MockKubeInterface::new())),
+                &dh_details,
+                &mut receiver,
+            )
+            .await;
+        // The closed stream is reported like a dropped connection
+        assert!(result.unwrap_err().message().contains("broken pipe"));
+        match instance_map
+            .lock()
+            .await
+            .get(&instance_name)
+            .unwrap()
+            .connectivity_status
+        {
+            InstanceConnectivityStatus::Offline(_) => {}
+            InstanceConnectivityStatus::Online => panic!("removed device should be offline"),
+        }
+        assert_eq!(
+            device1_receiver.recv().await.unwrap(),
+            device_plugin_service::ListAndWatchMessageKind::Continue
+        );
+        assert!(device1_receiver.try_recv().is_err());
+    }
+
+    // Tests that discovery over a v1 stream ends with an error rather than panicking when a response cannot be handled
+    #[tokio::test]
+    async fn test_internal_do_discover_v1_handling_error() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let path_to_config = "../test/yaml/config-a.yaml";
+        let config_yaml = std::fs::read_to_string(path_to_config).expect("Unable to read file");
+        let config: KubeAkriConfig = serde_yaml::from_str(&config_yaml).unwrap();
+        let instance_name = get_device_instance_name(
+            &generate_instance_digest("device1", false, 3),
+            &config.metadata.name,
+        );
+        let (device1_sender, _device1_receiver) = broadcast::channel(4);
+        let mut instance_map = HashMap::new();
+        instance_map.insert(
+            instance_name,
+            InstanceInfo {
+                list_and_watch_message_sender: device1_sender,
+                connectivity_status: InstanceConnectivityStatus::Online,
+            },
+        );
+        let discovery_operator = DiscoveryOperator::new(
+            Arc::new(std::sync::Mutex::new(HashMap::new())),
+            config,
+            Arc::new(tokio::sync::Mutex::new(instance_map)),
+        );
+        // The unshared device is no longer visible, so its Instance is deleted, which fails
+        let mut mock = MockKubeInterface::new();
+        mock.expect_delete_instance()
+            .returning(|_, _| Err("forbidden".into()));
+        mock.expect_find_instance().returning(|_, _| {
+            Err(kube::Error::Api(kube::ErrorResponse {
+                status: "Failure".to_string(),
+                message: "forbidden".to_string(),
+                reason: "Forbidden".to_string(),
+                code: 403,
+            }))
+        });
+        let (mut sender, mut receiver) = mpsc::channel(4);
+        sender
+            .send(Ok(v1::DiscoverResponse {
+                sequence_number: 0,
+                snapshot: true,
+                added: Vec::new(),
+                updated: Vec::new(),
+                removed: Vec::new(),
+            }))
+            .await
+            .unwrap();
+        let dh_details = create_discovery_handler_details(
+            "debugEcho",
+            DiscoveryHandlerEndpoint::Embedded,
+            false,
+        );
+        let result = discovery_operator
+            .internal_do_discover_v1(Arc::new(Box::new(mock)), &dh_details, &mut receiver)
+            .await;
+        assert!(result
+            .unwrap_err()
+            .message()
+            .contains("failed to handle discovery results"));
+    }
+
+    #[tokio::test]
+    async fn test_update_instance_broker_properties() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let path_to_config = "../test/yaml/config-a.yaml";
+        let config_yaml = std::fs::read_to_string(path_to_config).expect("Unable to read file");
+        let mut config: KubeAkriConfig = serde_yaml::from_str(&config_yaml).unwrap();
+        config.spec.broker_properties_mount_path = Some("/etc/akri/properties".to_string());
+        let instance_name = get_device_instance_name(
+            &generate_instance_digest("device1", true, 3),
+            &config.metadata.name,
+        );
+        let (sender, mut receiver) = broadcast::channel(2);
+        let instance_info = InstanceInfo {
+            list_and_watch_message_sender: sender,
+            connectivity_status: InstanceConnectivityStatus::Online,
+        };
+        let mut instance_map = HashMap::new();
+        instance_map.insert(instance_name.clone(), instance_info);
+        let discovery_operator = DiscoveryOperator::new(
+            Arc::new(std::sync::Mutex::new(HashMap::new())),
+            config.clone(),
+            Arc::new(tokio::sync::Mutex::new(instance_map)),
+        );
+        let device = Device {
+            id: "device1".to_string(),
+            properties: vec![("ENDPOINT".to_string(), "opc.tcp://b:4840/".to_string())]
+                .into_iter()
+                .collect(),
+            mounts: Vec::default(),
+            device_specs: Vec::default(),
+        };
+        let create_instance = move |name: &str, endpoint: &str| -> KubeAkriInstance {
+            let mut broker_properties = config.spec.broker_properties.clone();
+            broker_properties.insert("ENDPOINT".to_string(), endpoint.to_string());
+            serde_json::from_value(serde_json::json!({
+                "apiVersion": "akri.sh/v0",
+                "kind": "Instance",
+                "metadata": { "name": name, "namespace": "default" },
+                "spec": {
+                    "configurationName": "config-a",
+                    "shared": true,
+                    "nodes": ["node-a"],
+                    "deviceUsage": {},
+                    "brokerProperties": broker_properties
+                }
+            }))
+            .unwrap()
+        };
+
+        // Instance whose properties changed is updated and its DevicePluginService signaled
+        let mut mock = MockKubeInterface::new();
+        let found_instance = create_instance(&instance_name, "opc.tcp://a:4840/");
+        mock.expect_find_instance()
+            .times(1)
+            .returning(move |_, _| Ok(found_instance.clone()));
+        mock.expect_update_instance()
+            .times(1)
+            .withf(|instance, _, _| instance.broker_properties["ENDPOINT"] == "opc.tcp://b:4840/")
+            .returning(|_, _, _| Ok(()));
+        discovery_operator
+            .handle_discovery_deltas(
+                Arc::new(Box::new(mock)),
+                vec![device.clone()],
+                Vec::new(),
+                true,
+                Box::new(MockDevicePluginBuilderInterface::new()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            device_plugin_service::ListAndWatchMessageKind::Continue
+        );
+
+        // Instance whose properties are unchanged is not updated
+        let mut mock = MockKubeInterface::new();
+        let found_instance = create_instance(&instance_name, "opc.tcp://b:4840/");
+        mock.expect_find_instance()
+            .times(1)
+            .returning(move |_, _| Ok(found_instance.clone()));
+        discovery_operator
+            .handle_discovery_deltas(
+                Arc::new(Box::new(mock)),
+                vec![device.clone()],
+                Vec::new(),
+                true,
+                Box::new(MockDevicePluginBuilderInterface::new()),
+            )
+            .await
+            .unwrap();
+        assert!(receiver.try_recv().is_err());
+
+        // Full discovery results refresh the properties too
+        let mut mock = MockKubeInterface::new();
+        let found_instance = create_instance(&instance_name, "opc.tcp://a:4840/");
+        mock.expect_find_instance()
+            .times(1)
+            .returning(move |_, _| Ok(found_instance.clone()));
+        mock.expect_update_instance()
+            .times(1)
+            .withf(|instance, _, _| instance.broker_properties["ENDPOINT"] == "opc.tcp://b:4840/")
+            .returning(|_, _, _| Ok(()));
+        discovery_operator
+            .handle_discovery_results(
+                Arc::new(Box::new(mock)),
+                vec![device],
+                true,
+                Box::new(MockDevicePluginBuilderInterface::new()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            device_plugin_service::ListAndWatchMessageKind::Continue
+        );
+    }
+
     // Checks either that InstanceConnectivityStatus changed to expected value until success or exceeded tries
     // or that all instances have been deleted from map.
     // Sleep between tries to give update_instance_connectivity_status the chance chance to grab mutex InstanceMap.
@@ -1768,16 +3088,56 @@ pub mod tests {
             return_error,
         )
         .await;
+        // Discovery Handlers run with `akri-discovery-utils` serve the v1 Discovery API
+        if let Some(StreamType::ExternalV1(mut receiver)) =
+            discovery_operator.get_stream(&dh_endpoint).await
+        {
+            // MockDiscoveryHandler returns an empty array of devices, which is sent as the first snapshot
+            let response = receiver.get_message().await.unwrap().unwrap();
+            assert!(response.snapshot);
+            assert_eq!(response.added.len(), 0);
+        } else {
+            panic!("expected external v1 stream");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_stream_external_v0_fallback() {
+        let (endpoint_dir, endpoint) =
+            mock_discovery_handler::get_mock_discovery_handler_dir_and_endpoint("mock.sock");
+        let dh_endpoint = DiscoveryHandlerEndpoint::Uds(endpoint.to_string());
+        let discovery_operator = setup_non_mocked_dh("mockName", &dh_endpoint);
+        // Start a mock DH that only serves the v0 Discovery API
+        let endpoint_clone = endpoint.clone();
+        let _dh_server_thread_handle = tokio::spawn(async move {
+            std::fs::create_dir_all(&endpoint_dir).unwrap();
+            let mut uds = tokio::net::UnixListener::bind(&endpoint_clone).unwrap();
+            tonic::transport::Server::builder()
+                .add_service(DiscoveryHandlerServer::new(
+                    mock_discovery_handler::MockDiscoveryHandler {
+                        return_error: false,
+                        devices: Vec::new(),
+                    },
+                ))
+                .serve_with_incoming(
+                    uds.incoming()
+                        .map_ok(akri_shared::uds::unix_stream::UnixStream),
+                )
+                .await
+                .unwrap();
+        });
+        akri_shared::uds::unix_stream::try_connect(&endpoint)
+            .await
+            .unwrap();
         if let Some(StreamType::External(mut receiver)) =
             discovery_operator.get_stream(&dh_endpoint).await
         {
-            // MockDiscoveryHandler returns an empty array of devices
             assert_eq!(
                 receiver.get_message().await.unwrap().unwrap().devices.len(),
                 0
             );
         } else {
-            panic!("expected external stream");
+            panic!("expected external v0 stream");
         }
     }
 }
@@ -0,0 +1,264 @@
+use super::{
+    device_credentials::get_device_credential_key,
+    device_plugin_service::get_all_broker_properties,
+    discovery_details_validation::validate_discovery_details,
+    discovery_operator::inner_generate_instance_name_candidates,
+    embedded_discovery_handlers::get_discovery_handler,
+    registration::{
+        register_embedded_discovery_handlers, DiscoveryHandlerEndpoint,
+        RegisteredDiscoveryHandlerMap,
+    },
+};
+use akri_discovery_utils::discovery::v0::{Device, DiscoverRequest};
+use akri_shared::{
+    akri::configuration::{Configuration, DeviceCredentials},
+    os::env_var::{ActualEnvVarQuery, EnvVarQuery},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+/// Runs the embedded Discovery Handler of a Configuration locally, without creating anything in a cluster, and
+/// writes what the Agent would make of each set of discovered devices to `output`. Returns after the first set of
+/// devices unless `watch` is set, in which case every update from the Discovery Handler is written until it stops
+/// discovering.
+pub async fn dry_run_discovery(
+    config_name: &str,
+    config: &Configuration,
+    watch: bool,
+    output: &mut dyn std::io::Write,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let discovery_handler_map: RegisteredDiscoveryHandlerMap = Arc::new(Mutex::new(HashMap::new()));
+    register_embedded_discovery_handlers(discovery_handler_map.clone())?;
+    let discovery_handler_name = &config.discovery_handler.name;
+    let (shared, discovery_details_schema) = match discovery_handler_map
+        .lock()
+        .unwrap()
+        .get(discovery_handler_name)
+        .and_then(|endpoints| endpoints.get(&DiscoveryHandlerEndpoint::Embedded))
+    {
+        Some(dh_details) => (
+            dh_details.shared,
+            dh_details.discovery_details_schema.clone(),
+        ),
+        None => {
+            return Err(format!(
+                "{} is not embedded in this build, which only embeds the Discovery Handlers of its features (and debugEcho if ENABLE_DEBUG_ECHO is set)",
+                discovery_handler_name
+            )
+            .into())
+        }
+    };
+    if let Some(schema) = discovery_details_schema {
+        validate_discovery_details(&schema, &config.discovery_handler.discovery_details)?;
+    }
+
+    let discovery_handler =
+        get_discovery_handler(&config.discovery_handler).map_err(|e| e.to_string())?;
+    let mut stream = discovery_handler
+        .discover(tonic::Request::new(DiscoverRequest {
+            discovery_details: config.discovery_handler.discovery_details.clone(),
+        }))
+        .await?
+        .into_inner();
+    while let Some(response) = stream.recv().await {
+        let devices = response?.devices;
+        writeln!(
+            output,
+            "{}",
+            describe_devices(config_name, config, shared, &devices)
+        )?;
+        if !watch {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Describes what the Agent would create for each discovered device: the name of its Instance, the environment
+/// variables set in its brokers and what is mounted into them.
+pub fn describe_devices(
+    config_name: &str,
+    config: &Configuration,
+    shared: bool,
+    devices: &[Device],
+) -> String {
+    let query = ActualEnvVarQuery {};
+    inner_describe_devices(config_name, config, shared, devices, &query)
+}
+
+fn inner_describe_devices(
+    config_name: &str,
+    config: &Configuration,
+    shared: bool,
+    devices: &[Device],
+    query: &impl EnvVarQuery,
+) -> String {
+    let mut description = format!(
+        "Discovered {} device(s) with the {} Discovery Handler ({})\n",
+        devices.len(),
+        config.discovery_handler.name,
+        if shared { "shared" } else { "not shared" }
+    );
+    // Names of Instances that exist in the cluster are not known, so devices get the first of their names that is
+    // not taken by another discovered device
+    let mut instance_names: HashSet<String> = HashSet::new();
+    for device in devices {
+        let candidates =
+            inner_generate_instance_name_candidates(config_name, config, device, shared, query);
+        let instance_name = candidates
+            .iter()
+            .find(|candidate| !instance_names.contains(*candidate))
+            .or_else(|| candidates.last())
+            .unwrap()
+            .clone();
+        instance_names.insert(instance_name.clone());
+        description.push_str(&format!(
+            "\nInstance: {}\n  Device ID: {}\n",
+            instance_name, device.id
+        ));
+        description.push_str("  Broker environment variables:\n");
+        let mut broker_properties: Vec<(String, String)> =
+            get_all_broker_properties(&config.broker_properties, &device.properties)
+                .into_iter()
+                .collect();
+        broker_properties.sort();
+        for (name, value) in broker_properties {
+            description.push_str(&format!("    {}={}\n", name, value));
+        }
+        if let Some(device_credentials) = &config.device_credentials {
+            if let Some(env_var) = &device_credentials.env_var {
+                description.push_str(&format!(
+                    "    {}={}\n",
+                    env_var,
+                    describe_device_credential(device_credentials, device)
+                ));
+            }
+        }
+        description.push_str("  Mounts:\n");
+        for mount in &device.mounts {
+            description.push_str(&format!(
+                "    {} -> {}{}\n",
+                mount.host_path,
+                mount.container_path,
+                if mount.read_only { " (read-only)" } else { "" }
+            ));
+        }
+        if let Some(container_path) = &config.broker_properties_mount_path {
+            description.push_str(&format!(
+                "    broker properties -> {} (read-only)\n",
+                container_path
+            ));
+        }
+        if let Some(device_credentials) = &config.device_credentials {
+            if let Some(mount_path) = &device_credentials.mount_path {
+                description.push_str(&format!(
+                    "    {} -> {} (read-only)\n",
+                    describe_device_credential(device_credentials, device),
+                    mount_path
+                ));
+            }
+        }
+        description.push_str("  Devices:\n");
+        for device_spec in &device.device_specs {
+            description.push_str(&format!(
+                "    {} -> {} ({})\n",
+                device_spec.host_path, device_spec.container_path, device_spec.permissions
+            ));
+        }
+    }
+    description
+}
+
+/// Describes where the credential of a device would come from, since the Secret is not read during a dry run
+fn describe_device_credential(device_credentials: &DeviceCredentials, device: &Device) -> String {
+    match get_device_credential_key(device_credentials, device) {
+        Some(key) => format!(
+            "<entry {} of Secret {}>",
+            key, device_credentials.secret_name
+        ),
+        None => "<none, the device does not have the key property>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::discovery_operator::inner_generate_instance_digest;
+    use super::*;
+    use akri_discovery_utils::discovery::v0::{DeviceSpec, Mount};
+    use akri_shared::os::env_var::MockEnvVarQuery;
+
+    fn config() -> Configuration {
+        serde_yaml::from_str(
+            r#"
+            discoveryHandler:
+              name: udev
+              discoveryDetails: |+
+                udevRules:
+                - KERNEL=="video[0-9]*"
+            brokerProperties:
+              RESOLUTION_WIDTH: "800"
+              UDEV_DEVNODE: "overridden by the device"
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn device() -> Device {
+        Device {
+            id: "/sys/devices/video0".to_string(),
+            properties: vec![("UDEV_DEVNODE".to_string(), "/dev/video0".to_string())]
+                .into_iter()
+                .collect(),
+            mounts: vec![Mount {
+                container_path: "/run/udev".to_string(),
+                host_path: "/run/udev".to_string(),
+                read_only: true,
+            }],
+            device_specs: vec![DeviceSpec {
+                container_path: "/dev/video0".to_string(),
+                host_path: "/dev/video0".to_string(),
+                permissions: "rwm".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_describe_devices() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut mock_query = MockEnvVarQuery::new();
+        mock_query
+            .expect_get_env_var()
+            .returning(|_| Ok("node-a".to_string()));
+        let description =
+            inner_describe_devices("akri-udev", &config(), false, &[device()], &mock_query);
+        let digest = inner_generate_instance_digest("/sys/devices/video0", false, 3, &mock_query);
+        assert_eq!(
+            description,
+            format!(
+                "Discovered 1 device(s) with the udev Discovery Handler (not shared)\n\
+                 \n\
+                 Instance: akri-udev-{}\n  \
+                 Device ID: /sys/devices/video0\n  \
+                 Broker environment variables:\n    \
+                 RESOLUTION_WIDTH=800\n    \
+                 UDEV_DEVNODE=/dev/video0\n  \
+                 Mounts:\n    \
+                 /run/udev -> /run/udev (read-only)\n  \
+                 Devices:\n    \
+                 /dev/video0 -> /dev/video0 (rwm)\n",
+                digest
+            )
+        );
+    }
+
+    #[test]
+    fn test_describe_devices_none_found() {
+        let mock_query = MockEnvVarQuery::new();
+        assert_eq!(
+            inner_describe_devices("akri-udev", &config(), true, &[], &mock_query),
+            "Discovered 0 device(s) with the udev Discovery Handler (shared)\n"
+        );
+    }
+}
@@ -1,9 +1,15 @@
+mod broker_properties_files;
 pub mod config_action;
 pub mod constants;
 pub mod crictl_containers;
+mod device_credentials;
 mod device_plugin_builder;
 mod device_plugin_service;
+pub mod discovery_details_validation;
+pub mod discovery_handler_health;
 pub mod discovery_operator;
+#[cfg(any(test, feature = "agent-full"))]
+pub mod dry_run;
 pub mod embedded_discovery_handlers;
 pub mod registration;
 pub mod slot_reconciliation;
@@ -1,10 +1,14 @@
-use super::constants::{
-    CLOSE_DISCOVERY_HANDLER_CONNECTION_CHANNEL_CAPACITY, ENABLE_DEBUG_ECHO_LABEL,
+use super::{
+    constants::{CLOSE_DISCOVERY_HANDLER_CONNECTION_CHANNEL_CAPACITY, ENABLE_DEBUG_ECHO_LABEL},
+    discovery_details_validation::check_discovery_details_schema,
 };
-use akri_discovery_utils::discovery::v0::{
-    register_discovery_handler_request::EndpointType,
-    registration_server::{Registration, RegistrationServer},
-    Empty, RegisterDiscoveryHandlerRequest,
+use akri_discovery_utils::{
+    discovery::v0::{
+        register_discovery_handler_request::EndpointType,
+        registration_server::{Registration, RegistrationServer},
+        Empty, RegisterDiscoveryHandlerRequest,
+    },
+    security::{check_registration_token, get_registration_token, get_tls_files},
 };
 use akri_shared::{
     os::env_var::{ActualEnvVarQuery, EnvVarQuery},
@@ -69,6 +73,9 @@ pub struct DiscoveryDetails {
     pub close_discovery_handler_connection: broadcast::Sender<()>,
     /// Connection state of the `DiscoveryHandler`.
     pub connectivity_status: DiscoveryHandlerStatus,
+    /// JSON Schema that Configurations' discovery details must satisfy to be used by the `DiscoveryHandler`, if it
+    /// advertised one.
+    pub discovery_details_schema: Option<String>,
 }
 
 /// This maps the endpoint string and endpoint type of a `RegisterDiscoveryHandlerRequest` into a
@@ -83,24 +90,66 @@ pub fn create_discovery_handler_endpoint(
     }
 }
 
+/// Requirements a Discovery Handler must meet in order to register with the Agent
+#[derive(Debug, Clone, Default)]
+pub struct RegistrationSecurity {
+    /// Token that registering Discovery Handlers must present as a bearer token in the request metadata
+    pub registration_token: Option<String>,
+    /// Whether network based Discovery Handlers must be served over TLS (registered with an `https` endpoint)
+    pub require_tls: bool,
+}
+
+impl RegistrationSecurity {
+    /// Creates the `RegistrationSecurity` for this Agent. A registration token is required if
+    /// `DISCOVERY_HANDLER_REGISTRATION_TOKEN_PATH` is set and TLS is required if `DISCOVERY_HANDLER_TLS_DIRECTORY` is
+    /// set.
+    pub fn from_env() -> Result<Self, anyhow::Error> {
+        Ok(RegistrationSecurity {
+            registration_token: get_registration_token()?,
+            require_tls: get_tls_files()?.is_some(),
+        })
+    }
+}
+
 /// Hosts a register service that external Discovery Handlers can call in order to be added to the
 /// RegisteredDiscoveryHandlerMap that is shared with DiscoveryOperators. When a new Discovery Handler is registered, a
 /// message is broadcast to inform any running DiscoveryOperators in case they should use the new Discovery Handler.
 pub struct AgentRegistration {
     new_discovery_handler_sender: broadcast::Sender<DiscoveryHandlerName>,
     registered_discovery_handlers: RegisteredDiscoveryHandlerMap,
+    security: RegistrationSecurity,
 }
 
 impl AgentRegistration {
     pub fn new(
         new_discovery_handler_sender: broadcast::Sender<DiscoveryHandlerName>,
         registered_discovery_handlers: RegisteredDiscoveryHandlerMap,
+        security: RegistrationSecurity,
     ) -> Self {
         AgentRegistration {
             new_discovery_handler_sender,
             registered_discovery_handlers,
+            security,
         }
     }
+
+    /// Checks that a register request meets the Agent's `RegistrationSecurity` requirements.
+    fn authorize(&self, request: &Request<RegisterDiscoveryHandlerRequest>) -> Result<(), Status> {
+        if let Some(token) = &self.security.registration_token {
+            check_registration_token(request.metadata(), token)?;
+        }
+        let req = request.get_ref();
+        if self.security.require_tls
+            && req.endpoint_type == EndpointType::Network as i32
+            && !req.endpoint.starts_with("https://")
+        {
+            return Err(Status::permission_denied(format!(
+                "network discovery handlers must be served over TLS but {} discovery handler registered endpoint {}",
+                req.name, req.endpoint
+            )));
+        }
+        Ok(())
+    }
 }
 
 #[tonic::async_trait]
@@ -113,7 +162,27 @@ impl Registration for AgentRegistration {
         &self,
         request: Request<RegisterDiscoveryHandlerRequest>,
     ) -> Result<Response<Empty>, Status> {
+        if let Err(status) = self.authorize(&request) {
+            error!(
+                "register - rejected register request with status {:?}",
+                status
+            );
+            return Err(status);
+        }
         let req = request.into_inner();
+        let discovery_details_schema = if req.discovery_details_schema.is_empty() {
+            None
+        } else {
+            // Reject schemas that cannot be used to validate Configurations
+            if let Err(e) = check_discovery_details_schema(&req.discovery_details_schema) {
+                error!(
+                    "register - rejected register request from {} discovery handler with invalid schema: {}",
+                    req.name, e
+                );
+                return Err(Status::invalid_argument(e));
+            }
+            Some(req.discovery_details_schema.clone())
+        };
         let dh_name = req.name.clone();
         let endpoint = req.endpoint.clone();
         let dh_endpoint = create_discovery_handler_endpoint(
@@ -129,13 +198,17 @@ impl Registration for AgentRegistration {
             shared: req.shared,
             close_discovery_handler_connection,
             connectivity_status: DiscoveryHandlerStatus::Waiting,
+            discovery_details_schema: discovery_details_schema.clone(),
         };
         let mut registered_discovery_handlers = self.registered_discovery_handlers.lock().unwrap();
         // Check if any DiscoveryHandlers have been registered under this name
         if let Some(register_request_map) = registered_discovery_handlers.get_mut(&dh_name) {
             if let Some(dh_details) = register_request_map.get(&dh_endpoint) {
                 // Check if DH at that endpoint is already registered but changed request
-                if dh_details.shared != req.shared || dh_details.endpoint != dh_endpoint {
+                if dh_details.shared != req.shared
+                    || dh_details.endpoint != dh_endpoint
+                    || dh_details.discovery_details_schema != discovery_details_schema
+                {
                     // Stop current discovery with this DH if any. A receiver may not exist if
                     // 1) no configuration has been applied that uses this DH or
                     // 2) a connection cannot be made with the DH's endpoint
@@ -179,6 +252,7 @@ pub async fn run_registration_server(
         discovery_handler_map,
         new_discovery_handler_sender,
         &akri_discovery_utils::get_registration_socket(),
+        RegistrationSecurity::from_env()?,
     )
     .await
 }
@@ -187,9 +261,14 @@ pub async fn internal_run_registration_server(
     discovery_handler_map: RegisteredDiscoveryHandlerMap,
     new_discovery_handler_sender: broadcast::Sender<DiscoveryHandlerName>,
     socket_path: &str,
+    security: RegistrationSecurity,
 ) -> Result<(), Box<dyn std::error::Error>> {
     info!("internal_run_registration_server - entered");
-    let registration = AgentRegistration::new(new_discovery_handler_sender, discovery_handler_map);
+    let registration = AgentRegistration::new(
+        new_discovery_handler_sender,
+        discovery_handler_map,
+        security,
+    );
     trace!(
         "internal_run_registration_server - registration server listening on socket {}",
         socket_path
@@ -227,7 +306,10 @@ pub fn inner_register_embedded_discovery_handlers(
     discovery_handler_map: RegisteredDiscoveryHandlerMap,
     query: &impl EnvVarQuery,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
-    type Details = (String, bool);
+    use akri_debug_echo::discovery_handler::DebugEchoDiscoveryDetails;
+    use akri_discovery_utils::discovery::discovery_handler::discovery_details_schema;
+    // Name, whether discovered devices are shared, and discovery details schema
+    type Details = (String, bool, String);
     let mut embedded_discovery_handlers: Vec<Details> = Vec::new();
     if query.get_env_var(ENABLE_DEBUG_ECHO_LABEL).is_ok() {
         let shared: bool = query
@@ -235,27 +317,33 @@ pub fn inner_register_embedded_discovery_handlers(
             .unwrap()
             .parse()
             .unwrap();
-        embedded_discovery_handlers
-            .push((akri_debug_echo::DISCOVERY_HANDLER_NAME.to_string(), shared));
+        embedded_discovery_handlers.push((
+            akri_debug_echo::DISCOVERY_HANDLER_NAME.to_string(),
+            shared,
+            discovery_details_schema::<DebugEchoDiscoveryDetails>(),
+        ));
     }
     #[cfg(feature = "onvif-feat")]
     embedded_discovery_handlers.push((
         akri_onvif::DISCOVERY_HANDLER_NAME.to_string(),
         akri_onvif::SHARED,
+        discovery_details_schema::<akri_onvif::discovery_handler::OnvifDiscoveryDetails>(),
     ));
     #[cfg(feature = "udev-feat")]
     embedded_discovery_handlers.push((
         akri_udev::DISCOVERY_HANDLER_NAME.to_string(),
         akri_udev::SHARED,
+        discovery_details_schema::<akri_udev::discovery_handler::UdevDiscoveryDetails>(),
     ));
     #[cfg(feature = "opcua-feat")]
     embedded_discovery_handlers.push((
         akri_opcua::DISCOVERY_HANDLER_NAME.to_string(),
         akri_opcua::SHARED,
+        discovery_details_schema::<akri_opcua::discovery_handler::OpcuaDiscoveryDetails>(),
     ));
 
     embedded_discovery_handlers.into_iter().for_each(|dh| {
-        let (name, shared) = dh;
+        let (name, shared, discovery_details_schema) = dh;
         let (close_discovery_handler_connection, _) =
             broadcast::channel(CLOSE_DISCOVERY_HANDLER_CONNECTION_CHANNEL_CAPACITY);
         let discovery_handler_details = DiscoveryDetails {
@@ -264,6 +352,7 @@ pub fn inner_register_embedded_discovery_handlers(
             shared,
             close_discovery_handler_connection,
             connectivity_status: DiscoveryHandlerStatus::Waiting,
+            discovery_details_schema: Some(discovery_details_schema),
         };
         let mut register_request_map = HashMap::new();
         register_request_map.insert(
@@ -360,6 +449,7 @@ mod tests {
                 thread_discovery_handler_map,
                 new_discovery_handler_sender,
                 &registration_socket_path_string_thread,
+                RegistrationSecurity::default(),
             )
             .await
             .unwrap();
@@ -388,6 +478,7 @@ mod tests {
             endpoint: endpoint_string.clone(),
             endpoint_type: EndpointType::Uds as i32,
             shared: true,
+            discovery_details_schema: String::new(),
         };
         assert!(registration_client
             .register_discovery_handler(request.clone())
@@ -427,6 +518,7 @@ mod tests {
             endpoint: endpoint_string,
             endpoint_type: EndpointType::Uds as i32,
             shared: false,
+            discovery_details_schema: String::new(),
         };
         assert!(registration_client
             .register_discovery_handler(local_request.clone())
@@ -448,6 +540,122 @@ mod tests {
         assert_eq!(discovery_handler_details.shared, local_request.shared);
     }
 
+    // Tests that register requests without the registration token or with an insecure network endpoint are rejected
+    #[tokio::test]
+    async fn test_register_discovery_handler_security() {
+        let (new_discovery_handler_sender, _) = broadcast::channel(4);
+        let discovery_handler_map = Arc::new(Mutex::new(HashMap::new()));
+        let registration = AgentRegistration::new(
+            new_discovery_handler_sender,
+            discovery_handler_map.clone(),
+            RegistrationSecurity {
+                registration_token: Some("secret".to_string()),
+                require_tls: true,
+            },
+        );
+        let build_request = |endpoint: &str, token: Option<&str>| {
+            let mut request = Request::new(RegisterDiscoveryHandlerRequest {
+                name: "name".to_string(),
+                endpoint: endpoint.to_string(),
+                endpoint_type: EndpointType::Network as i32,
+                shared: true,
+                discovery_details_schema: String::new(),
+            });
+            if let Some(token) = token {
+                request.metadata_mut().insert(
+                    akri_discovery_utils::security::REGISTRATION_TOKEN_METADATA_KEY,
+                    format!("Bearer {}", token).parse().unwrap(),
+                );
+            }
+            request
+        };
+
+        // Missing token
+        let status = registration
+            .register_discovery_handler(build_request("https://10.1.2.3:10000", None))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        // Wrong token
+        let status = registration
+            .register_discovery_handler(build_request("https://10.1.2.3:10000", Some("wrong")))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        // Insecure network endpoint
+        let status = registration
+            .register_discovery_handler(build_request("http://10.1.2.3:10000", Some("secret")))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        assert!(discovery_handler_map.lock().unwrap().is_empty());
+
+        assert!(registration
+            .register_discovery_handler(build_request("https://10.1.2.3:10000", Some("secret")))
+            .await
+            .is_ok());
+        assert!(discovery_handler_map.lock().unwrap().get("name").is_some());
+    }
+
+    // Tests that discovery details schemas are stored and that invalid ones are rejected
+    #[tokio::test]
+    async fn test_register_discovery_handler_discovery_details_schema() {
+        let (new_discovery_handler_sender, _) = broadcast::channel(4);
+        let discovery_handler_map = Arc::new(Mutex::new(HashMap::new()));
+        let registration = AgentRegistration::new(
+            new_discovery_handler_sender,
+            discovery_handler_map.clone(),
+            RegistrationSecurity::default(),
+        );
+        let build_request = |discovery_details_schema: &str| {
+            Request::new(RegisterDiscoveryHandlerRequest {
+                name: "name".to_string(),
+                endpoint: "/path/to/socket/name.sock".to_string(),
+                endpoint_type: EndpointType::Uds as i32,
+                shared: false,
+                discovery_details_schema: discovery_details_schema.to_string(),
+            })
+        };
+        let status = registration
+            .register_discovery_handler(build_request("not json"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(discovery_handler_map.lock().unwrap().is_empty());
+
+        let schema = akri_discovery_utils::discovery::discovery_handler::discovery_details_schema::<
+            akri_udev::discovery_handler::UdevDiscoveryDetails,
+        >();
+        assert!(registration
+            .register_discovery_handler(build_request(&schema))
+            .await
+            .is_ok());
+        let discovery_handler_details = discovery_handler_map
+            .lock()
+            .unwrap()
+            .get("name")
+            .unwrap()
+            .get(&DiscoveryHandlerEndpoint::Uds(
+                "/path/to/socket/name.sock".to_string(),
+            ))
+            .unwrap()
+            .clone();
+        assert_eq!(
+            discovery_handler_details.discovery_details_schema,
+            Some(schema)
+        );
+
+        // Re-registering with a different schema should terminate existing discovery clients
+        let mut stop_discovery_receiver = discovery_handler_details
+            .close_discovery_handler_connection
+            .subscribe();
+        assert!(registration
+            .register_discovery_handler(build_request(""))
+            .await
+            .is_ok());
+        assert!(stop_discovery_receiver.try_recv().is_ok());
+    }
+
     #[test]
     fn test_create_discovery_handler_endpoint() {
         // Assert the endpoint with EndpointType::Uds in converted to DiscoveryHandlerEndpoint::Uds(endpoint)
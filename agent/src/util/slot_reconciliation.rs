@@ -1,4 +1,11 @@
-use super::{constants::SLOT_RECONCILIATION_CHECK_DELAY_SECS, crictl_containers};
+use super::{
+    broker_properties_files::{
+        get_broker_properties_directory, get_slot_directory, remove_broker_properties,
+    },
+    constants::SLOT_RECONCILIATION_CHECK_DELAY_SECS,
+    crictl_containers,
+    device_credentials::{get_device_credentials_directory, remove_device_credentials},
+};
 use akri_shared::{akri::instance::Instance, k8s::KubeInterface};
 use async_trait::async_trait;
 use k8s_openapi::api::core::v1::PodStatus;
@@ -267,9 +274,31 @@ impl DevicePluginSlotReconciler {
                     .await
                 {
                     Ok(()) => {
+                        let broker_properties_directory = get_broker_properties_directory();
+                        let device_credentials_directory = get_device_credentials_directory();
                         slots_to_clean.iter().for_each(|slot| {
                             trace!("reconcile - remove {} from removal_slot_map", slot);
                             self.removal_slot_map.lock().unwrap().remove(slot);
+                            if let Err(e) = remove_broker_properties(&get_slot_directory(
+                                &broker_properties_directory,
+                                &instance.metadata.name,
+                                slot,
+                            )) {
+                                error!(
+                                    "reconcile - could not remove broker properties of slot {}: {}",
+                                    slot, e
+                                );
+                            }
+                            if let Err(e) = remove_device_credentials(&get_slot_directory(
+                                &device_credentials_directory,
+                                &instance.metadata.name,
+                                slot,
+                            )) {
+                                error!(
+                                    "reconcile - could not remove device credentials of slot {}: {}",
+                                    slot, e
+                                );
+                            }
                         });
                     }
                     Err(e) => {
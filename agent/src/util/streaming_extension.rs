@@ -1,4 +1,4 @@
-use akri_discovery_utils::discovery::v0::DiscoverResponse;
+use akri_discovery_utils::discovery::{v0::DiscoverResponse, v1};
 use async_trait::async_trait;
 use tokio::sync::mpsc;
 use tonic::{Code, Status};
@@ -29,3 +29,30 @@ impl StreamingExt for tonic::codec::Streaming<DiscoverResponse> {
         self.message().await
     }
 }
+
+/// An extension trait that is used to get the latest message from streams of Discovery Handlers
+/// that serve the v1 Discovery API.
+#[async_trait]
+pub trait DeltaStreamingExt: Send {
+    async fn get_message(&mut self) -> Result<Option<v1::DiscoverResponse>, Status>;
+}
+
+#[async_trait]
+impl DeltaStreamingExt for mpsc::Receiver<Result<v1::DiscoverResponse, Status>> {
+    async fn get_message(&mut self) -> Result<Option<v1::DiscoverResponse>, Status> {
+        match self.recv().await {
+            Some(result) => match result {
+                Ok(res) => Ok(Some(res)),
+                Err(e) => Err(e),
+            },
+            None => Err(Status::new(Code::Unavailable, "broken pipe")),
+        }
+    }
+}
+
+#[async_trait]
+impl DeltaStreamingExt for tonic::codec::Streaming<v1::DiscoverResponse> {
+    async fn get_message(&mut self) -> Result<Option<v1::DiscoverResponse>, Status> {
+        self.message().await
+    }
+}
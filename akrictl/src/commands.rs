@@ -0,0 +1,610 @@
+use akri_shared::{
+    akri::instance::{is_cordoned, KubeAkriInstance},
+    k8s::{pod::AKRI_INSTANCE_LABEL_NAME, KubeInterface, ERROR_NOT_FOUND},
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// Shown in place of the node of a slot that has not been claimed by any node
+const FREE_SLOT: &str = "<free>";
+/// Shown in place of a value that is not set
+const NONE: &str = "<none>";
+
+/// Lists the Configurations and their Instances along with how many slots of each Instance are claimed by each
+/// node. Instances whose Configuration no longer exists are listed too.
+pub async fn list(
+    kube_interface: &dyn KubeInterface,
+    namespace: Option<&str>,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let in_namespace = |object_namespace: &Option<String>| {
+        namespace.map_or(true, |namespace| {
+            object_namespace.as_deref().unwrap_or_default() == namespace
+        })
+    };
+    // Group the Instances by the namespace and name of their Configuration
+    let mut configurations: BTreeMap<(String, String), Vec<KubeAkriInstance>> = BTreeMap::new();
+    for config in kube_interface.get_configurations().await?.items {
+        if in_namespace(&config.metadata.namespace) {
+            configurations.insert(
+                (
+                    config.metadata.namespace.unwrap_or_default(),
+                    config.metadata.name,
+                ),
+                Vec::new(),
+            );
+        }
+    }
+    for instance in kube_interface.get_instances().await?.items {
+        if in_namespace(&instance.metadata.namespace) {
+            configurations
+                .entry((
+                    instance.metadata.namespace.clone().unwrap_or_default(),
+                    instance.spec.configuration_name.clone(),
+                ))
+                .or_insert_with(Vec::new)
+                .push(instance);
+        }
+    }
+
+    let mut rows = Vec::new();
+    for ((config_namespace, config_name), mut instances) in configurations {
+        if instances.is_empty() {
+            rows.push(vec![
+                config_namespace,
+                config_name,
+                NONE.to_string(),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+            ]);
+            continue;
+        }
+        instances.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+        for instance in instances {
+            rows.push(vec![
+                config_namespace.clone(),
+                config_name.clone(),
+                instance.metadata.name.clone(),
+                instance.spec.shared.to_string(),
+                is_cordoned(&instance).to_string(),
+                join_or_none(&instance.spec.nodes),
+                format_slot_usage(&instance.spec.device_usage),
+            ]);
+        }
+    }
+    Ok(format_table(
+        &[
+            "NAMESPACE",
+            "CONFIGURATION",
+            "INSTANCE",
+            "SHARED",
+            "CORDONED",
+            "NODES",
+            "SLOTS",
+        ],
+        &rows,
+    ))
+}
+
+/// Describes an Instance along with the broker Pods and Services that the Controller created for it
+pub async fn describe(
+    kube_interface: &dyn KubeInterface,
+    name: &str,
+    namespace: &str,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let instance = find_instance(kube_interface, name, namespace).await?;
+    let selector = format!("{}={}", AKRI_INSTANCE_LABEL_NAME, name);
+    let in_namespace = |object_namespace: &Option<String>| {
+        object_namespace.as_deref().unwrap_or_default() == namespace
+    };
+
+    let mut description = format_table(
+        &["Name:", instance.metadata.name.as_str()],
+        &[
+            vec!["Namespace:".to_string(), namespace.to_string()],
+            vec![
+                "Configuration:".to_string(),
+                instance.spec.configuration_name.clone(),
+            ],
+            vec!["Shared:".to_string(), instance.spec.shared.to_string()],
+            vec!["Cordoned:".to_string(), is_cordoned(&instance).to_string()],
+            vec!["Nodes:".to_string(), join_or_none(&instance.spec.nodes)],
+            vec![
+                "Slots:".to_string(),
+                format_slot_usage(&instance.spec.device_usage),
+            ],
+        ],
+    );
+
+    let mut broker_properties: Vec<Vec<String>> = instance
+        .spec
+        .broker_properties
+        .iter()
+        .map(|(key, value)| vec![key.clone(), value.clone()])
+        .collect();
+    broker_properties.sort();
+    description.push_str("\nBroker Properties:\n");
+    description.push_str(&indent(&format_table(
+        &["NAME", "VALUE"],
+        &broker_properties,
+    )));
+
+    description.push_str("\nSlots:\n");
+    description.push_str(&indent(&format_slots(&instance.spec.device_usage)));
+
+    let mut pods: Vec<Vec<String>> = kube_interface
+        .find_pods_with_label(&selector)
+        .await?
+        .items
+        .into_iter()
+        .filter(|pod| in_namespace(&pod.metadata.namespace))
+        .map(|pod| {
+            vec![
+                pod.metadata.name,
+                pod.spec.node_name.unwrap_or_else(|| NONE.to_string()),
+                pod.status
+                    .and_then(|status| status.phase)
+                    .unwrap_or_else(|| NONE.to_string()),
+            ]
+        })
+        .collect();
+    pods.sort();
+    description.push_str("\nBroker Pods:\n");
+    description.push_str(&indent(&format_table(&["NAME", "NODE", "PHASE"], &pods)));
+
+    let mut services: Vec<Vec<String>> = kube_interface
+        .find_services(&selector)
+        .await?
+        .items
+        .into_iter()
+        .filter(|svc| in_namespace(&svc.metadata.namespace))
+        .map(|svc| {
+            let ports = svc
+                .spec
+                .ports
+                .unwrap_or_default()
+                .iter()
+                .map(|port| {
+                    format!(
+                        "{}/{}",
+                        port.port,
+                        port.protocol.as_deref().unwrap_or("TCP")
+                    )
+                })
+                .collect::<Vec<String>>();
+            vec![
+                svc.metadata.name,
+                svc.spec.type_.unwrap_or_else(|| "ClusterIP".to_string()),
+                svc.spec.cluster_ip.unwrap_or_else(|| NONE.to_string()),
+                join_or_none(&ports),
+            ]
+        })
+        .collect();
+    services.sort();
+    description.push_str("\nServices:\n");
+    description.push_str(&indent(&format_table(
+        &["NAME", "TYPE", "CLUSTER-IP", "PORTS"],
+        &services,
+    )));
+    Ok(description)
+}
+
+/// Shows which node has claimed each of an Instance's slots
+pub async fn slots(
+    kube_interface: &dyn KubeInterface,
+    name: &str,
+    namespace: &str,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let instance = find_instance(kube_interface, name, namespace).await?;
+    Ok(format_slots(&instance.spec.device_usage))
+}
+
+/// Releases a slot of an Instance so that it can be claimed again, for example after the node that claimed it was
+/// removed from the cluster before its Agent could release it. The slot can be given by its name or its number.
+pub async fn release(
+    kube_interface: &dyn KubeInterface,
+    name: &str,
+    namespace: &str,
+    slot: &str,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let instance = find_instance(kube_interface, name, namespace).await?;
+    let slot = if slot.parse::<u32>().is_ok() {
+        format!("{}-{}", name, slot)
+    } else {
+        slot.to_string()
+    };
+    let node = match instance.spec.device_usage.get(&slot) {
+        None => return Err(format!("Instance {} has no slot {}", name, slot).into()),
+        Some(node) if node.is_empty() => {
+            return Err(format!("slot {} of Instance {} is not claimed", slot, name).into())
+        }
+        Some(node) => node.clone(),
+    };
+    let mut spec = instance.spec;
+    spec.device_usage.insert(slot.clone(), String::new());
+    kube_interface
+        .update_instance(&spec, name, namespace)
+        .await?;
+    Ok(format!(
+        "released slot {} of Instance {}, which was claimed by node {}",
+        slot, name, node
+    ))
+}
+
+/// Cordons or uncordons an Instance. No new broker Pods are scheduled to a cordoned Instance while the ones that
+/// are already running are left in place.
+pub async fn cordon(
+    kube_interface: &dyn KubeInterface,
+    name: &str,
+    namespace: &str,
+    cordoned: bool,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    find_instance(kube_interface, name, namespace).await?;
+    kube_interface
+        .cordon_instance(name, namespace, cordoned)
+        .await?;
+    Ok(format!(
+        "Instance {} {}",
+        name,
+        if cordoned { "cordoned" } else { "uncordoned" }
+    ))
+}
+
+async fn find_instance(
+    kube_interface: &dyn KubeInterface,
+    name: &str,
+    namespace: &str,
+) -> Result<KubeAkriInstance, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    match kube_interface.find_instance(name, namespace).await {
+        Ok(instance) => Ok(instance),
+        Err(kube::Error::Api(ae)) if ae.code == ERROR_NOT_FOUND => {
+            Err(format!("Instance {} not found in namespace {}", name, namespace).into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Orders slots by their number, which is the suffix of their name, rather than alphabetically
+fn sorted_slots(device_usage: &HashMap<String, String>) -> Vec<(&String, &String)> {
+    let mut slots: Vec<(&String, &String)> = device_usage.iter().collect();
+    slots.sort_by_key(|(slot, _)| {
+        (
+            slot.rsplit('-')
+                .next()
+                .and_then(|number| number.parse::<u32>().ok()),
+            slot.to_string(),
+        )
+    });
+    slots
+}
+
+fn format_slots(device_usage: &HashMap<String, String>) -> String {
+    let rows: Vec<Vec<String>> = sorted_slots(device_usage)
+        .into_iter()
+        .map(|(slot, node)| {
+            vec![
+                slot.clone(),
+                if node.is_empty() {
+                    FREE_SLOT.to_string()
+                } else {
+                    node.clone()
+                },
+            ]
+        })
+        .collect();
+    format_table(&["SLOT", "NODE"], &rows)
+}
+
+/// Summarizes how many slots are claimed by each node and how many are free, such as `node-a=2,<free>=1`
+fn format_slot_usage(device_usage: &HashMap<String, String>) -> String {
+    let mut usage: BTreeMap<&str, usize> = BTreeMap::new();
+    for node in device_usage.values() {
+        let node = if node.is_empty() {
+            FREE_SLOT
+        } else {
+            node.as_str()
+        };
+        *usage.entry(node).or_insert(0) += 1;
+    }
+    let usage: Vec<String> = usage
+        .iter()
+        .map(|(node, count)| format!("{}={}", node, count))
+        .collect();
+    join_or_none(&usage)
+}
+
+fn join_or_none(values: &[String]) -> String {
+    if values.is_empty() {
+        NONE.to_string()
+    } else {
+        values.join(",")
+    }
+}
+
+fn indent(text: &str) -> String {
+    text.lines().map(|line| format!("  {}\n", line)).collect()
+}
+
+/// Formats rows as a table whose columns are aligned like the output of `kubectl get`
+fn format_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = std::cmp::max(*width, cell.len());
+        }
+    }
+    let format_row = |cells: Vec<&str>| {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        format!("{}\n", line.join("   ").trim_end())
+    };
+    let mut table = format_row(headers.to_vec());
+    for row in rows {
+        table.push_str(&format_row(row.iter().map(|cell| cell.as_str()).collect()));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use akri_shared::{
+        akri::{
+            configuration::KubeAkriConfigList,
+            instance::{Instance, KubeAkriInstanceList},
+        },
+        k8s::MockKubeInterface,
+        os::file,
+    };
+
+    fn instance(device_usage: &[(&str, &str)], annotations: serde_json::Value) -> KubeAkriInstance {
+        let device_usage: HashMap<&str, &str> = device_usage.iter().cloned().collect();
+        serde_json::from_value(serde_json::json!({
+            "apiVersion": "akri.sh/v0",
+            "kind": "Instance",
+            "metadata": {
+                "name": "config-a-b494b6",
+                "namespace": "config-a-namespace",
+                "annotations": annotations
+            },
+            "spec": {
+                "configurationName": "config-a",
+                "brokerProperties": { "RESOLUTION_WIDTH": "800" },
+                "shared": true,
+                "nodes": ["node-a", "node-b"],
+                "deviceUsage": device_usage
+            }
+        }))
+        .unwrap()
+    }
+
+    fn mock_find_instance(mock: &mut MockKubeInterface, instance: KubeAkriInstance) {
+        mock.expect_find_instance()
+            .withf(|name, namespace| name == "config-a-b494b6" && namespace == "config-a-namespace")
+            .returning(move |_, _| Ok(instance.clone()));
+    }
+
+    const SLOTS: [(&str, &str); 3] = [
+        ("config-a-b494b6-0", "node-a"),
+        ("config-a-b494b6-1", ""),
+        ("config-a-b494b6-10", "node-b"),
+    ];
+
+    /// Splits output into the words of each line so that tests do not depend on the width of columns
+    fn words(output: &str) -> Vec<Vec<&str>> {
+        output
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_list() {
+        let mut mock = MockKubeInterface::new();
+        mock.expect_get_configurations().returning(|| {
+            let config_a: serde_json::Value =
+                serde_json::from_str(&file::read_file_to_string("../test/json/config-a.json"))
+                    .unwrap();
+            let configs: KubeAkriConfigList = serde_json::from_value(serde_json::json!({
+                "apiVersion": "v1",
+                "kind": "List",
+                "metadata": { "resourceVersion": "", "selfLink": "" },
+                "items": [
+                    config_a,
+                    {
+                        "apiVersion": "akri.sh/v0",
+                        "kind": "Configuration",
+                        "metadata": { "name": "config-b", "namespace": "config-a-namespace" },
+                        "spec": { "discoveryHandler": { "name": "debugEcho" } }
+                    }
+                ]
+            }))
+            .unwrap();
+            Ok(configs)
+        });
+        mock.expect_get_instances().returning(|| {
+            let mut instances: KubeAkriInstanceList = serde_json::from_str(
+                &file::read_file_to_string("../test/json/local-instance-list.json"),
+            )
+            .unwrap();
+            instances.items = vec![instance(&SLOTS, serde_json::json!({}))];
+            Ok(instances)
+        });
+        let output = list(&mock, None).await.unwrap();
+        assert_eq!(
+            words(&output),
+            vec![
+                vec![
+                    "NAMESPACE",
+                    "CONFIGURATION",
+                    "INSTANCE",
+                    "SHARED",
+                    "CORDONED",
+                    "NODES",
+                    "SLOTS"
+                ],
+                vec![
+                    "config-a-namespace",
+                    "config-a",
+                    "config-a-b494b6",
+                    "true",
+                    "false",
+                    "node-a,node-b",
+                    "<free>=1,node-a=1,node-b=1"
+                ],
+                vec!["config-a-namespace", "config-b", "<none>"],
+            ]
+        );
+        // Columns are aligned
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0].find("INSTANCE"), lines[1].find("config-a-b494b6"));
+
+        let output = list(&mock, Some("other-namespace")).await.unwrap();
+        assert_eq!(words(&output).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_describe() {
+        let mut mock = MockKubeInterface::new();
+        mock_find_instance(
+            &mut mock,
+            instance(&SLOTS, serde_json::json!({ "akri.sh/cordoned": "true" })),
+        );
+        mock.expect_find_pods_with_label()
+            .withf(|selector| selector == "akri.sh/instance=config-a-b494b6")
+            .returning(|_| {
+                Ok(serde_json::from_str(&file::read_file_to_string(
+                    "../test/json/running-pod-list-for-config-a-local.json",
+                ))
+                .unwrap())
+            });
+        mock.expect_find_services()
+            .withf(|selector| selector == "akri.sh/instance=config-a-b494b6")
+            .returning(|_| {
+                Ok(serde_json::from_str(&file::read_file_to_string(
+                    "../test/json/running-instance-svc-list-for-config-a-local.json",
+                ))
+                .unwrap())
+            });
+        let output = describe(&mock, "config-a-b494b6", "config-a-namespace")
+            .await
+            .unwrap();
+        let lines = words(&output);
+        assert_eq!(lines[0], vec!["Name:", "config-a-b494b6"]);
+        for expected_line in &[
+            vec!["Cordoned:", "true"],
+            vec!["Slots:", "<free>=1,node-a=1,node-b=1"],
+            vec!["RESOLUTION_WIDTH", "800"],
+            vec!["config-a-b494b6-1", "<free>"],
+            vec!["config-a-b494b6-pod", "<none>", "Running"],
+            vec![
+                "node-a-config-a-b494b6-svc",
+                "ClusterIP",
+                "10.103.66.209",
+                "6052/TCP",
+            ],
+        ] {
+            assert!(lines.contains(expected_line), "{}", output);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_describe_not_found() {
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_instance().returning(|_, _| {
+            Err(kube::Error::Api(kube::ErrorResponse {
+                status: "Failure".to_string(),
+                message: "instances.akri.sh \"foo\" not found".to_string(),
+                reason: "NotFound".to_string(),
+                code: ERROR_NOT_FOUND,
+            }))
+        });
+        assert_eq!(
+            describe(&mock, "foo", "default")
+                .await
+                .unwrap_err()
+                .to_string(),
+            "Instance foo not found in namespace default"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slots() {
+        let mut mock = MockKubeInterface::new();
+        mock_find_instance(&mut mock, instance(&SLOTS, serde_json::json!({})));
+        let output = slots(&mock, "config-a-b494b6", "config-a-namespace")
+            .await
+            .unwrap();
+        // Slots are ordered by number
+        assert_eq!(
+            words(&output),
+            vec![
+                vec!["SLOT", "NODE"],
+                vec!["config-a-b494b6-0", "node-a"],
+                vec!["config-a-b494b6-1", "<free>"],
+                vec!["config-a-b494b6-10", "node-b"],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_release() {
+        let mut mock = MockKubeInterface::new();
+        mock_find_instance(&mut mock, instance(&SLOTS, serde_json::json!({})));
+        mock.expect_update_instance()
+            .times(1)
+            .withf(|spec: &Instance, name, namespace| {
+                name == "config-a-b494b6"
+                    && namespace == "config-a-namespace"
+                    && spec.device_usage["config-a-b494b6-10"].is_empty()
+                    && spec.device_usage["config-a-b494b6-0"] == "node-a"
+            })
+            .returning(|_, _, _| Ok(()));
+        assert_eq!(
+            release(&mock, "config-a-b494b6", "config-a-namespace", "10")
+                .await
+                .unwrap(),
+            "released slot config-a-b494b6-10 of Instance config-a-b494b6, which was claimed by node node-b"
+        );
+        assert_eq!(
+            release(
+                &mock,
+                "config-a-b494b6",
+                "config-a-namespace",
+                "config-a-b494b6-1"
+            )
+            .await
+            .unwrap_err()
+            .to_string(),
+            "slot config-a-b494b6-1 of Instance config-a-b494b6 is not claimed"
+        );
+        assert_eq!(
+            release(&mock, "config-a-b494b6", "config-a-namespace", "5")
+                .await
+                .unwrap_err()
+                .to_string(),
+            "Instance config-a-b494b6 has no slot config-a-b494b6-5"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cordon() {
+        let mut mock = MockKubeInterface::new();
+        mock_find_instance(&mut mock, instance(&SLOTS, serde_json::json!({})));
+        mock.expect_cordon_instance()
+            .times(1)
+            .withf(|name, namespace, cordoned| {
+                name == "config-a-b494b6" && namespace == "config-a-namespace" && *cordoned
+            })
+            .returning(|_, _, _| Ok(()));
+        assert_eq!(
+            cordon(&mock, "config-a-b494b6", "config-a-namespace", true)
+                .await
+                .unwrap(),
+            "Instance config-a-b494b6 cordoned"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+mod commands;
+
+use akri_shared::k8s;
+use clap::{App, AppSettings, Arg, ArgMatches};
+
+/// Namespace that Instances are looked up in when none is given
+const DEFAULT_NAMESPACE: &str = "default";
+
+fn namespace_arg() -> Arg<'static> {
+    Arg::new("namespace")
+        .short('n')
+        .long("namespace")
+        .takes_value(true)
+        .about("Namespace of the Instance")
+}
+
+fn instance_arg() -> Arg<'static> {
+    Arg::new("instance")
+        .required(true)
+        .about("Name of the Instance")
+}
+
+fn namespace(matches: &ArgMatches) -> &str {
+    matches.value_of("namespace").unwrap_or(DEFAULT_NAMESPACE)
+}
+
+/// This is the entry point for akrictl, which inspects and repairs Akri Configurations and Instances.
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+
+    let matches = App::new("akrictl")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Inspects and repairs Akri Configurations and Instances")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            App::new("list")
+                .about("Lists Configurations, their Instances and which nodes claimed their slots")
+                .arg(
+                    Arg::new("namespace")
+                        .short('n')
+                        .long("namespace")
+                        .takes_value(true)
+                        .about("Only list Configurations and Instances in this namespace"),
+                ),
+        )
+        .subcommand(
+            App::new("describe")
+                .about("Describes an Instance and its broker Pods and Services")
+                .arg(instance_arg())
+                .arg(namespace_arg()),
+        )
+        .subcommand(
+            App::new("slots")
+                .about("Shows which node claimed each slot of an Instance")
+                .arg(instance_arg())
+                .arg(namespace_arg()),
+        )
+        .subcommand(
+            App::new("release")
+                .about("Releases a slot of an Instance that is still claimed by a node that no longer uses it")
+                .arg(instance_arg())
+                .arg(
+                    Arg::new("slot")
+                        .required(true)
+                        .about("Name or number of the slot, such as akri-udev-8120fe-0 or 0"),
+                )
+                .arg(namespace_arg()),
+        )
+        .subcommand(
+            App::new("cordon")
+                .about("Stops new broker Pods from being scheduled to an Instance")
+                .arg(instance_arg())
+                .arg(namespace_arg()),
+        )
+        .subcommand(
+            App::new("uncordon")
+                .about("Allows new broker Pods to be scheduled to an Instance again")
+                .arg(instance_arg())
+                .arg(namespace_arg()),
+        )
+        .get_matches();
+
+    let kube_interface = k8s::create_kube_interface();
+    let result = match matches.subcommand() {
+        Some(("list", sub_matches)) => {
+            commands::list(&kube_interface, sub_matches.value_of("namespace")).await
+        }
+        Some(("describe", sub_matches)) => {
+            commands::describe(
+                &kube_interface,
+                sub_matches.value_of("instance").unwrap(),
+                namespace(sub_matches),
+            )
+            .await
+        }
+        Some(("slots", sub_matches)) => {
+            commands::slots(
+                &kube_interface,
+                sub_matches.value_of("instance").unwrap(),
+                namespace(sub_matches),
+            )
+            .await
+        }
+        Some(("release", sub_matches)) => {
+            commands::release(
+                &kube_interface,
+                sub_matches.value_of("instance").unwrap(),
+                namespace(sub_matches),
+                sub_matches.value_of("slot").unwrap(),
+            )
+            .await
+        }
+        Some(("cordon", sub_matches)) => {
+            commands::cordon(
+                &kube_interface,
+                sub_matches.value_of("instance").unwrap(),
+                namespace(sub_matches),
+                true,
+            )
+            .await
+        }
+        Some(("uncordon", sub_matches)) => {
+            commands::cordon(
+                &kube_interface,
+                sub_matches.value_of("instance").unwrap(),
+                namespace(sub_matches),
+                false,
+            )
+            .await
+        }
+        _ => unreachable!("a subcommand is required"),
+    };
+    match result {
+        Ok(output) => println!("{}", output.trim_end()),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
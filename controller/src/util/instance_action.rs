@@ -2,8 +2,9 @@ use super::super::BROKER_POD_COUNT_METRIC;
 use super::{pod_action::PodAction, pod_action::PodActionInfo};
 use akri_shared::{
     akri::{
-        configuration::KubeAkriConfig, instance::KubeAkriInstance, AKRI_PREFIX, API_INSTANCES,
-        API_NAMESPACE, API_VERSION,
+        configuration::{add_property_labels_and_annotations, KubeAkriConfig},
+        instance::{is_cordoned, KubeAkriInstance},
+        AKRI_PREFIX, API_INSTANCES, API_NAMESPACE, API_VERSION,
     },
     k8s,
     k8s::{
@@ -355,6 +356,7 @@ async fn handle_addition_work(
     instance_namespace: &str,
     instance_class_name: &str,
     instance_shared: bool,
+    instance_properties: &HashMap<String, String>,
     new_node: &str,
     instance_configuration: &KubeAkriConfig,
     kube_interface: &impl KubeInterface,
@@ -366,7 +368,7 @@ async fn handle_addition_work(
 
     if let Some(broker_pod_spec) = &instance_configuration.spec.broker_pod_spec {
         let capability_id = format!("{}/{}", AKRI_PREFIX, instance_name);
-        let new_pod = pod::create_new_pod_from_spec(
+        let mut new_pod = pod::create_new_pod_from_spec(
             &instance_namespace,
             &instance_name,
             &instance_class_name,
@@ -380,6 +382,13 @@ async fn handle_addition_work(
             instance_shared,
             &broker_pod_spec,
         )?;
+        if let Some(property_metadata) = &instance_configuration.spec.property_metadata {
+            add_property_labels_and_annotations(
+                new_pod.metadata.get_or_insert_with(Default::default),
+                property_metadata,
+                instance_properties,
+            );
+        }
 
         trace!("handle_addition_work - New pod spec={:?}", new_pod);
 
@@ -483,8 +492,17 @@ pub async fn handle_instance_change(
         .await?
     }
 
+    // A cordoned Instance keeps its running broker Pods but no new ones are added
+    let cordoned = is_cordoned(instance);
+    if cordoned {
+        info!(
+            "handle_instance_change - Instance {} is cordoned so no broker Pods will be added",
+            instance_name
+        );
+    }
     let nodes_to_add = nodes_to_act_on
         .iter()
+        .filter(|_| !cordoned)
         .filter_map(|(node, context)| {
             if ((context.action) == PodAction::Add) | ((context.action) == PodAction::RemoveAndAdd)
             {
@@ -534,6 +552,7 @@ pub async fn handle_instance_change(
             &instance_namespace,
             &instance.spec.configuration_name,
             instance.spec.shared,
+            &instance.spec.broker_properties,
             &new_node,
             &instance_configuration_option.as_ref().unwrap(),
             kube_interface,
@@ -812,6 +831,72 @@ mod handle_instance_tests {
         internal_handle_existing_instances(&mock).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_handle_instance_change_for_add_cordoned_local_instance() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockKubeInterface::new();
+        configure_for_handle_instance_change(
+            &mut mock,
+            &HandleInstanceWork {
+                find_pods_selector: "akri.sh/instance=config-a-b494b6",
+                find_pods_result: "../test/json/empty-list.json",
+                find_pods_phase: None,
+                find_pods_start_time: None,
+                find_pods_delete_start_time: false,
+                deletion_work: None,
+                addition_work: None,
+            },
+        );
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        let mut instance: KubeAkriInstance = serde_json::from_str(&instance_json).unwrap();
+        instance.metadata.annotations.insert(
+            akri_shared::akri::AKRI_CORDONED_ANNOTATION_NAME.to_string(),
+            "true".to_string(),
+        );
+        handle_instance(WatchEvent::Added(instance), &mock)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_addition_work_property_labels() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let config_json = file::read_file_to_string("../test/json/config-a.json");
+        let mut config: KubeAkriConfig = serde_json::from_str(&config_json).unwrap();
+        config.spec.property_metadata = Some(
+            serde_json::from_str(
+                r#"{"labels": {"MODEL": "example.com/model"}, "propagateToBrokers": true}"#,
+            )
+            .unwrap(),
+        );
+        let mut properties = HashMap::new();
+        properties.insert("MODEL".to_string(), "Camera 2000".to_string());
+        let mut mock = MockKubeInterface::new();
+        mock.expect_create_pod()
+            .times(1)
+            .withf(|pod, _| {
+                let labels = pod.metadata.as_ref().unwrap().labels.as_ref().unwrap();
+                labels.get("example.com/model") == Some(&"Camera-2000".to_string())
+                    && labels.get(AKRI_INSTANCE_LABEL_NAME) == Some(&"config-a-b494b6".to_string())
+            })
+            .returning(|_, _| Ok(()));
+        handle_addition_work(
+            "config-a-b494b6",
+            "instance-uid",
+            "config-a-namespace",
+            "config-a",
+            true,
+            &properties,
+            "node-a",
+            &config,
+            &mock,
+        )
+        .await
+        .unwrap();
+    }
+
     #[tokio::test]
     async fn test_handle_instance_change_for_add_new_local_instance() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -1,6 +1,6 @@
 use akri_shared::{
     akri::{
-        configuration::KubeAkriConfig,
+        configuration::{add_property_labels_and_annotations, KubeAkriConfig, PropertyMetadata},
         retry::{random_delay, MAX_INSTANCE_UPDATE_TRIES},
     },
     k8s,
@@ -437,6 +437,7 @@ impl BrokerPodWatcher {
             &namespace,
             &configuration_name,
             &configuration,
+            &instance.spec.broker_properties,
             kube_interface,
         )
         .await?;
@@ -445,6 +446,8 @@ impl BrokerPodWatcher {
     }
 
     /// This creates new service or updates existing service with ownership.
+    /// New services are labeled and annotated with the properties of their Instance's device that `property_metadata`
+    /// selects, if any.
     async fn create_or_update_service(
         &self,
         instance_name: &str,
@@ -455,6 +458,7 @@ impl BrokerPodWatcher {
         ownership: OwnershipInfo,
         service_spec: &ServiceSpec,
         is_instance_service: bool,
+        property_metadata: Option<(&PropertyMetadata, &HashMap<String, String>)>,
         kube_interface: &impl KubeInterface,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
         trace!(
@@ -487,7 +491,7 @@ impl BrokerPodWatcher {
         }
 
         if create_new_service {
-            let new_instance_svc = service::create_new_service_from_spec(
+            let mut new_instance_svc = service::create_new_service_from_spec(
                 &namespace,
                 &instance_name,
                 &configuration_name,
@@ -495,6 +499,15 @@ impl BrokerPodWatcher {
                 service_spec,
                 is_instance_service,
             )?;
+            if let Some((property_metadata, properties)) = property_metadata {
+                add_property_labels_and_annotations(
+                    new_instance_svc
+                        .metadata
+                        .get_or_insert_with(Default::default),
+                    property_metadata,
+                    properties,
+                );
+            }
             trace!(
                 "create_or_update_service - New instance svc spec={:?}",
                 new_instance_svc
@@ -516,6 +529,7 @@ impl BrokerPodWatcher {
         namespace: &str,
         configuration_name: &str,
         configuration: &KubeAkriConfig,
+        instance_properties: &HashMap<String, String>,
         kube_interface: &impl KubeInterface,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
         trace!(
@@ -541,6 +555,11 @@ impl BrokerPodWatcher {
                         ownership.clone(),
                         instance_service_spec,
                         true,
+                        configuration
+                            .spec
+                            .property_metadata
+                            .as_ref()
+                            .map(|property_metadata| (property_metadata, instance_properties)),
                         kube_interface,
                     )
                     .await
@@ -578,6 +597,7 @@ impl BrokerPodWatcher {
                         ownership.clone(),
                         configuration_service_spec,
                         false,
+                        None,
                         kube_interface,
                     )
                     .await
@@ -1182,6 +1202,7 @@ mod tests {
                 ownership,
                 &dcc.spec.instance_service_spec.unwrap().clone(),
                 true,
+                None,
                 &mock,
             )
             .await
@@ -1225,6 +1246,7 @@ mod tests {
                 ownership,
                 &dcc.spec.instance_service_spec.unwrap().clone(),
                 true,
+                None,
                 &mock
             )
             .await
@@ -1269,6 +1291,7 @@ mod tests {
                 ownership,
                 &dcc.spec.instance_service_spec.unwrap().clone(),
                 true,
+                None,
                 &mock,
             )
             .await
@@ -1309,6 +1332,7 @@ mod tests {
                 ownership,
                 &dcc.spec.instance_service_spec.unwrap().clone(),
                 true,
+                None,
                 &mock
             )
             .await
@@ -61,3 +61,59 @@ Create the name of the service account to use
 {{- default "default" .Values.serviceAccount.name }}
 {{- end }}
 {{- end }}
+
+{{/*
+Environment variables that configure mTLS and registration tokens between the Agent and Discovery Handlers
+*/}}
+{{- define "akri.discoveryHandlerSecurity.env" -}}
+{{- with .Values.discoveryHandlerSecurity }}
+{{- if .tlsSecretName }}
+- name: DISCOVERY_HANDLER_TLS_DIRECTORY
+  value: /etc/akri/discovery-handler-tls
+{{- end }}
+{{- if .tlsDomainName }}
+- name: DISCOVERY_HANDLER_TLS_DOMAIN_NAME
+  value: {{ .tlsDomainName | quote }}
+{{- end }}
+{{- if .registrationTokenSecretName }}
+- name: DISCOVERY_HANDLER_REGISTRATION_TOKEN_PATH
+  value: /etc/akri/discovery-handler-registration/token
+{{- end }}
+{{- end }}
+{{- end }}
+
+{{/*
+Volume mounts for the Secrets referenced in discoveryHandlerSecurity
+*/}}
+{{- define "akri.discoveryHandlerSecurity.volumeMounts" -}}
+{{- with .Values.discoveryHandlerSecurity }}
+{{- if .tlsSecretName }}
+- name: discovery-handler-tls
+  mountPath: /etc/akri/discovery-handler-tls
+  readOnly: true
+{{- end }}
+{{- if .registrationTokenSecretName }}
+- name: discovery-handler-registration
+  mountPath: /etc/akri/discovery-handler-registration
+  readOnly: true
+{{- end }}
+{{- end }}
+{{- end }}
+
+{{/*
+Volumes for the Secrets referenced in discoveryHandlerSecurity
+*/}}
+{{- define "akri.discoveryHandlerSecurity.volumes" -}}
+{{- with .Values.discoveryHandlerSecurity }}
+{{- if .tlsSecretName }}
+- name: discovery-handler-tls
+  secret:
+    secretName: {{ .tlsSecretName }}
+{{- end }}
+{{- if .registrationTokenSecretName }}
+- name: discovery-handler-registration
+  secret:
+    secretName: {{ .registrationTokenSecretName }}
+{{- end }}
+{{- end }}
+{{- end }}
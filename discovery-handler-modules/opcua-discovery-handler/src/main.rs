@@ -1,7 +1,10 @@
 use akri_discovery_utils::discovery::discovery_handler::{
-    run_discovery_handler, REGISTER_AGAIN_CHANNEL_CAPACITY,
+    discovery_details_schema, run_versioned_discovery_handler, REGISTER_AGAIN_CHANNEL_CAPACITY,
+};
+use akri_opcua::{
+    discovery_handler::{DiscoveryHandlerImpl, OpcuaDiscoveryDetails},
+    DISCOVERY_HANDLER_NAME, SHARED,
 };
-use akri_opcua::{discovery_handler::DiscoveryHandlerImpl, DISCOVERY_HANDLER_NAME, SHARED};
 use log::info;
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
@@ -10,11 +13,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
     let (register_sender, register_receiver) =
         tokio::sync::mpsc::channel(REGISTER_AGAIN_CHANNEL_CAPACITY);
     let discovery_handler = DiscoveryHandlerImpl::new(Some(register_sender));
-    run_discovery_handler(
+    run_versioned_discovery_handler(
         discovery_handler,
         register_receiver,
         DISCOVERY_HANDLER_NAME,
         SHARED,
+        Some(discovery_details_schema::<OpcuaDiscoveryDetails>()),
     )
     .await?;
     info!("main - opcua discovery handler ended");
@@ -1,14 +1,17 @@
 use akri_discovery_utils::discovery::{
-    discovery_handler::{deserialize_discovery_details, DISCOVERED_DEVICES_CHANNEL_CAPACITY},
-    v0::{discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, DiscoverResponse},
-    DiscoverStream,
+    discovery_handler::deserialize_discovery_details,
+    polling::{
+        spawn_polling_discovery, spawn_polling_discovery_v1, PollingDiscoverer, PollingSettings,
+    },
+    v0::{discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest},
+    v1, DiscoverStream, V1DiscoverStream,
 };
 use async_trait::async_trait;
-use log::{error, info, trace};
+use log::{info, trace};
+use schemars::JsonSchema;
 use std::time::Duration;
 use std::{collections::HashMap, fs};
 use tokio::sync::mpsc;
-use tokio::time::delay_for;
 use tonic::{Response, Status};
 
 // TODO: make this configurable
@@ -26,12 +29,36 @@ pub const OFFLINE: &str = "OFFLINE";
 /// DebugEchoDiscoveryDetails describes the necessary information needed to discover and filter debug echo devices.
 /// Specifically, it contains a list (`descriptions`) of fake devices to be discovered.
 /// This information is expected to be serialized in the discovery details map sent during Discover requests.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
 pub struct DebugEchoDiscoveryDetails {
     pub descriptions: Vec<String>,
 }
 
+impl DebugEchoDiscoveryDetails {
+    /// Checks that at least one device is described and that no description is empty.
+    /// Only the Configuration webhook enforces this; `discover` accepts any list of descriptions.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.descriptions.is_empty() {
+            return Err(anyhow::format_err!(
+                "descriptions must list at least one device"
+            ));
+        }
+        if let Some(index) = self
+            .descriptions
+            .iter()
+            .position(|description| description.trim().is_empty())
+        {
+            return Err(anyhow::format_err!(
+                "descriptions[{}] must not be empty",
+                index
+            ));
+        }
+        Ok(())
+    }
+}
+
 /// The DiscoveryHandlerImpl discovers a list of devices, named in its `descriptions`.
 /// It mocks discovering the devices by inspecting the contents of the file at `DEBUG_ECHO_AVAILABILITY_CHECK_PATH`.
 /// If the file contains "OFFLINE", it won't discover any of the devices, else it discovers them all.
@@ -43,6 +70,16 @@ impl DiscoveryHandlerImpl {
     pub fn new(register_sender: Option<mpsc::Sender<()>>) -> Self {
         DiscoveryHandlerImpl { register_sender }
     }
+
+    /// Returns the discoverer that scans for the devices described by the discovery details of a Discover request
+    fn discoverer(discovery_details: &str) -> Result<DebugEchoDiscoverer, Status> {
+        let discovery_handler_config: DebugEchoDiscoveryDetails =
+            deserialize_discovery_details(discovery_details)
+                .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
+        Ok(DebugEchoDiscoverer {
+            descriptions: discovery_handler_config.descriptions,
+        })
+    }
 }
 
 #[async_trait]
@@ -53,81 +90,67 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
         request: tonic::Request<DiscoverRequest>,
     ) -> Result<Response<Self::DiscoverStream>, Status> {
         info!("discover - called for debug echo protocol");
-        let register_sender = self.register_sender.clone();
-        let discover_request = request.get_ref();
-        let (mut discovered_devices_sender, discovered_devices_receiver) =
-            mpsc::channel(DISCOVERED_DEVICES_CHANNEL_CAPACITY);
-        let discovery_handler_config: DebugEchoDiscoveryDetails =
-            deserialize_discovery_details(&discover_request.discovery_details)
-                .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
-        let descriptions = discovery_handler_config.descriptions;
-        let mut offline = fs::read_to_string(DEBUG_ECHO_AVAILABILITY_CHECK_PATH)
-            .unwrap_or_default()
-            .contains(OFFLINE);
-        let mut first_loop = true;
-        tokio::spawn(async move {
-            loop {
-                let availability =
-                    fs::read_to_string(DEBUG_ECHO_AVAILABILITY_CHECK_PATH).unwrap_or_default();
-                trace!(
-                    "discover -- debugEcho devices are online? {}",
-                    !availability.contains(OFFLINE)
+        Ok(Response::new(spawn_polling_discovery(
+            Self::discoverer(&request.get_ref().discovery_details)?,
+            PollingSettings::new(Duration::from_secs(DISCOVERY_INTERVAL_SECS)),
+            self.register_sender.clone(),
+        )))
+    }
+}
+
+#[async_trait]
+impl akri_discovery_utils::discovery::v1::discovery_handler_server::DiscoveryHandler
+    for DiscoveryHandlerImpl
+{
+    type DiscoverStream = V1DiscoverStream;
+    async fn discover(
+        &self,
+        request: tonic::Request<v1::DiscoverRequest>,
+    ) -> Result<Response<Self::DiscoverStream>, Status> {
+        info!("discover - called for debug echo protocol over the v1 Discovery API");
+        Ok(Response::new(spawn_polling_discovery_v1(
+            Self::discoverer(&request.get_ref().discovery_details)?,
+            PollingSettings::new(Duration::from_secs(DISCOVERY_INTERVAL_SECS)),
+            self.register_sender.clone(),
+        )))
+    }
+}
+
+/// Discovers all of the described devices unless `DEBUG_ECHO_AVAILABILITY_CHECK_PATH` says they are offline
+struct DebugEchoDiscoverer {
+    descriptions: Vec<String>,
+}
+
+#[async_trait]
+impl PollingDiscoverer for DebugEchoDiscoverer {
+    async fn discover_once(&mut self) -> Result<Vec<Device>, anyhow::Error> {
+        let availability =
+            fs::read_to_string(DEBUG_ECHO_AVAILABILITY_CHECK_PATH).unwrap_or_default();
+        trace!(
+            "discover_once -- debugEcho devices are online? {}",
+            !availability.contains(OFFLINE)
+        );
+        // If the devices are offline, return an empty list of instance info
+        if availability.contains(OFFLINE) {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .descriptions
+            .iter()
+            .map(|description| {
+                let mut properties = HashMap::new();
+                properties.insert(
+                    super::DEBUG_ECHO_DESCRIPTION_LABEL.to_string(),
+                    description.clone(),
                 );
-                if (availability.contains(OFFLINE) && !offline) || offline && first_loop {
-                    if first_loop {
-                        first_loop = false;
-                    }
-                    // If the device is now offline, return an empty list of instance info
-                    offline = true;
-                    if let Err(e) = discovered_devices_sender
-                        .send(Ok(DiscoverResponse {
-                            devices: Vec::new(),
-                        }))
-                        .await
-                    {
-                        error!("discover - for debugEcho failed to send discovery response with error {}", e);
-                        if let Some(mut sender) = register_sender {
-                            sender.send(()).await.unwrap();
-                        }
-                        break;
-                    }
-                } else if (!availability.contains(OFFLINE) && offline) || !offline && first_loop {
-                    if first_loop {
-                        first_loop = false;
-                    }
-                    offline = false;
-                    let devices = descriptions
-                        .iter()
-                        .map(|description| {
-                            let mut properties = HashMap::new();
-                            properties.insert(
-                                super::DEBUG_ECHO_DESCRIPTION_LABEL.to_string(),
-                                description.clone(),
-                            );
-                            Device {
-                                id: description.clone(),
-                                properties,
-                                mounts: Vec::default(),
-                                device_specs: Vec::default(),
-                            }
-                        })
-                        .collect::<Vec<Device>>();
-                    if let Err(e) = discovered_devices_sender
-                        .send(Ok(DiscoverResponse { devices }))
-                        .await
-                    {
-                        // TODO: consider re-registering here
-                        error!("discover - for debugEcho failed to send discovery response with error {}", e);
-                        if let Some(mut sender) = register_sender {
-                            sender.send(()).await.unwrap();
-                        }
-                        break;
-                    }
+                Device {
+                    id: description.clone(),
+                    properties,
+                    mounts: Vec::default(),
+                    device_specs: Vec::default(),
                 }
-                delay_for(Duration::from_secs(DISCOVERY_INTERVAL_SECS)).await;
-            }
-        });
-        Ok(Response::new(discovered_devices_receiver))
+            })
+            .collect())
     }
 }
 
@@ -199,4 +222,25 @@ mod tests {
         assert_eq!(1, devices.len());
         assert_eq!(devices[0], device);
     }
+
+    #[test]
+    fn test_validate_discovery_details() {
+        let dh_config: DebugEchoDiscoveryDetails =
+            deserialize_discovery_details("descriptions: [\"foo1\"]").unwrap();
+        assert!(dh_config.validate().is_ok());
+
+        let dh_config: DebugEchoDiscoveryDetails =
+            deserialize_discovery_details("descriptions: []").unwrap();
+        assert_eq!(
+            dh_config.validate().unwrap_err().to_string(),
+            "descriptions must list at least one device"
+        );
+
+        let dh_config: DebugEchoDiscoveryDetails =
+            deserialize_discovery_details("descriptions: [\"foo1\", \" \"]").unwrap();
+        assert_eq!(
+            dh_config.validate().unwrap_err().to_string(),
+            "descriptions[1] must not be empty"
+        );
+    }
 }
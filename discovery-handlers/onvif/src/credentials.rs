@@ -0,0 +1,240 @@
+use k8s_openapi::api::core::v1::Secret;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+
+/// Key of the username that is used for cameras without credentials of their own in a Secret of ONVIF credentials
+pub const DEFAULT_USERNAME_KEY: &str = "username";
+/// Key of the password that is used for cameras without credentials of their own in a Secret of ONVIF credentials
+pub const DEFAULT_PASSWORD_KEY: &str = "password";
+/// Suffix of the keys of a camera's username, which are prefixed with the host of its device service url
+pub const USERNAME_KEY_SUFFIX: &str = ".username";
+/// Suffix of the keys of a camera's password, which are prefixed with the host of its device service url
+pub const PASSWORD_KEY_SUFFIX: &str = ".password";
+
+const WSSE_NAMESPACE: &str =
+    "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-secext-1.0.xsd";
+const WSU_NAMESPACE: &str =
+    "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-utility-1.0.xsd";
+const PASSWORD_DIGEST_TYPE: &str =
+    "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-username-token-profile-1.0#PasswordDigest";
+const BASE64_ENCODING_TYPE: &str =
+    "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-soap-message-security-1.0#Base64Binary";
+
+/// Username and password with which an ONVIF camera is queried
+#[derive(Clone, Debug, PartialEq)]
+pub struct OnvifCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// Credentials of ONVIF cameras read from a Secret, which holds a default `username` and `password` and/or the
+/// `<host>.username` and `<host>.password` of specific cameras, where `<host>` is the host of a camera's device
+/// service url with every character that Secret keys do not allow replaced with `_`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OnvifCredentialsStore {
+    default: Option<OnvifCredentials>,
+    devices: HashMap<String, OnvifCredentials>,
+}
+
+impl OnvifCredentialsStore {
+    /// Reads the credentials in a Secret. Usernames without a password and values that are not valid UTF-8 are
+    /// ignored.
+    pub fn from_secret(secret: &Secret) -> Self {
+        let data: HashMap<&str, String> = secret
+            .data
+            .iter()
+            .flatten()
+            .filter_map(|(key, value)| {
+                String::from_utf8(value.0.clone())
+                    .ok()
+                    .map(|value| (key.as_str(), value))
+            })
+            .collect();
+        let get_credentials = |username_key: &str, password_key: &str| {
+            Some(OnvifCredentials {
+                username: data.get(username_key)?.clone(),
+                password: data.get(password_key)?.clone(),
+            })
+        };
+        let devices = data
+            .keys()
+            .filter_map(|key| key.strip_suffix(USERNAME_KEY_SUFFIX))
+            .filter_map(|host| {
+                get_credentials(
+                    &format!("{}{}", host, USERNAME_KEY_SUFFIX),
+                    &format!("{}{}", host, PASSWORD_KEY_SUFFIX),
+                )
+                .map(|credentials| (host.to_string(), credentials))
+            })
+            .collect();
+        OnvifCredentialsStore {
+            default: get_credentials(DEFAULT_USERNAME_KEY, DEFAULT_PASSWORD_KEY),
+            devices,
+        }
+    }
+
+    /// Returns the credentials of the camera with a device service url, falling back to the default credentials
+    pub fn get(&self, device_service_url: &str) -> Option<&OnvifCredentials> {
+        get_host_key(device_service_url)
+            .and_then(|host| self.devices.get(&host))
+            .or_else(|| self.default.as_ref())
+    }
+}
+
+/// Returns the host of a device service url as it is used in the keys of a Secret of ONVIF credentials
+fn get_host_key(device_service_url: &str) -> Option<String> {
+    let uri = device_service_url.parse::<hyper::Uri>().ok()?;
+    let host = uri.host()?.trim_start_matches('[').trim_end_matches(']');
+    Some(
+        host.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Adds a WS-Security UsernameToken header with a password digest to a SOAP message, using a random nonce and the
+/// current time
+pub fn secure_message(message: &str, credentials: &OnvifCredentials) -> String {
+    let nonce = uuid::Uuid::new_v4();
+    let created = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    add_security_header(
+        message,
+        &get_username_token(credentials, nonce.as_bytes(), &created),
+    )
+}
+
+/// Returns a WS-Security header with a UsernameToken whose password is the base64 encoded SHA-1 digest of the nonce,
+/// the creation time and the password, as ONVIF cameras require
+fn get_username_token(credentials: &OnvifCredentials, nonce: &[u8], created: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(nonce);
+    hasher.update(created.as_bytes());
+    hasher.update(credentials.password.as_bytes());
+    format!(
+        r#"<wsse:Security soap:mustUnderstand="1" xmlns:wsse="{}" xmlns:wsu="{}"><wsse:UsernameToken><wsse:Username>{}</wsse:Username><wsse:Password Type="{}">{}</wsse:Password><wsse:Nonce EncodingType="{}">{}</wsse:Nonce><wsu:Created>{}</wsu:Created></wsse:UsernameToken></wsse:Security>"#,
+        WSSE_NAMESPACE,
+        WSU_NAMESPACE,
+        escape_xml(&credentials.username),
+        PASSWORD_DIGEST_TYPE,
+        base64::encode(hasher.finalize()),
+        BASE64_ENCODING_TYPE,
+        base64::encode(nonce),
+        created
+    )
+}
+
+/// Replaces the empty header of a SOAP message with one that holds a security header
+fn add_security_header(message: &str, security_header: &str) -> String {
+    message.replacen(
+        "<soap:Header/>",
+        &format!("<soap:Header>{}</soap:Header>", security_header),
+        1,
+    )
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::ByteString;
+
+    fn secret(data: &[(&str, &str)]) -> Secret {
+        Secret {
+            data: Some(
+                data.iter()
+                    .map(|(key, value)| (key.to_string(), ByteString(value.as_bytes().to_vec())))
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    fn credentials(username: &str, password: &str) -> OnvifCredentials {
+        OnvifCredentials {
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_credentials_store() {
+        let store = OnvifCredentialsStore::from_secret(&secret(&[
+            ("username", "admin"),
+            ("password", "default-password"),
+            ("10.0.0.1.username", "camera1"),
+            ("10.0.0.1.password", "camera1-password"),
+            ("fe80__1.username", "camera2"),
+            ("fe80__1.password", "camera2-password"),
+            ("10.0.0.3.username", "no-password"),
+        ]));
+        assert_eq!(
+            store.get("http://10.0.0.1:80/onvif/device_service"),
+            Some(&credentials("camera1", "camera1-password"))
+        );
+        assert_eq!(
+            store.get("http://[fe80::1]/onvif/device_service"),
+            Some(&credentials("camera2", "camera2-password"))
+        );
+        assert_eq!(
+            store.get("http://10.0.0.3/onvif/device_service"),
+            Some(&credentials("admin", "default-password"))
+        );
+
+        let store = OnvifCredentialsStore::from_secret(&secret(&[
+            ("10.0.0.1.username", "camera1"),
+            ("10.0.0.1.password", "camera1-password"),
+        ]));
+        assert_eq!(store.get("http://10.0.0.2/onvif/device_service"), None);
+        assert_eq!(
+            OnvifCredentialsStore::from_secret(&Secret::default()),
+            OnvifCredentialsStore::default()
+        );
+    }
+
+    #[test]
+    fn test_get_username_token() {
+        // Example from the ONVIF Application Programmer's Guide
+        let nonce = base64::decode("LKqI6G/AikKCQrN0zqZFlg==").unwrap();
+        let token = get_username_token(
+            &credentials("admin", "userpassword"),
+            &nonce,
+            "2010-09-16T07:50:45Z",
+        );
+        assert!(token.contains("<wsse:Username>admin</wsse:Username>"));
+        assert!(token.contains(">tuOSpGlFlIXsozq4HFNeeGeFLEI=</wsse:Password>"));
+        assert!(token.contains(">LKqI6G/AikKCQrN0zqZFlg==</wsse:Nonce>"));
+        assert!(token.contains("<wsu:Created>2010-09-16T07:50:45Z</wsu:Created>"));
+
+        let token = get_username_token(
+            &credentials("<admin>", "userpassword"),
+            &nonce,
+            "2010-09-16T07:50:45Z",
+        );
+        assert!(token.contains("<wsse:Username>&lt;admin&gt;</wsse:Username>"));
+    }
+
+    #[test]
+    fn test_secure_message() {
+        let message = r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope"><soap:Header/><soap:Body/></soap:Envelope>"#;
+        let secured = secure_message(message, &credentials("admin", "userpassword"));
+        assert!(secured.starts_with(
+            r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope"><soap:Header><wsse:Security"#
+        ));
+        assert!(secured.ends_with("</wsse:Security></soap:Header><soap:Body/></soap:Envelope>"));
+        sxd_document::parser::parse(&secured).unwrap();
+    }
+}
@@ -1,34 +1,78 @@
-use super::discovery_impl::util;
+use super::credentials::OnvifCredentialsStore;
+use super::discovery_impl::util::{self, Announcement, ProbeTargets};
 use super::discovery_utils::{
-    OnvifQuery, OnvifQueryImpl, ONVIF_DEVICE_IP_ADDRESS_LABEL_ID,
-    ONVIF_DEVICE_MAC_ADDRESS_LABEL_ID, ONVIF_DEVICE_SERVICE_URL_LABEL_ID,
+    OnvifDeviceInformation, OnvifQuery, OnvifQueryImpl, MEDIA_WSDL,
+    ONVIF_DEVICE_FIRMWARE_VERSION_LABEL_ID, ONVIF_DEVICE_HARDWARE_ID_LABEL_ID,
+    ONVIF_DEVICE_HOSTNAME_LABEL_ID, ONVIF_DEVICE_IP_ADDRESS_LABEL_ID,
+    ONVIF_DEVICE_MAC_ADDRESS_LABEL_ID, ONVIF_DEVICE_MANUFACTURER_LABEL_ID,
+    ONVIF_DEVICE_MODEL_LABEL_ID, ONVIF_DEVICE_PROFILES_LABEL_ID,
+    ONVIF_DEVICE_PROFILE_STREAM_URI_LABEL_PREFIX, ONVIF_DEVICE_SERIAL_NUMBER_LABEL_ID,
+    ONVIF_DEVICE_SERVICE_URL_LABEL_ID, ONVIF_DEVICE_STREAM_URI_LABEL_ID, PTZ_WSDL,
 };
 use akri_discovery_utils::{
     discovery::{
-        discovery_handler::{deserialize_discovery_details, DISCOVERED_DEVICES_CHANNEL_CAPACITY},
-        v0::{
-            discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, DiscoverResponse,
+        discovery_handler::deserialize_discovery_details,
+        polling::{
+            spawn_polling_discovery, spawn_polling_discovery_v1, PollingDiscoverer, PollingSettings,
         },
-        DiscoverStream,
+        v0::{discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest},
+        v1, DiscoverStream, V1DiscoverStream,
     },
     filtering::{FilterList, FilterType},
 };
+use akri_shared::k8s::{self, KubeInterface};
 use async_trait::async_trait;
-use log::{error, info, trace};
-use std::{collections::HashMap, time::Duration};
-use tokio::{sync::mpsc, time::delay_for};
+use ipnet::Ipv4Net;
+use log::{error, info, trace, warn};
+use schemars::JsonSchema;
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, Ipv4Addr},
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{mpsc, mpsc::error::TryRecvError, oneshot},
+    time::delay_for,
+};
 use tonic::{Response, Status};
 
-// TODO: make this configurable
+/// Default interval between probes, which `discoveryIntervalSeconds` can change
 pub const DISCOVERY_INTERVAL_SECS: u64 = 10;
+/// Capacity of the channel of announcements from the listener of Hello and Bye
+const ANNOUNCEMENT_CHANNEL_CAPACITY: usize = 64;
+/// Seconds for which credentials read from a Secret are used before the Secret is read again
+pub const CREDENTIALS_REFRESH_SECS: u64 = 60;
+/// Prefix of the capabilities of a camera's media profiles, which is followed by the profile's token
+pub const MEDIA_PROFILE_CAPABILITY_PREFIX: &str = "MediaProfile/";
+/// Names of the capabilities of cameras that offer ONVIF services, by the services' namespaces
+const ONVIF_SERVICES: [(&str, &str); 10] = [
+    ("Media", MEDIA_WSDL),
+    ("Media2", "http://www.onvif.org/ver20/media/wsdl"),
+    ("PTZ", PTZ_WSDL),
+    ("Imaging", "http://www.onvif.org/ver20/imaging/wsdl"),
+    ("Events", "http://www.onvif.org/ver10/events/wsdl"),
+    ("Analytics", "http://www.onvif.org/ver20/analytics/wsdl"),
+    ("DeviceIO", "http://www.onvif.org/ver10/deviceIO/wsdl"),
+    ("Recording", "http://www.onvif.org/ver10/recording/wsdl"),
+    ("Replay", "http://www.onvif.org/ver10/replay/wsdl"),
+    ("Search", "http://www.onvif.org/ver10/search/wsdl"),
+];
+
+/// Maximum number of addresses that `unicastAddresses` may expand to, since each is probed on every discovery
+pub const MAX_UNICAST_ADDRESSES: u64 = 1024;
 
 /// This defines the ONVIF data stored in the Configuration
 /// CRD
 ///
 /// The ONVIF discovery handler is structured to store a filter list for
-/// ip addresses, mac addresses, and ONVIF scopes.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// ip addresses, mac addresses, ONVIF scopes, WS-Discovery types, device
+/// information and capabilities, along with whether to query
+/// the device information and stream uris of cameras and pass them to brokers,
+/// where to send probes besides the WS-Discovery multicast group, how often to
+/// probe and whether to also react to cameras announcing themselves.
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
 pub struct OnvifDiscoveryDetails {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub ip_addresses: Option<FilterList>,
@@ -36,14 +80,203 @@ pub struct OnvifDiscoveryDetails {
     pub mac_addresses: Option<FilterList>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub scopes: Option<FilterList>,
+    /// Filters cameras on the WS-Discovery types they answer probes with, such as `dn:NetworkVideoTransmitter`.
+    /// Types are compared by their local names, ignoring namespace prefixes. Cameras that were only probed on their
+    /// device service, such as the ones found through `unicastAddresses`, have no types and do not pass `Include`
+    /// filters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub types: Option<FilterList>,
+    /// Filters cameras on the manufacturer in their device information
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufacturers: Option<FilterList>,
+    /// Filters cameras on the hardware model in their device information
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub models: Option<FilterList>,
+    /// Filters cameras on the firmware version in their device information
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firmware_versions: Option<FilterList>,
+    /// Filters cameras on their capabilities, which are the services they offer, such as `PTZ`, and their media
+    /// profiles as `MediaProfile/<token>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<FilterList>,
     #[serde(default = "default_discovery_timeout_seconds")]
     pub discovery_timeout_seconds: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials_secret: Option<OnvifCredentialsSecret>,
+    /// Adds the manufacturer, model, firmware version, serial number, hardware id and hostname of cameras to the
+    /// properties of their devices
+    #[serde(default)]
+    pub include_device_information: bool,
+    /// Adds the tokens of the media profiles of cameras and the RTSP stream uri of each profile to the properties of
+    /// their devices
+    #[serde(default)]
+    pub include_stream_uris: bool,
+    /// IPv4 addresses or CIDR ranges of cameras to probe directly, for networks that do not route multicast
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unicast_addresses: Vec<String>,
+    /// IPv4 addresses of the local interfaces to send multicast probes from on nodes with several interfaces
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub multicast_interfaces: Vec<String>,
+    /// Seconds between probes, which can be raised to reduce probe traffic when cameras announce themselves
+    #[serde(default = "default_discovery_interval_seconds")]
+    pub discovery_interval_seconds: i32,
+    /// Listens for cameras announcing with WS-Discovery Hello and Bye that they joined or left the network, which
+    /// updates the discovered cameras right away rather than on the next probe
+    #[serde(default = "default_listen_for_announcements")]
+    pub listen_for_announcements: bool,
+}
+
+/// Discovery details of a Configuration that sets none of them, which finds every camera that answers a probe
+impl Default for OnvifDiscoveryDetails {
+    fn default() -> Self {
+        OnvifDiscoveryDetails {
+            ip_addresses: None,
+            mac_addresses: None,
+            scopes: None,
+            types: None,
+            manufacturers: None,
+            models: None,
+            firmware_versions: None,
+            capabilities: None,
+            discovery_timeout_seconds: default_discovery_timeout_seconds(),
+            credentials_secret: None,
+            include_device_information: false,
+            include_stream_uris: false,
+            unicast_addresses: Vec::new(),
+            multicast_interfaces: Vec::new(),
+            discovery_interval_seconds: default_discovery_interval_seconds(),
+            listen_for_announcements: default_listen_for_announcements(),
+        }
+    }
 }
 
 fn default_discovery_timeout_seconds() -> i32 {
     1
 }
 
+fn default_discovery_interval_seconds() -> i32 {
+    DISCOVERY_INTERVAL_SECS as i32
+}
+
+fn default_listen_for_announcements() -> bool {
+    true
+}
+
+/// Secret that holds the credentials with which the Discovery Handler authenticates to cameras. It may hold a default
+/// `username` and `password` along with the `<host>.username` and `<host>.password` of specific cameras, where `<host>`
+/// is the host of a camera's device service url (see `OnvifCredentialsStore`). The namespace is required, since
+/// Discovery Handlers are not told the namespace of the Configuration.
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct OnvifCredentialsSecret {
+    pub name: String,
+    pub namespace: String,
+}
+
+impl OnvifDiscoveryDetails {
+    /// Checks that the IP address filter only contains valid IP addresses and the MAC address filter only contains
+    /// valid MAC addresses, along with the other fields of the details. The Configuration webhook runs this, while
+    /// `discover` only rejects probe targets that cannot be parsed.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if let Some(ip_addresses) = &self.ip_addresses {
+            for (index, item) in ip_addresses.items.iter().enumerate() {
+                if item.parse::<IpAddr>().is_err() {
+                    return Err(anyhow::format_err!(
+                        "ipAddresses.items[{}] \"{}\" is not a valid IP address",
+                        index,
+                        item
+                    ));
+                }
+            }
+        }
+        if let Some(mac_addresses) = &self.mac_addresses {
+            for (index, item) in mac_addresses.items.iter().enumerate() {
+                if !is_mac_address(item) {
+                    return Err(anyhow::format_err!(
+                        "macAddresses.items[{}] \"{}\" is not a valid MAC address, expected six pairs of hexadecimal digits separated by ':' or '-'",
+                        index,
+                        item
+                    ));
+                }
+            }
+        }
+        if let Some(credentials_secret) = &self.credentials_secret {
+            if credentials_secret.name.is_empty() {
+                return Err(anyhow::format_err!(
+                    "credentialsSecret.name must not be empty"
+                ));
+            }
+        }
+        if self.discovery_interval_seconds < 1 {
+            return Err(anyhow::format_err!(
+                "discoveryIntervalSeconds must be at least 1"
+            ));
+        }
+        self.get_probe_targets()?;
+        Ok(())
+    }
+
+    /// Returns the interfaces to send multicast probes from and the addresses that `unicastAddresses` expands to.
+    /// The network and broadcast addresses of CIDR ranges are not probed.
+    pub fn get_probe_targets(&self) -> Result<ProbeTargets, anyhow::Error> {
+        let mut unicast_addresses = Vec::new();
+        let mut address_count = 0;
+        for (index, item) in self.unicast_addresses.iter().enumerate() {
+            if let Ok(address) = item.parse::<Ipv4Addr>() {
+                address_count += 1;
+                unicast_addresses.push(address);
+            } else if let Ok(network) = item.parse::<Ipv4Net>() {
+                address_count += 1u64 << (32 - network.prefix_len());
+                if address_count <= MAX_UNICAST_ADDRESSES {
+                    unicast_addresses.extend(network.hosts());
+                }
+            } else {
+                return Err(anyhow::format_err!(
+                    "unicastAddresses[{}] \"{}\" is not a valid IPv4 address or CIDR range",
+                    index,
+                    item
+                ));
+            }
+        }
+        if address_count > MAX_UNICAST_ADDRESSES {
+            return Err(anyhow::format_err!(
+                "unicastAddresses expand to {} addresses, more than the maximum of {}",
+                address_count,
+                MAX_UNICAST_ADDRESSES
+            ));
+        }
+        let multicast_interfaces = self
+            .multicast_interfaces
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                item.parse::<Ipv4Addr>().map_err(|_| {
+                    anyhow::format_err!(
+                        "multicastInterfaces[{}] \"{}\" is not a valid IPv4 address",
+                        index,
+                        item
+                    )
+                })
+            })
+            .collect::<Result<Vec<Ipv4Addr>, anyhow::Error>>()?;
+        Ok(ProbeTargets {
+            multicast_interfaces,
+            unicast_addresses,
+        })
+    }
+}
+
+/// Returns whether a string is a MAC address made up of six pairs of hexadecimal digits separated by ':' or '-'
+fn is_mac_address(mac_address: &str) -> bool {
+    let separator = if mac_address.contains('-') { '-' } else { ':' };
+    let octets: Vec<&str> = mac_address.split(separator).collect();
+    octets.len() == 6
+        && octets
+            .iter()
+            .all(|octet| octet.len() == 2 && octet.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
 /// `DiscoveryHandlerImpl` discovers the onvif instances as described by the filters `discover_handler_config.ip_addresses`,
 /// `discover_handler_config.mac_addresses`, and `discover_handler_config.scopes`.
 /// The instances it discovers are always shared.
@@ -55,6 +288,82 @@ impl DiscoveryHandlerImpl {
     pub fn new(register_sender: Option<mpsc::Sender<()>>) -> Self {
         DiscoveryHandlerImpl { register_sender }
     }
+
+    /// Returns the discoverer that scans for the devices described by the discovery details of a Discover request,
+    /// along with how often it scans
+    fn discoverer(
+        discovery_details: &str,
+    ) -> Result<(impl PollingDiscoverer, PollingSettings), Status> {
+        let discovery_handler_config: OnvifDiscoveryDetails =
+            deserialize_discovery_details(discovery_details)
+                .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
+        // Only the probe targets are checked here, since discovery cannot start without them. The other checks in
+        // `validate` are left to the Configuration webhook, so that details accepted before they were added still work.
+        let probe_targets = discovery_handler_config
+            .get_probe_targets()
+            .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
+        let discovery_interval =
+            Duration::from_secs(discovery_handler_config.discovery_interval_seconds.max(1) as u64);
+        let discovery_timeout =
+            Duration::from_secs(discovery_handler_config.discovery_timeout_seconds as u64);
+        let (announcement_receiver, stop_listening_sender) =
+            if discovery_handler_config.listen_for_announcements {
+                let (announcement_sender, announcement_receiver) =
+                    mpsc::channel(ANNOUNCEMENT_CHANNEL_CAPACITY);
+                let (stop_listening_sender, stop_listening_receiver) = oneshot::channel();
+                let multicast_interfaces = probe_targets.multicast_interfaces.clone();
+                tokio::spawn(async move {
+                    // Cameras are still discovered by probing if announcements cannot be received
+                    if let Err(e) = util::listen_for_announcements(
+                        multicast_interfaces,
+                        announcement_sender,
+                        stop_listening_receiver,
+                    )
+                    .await
+                    {
+                        error!(
+                            "discover - stopped listening for ONVIF announcements with error {}",
+                            e
+                        );
+                    }
+                });
+                (Some(announcement_receiver), Some(stop_listening_sender))
+            } else {
+                (None, None)
+            };
+        let kube_interface = match &discovery_handler_config.credentials_secret {
+            Some(_) => Some(k8s::try_create_kube_interface().map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::FailedPrecondition,
+                    format!(
+                        "could not create a Kubernetes client to read credentialsSecret: {}",
+                        e
+                    ),
+                )
+            })?),
+            None => None,
+        };
+        Ok((
+            OnvifDiscoverer {
+                discovery_handler_config,
+                probe_targets,
+                kube_interface,
+                credentials: CachedCredentials::default(),
+                announcement_receiver,
+                _stop_listening_sender: stop_listening_sender,
+                announcements: Vec::new(),
+                endpoint_references: HashMap::new(),
+                discovered_cameras: HashMap::new(),
+                next_probe: Instant::now(),
+                discovery_interval,
+                discovery_timeout,
+            },
+            PollingSettings {
+                max_scan_duration: discovery_timeout,
+                ..PollingSettings::new(discovery_interval)
+            },
+        ))
+    }
 }
 
 #[async_trait]
@@ -65,67 +374,269 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
         request: tonic::Request<DiscoverRequest>,
     ) -> Result<Response<Self::DiscoverStream>, Status> {
         info!("discover - called for ONVIF protocol");
-        let register_sender = self.register_sender.clone();
-        let discover_request = request.get_ref();
-        let (mut discovered_devices_sender, discovered_devices_receiver) =
-            mpsc::channel(DISCOVERED_DEVICES_CHANNEL_CAPACITY);
-        let discovery_handler_config: OnvifDiscoveryDetails =
-            deserialize_discovery_details(&discover_request.discovery_details)
-                .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
-        let mut cameras: Vec<Device> = Vec::new();
-        tokio::spawn(async move {
-            loop {
-                let onvif_query = OnvifQueryImpl {};
-
-                trace!("discover - filters:{:?}", &discovery_handler_config,);
-                let discovered_onvif_cameras = util::simple_onvif_discover(Duration::from_secs(
-                    discovery_handler_config.discovery_timeout_seconds as u64,
-                ))
+        let (discoverer, settings) = Self::discoverer(&request.get_ref().discovery_details)?;
+        Ok(Response::new(spawn_polling_discovery(
+            discoverer,
+            settings,
+            self.register_sender.clone(),
+        )))
+    }
+}
+
+#[async_trait]
+impl akri_discovery_utils::discovery::v1::discovery_handler_server::DiscoveryHandler
+    for DiscoveryHandlerImpl
+{
+    type DiscoverStream = V1DiscoverStream;
+    async fn discover(
+        &self,
+        request: tonic::Request<v1::DiscoverRequest>,
+    ) -> Result<Response<Self::DiscoverStream>, Status> {
+        info!("discover - called for ONVIF protocol over the v1 Discovery API");
+        let (discoverer, settings) = Self::discoverer(&request.get_ref().discovery_details)?;
+        Ok(Response::new(spawn_polling_discovery_v1(
+            discoverer,
+            settings,
+            self.register_sender.clone(),
+        )))
+    }
+}
+
+/// Discovers the cameras that pass a Configuration's filters by probing for them, and keeps the cameras found by the
+/// last probe up to date with the Hello and Bye announcements received in between probes
+struct OnvifDiscoverer<K> {
+    discovery_handler_config: OnvifDiscoveryDetails,
+    probe_targets: ProbeTargets,
+    kube_interface: Option<K>,
+    credentials: CachedCredentials,
+    announcement_receiver: Option<mpsc::Receiver<Announcement>>,
+    /// Stops the listener of announcements when it is dropped along with the discoverer at the end of discovery
+    _stop_listening_sender: Option<oneshot::Sender<()>>,
+    announcements: Vec<Announcement>,
+    /// Device service urls of the discovered cameras by their endpoint references, which Byes identify cameras with
+    endpoint_references: HashMap<String, Vec<String>>,
+    discovered_cameras: HashMap<String, Vec<String>>,
+    next_probe: Instant,
+    discovery_interval: Duration,
+    discovery_timeout: Duration,
+}
+
+#[async_trait]
+impl<K: KubeInterface + 'static> PollingDiscoverer for OnvifDiscoverer<K> {
+    async fn discover_once(&mut self) -> Result<Vec<Device>, anyhow::Error> {
+        let credentials = match (
+            &self.discovery_handler_config.credentials_secret,
+            &self.kube_interface,
+        ) {
+            (Some(credentials_secret), Some(kube_interface)) => self
+                .credentials
+                .get(credentials_secret, kube_interface)
                 .await
-                .unwrap();
-                trace!("discover - discovered:{:?}", &discovered_onvif_cameras,);
-                // apply_filters never returns an error -- safe to unwrap
-                let filtered_onvif_cameras = apply_filters(
-                    &discovery_handler_config,
-                    discovered_onvif_cameras,
-                    &onvif_query,
-                )
+                .clone(),
+            _ => OnvifCredentialsStore::default(),
+        };
+        let onvif_query = OnvifQueryImpl { credentials };
+
+        trace!(
+            "discover_once - filters:{:?}",
+            &self.discovery_handler_config
+        );
+        // Announcements update the cameras found by the last probe rather than triggering a new one
+        if Instant::now() >= self.next_probe {
+            // A failed probe is retried on the next scan
+            let probed_cameras =
+                util::simple_onvif_discover(self.discovery_timeout, &self.probe_targets).await?;
+            self.next_probe = Instant::now() + self.discovery_interval;
+            self.discovered_cameras = probed_cameras.cameras;
+            self.endpoint_references = probed_cameras.endpoint_references;
+            let probed_onvif_cameras = probe_device_services(
+                &self.probe_targets.unicast_addresses,
+                &self
+                    .discovered_cameras
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<String>>(),
+                self.discovery_timeout,
+                &onvif_query,
+            )
+            .await;
+            // The WS-Discovery types of cameras that only answered on their device service are unknown
+            self.discovered_cameras.extend(
+                probed_onvif_cameras
+                    .into_iter()
+                    .map(|url| (url, Vec::new())),
+            );
+        }
+        apply_announcements(
+            &mut self.discovered_cameras,
+            &std::mem::take(&mut self.announcements),
+            &mut self.endpoint_references,
+        );
+        let discovered_onvif_cameras = self.discovered_cameras.clone();
+        trace!("discover_once - discovered:{:?}", &discovered_onvif_cameras);
+        let filtered_onvif_cameras = apply_filters(
+            &self.discovery_handler_config,
+            discovered_onvif_cameras,
+            &onvif_query,
+        )
+        .await?;
+        trace!("discover_once - filtered:{:?}", &filtered_onvif_cameras);
+        Ok(filtered_onvif_cameras)
+    }
+
+    /// Waits until the next probe is due rather than for a whole interval, since discoveries that apply announcements
+    /// do not probe, and discovers again early when cameras announce themselves
+    async fn wait_for_next_scan(&mut self, _interval: Duration) {
+        let until_next_probe = self.next_probe.saturating_duration_since(Instant::now());
+        self.announcements = match &mut self.announcement_receiver {
+            Some(announcement_receiver) => {
+                wait_for_announcements(announcement_receiver, until_next_probe).await
+            }
+            None => {
+                delay_for(until_next_probe).await;
+                Vec::new()
+            }
+        };
+    }
+}
+
+/// Credentials of cameras read from a Secret, which is read again once the credentials are `CREDENTIALS_REFRESH_SECS`
+/// old so that changed credentials are picked up without reading the Secret on every discovery
+#[derive(Default)]
+struct CachedCredentials {
+    credentials: OnvifCredentialsStore,
+    read_at: Option<Instant>,
+}
+
+impl CachedCredentials {
+    /// Returns the cached credentials, reading the Secret first if they are too old. If the Secret cannot be read, the
+    /// credentials that were last read are kept, or cameras are queried without credentials, so that cameras that do
+    /// not enforce authentication are still discovered.
+    async fn get(
+        &mut self,
+        credentials_secret: &OnvifCredentialsSecret,
+        kube_interface: &impl KubeInterface,
+    ) -> &OnvifCredentialsStore {
+        let refresh_interval = Duration::from_secs(CREDENTIALS_REFRESH_SECS);
+        if self
+            .read_at
+            .map_or(true, |read_at| read_at.elapsed() >= refresh_interval)
+        {
+            self.read_at = Some(Instant::now());
+            match kube_interface
+                .find_secret(&credentials_secret.name, &credentials_secret.namespace)
                 .await
-                .unwrap();
-                trace!("discover - filtered:{:?}", &filtered_onvif_cameras);
-                let mut changed_camera_list = false;
-                let mut matching_camera_count = 0;
-                filtered_onvif_cameras.iter().for_each(|camera| {
-                    if !cameras.contains(camera) {
-                        changed_camera_list = true;
-                    } else {
-                        matching_camera_count += 1;
-                    }
-                });
-                if changed_camera_list || matching_camera_count != cameras.len() {
-                    trace!("discover - sending updated device list");
-                    cameras = filtered_onvif_cameras.clone();
-                    if let Err(e) = discovered_devices_sender
-                        .send(Ok(DiscoverResponse {
-                            devices: filtered_onvif_cameras,
-                        }))
-                        .await
-                    {
-                        error!(
-                            "discover - for ONVIF failed to send discovery response with error {}",
-                            e
-                        );
-                        if let Some(mut sender) = register_sender {
-                            sender.send(()).await.unwrap();
-                        }
-                        break;
-                    }
+            {
+                Ok(secret) => self.credentials = OnvifCredentialsStore::from_secret(&secret),
+                Err(e) => error!(
+                    "get - could not get Secret {} in namespace {}, keeping the credentials that were last read: {}",
+                    credentials_secret.name, credentials_secret.namespace, e
+                ),
+            }
+        }
+        &self.credentials
+    }
+}
+
+/// Checks the device service of each unicast address whose camera did not answer the WS-Discovery probe, since some
+/// cameras only answer probes sent to the multicast group, and returns the urls of the device services that answered
+async fn probe_device_services(
+    unicast_addresses: &[Ipv4Addr],
+    discovered_device_service_urls: &[String],
+    timeout: Duration,
+    onvif_query: &impl OnvifQuery,
+) -> Vec<String> {
+    let discovered_hosts: HashSet<String> = discovered_device_service_urls
+        .iter()
+        .filter_map(|url| Some(url.parse::<hyper::Uri>().ok()?.host()?.to_string()))
+        .collect();
+    let checks = unicast_addresses
+        .iter()
+        .filter(|address| !discovered_hosts.contains(&address.to_string()))
+        .map(|address| async move {
+            let url = format!("http://{}/onvif/device_service", address);
+            match tokio::time::timeout(timeout, onvif_query.check_device_service(&url)).await {
+                Ok(Ok(())) => Some(url),
+                Ok(Err(e)) => {
+                    trace!("probe_device_services - {} is not a camera: {}", url, e);
+                    None
+                }
+                Err(_) => {
+                    trace!("probe_device_services - {} did not answer in time", url);
+                    None
                 }
-                delay_for(Duration::from_secs(DISCOVERY_INTERVAL_SECS)).await;
             }
         });
-        Ok(Response::new(discovered_devices_receiver))
+    futures_util::future::join_all(checks)
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Waits until the next probe is due or cameras announce themselves, whichever comes first, and returns the
+/// announcements
+async fn wait_for_announcements(
+    announcement_receiver: &mut mpsc::Receiver<Announcement>,
+    until_next_probe: Duration,
+) -> Vec<Announcement> {
+    let mut announcements = Vec::new();
+    match tokio::time::timeout(until_next_probe, announcement_receiver.recv()).await {
+        Ok(Some(announcement)) => announcements.push(announcement),
+        Ok(None) => {
+            // The listener stopped, so only probe from now on
+            delay_for(until_next_probe).await;
+            return announcements;
+        }
+        Err(_) => return announcements,
+    }
+    // Cameras that restart together announce themselves together, so they are handled in a single discovery
+    loop {
+        match announcement_receiver.try_recv() {
+            Ok(announcement) => announcements.push(announcement),
+            Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+        }
     }
+    trace!("wait_for_announcements - received {:?}", announcements);
+    announcements
+}
+
+/// Adds the device service urls and WS-Discovery types of cameras that announced with Hello that they joined the
+/// network to the discovered ones, in case they did not answer the probe yet, and removes the ones of cameras that
+/// announced with Bye that they are leaving, in case they still answered it. Since Byes often leave out the device
+/// service urls, the urls of cameras that answered the probe or said Hello are looked up by their endpoint reference.
+/// Endpoint references of cameras that are no longer discovered are forgotten.
+fn apply_announcements(
+    discovered_cameras: &mut HashMap<String, Vec<String>>,
+    announcements: &[Announcement],
+    endpoint_references: &mut HashMap<String, Vec<String>>,
+) {
+    for announcement in announcements {
+        match announcement {
+            Announcement::Hello {
+                endpoint_reference,
+                device_service_urls: urls,
+                types,
+            } => {
+                for url in urls {
+                    discovered_cameras.insert(url.clone(), types.clone());
+                }
+                endpoint_references.insert(endpoint_reference.clone(), urls.clone());
+            }
+            Announcement::Bye {
+                endpoint_reference,
+                device_service_urls: urls,
+            } => {
+                let mut leaving_urls = urls.clone();
+                if let Some(known_urls) = endpoint_references.remove(endpoint_reference) {
+                    leaving_urls.extend(known_urls);
+                }
+                discovered_cameras.retain(|url, _| !leaving_urls.contains(url));
+            }
+        }
+    }
+    endpoint_references
+        .retain(|_, urls| urls.iter().any(|url| discovered_cameras.contains_key(url)));
 }
 
 fn execute_filter(filter_list: Option<&FilterList>, filter_against: &[String]) -> bool {
@@ -153,14 +664,63 @@ fn execute_filter(filter_list: Option<&FilterList>, filter_against: &[String]) -
     }
 }
 
+/// Returns the local name of a qualified name, such as `NetworkVideoTransmitter` of `dn:NetworkVideoTransmitter`
+fn get_local_name(qualified_name: &str) -> &str {
+    qualified_name.rsplit(':').next().unwrap_or(qualified_name)
+}
+
+/// Like `execute_filter`, but matches types whose local names equal the local name of an item, since cameras are
+/// free to pick the namespace prefixes of their types
+fn execute_types_filter(filter_list: Option<&FilterList>, types: &[String]) -> bool {
+    let filter_list = match filter_list {
+        Some(filter_list) => filter_list,
+        None => return false,
+    };
+    let matched = filter_list.items.iter().any(|item| {
+        types
+            .iter()
+            .any(|camera_type| get_local_name(camera_type) == get_local_name(item))
+    });
+    if FilterType::Include == filter_list.action {
+        !matched
+    } else {
+        matched
+    }
+}
+
+/// Like `execute_filter`, but only matches values that equal an item, since capabilities such as `Media` are
+/// prefixes of others such as `Media2` and `MediaProfile/<token>`
+fn execute_exact_filter(filter_list: Option<&FilterList>, filter_against: &[String]) -> bool {
+    let filter_list = match filter_list {
+        Some(filter_list) => filter_list,
+        None => return false,
+    };
+    let matched = filter_list
+        .items
+        .iter()
+        .any(|item| filter_against.iter().any(|value| value == item));
+    if FilterType::Include == filter_list.action {
+        !matched
+    } else {
+        matched
+    }
+}
+
+/// Filters the discovered cameras, given as their device service urls along with their WS-Discovery types, and
+/// returns the ones that pass as devices. Filters that do not need to query cameras are evaluated first.
 async fn apply_filters(
     discovery_handler_config: &OnvifDiscoveryDetails,
-    device_service_uris: Vec<String>,
+    discovered_cameras: HashMap<String, Vec<String>>,
     onvif_query: &impl OnvifQuery,
 ) -> Result<Vec<Device>, anyhow::Error> {
     let mut result = Vec::new();
-    for device_service_url in device_service_uris.iter() {
+    for (device_service_url, types) in discovered_cameras.iter() {
         trace!("apply_filters - device service url {}", &device_service_url);
+        // Evaluate camera WS-Discovery types against types filter if provided
+        if execute_types_filter(discovery_handler_config.types.as_ref(), types) {
+            continue;
+        }
+
         let (ip_address, mac_address) = match onvif_query
             .get_device_ip_and_mac_address(&device_service_url)
             .await
@@ -204,6 +764,65 @@ async fn apply_filters(
             continue;
         }
 
+        // Evaluate camera device information against manufacturer, model and firmware version filters if provided
+        let filters_on_device_information = discovery_handler_config.manufacturers.is_some()
+            || discovery_handler_config.models.is_some()
+            || discovery_handler_config.firmware_versions.is_some();
+        let device_information = if filters_on_device_information
+            || discovery_handler_config.include_device_information
+        {
+            match onvif_query
+                .get_device_information(&device_service_url)
+                .await
+            {
+                Ok(device_information) => Some(device_information),
+                Err(e) if filters_on_device_information => {
+                    error!("apply_filters - error getting device information: {}", e);
+                    continue;
+                }
+                Err(e) => {
+                    warn!(
+                        "apply_filters - error getting device information of {}: {}",
+                        device_service_url, e
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        if let Some(device_information) = &device_information {
+            if execute_filter(
+                discovery_handler_config.manufacturers.as_ref(),
+                &[device_information.manufacturer.clone()],
+            ) || execute_filter(
+                discovery_handler_config.models.as_ref(),
+                &[device_information.model.clone()],
+            ) || execute_filter(
+                discovery_handler_config.firmware_versions.as_ref(),
+                &[device_information.firmware_version.clone()],
+            ) {
+                continue;
+            }
+        }
+
+        // Evaluate camera capabilities against capabilities filter if provided
+        if discovery_handler_config.capabilities.is_some() {
+            let capabilities = match get_capabilities(&device_service_url, onvif_query).await {
+                Ok(capabilities) => capabilities,
+                Err(e) => {
+                    error!("apply_filters - error getting capabilities: {}", e);
+                    continue;
+                }
+            };
+            if execute_exact_filter(
+                discovery_handler_config.capabilities.as_ref(),
+                &capabilities,
+            ) {
+                continue;
+            }
+        }
+
         let mut properties = HashMap::new();
         properties.insert(
             ONVIF_DEVICE_SERVICE_URL_LABEL_ID.to_string(),
@@ -211,6 +830,19 @@ async fn apply_filters(
         );
         properties.insert(ONVIF_DEVICE_IP_ADDRESS_LABEL_ID.into(), ip_address);
         properties.insert(ONVIF_DEVICE_MAC_ADDRESS_LABEL_ID.into(), mac_address);
+        if discovery_handler_config.include_device_information {
+            properties.extend(
+                get_device_information_properties(
+                    device_service_url,
+                    device_information.as_ref(),
+                    onvif_query,
+                )
+                .await,
+            );
+        }
+        if discovery_handler_config.include_stream_uris {
+            properties.extend(get_stream_uri_properties(device_service_url, onvif_query).await);
+        }
 
         trace!(
             "apply_filters - returns DiscoveryResult ip/mac: {:?}, props: {:?}",
@@ -227,10 +859,172 @@ async fn apply_filters(
     Ok(result)
 }
 
+/// Returns the device information, which has already been queried, and hostname of a camera as properties.
+/// Information that cannot be queried is left out rather than filtering out the camera.
+async fn get_device_information_properties(
+    device_service_url: &str,
+    device_information: Option<&OnvifDeviceInformation>,
+    onvif_query: &impl OnvifQuery,
+) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    if let Some(device_information) = device_information {
+        for (label, value) in vec![
+            (
+                ONVIF_DEVICE_MANUFACTURER_LABEL_ID,
+                &device_information.manufacturer,
+            ),
+            (ONVIF_DEVICE_MODEL_LABEL_ID, &device_information.model),
+            (
+                ONVIF_DEVICE_FIRMWARE_VERSION_LABEL_ID,
+                &device_information.firmware_version,
+            ),
+            (
+                ONVIF_DEVICE_SERIAL_NUMBER_LABEL_ID,
+                &device_information.serial_number,
+            ),
+            (
+                ONVIF_DEVICE_HARDWARE_ID_LABEL_ID,
+                &device_information.hardware_id,
+            ),
+        ] {
+            if !value.is_empty() {
+                properties.insert(label.to_string(), value.clone());
+            }
+        }
+    }
+    match onvif_query.get_device_hostname(device_service_url).await {
+        Ok(hostname) if !hostname.is_empty() => {
+            properties.insert(ONVIF_DEVICE_HOSTNAME_LABEL_ID.to_string(), hostname);
+        }
+        Ok(_) => {}
+        Err(e) => warn!(
+            "get_device_information_properties - error getting hostname of {}: {}",
+            device_service_url, e
+        ),
+    }
+    properties
+}
+
+/// Returns the capabilities of a camera, which are the names of the services it offers, such as `PTZ`, and the
+/// tokens of its media profiles as `MediaProfile/<token>`
+async fn get_capabilities(
+    device_service_url: &str,
+    onvif_query: &impl OnvifQuery,
+) -> Result<Vec<String>, anyhow::Error> {
+    let services = onvif_query.get_device_services(device_service_url).await?;
+    let mut capabilities: Vec<String> = ONVIF_SERVICES
+        .iter()
+        .filter(|(_, namespace)| services.contains_key(*namespace))
+        .map(|(name, _)| name.to_string())
+        .collect();
+    if let Some(media_service_url) = services.get(MEDIA_WSDL) {
+        let profiles = onvif_query.get_device_profiles(media_service_url).await?;
+        capabilities.extend(
+            profiles
+                .iter()
+                .map(|profile| format!("{}{}", MEDIA_PROFILE_CAPABILITY_PREFIX, profile)),
+        );
+    }
+    trace!(
+        "get_capabilities - capabilities of {}: {:?}",
+        device_service_url,
+        capabilities
+    );
+    Ok(capabilities)
+}
+
+/// Returns the tokens of the media profiles of a camera and the RTSP stream uri of each as properties, along with the
+/// stream uri of its first profile. Profiles whose stream uri cannot be queried are left out.
+async fn get_stream_uri_properties(
+    device_service_url: &str,
+    onvif_query: &impl OnvifQuery,
+) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    let media_service_url = match onvif_query
+        .get_device_service_uri(device_service_url, MEDIA_WSDL)
+        .await
+    {
+        Ok(url) if !url.is_empty() => url,
+        Ok(_) => {
+            warn!(
+                "get_stream_uri_properties - {} does not have a media service",
+                device_service_url
+            );
+            return properties;
+        }
+        Err(e) => {
+            warn!(
+                "get_stream_uri_properties - error getting media service of {}: {}",
+                device_service_url, e
+            );
+            return properties;
+        }
+    };
+    let profiles = match onvif_query.get_device_profiles(&media_service_url).await {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            warn!(
+                "get_stream_uri_properties - error getting profiles of {}: {}",
+                device_service_url, e
+            );
+            return properties;
+        }
+    };
+    let mut profiles_with_stream_uri = Vec::new();
+    for profile in profiles {
+        match onvif_query
+            .get_device_profile_streaming_uri(&media_service_url, &profile)
+            .await
+        {
+            Ok(stream_uri) if !stream_uri.is_empty() => {
+                if profiles_with_stream_uri.is_empty() {
+                    properties.insert(
+                        ONVIF_DEVICE_STREAM_URI_LABEL_ID.to_string(),
+                        stream_uri.clone(),
+                    );
+                }
+                properties.insert(get_profile_stream_uri_label(&profile), stream_uri);
+                profiles_with_stream_uri.push(profile);
+            }
+            Ok(_) => {}
+            Err(e) => warn!(
+                "get_stream_uri_properties - error getting stream uri of profile {} of {}: {}",
+                profile, device_service_url, e
+            ),
+        }
+    }
+    if !profiles_with_stream_uri.is_empty() {
+        properties.insert(
+            ONVIF_DEVICE_PROFILES_LABEL_ID.to_string(),
+            profiles_with_stream_uri.join(","),
+        );
+    }
+    properties
+}
+
+/// Returns the name of the property of a profile's stream uri, in which every character of the profile token that is
+/// not allowed in environment variable names is replaced with `_`
+fn get_profile_stream_uri_label(profile: &str) -> String {
+    format!(
+        "{}{}",
+        ONVIF_DEVICE_PROFILE_STREAM_URI_LABEL_PREFIX,
+        profile
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            })
+            .collect::<String>()
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::discovery_utils::MockOnvifQuery;
     use super::*;
+    use akri_shared::k8s::MockKubeInterface;
+    use k8s_openapi::{api::core::v1::Secret, ByteString};
 
     struct IpAndMac {
         mock_uri: &'static str,
@@ -243,6 +1037,15 @@ mod tests {
         mock_scope: &'static str,
     }
 
+    fn discovered_camera(uri: &str) -> HashMap<String, Vec<String>> {
+        vec![(
+            uri.to_string(),
+            vec!["dn:NetworkVideoTransmitter".to_string()],
+        )]
+        .into_iter()
+        .collect()
+    }
+
     fn configure_scenario(
         mock: &mut MockOnvifQuery,
         ip_and_mac: Option<IpAndMac>,
@@ -288,8 +1091,13 @@ mod tests {
     fn test_deserialize_discovery_details() {
         let dh_config: OnvifDiscoveryDetails = deserialize_discovery_details("{}").unwrap();
         let serialized = serde_json::to_string(&dh_config).unwrap();
-        let expected_deserialized = r#"{"discoveryTimeoutSeconds":1}"#;
+        let expected_deserialized = r#"{"discoveryTimeoutSeconds":1,"includeDeviceInformation":false,"includeStreamUris":false,"discoveryIntervalSeconds":10,"listenForAnnouncements":true}"#;
         assert_eq!(expected_deserialized, serialized);
+        // Default matches details that set nothing
+        assert_eq!(
+            expected_deserialized,
+            serde_json::to_string(&OnvifDiscoveryDetails::default()).unwrap()
+        );
     }
 
     #[tokio::test]
@@ -310,13 +1118,8 @@ mod tests {
             }),
         );
 
-        let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
-            mac_addresses: None,
-            scopes: None,
-            discovery_timeout_seconds: 1,
-        };
-        let instances = apply_filters(&onvif_config, vec![mock_uri.to_string()], &mock)
+        let onvif_config = OnvifDiscoveryDetails::default();
+        let instances = apply_filters(&onvif_config, discovered_camera(mock_uri), &mock)
             .await
             .unwrap();
 
@@ -347,11 +1150,9 @@ mod tests {
                 action: FilterType::Include,
                 items: vec![mock_ip.to_string()],
             }),
-            mac_addresses: None,
-            scopes: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
-        let instances = apply_filters(&onvif_config, vec![mock_uri.to_string()], &mock)
+        let instances = apply_filters(&onvif_config, discovered_camera(mock_uri), &mock)
             .await
             .unwrap();
 
@@ -378,11 +1179,9 @@ mod tests {
                 action: FilterType::Include,
                 items: vec!["nonexist.ip".to_string()],
             }),
-            mac_addresses: None,
-            scopes: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
-        let instances = apply_filters(&onvif_config, vec![mock_uri.to_string()], &mock)
+        let instances = apply_filters(&onvif_config, discovered_camera(mock_uri), &mock)
             .await
             .unwrap();
 
@@ -412,11 +1211,9 @@ mod tests {
                 action: FilterType::Exclude,
                 items: vec!["nonexist.ip".to_string()],
             }),
-            mac_addresses: None,
-            scopes: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
-        let instances = apply_filters(&onvif_config, vec![mock_uri.to_string()], &mock)
+        let instances = apply_filters(&onvif_config, discovered_camera(mock_uri), &mock)
             .await
             .unwrap();
 
@@ -444,11 +1241,9 @@ mod tests {
                 action: FilterType::Exclude,
                 items: vec![mock_ip.to_string()],
             }),
-            mac_addresses: None,
-            scopes: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
-        let instances = apply_filters(&onvif_config, vec![mock_uri.to_string()], &mock)
+        let instances = apply_filters(&onvif_config, discovered_camera(mock_uri), &mock)
             .await
             .unwrap();
 
@@ -475,15 +1270,13 @@ mod tests {
         );
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
             mac_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_mac.to_string()],
             }),
-            scopes: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
-        let instances = apply_filters(&onvif_config, vec![mock_uri.to_string()], &mock)
+        let instances = apply_filters(&onvif_config, discovered_camera(mock_uri), &mock)
             .await
             .unwrap();
 
@@ -506,15 +1299,13 @@ mod tests {
         );
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
             mac_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["nonexist:mac".to_string()],
             }),
-            scopes: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
-        let instances = apply_filters(&onvif_config, vec![mock_uri.to_string()], &mock)
+        let instances = apply_filters(&onvif_config, discovered_camera(mock_uri), &mock)
             .await
             .unwrap();
 
@@ -540,15 +1331,13 @@ mod tests {
         );
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
             mac_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["nonexist:mac".to_string()],
             }),
-            scopes: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
-        let instances = apply_filters(&onvif_config, vec![mock_uri.to_string()], &mock)
+        let instances = apply_filters(&onvif_config, discovered_camera(mock_uri), &mock)
             .await
             .unwrap();
 
@@ -572,18 +1361,602 @@ mod tests {
         );
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
             mac_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec![mock_mac.to_string()],
             }),
-            scopes: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
-        let instances = apply_filters(&onvif_config, vec![mock_uri.to_string()], &mock)
+        let instances = apply_filters(&onvif_config, discovered_camera(mock_uri), &mock)
             .await
             .unwrap();
 
         assert_eq!(0, instances.len());
     }
+
+    #[tokio::test]
+    async fn test_apply_filters_include_metadata() {
+        let mock_uri = "device_uri";
+
+        let mut mock = MockOnvifQuery::new();
+        configure_scenario(
+            &mut mock,
+            Some(IpAndMac {
+                mock_uri,
+                mock_ip: "mock.ip",
+                mock_mac: "mock:mac",
+            }),
+            Some(Scope {
+                mock_uri,
+                mock_scope: "mock.scope",
+            }),
+        );
+        mock.expect_get_device_information()
+            .times(1)
+            .withf(move |u| u == mock_uri)
+            .returning(|_| {
+                Ok(OnvifDeviceInformation {
+                    manufacturer: "Contoso".to_string(),
+                    model: "IPC-model".to_string(),
+                    firmware_version: "V4.11.0".to_string(),
+                    serial_number: "00123456".to_string(),
+                    hardware_id: String::new(),
+                })
+            });
+        mock.expect_get_device_hostname()
+            .times(1)
+            .returning(|_| Err(anyhow::format_err!("not authorized")));
+        mock.expect_get_device_service_uri()
+            .times(1)
+            .withf(move |u, service| u == mock_uri && service == MEDIA_WSDL)
+            .returning(|_, _| Ok("media_uri".to_string()));
+        mock.expect_get_device_profiles()
+            .times(1)
+            .withf(|u| u == "media_uri")
+            .returning(|_| {
+                Ok(vec![
+                    "main".to_string(),
+                    "sub-1".to_string(),
+                    "broken".to_string(),
+                ])
+            });
+        mock.expect_get_device_profile_streaming_uri()
+            .times(3)
+            .withf(|u, _| u == "media_uri")
+            .returning(|_, profile| match profile {
+                "broken" => Err(anyhow::format_err!("no stream")),
+                _ => Ok(format!("rtsp://mock.ip/{}", profile)),
+            });
+
+        let onvif_config: OnvifDiscoveryDetails = deserialize_discovery_details(
+            "includeDeviceInformation: true\nincludeStreamUris: true",
+        )
+        .unwrap();
+        let instances = apply_filters(&onvif_config, discovered_camera(mock_uri), &mock)
+            .await
+            .unwrap();
+
+        assert_eq!(1, instances.len());
+        let properties = &instances[0].properties;
+        assert_eq!(properties[ONVIF_DEVICE_MANUFACTURER_LABEL_ID], "Contoso");
+        assert_eq!(
+            properties[ONVIF_DEVICE_FIRMWARE_VERSION_LABEL_ID],
+            "V4.11.0"
+        );
+        assert!(!properties.contains_key(ONVIF_DEVICE_HARDWARE_ID_LABEL_ID));
+        assert!(!properties.contains_key(ONVIF_DEVICE_HOSTNAME_LABEL_ID));
+        assert_eq!(properties[ONVIF_DEVICE_PROFILES_LABEL_ID], "main,sub-1");
+        assert_eq!(
+            properties[ONVIF_DEVICE_STREAM_URI_LABEL_ID],
+            "rtsp://mock.ip/main"
+        );
+        assert_eq!(
+            properties["ONVIF_DEVICE_STREAM_URI_MAIN"],
+            "rtsp://mock.ip/main"
+        );
+        assert_eq!(
+            properties["ONVIF_DEVICE_STREAM_URI_SUB_1"],
+            "rtsp://mock.ip/sub-1"
+        );
+        assert_eq!(properties.len(), 11);
+    }
+
+    #[tokio::test]
+    async fn test_apply_filters_include_types() {
+        let mock_uri = "device_uri";
+
+        let mut mock = MockOnvifQuery::new();
+        configure_scenario(
+            &mut mock,
+            Some(IpAndMac {
+                mock_uri: "device_uri",
+                mock_ip: "mock.ip",
+                mock_mac: "mock:mac",
+            }),
+            Some(Scope {
+                mock_uri: "device_uri",
+                mock_scope: "mock.scope",
+            }),
+        );
+
+        let onvif_config: OnvifDiscoveryDetails = deserialize_discovery_details(
+            "types:\n  action: Include\n  items:\n  - tds:NetworkVideoTransmitter",
+        )
+        .unwrap();
+        // Cameras are not queried unless their types pass the filter, which compares types by local name
+        let mut discovered_cameras = discovered_camera(mock_uri);
+        discovered_cameras.insert(
+            "printer_uri".to_string(),
+            vec!["wprt:PrintDeviceType".to_string()],
+        );
+        discovered_cameras.insert(
+            "transmitter_bridge_uri".to_string(),
+            vec!["dn:NetworkVideoTransmitterBridge".to_string()],
+        );
+        discovered_cameras.insert("unicast_uri".to_string(), Vec::new());
+        let instances = apply_filters(&onvif_config, discovered_cameras, &mock)
+            .await
+            .unwrap();
+
+        assert_eq!(1, instances.len());
+        assert_eq!("mock.ip-mock:mac", instances[0].id);
+    }
+
+    fn configure_device_information(
+        mock: &mut MockOnvifQuery,
+        uri: &'static str,
+        model: &'static str,
+    ) {
+        mock.expect_get_device_information()
+            .times(1)
+            .withf(move |u| u == uri)
+            .returning(move |_| {
+                Ok(OnvifDeviceInformation {
+                    manufacturer: "Contoso".to_string(),
+                    model: model.to_string(),
+                    firmware_version: "V4.11.0".to_string(),
+                    ..Default::default()
+                })
+            });
+    }
+
+    #[tokio::test]
+    async fn test_apply_filters_device_information() {
+        let mut mock = MockOnvifQuery::new();
+        for (uri, ip) in vec![("ptz_uri", "10.0.0.1"), ("fixed_uri", "10.0.0.2")] {
+            configure_get_device_ip_and_mac_address(&mut mock, uri, ip, "mock:mac");
+            configure_get_device_scopes(&mut mock, uri, "mock.scope");
+        }
+        configure_device_information(&mut mock, "ptz_uri", "IPC-PTZ");
+        configure_device_information(&mut mock, "fixed_uri", "IPC-Fixed");
+
+        let onvif_config: OnvifDiscoveryDetails = deserialize_discovery_details(
+            "manufacturers:\n  action: Include\n  items:\n  - Contoso\nmodels:\n  action: Exclude\n  items:\n  - PTZ",
+        )
+        .unwrap();
+        let mut discovered_cameras = discovered_camera("ptz_uri");
+        discovered_cameras.extend(discovered_camera("fixed_uri"));
+        let instances = apply_filters(&onvif_config, discovered_cameras, &mock)
+            .await
+            .unwrap();
+
+        assert_eq!(1, instances.len());
+        assert_eq!("10.0.0.2-mock:mac", instances[0].id);
+        // Device information is only passed to brokers if requested
+        assert!(!instances[0]
+            .properties
+            .contains_key(ONVIF_DEVICE_MODEL_LABEL_ID));
+    }
+
+    #[tokio::test]
+    async fn test_apply_filters_capabilities() {
+        let mut mock = MockOnvifQuery::new();
+        for (uri, ip) in vec![("ptz_uri", "10.0.0.1"), ("fixed_uri", "10.0.0.2")] {
+            configure_get_device_ip_and_mac_address(&mut mock, uri, ip, "mock:mac");
+            configure_get_device_scopes(&mut mock, uri, "mock.scope");
+        }
+        mock.expect_get_device_services().times(2).returning(|uri| {
+            let mut services: HashMap<String, String> =
+                vec![(MEDIA_WSDL.to_string(), format!("{}/media", uri))]
+                    .into_iter()
+                    .collect();
+            if uri == "ptz_uri" {
+                services.insert(PTZ_WSDL.to_string(), format!("{}/ptz", uri));
+            }
+            Ok(services)
+        });
+        mock.expect_get_device_profiles()
+            .times(2)
+            .returning(|_| Ok(vec!["main".to_string()]));
+
+        let onvif_config: OnvifDiscoveryDetails =
+            deserialize_discovery_details("capabilities:\n  action: Include\n  items:\n  - PTZ")
+                .unwrap();
+        let mut discovered_cameras = discovered_camera("ptz_uri");
+        discovered_cameras.extend(discovered_camera("fixed_uri"));
+        let instances = apply_filters(&onvif_config, discovered_cameras, &mock)
+            .await
+            .unwrap();
+        assert_eq!(1, instances.len());
+        assert_eq!("10.0.0.1-mock:mac", instances[0].id);
+    }
+
+    #[tokio::test]
+    async fn test_apply_filters_capabilities_exact() {
+        const MEDIA2_WSDL: &str = "http://www.onvif.org/ver20/media/wsdl";
+        // One camera only offers the Media service and the other only Media2
+        fn media_mock() -> MockOnvifQuery {
+            let mut mock = MockOnvifQuery::new();
+            for (uri, ip) in vec![("media_uri", "10.0.0.1"), ("media2_uri", "10.0.0.2")] {
+                configure_get_device_ip_and_mac_address(&mut mock, uri, ip, "mock:mac");
+                configure_get_device_scopes(&mut mock, uri, "mock.scope");
+            }
+            mock.expect_get_device_services().times(2).returning(|uri| {
+                let namespace = if uri == "media_uri" {
+                    MEDIA_WSDL
+                } else {
+                    MEDIA2_WSDL
+                };
+                Ok(vec![(namespace.to_string(), format!("{}/media", uri))]
+                    .into_iter()
+                    .collect())
+            });
+            mock.expect_get_device_profiles()
+                .times(1)
+                .returning(|_| Ok(vec!["main".to_string()]));
+            mock
+        }
+        let mut discovered_cameras = discovered_camera("media_uri");
+        discovered_cameras.extend(discovered_camera("media2_uri"));
+
+        // Media matches neither Media2 nor MediaProfile/main
+        let onvif_config: OnvifDiscoveryDetails =
+            deserialize_discovery_details("capabilities:\n  action: Include\n  items:\n  - Media")
+                .unwrap();
+        let instances = apply_filters(&onvif_config, discovered_cameras.clone(), &media_mock())
+            .await
+            .unwrap();
+        assert_eq!(1, instances.len());
+        assert_eq!("10.0.0.1-mock:mac", instances[0].id);
+
+        let onvif_config: OnvifDiscoveryDetails =
+            deserialize_discovery_details("capabilities:\n  action: Exclude\n  items:\n  - Media2")
+                .unwrap();
+        let instances = apply_filters(&onvif_config, discovered_cameras, &media_mock())
+            .await
+            .unwrap();
+        assert_eq!(1, instances.len());
+        assert_eq!("10.0.0.1-mock:mac", instances[0].id);
+    }
+
+    #[tokio::test]
+    async fn test_get_capabilities() {
+        let mut mock = MockOnvifQuery::new();
+        mock.expect_get_device_services()
+            .times(1)
+            .withf(|u| u == "device_uri")
+            .returning(|_| {
+                Ok(vec![
+                    (MEDIA_WSDL.to_string(), "media_uri".to_string()),
+                    (PTZ_WSDL.to_string(), "ptz_uri".to_string()),
+                    ("urn:unknown".to_string(), "unknown_uri".to_string()),
+                ]
+                .into_iter()
+                .collect())
+            });
+        mock.expect_get_device_profiles()
+            .times(1)
+            .withf(|u| u == "media_uri")
+            .returning(|_| Ok(vec!["main".to_string(), "sub".to_string()]));
+        assert_eq!(
+            get_capabilities("device_uri", &mock).await.unwrap(),
+            vec!["Media", "PTZ", "MediaProfile/main", "MediaProfile/sub"]
+        );
+    }
+
+    #[test]
+    fn test_apply_announcements() {
+        let network_video_transmitter = vec!["dn:NetworkVideoTransmitter".to_string()];
+        // Camera 0 answered the probe
+        let mut endpoint_references: HashMap<String, Vec<String>> = vec![(
+            "urn:uuid:0".to_string(),
+            vec!["http://10.0.0.10/onvif/device_service".to_string()],
+        )]
+        .into_iter()
+        .collect();
+        let mut discovered_cameras: HashMap<String, Vec<String>> = vec![
+            (
+                "http://10.0.0.1/onvif/device_service".to_string(),
+                Vec::new(),
+            ),
+            (
+                "http://10.0.0.10/onvif/device_service".to_string(),
+                network_video_transmitter.clone(),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        apply_announcements(
+            &mut discovered_cameras,
+            &[
+                Announcement::Hello {
+                    endpoint_reference: "urn:uuid:1".to_string(),
+                    device_service_urls: vec!["http://10.0.0.1/onvif/device_service".to_string()],
+                    types: network_video_transmitter.clone(),
+                },
+                Announcement::Hello {
+                    endpoint_reference: "urn:uuid:2".to_string(),
+                    device_service_urls: vec!["http://10.0.0.2/onvif/device_service".to_string()],
+                    types: network_video_transmitter.clone(),
+                },
+            ],
+            &mut endpoint_references,
+        );
+        assert_eq!(discovered_cameras.len(), 3);
+        assert_eq!(
+            discovered_cameras["http://10.0.0.1/onvif/device_service"],
+            network_video_transmitter
+        );
+        assert_eq!(
+            discovered_cameras["http://10.0.0.2/onvif/device_service"],
+            network_video_transmitter
+        );
+
+        // The Byes of camera 0 and 1 leave out their urls, which are remembered from the probe and the Hello
+        apply_announcements(
+            &mut discovered_cameras,
+            &[
+                Announcement::Bye {
+                    endpoint_reference: "urn:uuid:0".to_string(),
+                    device_service_urls: Vec::new(),
+                },
+                Announcement::Bye {
+                    endpoint_reference: "urn:uuid:1".to_string(),
+                    device_service_urls: Vec::new(),
+                },
+                Announcement::Bye {
+                    endpoint_reference: "urn:uuid:3".to_string(),
+                    device_service_urls: vec!["http://10.0.0.3/onvif/device_service".to_string()],
+                },
+            ],
+            &mut endpoint_references,
+        );
+        assert_eq!(
+            discovered_cameras.keys().collect::<Vec<&String>>(),
+            vec!["http://10.0.0.2/onvif/device_service"]
+        );
+        assert_eq!(endpoint_references.len(), 1);
+
+        // Endpoint references of cameras that are no longer discovered are forgotten
+        discovered_cameras.clear();
+        apply_announcements(&mut discovered_cameras, &[], &mut endpoint_references);
+        assert!(endpoint_references.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_announcements() {
+        let (mut sender, mut receiver) = mpsc::channel(ANNOUNCEMENT_CHANNEL_CAPACITY);
+        let bye = Announcement::Bye {
+            endpoint_reference: "urn:uuid:1".to_string(),
+            device_service_urls: Vec::new(),
+        };
+        sender.send(bye.clone()).await.unwrap();
+        sender.send(bye.clone()).await.unwrap();
+        assert_eq!(
+            wait_for_announcements(&mut receiver, Duration::from_secs(5)).await,
+            vec![bye.clone(), bye]
+        );
+        // Without announcements, the wait ends when the next probe is due
+        assert!(
+            wait_for_announcements(&mut receiver, Duration::from_millis(10))
+                .await
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_device_services() {
+        let mut mock = MockOnvifQuery::new();
+        mock.expect_check_device_service()
+            .times(1)
+            .withf(|u| u == "http://10.0.0.2/onvif/device_service")
+            .returning(|_| Ok(()));
+        mock.expect_check_device_service()
+            .times(1)
+            .withf(|u| u == "http://10.0.0.3/onvif/device_service")
+            .returning(|_| Err(anyhow::format_err!("not found")));
+        // 10.0.0.1 answered the WS-Discovery probe so is not checked again
+        let unicast_addresses: Vec<Ipv4Addr> = vec![
+            "10.0.0.1".parse().unwrap(),
+            "10.0.0.2".parse().unwrap(),
+            "10.0.0.3".parse().unwrap(),
+        ];
+        let probed = probe_device_services(
+            &unicast_addresses,
+            &["http://10.0.0.1:8000/onvif/device_service".to_string()],
+            Duration::from_secs(1),
+            &mock,
+        )
+        .await;
+        assert_eq!(probed, vec!["http://10.0.0.2/onvif/device_service"]);
+    }
+
+    #[test]
+    fn test_get_probe_targets() {
+        let yaml = r#"
+          unicastAddresses:
+          - 10.0.0.0/30
+          - 10.0.1.5
+          multicastInterfaces:
+          - 192.168.1.2
+        "#;
+        let dh_config: OnvifDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        let probe_targets = dh_config.get_probe_targets().unwrap();
+        assert_eq!(
+            probe_targets.unicast_addresses,
+            vec![
+                Ipv4Addr::new(10, 0, 0, 1),
+                Ipv4Addr::new(10, 0, 0, 2),
+                Ipv4Addr::new(10, 0, 1, 5)
+            ]
+        );
+        assert_eq!(
+            probe_targets.multicast_interfaces,
+            vec![Ipv4Addr::new(192, 168, 1, 2)]
+        );
+
+        let dh_config: OnvifDiscoveryDetails =
+            deserialize_discovery_details("unicastAddresses:\n- 10.0.0.0/33").unwrap();
+        assert_eq!(
+            dh_config.validate().unwrap_err().to_string(),
+            "unicastAddresses[0] \"10.0.0.0/33\" is not a valid IPv4 address or CIDR range"
+        );
+        let dh_config: OnvifDiscoveryDetails =
+            deserialize_discovery_details("unicastAddresses:\n- 10.0.0.0/22\n- 10.0.4.1").unwrap();
+        assert_eq!(
+            dh_config.validate().unwrap_err().to_string(),
+            "unicastAddresses expand to 1025 addresses, more than the maximum of 1024"
+        );
+        let dh_config: OnvifDiscoveryDetails =
+            deserialize_discovery_details("discoveryIntervalSeconds: 0").unwrap();
+        assert_eq!(
+            dh_config.validate().unwrap_err().to_string(),
+            "discoveryIntervalSeconds must be at least 1"
+        );
+        let dh_config: OnvifDiscoveryDetails =
+            deserialize_discovery_details("multicastInterfaces:\n- eth0").unwrap();
+        assert_eq!(
+            dh_config.validate().unwrap_err().to_string(),
+            "multicastInterfaces[0] \"eth0\" is not a valid IPv4 address"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cached_credentials() {
+        // The namespace of the Secret is required
+        assert!(deserialize_discovery_details::<OnvifDiscoveryDetails>(
+            "credentialsSecret:\n  name: onvif-credentials"
+        )
+        .is_err());
+        let credentials_secret: OnvifDiscoveryDetails = deserialize_discovery_details(
+            "credentialsSecret:\n  name: onvif-credentials\n  namespace: cameras",
+        )
+        .unwrap();
+        let credentials_secret = credentials_secret.credentials_secret.unwrap();
+
+        // The Secret is only read once while the credentials are fresh
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_secret()
+            .times(1)
+            .withf(|name, namespace| name == "onvif-credentials" && namespace == "cameras")
+            .returning(|_, _| {
+                Ok(Secret {
+                    data: Some(
+                        vec![
+                            ("username".to_string(), ByteString(b"admin".to_vec())),
+                            ("password".to_string(), ByteString(b"password".to_vec())),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    ),
+                    ..Default::default()
+                })
+            });
+        let mut cached_credentials = CachedCredentials::default();
+        for _ in 0..2 {
+            assert_eq!(
+                cached_credentials
+                    .get(&credentials_secret, &mock)
+                    .await
+                    .get("http://10.0.0.1/onvif/device_service")
+                    .unwrap()
+                    .username,
+                "admin"
+            );
+        }
+
+        // The credentials that were last read are kept if the Secret cannot be read again
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_secret()
+            .times(1)
+            .returning(|_, _| Err("forbidden".into()));
+        cached_credentials.read_at =
+            Some(Instant::now() - Duration::from_secs(CREDENTIALS_REFRESH_SECS));
+        assert!(cached_credentials
+            .get(&credentials_secret, &mock)
+            .await
+            .get("http://10.0.0.1/onvif/device_service")
+            .is_some());
+
+        // Cameras are queried without credentials if the Secret was never read
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_secret()
+            .times(1)
+            .returning(|_, _| Err("forbidden".into()));
+        assert_eq!(
+            CachedCredentials::default()
+                .get(&credentials_secret, &mock)
+                .await,
+            &OnvifCredentialsStore::default()
+        );
+    }
+
+    #[test]
+    fn test_validate_discovery_details() {
+        let yaml = r#"
+          ipAddresses:
+            items:
+            - 10.0.0.1
+            - "fe80::1"
+          macAddresses:
+            items:
+            - "00:11:22:aa:bb:cc"
+            - "00-11-22-AA-BB-CC"
+        "#;
+        let dh_config: OnvifDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        assert!(dh_config.validate().is_ok());
+
+        let yaml = r#"
+          ipAddresses:
+            items:
+            - 10.0.0.1
+            - 10.0.0.256
+        "#;
+        let dh_config: OnvifDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        assert_eq!(
+            dh_config.validate().unwrap_err().to_string(),
+            "ipAddresses.items[1] \"10.0.0.256\" is not a valid IP address"
+        );
+
+        for mac in &[
+            "00:11:22:aa:bb",
+            "00:11:22:aa:bb:cg",
+            "00:11-22:aa:bb:cc",
+            "0011.22aa.bbcc",
+        ] {
+            let dh_config = OnvifDiscoveryDetails {
+                mac_addresses: Some(FilterList {
+                    action: FilterType::Include,
+                    items: vec![mac.to_string()],
+                }),
+                ..Default::default()
+            };
+            assert!(dh_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .starts_with(&format!("macAddresses.items[0] \"{}\"", mac)));
+        }
+
+        let yaml = r#"
+          credentialsSecret:
+            name: ""
+            namespace: default
+        "#;
+        let dh_config: OnvifDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        assert_eq!(
+            dh_config.validate().unwrap_err().to_string(),
+            "credentialsSecret.name must not be empty"
+        );
+    }
 }
@@ -117,7 +117,7 @@ pub mod common {
         #[yaserde(prefix = "d", rename = "XAddrs")]
         pub xaddrs: String,
         #[yaserde(prefix = "wsa", rename = "EndpointReference")]
-        pub endpoint_reference: String,
+        pub endpoint_reference: EndpointReference,
         #[yaserde(prefix = "d", rename = "Types")]
         pub probe_types: Vec<String>,
         #[yaserde(prefix = "d", rename = "Scopes")]
@@ -126,6 +126,16 @@ pub mod common {
         pub metadata_version: String,
     }
 
+    #[derive(Default, PartialEq, Debug, YaDeserialize, YaSerialize)]
+    #[yaserde(
+        prefix = "wsa",
+        namespace = "wsa: http://schemas.xmlsoap.org/ws/2004/08/addressing"
+    )]
+    pub struct EndpointReference {
+        #[yaserde(prefix = "wsa", rename = "Address")]
+        pub address: String,
+    }
+
     #[derive(Default, PartialEq, Debug, YaDeserialize, YaSerialize)]
     #[yaserde(
         prefix = "d",
@@ -141,16 +151,42 @@ pub mod util {
     use super::{common, probe_types, to_deserialize, to_serialize};
     use log::{error, info, trace};
     use std::{
+        collections::HashMap,
         net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
         sync::{Arc, Mutex},
     };
     use tokio::{
         io::ErrorKind,
-        sync::{mpsc, mpsc::error::TryRecvError},
+        sync::{mpsc, mpsc::error::TryRecvError, oneshot},
         time,
         time::Duration,
     };
 
+    /// Port on which ONVIF cameras listen for WS-Discovery probes
+    const WS_DISCOVERY_PORT: u16 = 3702;
+    // WS-Discovery multicast ip and port selected from available standard
+    // options.  See https://en.wikipedia.org/wiki/WS-Discovery
+    const MULTI_IPV4_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+
+    /// Cameras that answered a probe
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct ProbedCameras {
+        /// WS-Discovery types of the cameras, by their device service urls
+        pub cameras: HashMap<String, Vec<String>>,
+        /// Device service urls of the cameras, by the endpoint references that they also identify themselves with in
+        /// Bye announcements
+        pub endpoint_references: HashMap<String, Vec<String>>,
+    }
+
+    /// Where WS-Discovery probes are sent besides the multicast group
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct ProbeTargets {
+        /// Addresses of the local interfaces to send multicast probes from. The default interface is used if empty.
+        pub multicast_interfaces: Vec<Ipv4Addr>,
+        /// Addresses of cameras to send unicast probes to, for networks that do not route multicast
+        pub unicast_addresses: Vec<Ipv4Addr>,
+    }
+
     fn create_onvif_discovery_message(uuid_string: &str) -> String {
         let probe_types: Vec<String> = vec![probe_types::NETWORK_VIDEO_TRANSMITTER.into()];
         let envelope = to_serialize::Envelope {
@@ -188,24 +224,48 @@ pub mod util {
         }
     }
 
-    fn get_device_uris_from_discovery_response(discovery_response: &str) -> Vec<String> {
+    /// Returns the device service urls in a probe response along with the WS-Discovery types and the endpoint
+    /// reference of each
+    fn get_device_uris_from_discovery_response(discovery_response: &str) -> ProbedCameras {
         let response_envelope =
             yaserde::de::from_str::<to_deserialize::Envelope>(&discovery_response);
         // The response envelope follows this format:
-        //   <Envelope><Body><ProbeMatches><ProbeMatch><XAddrs>
+        //   <Envelope><Body><ProbeMatches><ProbeMatch>
+        //     <EndpointReference><Address>urn:uuid:10919da4-5566-7788-99aa-0012414fb745</Address></EndpointReference>
+        //     <Types>dn:NetworkVideoTransmitter tds:Device</Types>
+        //     <XAddrs>
         //       https://10.0.0.1:5357/svc
         //       https://10.0.0.2:5357/svc
         //       https://10.0.0.3:5357/svc
-        //   </ProbeMatch></ProbeMatches></XAddrs></Body></Envelope>
-        response_envelope
-            .unwrap()
-            .body
-            .probe_matches
-            .probe_match
-            .iter()
-            .flat_map(|probe_match| probe_match.xaddrs.split_whitespace())
-            .map(|addr| addr.to_string())
-            .collect::<Vec<String>>()
+        //   </XAddrs></ProbeMatch></ProbeMatches></Body></Envelope>
+        let mut probed_cameras = ProbedCameras::default();
+        for probe_match in response_envelope.unwrap().body.probe_matches.probe_match {
+            let types: Vec<String> = probe_match
+                .probe_types
+                .iter()
+                .flat_map(|probe_types| probe_types.split_whitespace())
+                .map(|probe_type| probe_type.to_string())
+                .collect();
+            let device_uris: Vec<String> = probe_match
+                .xaddrs
+                .split_whitespace()
+                .map(|addr| addr.to_string())
+                .collect();
+            for device_uri in &device_uris {
+                probed_cameras
+                    .cameras
+                    .insert(device_uri.clone(), types.clone());
+            }
+            let endpoint_reference = probe_match.endpoint_reference.address.trim();
+            if !endpoint_reference.is_empty() {
+                probed_cameras
+                    .endpoint_references
+                    .entry(endpoint_reference.to_string())
+                    .or_insert_with(Vec::new)
+                    .extend(device_uris);
+            }
+        }
+        probed_cameras
     }
 
     #[cfg(test)]
@@ -217,123 +277,201 @@ pub mod util {
             let _ = env_logger::builder().is_test(true).try_init();
 
             let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
+            let expected_device_uris = ProbedCameras {
+                cameras: uris
+                    .iter()
+                    .map(|uri| (uri.clone(), vec!["dn:NetworkVideoTransmitter".to_string()]))
+                    .collect(),
+                endpoint_references: vec![(
+                    "urn:uuid:10919da4-5566-7788-99aa-0012414fb745".to_string(),
+                    uris.clone(),
+                )]
+                .into_iter()
+                .collect(),
+            };
             let response = format!(
                 "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<SOAP-ENV:Envelope xmlns:SOAP-ENV=\"http://www.w3.org/2003/05/soap-envelope\" xmlns:SOAP-ENC=\"http://www.w3.org/2003/05/soap-encoding\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" xmlns:xs=\"http://www.w3.org/2000/10/XMLSchema\" xmlns:wsse=\"http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-secext-1.0.xsd\" xmlns:wsa5=\"http://www.w3.org/2005/08/addressing\" xmlns:xop=\"http://www.w3.org/2004/08/xop/include\" xmlns:wsa=\"http://schemas.xmlsoap.org/ws/2004/08/addressing\" xmlns:tt=\"http://www.onvif.org/ver10/schema\" xmlns:ns1=\"http://www.w3.org/2005/05/xmlmime\" xmlns:wstop=\"http://docs.oasis-open.org/wsn/t-1\" xmlns:ns7=\"http://docs.oasis-open.org/wsrf/r-2\" xmlns:ns2=\"http://docs.oasis-open.org/wsrf/bf-2\" xmlns:dndl=\"http://www.onvif.org/ver10/network/wsdl/DiscoveryLookupBinding\" xmlns:dnrd=\"http://www.onvif.org/ver10/network/wsdl/RemoteDiscoveryBinding\" xmlns:d=\"http://schemas.xmlsoap.org/ws/2005/04/discovery\" xmlns:dn=\"http://www.onvif.org/ver10/network/wsdl\" xmlns:ns10=\"http://www.onvif.org/ver10/replay/wsdl\" xmlns:ns11=\"http://www.onvif.org/ver10/search/wsdl\" xmlns:ns13=\"http://www.onvif.org/ver20/analytics/wsdl/RuleEngineBinding\" xmlns:ns14=\"http://www.onvif.org/ver20/analytics/wsdl/AnalyticsEngineBinding\" xmlns:tan=\"http://www.onvif.org/ver20/analytics/wsdl\" xmlns:ns15=\"http://www.onvif.org/ver10/events/wsdl/PullPointSubscriptionBinding\" xmlns:ns16=\"http://www.onvif.org/ver10/events/wsdl/EventBinding\" xmlns:tev=\"http://www.onvif.org/ver10/events/wsdl\" xmlns:ns17=\"http://www.onvif.org/ver10/events/wsdl/SubscriptionManagerBinding\" xmlns:ns18=\"http://www.onvif.org/ver10/events/wsdl/NotificationProducerBinding\" xmlns:ns19=\"http://www.onvif.org/ver10/events/wsdl/NotificationConsumerBinding\" xmlns:ns20=\"http://www.onvif.org/ver10/events/wsdl/PullPointBinding\" xmlns:ns21=\"http://www.onvif.org/ver10/events/wsdl/CreatePullPointBinding\" xmlns:ns22=\"http://www.onvif.org/ver10/events/wsdl/PausableSubscriptionManagerBinding\" xmlns:wsnt=\"http://docs.oasis-open.org/wsn/b-2\" xmlns:ns3=\"http://www.onvif.org/ver10/analyticsdevice/wsdl\" xmlns:ns4=\"http://www.onvif.org/ver10/deviceIO/wsdl\" xmlns:ns5=\"http://www.onvif.org/ver10/display/wsdl\" xmlns:ns8=\"http://www.onvif.org/ver10/receiver/wsdl\" xmlns:ns9=\"http://www.onvif.org/ver10/recording/wsdl\" xmlns:tds=\"http://www.onvif.org/ver10/device/wsdl\" xmlns:timg=\"http://www.onvif.org/ver20/imaging/wsdl\" xmlns:tptz=\"http://www.onvif.org/ver20/ptz/wsdl\" xmlns:trt=\"http://www.onvif.org/ver10/media/wsdl\" xmlns:trt2=\"http://www.onvif.org/ver20/media/wsdl\" xmlns:ter=\"http://www.onvif.org/ver10/error\" xmlns:tns1=\"http://www.onvif.org/ver10/topics\" xmlns:tnsn=\"http://www.eventextension.com/2011/event/topics\"><SOAP-ENV:Header><wsa:MessageID>urn:uuid:2bc6f06c-5566-7788-99ac-0012414fb745</wsa:MessageID><wsa:RelatesTo>uuid:7b1d26aa-b02e-4ad2-8aab-4c928298ee0c</wsa:RelatesTo><wsa:To SOAP-ENV:mustUnderstand=\"true\">http://schemas.xmlsoap.org/ws/2004/08/addressing/role/anonymous</wsa:To><wsa:Action SOAP-ENV:mustUnderstand=\"true\">http://schemas.xmlsoap.org/ws/2005/04/discovery/ProbeMatches</wsa:Action></SOAP-ENV:Header><SOAP-ENV:Body><d:ProbeMatches><d:ProbeMatch><wsa:EndpointReference><wsa:Address>urn:uuid:10919da4-5566-7788-99aa-0012414fb745</wsa:Address></wsa:EndpointReference><d:Types>dn:NetworkVideoTransmitter</d:Types><d:Scopes>onvif://www.onvif.org/type/video_encoder onvif://www.onvif.org/type/audio_encoder onvif://www.onvif.org/hardware/IPC-model onvif://www.onvif.org/location/country/china onvif://www.onvif.org/name/NVT onvif://www.onvif.org/Profile/Streaming </d:Scopes><d:XAddrs>{}</d:XAddrs><d:MetadataVersion>10</d:MetadataVersion></d:ProbeMatch></d:ProbeMatches></SOAP-ENV:Body></SOAP-ENV:Envelope>",
                 &uris.join(" ")
             );
-            assert_eq!(uris, get_device_uris_from_discovery_response(&response));
+            assert_eq!(
+                expected_device_uris,
+                get_device_uris_from_discovery_response(&response)
+            );
+        }
+    }
+
+    /// Sends a probe to the multicast group from each of the selected interfaces, or the default one, and to each
+    /// unicast address. Failing to send from a selected interface or to a unicast address is only logged, since the
+    /// interface may not exist on the node and the address may not be reachable.
+    fn send_probes(
+        socket: &UdpSocket,
+        message: &str,
+        probe_targets: &ProbeTargets,
+        multi_socket_addr: SocketAddr,
+    ) -> std::io::Result<()> {
+        if probe_targets.multicast_interfaces.is_empty() {
+            socket.send_to(message.as_bytes(), multi_socket_addr)?;
+        } else {
+            // Options set through a duplicate of the socket apply to the socket itself
+            let socket_options = socket2::Socket::from(socket.try_clone()?);
+            for interface in &probe_targets.multicast_interfaces {
+                trace!("send_probes - sending multicast probe from {:?}", interface);
+                // The interface may not exist on every node
+                if let Err(e) = socket_options
+                    .set_multicast_if_v4(interface)
+                    .and_then(|_| socket.send_to(message.as_bytes(), multi_socket_addr))
+                {
+                    error!(
+                        "send_probes - failed to send multicast probe from {:?}: {:?}",
+                        interface, e
+                    );
+                }
+            }
+        }
+        for address in &probe_targets.unicast_addresses {
+            let unicast_socket_addr = SocketAddr::new(IpAddr::V4(*address), WS_DISCOVERY_PORT);
+            if let Err(e) = socket.send_to(message.as_bytes(), unicast_socket_addr) {
+                trace!(
+                    "send_probes - failed to send probe to {:?}: {:?}",
+                    unicast_socket_addr,
+                    e
+                );
+            }
         }
+        Ok(())
     }
 
-    pub async fn simple_onvif_discover(timeout: Duration) -> Result<Vec<String>, anyhow::Error> {
+    /// Probes for cameras and returns the device service urls of the ones that answered along with the WS-Discovery
+    /// types and endpoint reference of each
+    pub async fn simple_onvif_discover(
+        timeout: Duration,
+        probe_targets: &ProbeTargets,
+    ) -> Result<ProbedCameras, anyhow::Error> {
         let (mut discovery_timeout_tx, mut discovery_timeout_rx) = mpsc::channel(2);
         let (mut discovery_cancel_tx, mut discovery_cancel_rx) = mpsc::channel(2);
-        let shared_devices = Arc::new(Mutex::new(Vec::new()));
+        let shared_devices = Arc::new(Mutex::new(ProbedCameras::default()));
 
         let uuid_str = format!("uuid:{}", uuid::Uuid::new_v4());
         trace!("simple_onvif_discover - for {}", &uuid_str);
 
-        let thread_devices = shared_devices.clone();
-        tokio::spawn(async move {
-            trace!(
-                "simple_onvif_discover - spawned thread enter for {}",
-                &uuid_str
-            );
+        const LOCAL_IPV4_ADDR: Ipv4Addr = Ipv4Addr::UNSPECIFIED;
+        const LOCAL_PORT: u16 = 0;
+        let local_socket_addr = SocketAddr::new(IpAddr::V4(LOCAL_IPV4_ADDR), LOCAL_PORT);
 
-            const LOCAL_IPV4_ADDR: Ipv4Addr = Ipv4Addr::UNSPECIFIED;
-            const LOCAL_PORT: u16 = 0;
-            let local_socket_addr = SocketAddr::new(IpAddr::V4(LOCAL_IPV4_ADDR), LOCAL_PORT);
-
-            // WS-Discovery multicast ip and port selected from available standard
-            // options.  See https://en.wikipedia.org/wiki/WS-Discovery
-            const MULTI_IPV4_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
-            const MULTI_PORT: u16 = 3702;
-            let multi_socket_addr = SocketAddr::new(IpAddr::V4(MULTI_IPV4_ADDR), MULTI_PORT);
+        let multi_socket_addr = SocketAddr::new(IpAddr::V4(MULTI_IPV4_ADDR), WS_DISCOVERY_PORT);
 
+        trace!(
+            "simple_onvif_discover - binding to: {:?}",
+            local_socket_addr
+        );
+        let socket = UdpSocket::bind(local_socket_addr)?;
+        socket.set_write_timeout(Some(Duration::from_millis(200)))?;
+        socket.set_read_timeout(Some(Duration::from_millis(200)))?;
+        if probe_targets.multicast_interfaces.is_empty() {
             trace!(
-                "simple_onvif_discover - binding to: {:?}",
-                local_socket_addr
+                "simple_onvif_discover - joining multicast: {:?} {:?}",
+                &MULTI_IPV4_ADDR,
+                &LOCAL_IPV4_ADDR
             );
-            let socket = UdpSocket::bind(local_socket_addr).unwrap();
-            socket
-                .set_write_timeout(Some(Duration::from_millis(200)))
-                .unwrap();
-            socket
-                .set_read_timeout(Some(Duration::from_millis(200)))
-                .unwrap();
+            socket.join_multicast_v4(&MULTI_IPV4_ADDR, &LOCAL_IPV4_ADDR)?;
+        }
+        for interface in &probe_targets.multicast_interfaces {
             trace!(
                 "simple_onvif_discover - joining multicast: {:?} {:?}",
                 &MULTI_IPV4_ADDR,
-                &LOCAL_IPV4_ADDR
+                interface
+            );
+            // The interface may not exist on every node
+            if let Err(e) = socket.join_multicast_v4(&MULTI_IPV4_ADDR, interface) {
+                error!(
+                    "simple_onvif_discover - failed to join multicast on {:?}: {:?}",
+                    interface, e
+                );
+            }
+        }
+
+        let thread_devices = shared_devices.clone();
+        let probe_targets = probe_targets.clone();
+        tokio::spawn(async move {
+            trace!(
+                "simple_onvif_discover - spawned thread enter for {}",
+                &uuid_str
             );
-            socket
-                .join_multicast_v4(&MULTI_IPV4_ADDR, &LOCAL_IPV4_ADDR)
-                .unwrap();
 
             let envelope_as_string = create_onvif_discovery_message(&uuid_str);
-            match socket.send_to(&envelope_as_string.as_bytes(), multi_socket_addr) {
-                Ok(_) => {
-                    loop {
-                        let mut buf = vec![0; 16 * 1024];
-                        match socket.recv_from(&mut buf) {
-                            Ok((len, _)) => {
-                                let broadcast_response_as_string =
-                                    String::from_utf8_lossy(&buf[..len]).to_string();
-                                trace!(
-                                    "simple_onvif_discover - response: {:?}",
-                                    broadcast_response_as_string
-                                );
-
-                                get_device_uris_from_discovery_response(
-                                    &broadcast_response_as_string,
-                                )
-                                .iter()
-                                .for_each(|device_uri| {
-                                    trace!(
-                                        "simple_onvif_discover - device_uri parsed from response: {:?}",
-                                        device_uri
-                                    );
-                                    thread_devices.lock().unwrap().push(device_uri.to_string());
-                                    trace!(
-                                        "simple_onvif_discover - thread_devices: {:?}",
-                                        thread_devices.lock().unwrap()
-                                    );
-                                });
+            let result = match send_probes(
+                &socket,
+                &envelope_as_string,
+                &probe_targets,
+                multi_socket_addr,
+            ) {
+                Ok(_) => loop {
+                    let mut buf = vec![0; 16 * 1024];
+                    match socket.recv_from(&mut buf) {
+                        Ok((len, _)) => {
+                            let broadcast_response_as_string =
+                                String::from_utf8_lossy(&buf[..len]).to_string();
+                            trace!(
+                                "simple_onvif_discover - response: {:?}",
+                                broadcast_response_as_string
+                            );
+
+                            let probed_cameras = get_device_uris_from_discovery_response(
+                                &broadcast_response_as_string,
+                            );
+                            trace!(
+                                "simple_onvif_discover - device_uris parsed from response: {:?}",
+                                probed_cameras
+                            );
+                            let mut thread_devices = thread_devices.lock().unwrap();
+                            thread_devices.cameras.extend(probed_cameras.cameras);
+                            for (endpoint_reference, device_uris) in
+                                probed_cameras.endpoint_references
+                            {
+                                thread_devices
+                                    .endpoint_references
+                                    .entry(endpoint_reference)
+                                    .or_insert_with(Vec::new)
+                                    .extend(device_uris);
                             }
-                            Err(e) => match e.kind() {
-                                ErrorKind::WouldBlock | ErrorKind::TimedOut => {
-                                    match discovery_cancel_rx.try_recv() {
-                                        Err(TryRecvError::Closed) | Ok(_) => {
-                                            trace!("simple_onvif_discover - recv_from error ... timeout signalled/disconnected (stop collecting responses): {:?}", e);
-                                            break;
-                                        }
-                                        Err(TryRecvError::Empty) => {
-                                            trace!("simple_onvif_discover - recv_from error ... no timeout (continue collecting responses): {:?}", e);
-                                            // continue looping
-                                        }
+                            trace!(
+                                "simple_onvif_discover - thread_devices: {:?}",
+                                thread_devices
+                            );
+                        }
+                        Err(e) => match e.kind() {
+                            ErrorKind::WouldBlock | ErrorKind::TimedOut => {
+                                match discovery_cancel_rx.try_recv() {
+                                    Err(TryRecvError::Closed) | Ok(_) => {
+                                        trace!("simple_onvif_discover - recv_from error ... timeout signalled/disconnected (stop collecting responses): {:?}", e);
+                                        break Ok(());
+                                    }
+                                    Err(TryRecvError::Empty) => {
+                                        trace!("simple_onvif_discover - recv_from error ... no timeout (continue collecting responses): {:?}", e);
+                                        // continue looping
                                     }
                                 }
-                                e => {
-                                    error!("simple_onvif_discover - recv_from error: {:?}", e);
-                                    Err(e).unwrap()
-                                }
-                            },
-                        }
+                            }
+                            _ => {
+                                error!("simple_onvif_discover - recv_from error: {:?}", e);
+                                break Err(e);
+                            }
+                        },
                     }
-                }
+                },
                 Err(e) => match e.kind() {
                     ErrorKind::WouldBlock | ErrorKind::TimedOut => {
                         trace!("simple_onvif_discover - send_to timeout: {:?}", e);
                         return;
                     }
-                    e => {
+                    _ => {
                         error!("simple_onvif_discover - send_to error: {:?}", e);
-                        Err(e).unwrap()
+                        Err(e)
                     }
                 },
-            }
+            };
 
-            let _best_effort_send = discovery_timeout_tx.send(()).await;
+            let _best_effort_send = discovery_timeout_tx.send(result).await;
             trace!("simple_onvif_discover - spawned thread exit");
         });
 
@@ -349,12 +487,212 @@ pub mod util {
         );
         // Send cancel message to thread to ensure it doesn't hang around
         let _best_effort_cancel = discovery_cancel_tx.send(()).await;
+        // Probing fails if the socket could not be used rather than finding no cameras
+        if let Ok(Some(Err(e))) = discovery_timeout_rx_result {
+            return Err(e.into());
+        }
 
         let result_devices = shared_devices.lock().unwrap().clone();
         info!("simple_onvif_discover - devices: {:?}", result_devices);
         Ok(result_devices)
     }
 
+    /// Announcement of a camera joining or leaving the network
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum Announcement {
+        /// A camera announced with Hello that it can be reached at its device service urls
+        Hello {
+            endpoint_reference: String,
+            device_service_urls: Vec<String>,
+            types: Vec<String>,
+        },
+        /// A camera announced with Bye that it is leaving. Its device service urls are often left out.
+        Bye {
+            endpoint_reference: String,
+            device_service_urls: Vec<String>,
+        },
+    }
+
+    /// Parses a WS-Discovery Hello or Bye message. Hellos of devices that are not network video transmitters, such as
+    /// printers, are ignored.
+    fn get_announcement(message: &str) -> Option<Announcement> {
+        let package = sxd_document::parser::parse(message).ok()?;
+        let document = package.as_document();
+        let evaluate = |xpath: &str| {
+            sxd_xpath::evaluate_xpath(&document, xpath)
+                .map(|value| value.string())
+                .unwrap_or_default()
+        };
+        let has_element = |kind: &str| match sxd_xpath::evaluate_xpath(
+            &document,
+            &format!("//*[local-name()='Body']/*[local-name()='{}']", kind),
+        ) {
+            Ok(sxd_xpath::Value::Nodeset(nodes)) => nodes.size() > 0,
+            _ => false,
+        };
+        let get_field = |kind: &str, field: &str| {
+            evaluate(&format!(
+                "//*[local-name()='Body']/*[local-name()='{}']/*[local-name()='{}']",
+                kind, field
+            ))
+        };
+        let get_endpoint_reference = |kind: &str| {
+            evaluate(&format!(
+                "//*[local-name()='Body']/*[local-name()='{}']/*[local-name()='EndpointReference']/*[local-name()='Address']",
+                kind
+            ))
+            .trim()
+            .to_string()
+        };
+        let get_device_service_urls = |kind: &str| {
+            get_field(kind, "XAddrs")
+                .split_whitespace()
+                .map(|url| url.to_string())
+                .collect::<Vec<String>>()
+        };
+        if has_element("Hello") {
+            let types = get_field("Hello", "Types");
+            if !types.contains("NetworkVideoTransmitter") {
+                return None;
+            }
+            Some(Announcement::Hello {
+                endpoint_reference: get_endpoint_reference("Hello"),
+                device_service_urls: get_device_service_urls("Hello"),
+                types: types
+                    .split_whitespace()
+                    .map(|probe_type| probe_type.to_string())
+                    .collect(),
+            })
+        } else if has_element("Bye") {
+            Some(Announcement::Bye {
+                endpoint_reference: get_endpoint_reference("Bye"),
+                device_service_urls: get_device_service_urls("Bye"),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Listens on the WS-Discovery multicast group, joined on each of the selected interfaces or the default one, for
+    /// cameras announcing that they joined or left the network, and sends the announcements until the receiver is
+    /// dropped or `stop_receiver` resolves, which happens when its sender is dropped at the end of discovery. The port
+    /// is shared with any other listener on the node, such as the ones of other Configurations.
+    pub async fn listen_for_announcements(
+        multicast_interfaces: Vec<Ipv4Addr>,
+        mut announcement_sender: mpsc::Sender<Announcement>,
+        mut stop_receiver: oneshot::Receiver<()>,
+    ) -> Result<(), anyhow::Error> {
+        let socket = socket2::Socket::new(
+            socket2::Domain::ipv4(),
+            socket2::Type::dgram(),
+            Some(socket2::Protocol::udp()),
+        )?;
+        socket.set_reuse_address(true)?;
+        socket
+            .bind(&SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), WS_DISCOVERY_PORT).into())?;
+        let socket = socket.into_udp_socket();
+        if multicast_interfaces.is_empty() {
+            socket.join_multicast_v4(&MULTI_IPV4_ADDR, &Ipv4Addr::UNSPECIFIED)?;
+        }
+        for interface in &multicast_interfaces {
+            if let Err(e) = socket.join_multicast_v4(&MULTI_IPV4_ADDR, interface) {
+                error!(
+                    "listen_for_announcements - failed to join multicast on {:?}: {:?}",
+                    interface, e
+                );
+            }
+        }
+        socket.set_nonblocking(true)?;
+        let mut socket = tokio::net::UdpSocket::from_std(socket)?;
+        info!("listen_for_announcements - listening for Hello and Bye");
+        let mut buf = vec![0; 16 * 1024];
+        loop {
+            let (len, _) = tokio::select! {
+                result = socket.recv_from(&mut buf) => result?,
+                _ = &mut stop_receiver => {
+                    trace!("listen_for_announcements - discovery ended, stop listening");
+                    return Ok(());
+                }
+            };
+            let message = String::from_utf8_lossy(&buf[..len]).to_string();
+            if let Some(announcement) = get_announcement(&message) {
+                trace!("listen_for_announcements - received {:?}", announcement);
+                if announcement_sender.send(announcement).await.is_err() {
+                    trace!("listen_for_announcements - receiver dropped, stop listening");
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod announcement_tests {
+        use super::*;
+
+        #[test]
+        fn test_get_announcement() {
+            let hello = r#"<?xml version="1.0" encoding="UTF-8"?>
+<SOAP-ENV:Envelope xmlns:SOAP-ENV="http://www.w3.org/2003/05/soap-envelope" xmlns:wsa="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:d="http://schemas.xmlsoap.org/ws/2005/04/discovery" xmlns:dn="http://www.onvif.org/ver10/network/wsdl">
+    <SOAP-ENV:Header>
+        <wsa:Action>http://schemas.xmlsoap.org/ws/2005/04/discovery/Hello</wsa:Action>
+    </SOAP-ENV:Header>
+    <SOAP-ENV:Body>
+        <d:Hello>
+            <wsa:EndpointReference>
+                <wsa:Address>urn:uuid:10919da4-5566-7788-99aa-0012414fb745</wsa:Address>
+            </wsa:EndpointReference>
+            <d:Types>dn:NetworkVideoTransmitter</d:Types>
+            <d:Scopes>onvif://www.onvif.org/name/NVT</d:Scopes>
+            <d:XAddrs>http://10.0.0.1/onvif/device_service http://[fe80::1]/onvif/device_service</d:XAddrs>
+            <d:MetadataVersion>10</d:MetadataVersion>
+        </d:Hello>
+    </SOAP-ENV:Body>
+</SOAP-ENV:Envelope>"#;
+            assert_eq!(
+                get_announcement(hello),
+                Some(Announcement::Hello {
+                    endpoint_reference: "urn:uuid:10919da4-5566-7788-99aa-0012414fb745".to_string(),
+                    device_service_urls: vec![
+                        "http://10.0.0.1/onvif/device_service".to_string(),
+                        "http://[fe80::1]/onvif/device_service".to_string()
+                    ],
+                    types: vec!["dn:NetworkVideoTransmitter".to_string()],
+                })
+            );
+
+            // Hellos of other devices are ignored
+            assert_eq!(
+                get_announcement(
+                    &hello.replace("dn:NetworkVideoTransmitter", "wprt:PrintDeviceType")
+                ),
+                None
+            );
+
+            let bye = r#"<?xml version="1.0" encoding="UTF-8"?>
+<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:d="http://schemas.xmlsoap.org/ws/2005/04/discovery">
+    <s:Body>
+        <d:Bye>
+            <a:EndpointReference><a:Address>urn:uuid:10919da4-5566-7788-99aa-0012414fb745</a:Address></a:EndpointReference>
+        </d:Bye>
+    </s:Body>
+</s:Envelope>"#;
+            assert_eq!(
+                get_announcement(bye),
+                Some(Announcement::Bye {
+                    endpoint_reference: "urn:uuid:10919da4-5566-7788-99aa-0012414fb745".to_string(),
+                    device_service_urls: Vec::new(),
+                })
+            );
+
+            // Probes of other clients are ignored
+            assert_eq!(
+                get_announcement(&create_onvif_discovery_message("uuid:1")),
+                None
+            );
+            assert_eq!(get_announcement("not xml"), None);
+        }
+    }
+
     #[cfg(test)]
     mod discovery_tests {
         use super::*;
@@ -373,7 +711,9 @@ pub mod util {
             let thread_duration = duration.clone();
             tokio::spawn(async move {
                 let start = SystemTime::now();
-                let _ignore = simple_onvif_discover(timeout).await.unwrap();
+                let _ignore = simple_onvif_discover(timeout, &ProbeTargets::default())
+                    .await
+                    .unwrap();
                 let end = SystemTime::now();
                 let mut inner_duration = thread_duration.lock().unwrap();
                 *inner_duration = end.duration_since(start).unwrap();
@@ -1,22 +1,40 @@
+use super::credentials::{secure_message, OnvifCredentials, OnvifCredentialsStore};
 use async_trait::async_trait;
 use futures_util::stream::TryStreamExt;
 use hyper::Request;
 use log::trace;
 #[cfg(test)]
 use mockall::{automock, predicate::*};
-use std::io::{Error, ErrorKind};
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind},
+};
 use sxd_document::{parser, Package};
 use sxd_xpath::Value;
 
 pub const ONVIF_DEVICE_SERVICE_URL_LABEL_ID: &str = "ONVIF_DEVICE_SERVICE_URL";
 pub const ONVIF_DEVICE_IP_ADDRESS_LABEL_ID: &str = "ONVIF_DEVICE_IP_ADDRESS";
 pub const ONVIF_DEVICE_MAC_ADDRESS_LABEL_ID: &str = "ONVIF_DEVICE_MAC_ADDRESS";
+pub const ONVIF_DEVICE_MANUFACTURER_LABEL_ID: &str = "ONVIF_DEVICE_MANUFACTURER";
+pub const ONVIF_DEVICE_MODEL_LABEL_ID: &str = "ONVIF_DEVICE_MODEL";
+pub const ONVIF_DEVICE_FIRMWARE_VERSION_LABEL_ID: &str = "ONVIF_DEVICE_FIRMWARE_VERSION";
+pub const ONVIF_DEVICE_SERIAL_NUMBER_LABEL_ID: &str = "ONVIF_DEVICE_SERIAL_NUMBER";
+pub const ONVIF_DEVICE_HARDWARE_ID_LABEL_ID: &str = "ONVIF_DEVICE_HARDWARE_ID";
+pub const ONVIF_DEVICE_HOSTNAME_LABEL_ID: &str = "ONVIF_DEVICE_HOSTNAME";
+/// Comma separated tokens of a camera's media profiles
+pub const ONVIF_DEVICE_PROFILES_LABEL_ID: &str = "ONVIF_DEVICE_PROFILES";
+/// RTSP stream uri of a camera's first media profile
+pub const ONVIF_DEVICE_STREAM_URI_LABEL_ID: &str = "ONVIF_DEVICE_STREAM_URI";
+/// Prefix of the RTSP stream uri of each of a camera's media profiles, which is followed by the profile's token
+pub const ONVIF_DEVICE_PROFILE_STREAM_URI_LABEL_PREFIX: &str = "ONVIF_DEVICE_STREAM_URI_";
 pub const MEDIA_WSDL: &str = "http://www.onvif.org/ver10/media/wsdl";
 pub const DEVICE_WSDL: &str = "http://www.onvif.org/ver10/device/wsdl";
+pub const PTZ_WSDL: &str = "http://www.onvif.org/ver20/ptz/wsdl";
 
 /// OnvifQuery can access ONVIF properties given an ONVIF camera's device service url.
 ///
-/// An implementation of an onvif query can retrieve the camera's ip/mac address, scopes, profiles and streaming uri.
+/// An implementation of an onvif query can retrieve the camera's ip/mac address, scopes, device information, hostname,
+/// profiles and streaming uri.
 #[cfg_attr(test, automock)]
 #[async_trait]
 pub trait OnvifQuery {
@@ -25,11 +43,21 @@ pub trait OnvifQuery {
         service_url: &str,
     ) -> Result<(String, String), anyhow::Error>;
     async fn get_device_scopes(&self, url: &str) -> Result<Vec<String>, anyhow::Error>;
+    async fn check_device_service(&self, url: &str) -> Result<(), anyhow::Error>;
+    async fn get_device_information(
+        &self,
+        url: &str,
+    ) -> Result<OnvifDeviceInformation, anyhow::Error>;
+    async fn get_device_hostname(&self, url: &str) -> Result<String, anyhow::Error>;
     async fn get_device_service_uri(
         &self,
         url: &str,
         service: &str,
     ) -> Result<String, anyhow::Error>;
+    async fn get_device_services(
+        &self,
+        url: &str,
+    ) -> Result<HashMap<String, String>, anyhow::Error>;
     async fn get_device_profiles(&self, url: &str) -> Result<Vec<String>, anyhow::Error>;
     async fn get_device_profile_streaming_uri(
         &self,
@@ -38,7 +66,20 @@ pub trait OnvifQuery {
     ) -> Result<String, anyhow::Error>;
 }
 
-pub struct OnvifQueryImpl {}
+/// Manufacturer, model, firmware version, serial number and hardware id of an ONVIF camera
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OnvifDeviceInformation {
+    pub manufacturer: String,
+    pub model: String,
+    pub firmware_version: String,
+    pub serial_number: String,
+    pub hardware_id: String,
+}
+
+/// Queries ONVIF cameras, authenticating with the credentials of each camera, if any
+pub struct OnvifQueryImpl {
+    pub credentials: OnvifCredentialsStore,
+}
 
 #[async_trait]
 impl OnvifQuery for OnvifQueryImpl {
@@ -48,13 +89,35 @@ impl OnvifQuery for OnvifQueryImpl {
         service_url: &str,
     ) -> Result<(String, String), anyhow::Error> {
         let http = HttpRequest {};
-        inner_get_device_ip_and_mac_address(service_url, &http).await
+        inner_get_device_ip_and_mac_address(service_url, self.credentials.get(service_url), &http)
+            .await
     }
 
     /// Gets the list of scopes for a given ONVIF camera
     async fn get_device_scopes(&self, url: &str) -> Result<Vec<String>, anyhow::Error> {
         let http = HttpRequest {};
-        inner_get_device_scopes(url, &http).await
+        inner_get_device_scopes(url, self.credentials.get(url), &http).await
+    }
+
+    /// Checks whether a url is the device service of an ONVIF camera
+    async fn check_device_service(&self, url: &str) -> Result<(), anyhow::Error> {
+        let http = HttpRequest {};
+        inner_check_device_service(url, &http).await
+    }
+
+    /// Gets the manufacturer, model, firmware version, serial number and hardware id of a given ONVIF camera
+    async fn get_device_information(
+        &self,
+        url: &str,
+    ) -> Result<OnvifDeviceInformation, anyhow::Error> {
+        let http = HttpRequest {};
+        inner_get_device_information(url, self.credentials.get(url), &http).await
+    }
+
+    /// Gets the hostname of a given ONVIF camera
+    async fn get_device_hostname(&self, url: &str) -> Result<String, anyhow::Error> {
+        let http = HttpRequest {};
+        inner_get_device_hostname(url, self.credentials.get(url), &http).await
     }
 
     /// Gets specific service, like media, from a given ONVIF camera
@@ -64,13 +127,22 @@ impl OnvifQuery for OnvifQueryImpl {
         service: &str,
     ) -> Result<String, anyhow::Error> {
         let http = HttpRequest {};
-        inner_get_device_service_uri(url, service, &http).await
+        inner_get_device_service_uri(url, service, self.credentials.get(url), &http).await
+    }
+
+    /// Gets the namespace and uri of each service of a given ONVIF camera
+    async fn get_device_services(
+        &self,
+        url: &str,
+    ) -> Result<HashMap<String, String>, anyhow::Error> {
+        let http = HttpRequest {};
+        inner_get_device_services(url, self.credentials.get(url), &http).await
     }
 
     /// Gets the list of streaming profiles for a given ONVIF camera
     async fn get_device_profiles(&self, url: &str) -> Result<Vec<String>, anyhow::Error> {
         let http = HttpRequest {};
-        inner_get_device_profiles(url, &http).await
+        inner_get_device_profiles(url, self.credentials.get(url), &http).await
     }
 
     /// Gets the streaming uri for a given ONVIF camera's profile
@@ -80,7 +152,8 @@ impl OnvifQuery for OnvifQueryImpl {
         profile_token: &str,
     ) -> Result<String, anyhow::Error> {
         let http = HttpRequest {};
-        inner_get_device_profile_streaming_uri(url, profile_token, &http).await
+        inner_get_device_profile_streaming_uri(url, profile_token, self.credentials.get(url), &http)
+            .await
     }
 }
 
@@ -135,7 +208,7 @@ impl Http for HttpRequest {
             .header("CONTENT-TYPE", full_mime)
             .body(msg.to_string().into())
             .expect("infallible");
-        let response = hyper::Client::new().request(request).await.unwrap();
+        let response = hyper::Client::new().request(request).await?;
         if response.status() != 200 {
             return Err(anyhow::format_err!("failure"));
         }
@@ -166,16 +239,25 @@ fn get_action(wsdl: &str, function: &str) -> String {
     format!("action=\"{}/{}\"", wsdl, function)
 }
 
+/// Adds a WS-Security header to a SOAP request body if there are credentials for the camera
+fn get_message(message: &str, credentials: Option<&OnvifCredentials>) -> String {
+    match credentials {
+        Some(credentials) => secure_message(message, credentials),
+        None => message.to_string(),
+    }
+}
+
 /// Gets the ip and mac address for a given ONVIF camera
 async fn inner_get_device_ip_and_mac_address(
     service_url: &str,
+    credentials: Option<&OnvifCredentials>,
     http: &impl Http,
 ) -> Result<(String, String), anyhow::Error> {
     let network_interfaces_xml = match http
         .post(
             service_url,
             &get_action(DEVICE_WSDL, "GetNetworkInterfaces"),
-            &GET_NETWORK_INTERFACES_TEMPLATE.to_string(),
+            &get_message(GET_NETWORK_INTERFACES_TEMPLATE, credentials),
         )
         .await
     {
@@ -228,13 +310,14 @@ async fn inner_get_device_ip_and_mac_address(
 /// Gets the list of scopes for a given ONVIF camera
 async fn inner_get_device_scopes(
     url: &str,
+    credentials: Option<&OnvifCredentials>,
     http: &impl Http,
 ) -> Result<Vec<String>, anyhow::Error> {
     let scopes_xml = match http
         .post(
             &url,
             &get_action(DEVICE_WSDL, "GetScopes"),
-            &GET_SCOPES_TEMPLATE.to_string(),
+            &get_message(GET_SCOPES_TEMPLATE, credentials),
         )
         .await
     {
@@ -283,17 +366,163 @@ const GET_SCOPES_TEMPLATE: &str = r#"<soap:Envelope xmlns:soap="http://www.w3.or
         </soap:Body>
     </soap:Envelope>"#;
 
+/// Checks whether a url is the device service of an ONVIF camera by requesting its system date and time, which ONVIF
+/// cameras answer without authentication
+async fn inner_check_device_service(url: &str, http: &impl Http) -> Result<(), anyhow::Error> {
+    let system_date_and_time_xml = match http
+        .post(
+            &url,
+            &get_action(DEVICE_WSDL, "GetSystemDateAndTime"),
+            &GET_SYSTEM_DATE_AND_TIME_TEMPLATE.to_string(),
+        )
+        .await
+    {
+        Ok(xml) => xml,
+        Err(e) => {
+            return Err(anyhow::format_err!(
+                "failed to get system date and time from device: {:?}",
+                e
+            ))
+        }
+    };
+    let system_date_and_time_doc = system_date_and_time_xml.as_document();
+    match sxd_xpath::evaluate_xpath(
+        &system_date_and_time_doc,
+        "count(//*[local-name()='GetSystemDateAndTimeResponse'])",
+    ) {
+        Ok(Value::Number(count)) if count > 0.0 => Ok(()),
+        Ok(_) => Err(anyhow::format_err!(
+            "{} did not answer with its system date and time",
+            url
+        )),
+        Err(e) => Err(anyhow::format_err!(
+            "Failed to get ONVIF system date and time: {}",
+            e
+        )),
+    }
+}
+
+/// SOAP request body for getting the system date and time of an ONVIF camera
+const GET_SYSTEM_DATE_AND_TIME_TEMPLATE: &str = r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:wsdl="http://www.onvif.org/ver10/device/wsdl">
+    <soap:Header/>
+        <soap:Body>
+            <wsdl:GetSystemDateAndTime/>
+        </soap:Body>
+    </soap:Envelope>"#;
+
+/// Gets the device information for a given ONVIF camera
+async fn inner_get_device_information(
+    url: &str,
+    credentials: Option<&OnvifCredentials>,
+    http: &impl Http,
+) -> Result<OnvifDeviceInformation, anyhow::Error> {
+    let device_information_xml = match http
+        .post(
+            &url,
+            &get_action(DEVICE_WSDL, "GetDeviceInformation"),
+            &get_message(GET_DEVICE_INFORMATION_TEMPLATE, credentials),
+        )
+        .await
+    {
+        Ok(xml) => xml,
+        Err(e) => {
+            return Err(anyhow::format_err!(
+                "failed to get device information from device: {:?}",
+                e
+            ))
+        }
+    };
+    let device_information_doc = device_information_xml.as_document();
+    let get_field = |field: &str| -> Result<String, anyhow::Error> {
+        let xpath = format!(
+            "//*[local-name()='GetDeviceInformationResponse']/*[local-name()='{}']/text()",
+            field
+        );
+        match sxd_xpath::evaluate_xpath(&device_information_doc, &xpath) {
+            Ok(value) => Ok(value.string()),
+            Err(e) => Err(anyhow::format_err!(
+                "Failed to get ONVIF device information {}: {}",
+                field,
+                e
+            )),
+        }
+    };
+    let device_information = OnvifDeviceInformation {
+        manufacturer: get_field("Manufacturer")?,
+        model: get_field("Model")?,
+        firmware_version: get_field("FirmwareVersion")?,
+        serial_number: get_field("SerialNumber")?,
+        hardware_id: get_field("HardwareId")?,
+    };
+    trace!(
+        "inner_get_device_information - device information: {:?}",
+        device_information
+    );
+    Ok(device_information)
+}
+
+/// SOAP request body for getting the device information for an ONVIF camera
+const GET_DEVICE_INFORMATION_TEMPLATE: &str = r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:wsdl="http://www.onvif.org/ver10/device/wsdl">
+    <soap:Header/>
+        <soap:Body>
+            <wsdl:GetDeviceInformation/>
+        </soap:Body>
+    </soap:Envelope>"#;
+
+/// Gets the hostname of a given ONVIF camera
+async fn inner_get_device_hostname(
+    url: &str,
+    credentials: Option<&OnvifCredentials>,
+    http: &impl Http,
+) -> Result<String, anyhow::Error> {
+    let hostname_xml = match http
+        .post(
+            &url,
+            &get_action(DEVICE_WSDL, "GetHostname"),
+            &get_message(GET_HOSTNAME_TEMPLATE, credentials),
+        )
+        .await
+    {
+        Ok(xml) => xml,
+        Err(e) => {
+            return Err(anyhow::format_err!(
+                "failed to get hostname from device: {:?}",
+                e
+            ))
+        }
+    };
+    let hostname_doc = hostname_xml.as_document();
+    let hostname = match sxd_xpath::evaluate_xpath(
+        &hostname_doc,
+        "//*[local-name()='GetHostnameResponse']/*[local-name()='HostnameInformation']/*[local-name()='Name']/text()",
+    ) {
+        Ok(hostname) => hostname.string(),
+        Err(e) => return Err(anyhow::format_err!("Failed to get ONVIF hostname: {}", e)),
+    };
+    trace!("inner_get_device_hostname - hostname: {:?}", hostname);
+    Ok(hostname)
+}
+
+/// SOAP request body for getting the hostname of an ONVIF camera
+const GET_HOSTNAME_TEMPLATE: &str = r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:wsdl="http://www.onvif.org/ver10/device/wsdl">
+    <soap:Header/>
+        <soap:Body>
+            <wsdl:GetHostname/>
+        </soap:Body>
+    </soap:Envelope>"#;
+
 /// Gets a specific service (like media) uri from an ONVIF camera
 async fn inner_get_device_service_uri(
     url: &str,
     service: &str,
+    credentials: Option<&OnvifCredentials>,
     http: &impl Http,
 ) -> Result<String, anyhow::Error> {
     let services_xml = match http
         .post(
             &url,
             &get_action(DEVICE_WSDL, "GetServices"),
-            &GET_SERVICES_TEMPLATE.to_string(),
+            &get_message(GET_SERVICES_TEMPLATE, credentials),
         )
         .await
     {
@@ -328,6 +557,55 @@ async fn inner_get_device_service_uri(
     Ok(requested_device_service_uri)
 }
 
+/// Gets the uri of each service of an ONVIF camera by the service's namespace
+async fn inner_get_device_services(
+    url: &str,
+    credentials: Option<&OnvifCredentials>,
+    http: &impl Http,
+) -> Result<HashMap<String, String>, anyhow::Error> {
+    let services_xml = match http
+        .post(
+            &url,
+            &get_action(DEVICE_WSDL, "GetServices"),
+            &get_message(GET_SERVICES_TEMPLATE, credentials),
+        )
+        .await
+    {
+        Ok(xml) => xml,
+        Err(e) => {
+            return Err(anyhow::format_err!(
+                "failed to get services from device: {:?}",
+                e
+            ))
+        }
+    };
+    let services_doc = services_xml.as_document();
+    let get_service_fields = |field: &str| -> Result<Vec<String>, anyhow::Error> {
+        let xpath = format!(
+            "//*[local-name()='GetServicesResponse']/*[local-name()='Service']/*[local-name()='{}']",
+            field
+        );
+        match sxd_xpath::evaluate_xpath(&services_doc, &xpath) {
+            Ok(Value::Nodeset(items)) => Ok(items
+                .document_order()
+                .iter()
+                .map(|item| item.string_value().trim().to_string())
+                .collect()),
+            Ok(_) => Err(anyhow::format_err!(
+                "Failed to get ONVIF services: unexpected type"
+            )),
+            Err(e) => Err(anyhow::format_err!("Failed to get ONVIF services: {}", e)),
+        }
+    };
+    // Every service has both a namespace and a uri, so they pair up in document order
+    let services: HashMap<String, String> = get_service_fields("Namespace")?
+        .into_iter()
+        .zip(get_service_fields("XAddr")?.into_iter())
+        .collect();
+    trace!("inner_get_device_services - services: {:?}", services);
+    Ok(services)
+}
+
 /// SOAP request body for getting the supported services' uris for an ONVIF camera
 const GET_SERVICES_TEMPLATE: &str = r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:wsdl="http://www.onvif.org/ver10/device/wsdl">
     <soap:Header/>
@@ -339,10 +617,11 @@ const GET_SERVICES_TEMPLATE: &str = r#"<soap:Envelope xmlns:soap="http://www.w3.
 /// Gets list of media profiles for a given ONVIF camera
 async fn inner_get_device_profiles(
     url: &str,
+    credentials: Option<&OnvifCredentials>,
     http: &impl Http,
 ) -> Result<Vec<String>, anyhow::Error> {
     let action = get_action(MEDIA_WSDL, "GetProfiles");
-    let message = GET_PROFILES_TEMPLATE.to_string();
+    let message = get_message(GET_PROFILES_TEMPLATE, credentials);
     let profiles_xml = match http.post(&url, &action, &message).await {
         Ok(xml) => xml,
         Err(e) => {
@@ -377,9 +656,10 @@ async fn inner_get_device_profiles(
 async fn inner_get_device_profile_streaming_uri(
     url: &str,
     profile_token: &str,
+    credentials: Option<&OnvifCredentials>,
     http: &impl Http,
 ) -> Result<String, anyhow::Error> {
-    let stream_soap = get_stream_uri_message(&profile_token);
+    let stream_soap = get_message(&get_stream_uri_message(&profile_token), credentials);
     let stream_uri_xml = match http
         .post(&url, &get_action(MEDIA_WSDL, "GetStreamUri"), &stream_soap)
         .await
@@ -437,20 +717,6 @@ const GET_PROFILES_TEMPLATE: &str = r#"<soap:Envelope xmlns:soap="http://www.w3.
         </soap:Body>
     </soap:Envelope>"#;
 
-//  const GET_DEVICE_INFORMATION_TEMPLATE: &str = r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:wsdl="http://www.onvif.org/ver10/device/wsdl">
-//     <soap:Header/>
-//         <soap:Body>
-//             <wsdl:GetDeviceInformation/>
-//         </soap:Body>
-//     </soap:Envelope>"#;
-
-//  const GET_HOSTNAME_TEMPLATE: &str = r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:wsdl="http://www.onvif.org/ver10/device/wsdl">
-//     <soap:Header/>
-//         <soap:Body>
-//             <wsdl:GetHostname/>
-//         </soap:Body>
-//     </soap:Envelope>"#;
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -489,6 +755,7 @@ mod tests {
             ("192.168.1.36".to_string(), "00:12:41:5c:a1:a5".to_string()),
             inner_get_device_ip_and_mac_address(
                 &"test_inner_get_device_ip_and_mac_address-url".to_string(),
+                None,
                 &mock
             )
             .await
@@ -516,6 +783,7 @@ mod tests {
             ),
             inner_get_device_ip_and_mac_address(
                 &"test_inner_get_device_ip_and_mac_address-url".to_string(),
+                None,
                 &mock
             )
             .await
@@ -550,7 +818,7 @@ mod tests {
         expected.sort();
 
         let mut actual =
-            inner_get_device_scopes(&"test_inner_get_device_scopes-url".to_string(), &mock)
+            inner_get_device_scopes(&"test_inner_get_device_scopes-url".to_string(), None, &mock)
                 .await
                 .unwrap();
         actual.sort();
@@ -576,6 +844,7 @@ mod tests {
             inner_get_device_service_uri(
                 &"test_inner_get_device_service_uri-url".to_string(),
                 &MEDIA_WSDL.to_string(),
+                None,
                 &mock
             )
             .await
@@ -583,6 +852,32 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_inner_get_device_services() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockHttp::new();
+        let response = r#"<?xml version="1.0" encoding="UTF-8"?>
+<SOAP-ENV:Envelope xmlns:SOAP-ENV="http://www.w3.org/2003/05/soap-envelope" xmlns:tds="http://www.onvif.org/ver10/device/wsdl" xmlns:tt="http://www.onvif.org/ver10/schema"><SOAP-ENV:Body><tds:GetServicesResponse><tds:Service><tds:Namespace>http://www.onvif.org/ver10/device/wsdl</tds:Namespace><tds:XAddr>http://192.168.1.35:8899/onvif/device_service</tds:XAddr><tds:Version><tt:Major>2</tt:Major><tt:Minor>41</tt:Minor></tds:Version></tds:Service><tds:Service><tds:Namespace>http://www.onvif.org/ver10/media/wsdl</tds:Namespace><tds:XAddr>http://192.168.1.35:8899/onvif/Media</tds:XAddr><tds:Version><tt:Major>2</tt:Major><tt:Minor>41</tt:Minor></tds:Version></tds:Service><tds:Service><tds:Namespace>http://www.onvif.org/ver20/ptz/wsdl</tds:Namespace><tds:XAddr>http://192.168.1.35:8899/onvif/PTZ</tds:XAddr><tds:Version><tt:Major>2</tt:Major><tt:Minor>41</tt:Minor></tds:Version></tds:Service></tds:GetServicesResponse></SOAP-ENV:Body></SOAP-ENV:Envelope>"#;
+        configure_post(
+            &mut mock,
+            "test_inner_get_device_services-url",
+            &get_action(DEVICE_WSDL, "GetServices"),
+            GET_SERVICES_TEMPLATE,
+            response,
+        );
+        let services = inner_get_device_services("test_inner_get_device_services-url", None, &mock)
+            .await
+            .unwrap();
+        assert_eq!(services.len(), 3);
+        assert_eq!(
+            services[DEVICE_WSDL],
+            "http://192.168.1.35:8899/onvif/device_service"
+        );
+        assert_eq!(services[MEDIA_WSDL], "http://192.168.1.35:8899/onvif/Media");
+        assert_eq!(services[PTZ_WSDL], "http://192.168.1.35:8899/onvif/PTZ");
+    }
+
     #[tokio::test]
     async fn test_inner_get_device_profiles() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -599,10 +894,13 @@ mod tests {
                 &response.to_string(),
             );
         }
-        let mut actual_profiles =
-            inner_get_device_profiles(&"test_inner_get_device_profiles-url".to_string(), &mock)
-                .await
-                .unwrap();
+        let mut actual_profiles = inner_get_device_profiles(
+            &"test_inner_get_device_profiles-url".to_string(),
+            None,
+            &mock,
+        )
+        .await
+        .unwrap();
         actual_profiles.sort();
         assert_eq!(
             vec!["000".to_string(), "001".to_string(), "002".to_string()],
@@ -641,6 +939,7 @@ mod tests {
                 inner_get_device_profile_streaming_uri(
                     &"test_inner_get_device_profile_streaming_uri-url".to_string(),
                     &profile,
+                    None,
                     &mock
                 )
                 .await
@@ -649,6 +948,161 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_inner_get_device_scopes_with_credentials() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockHttp::new();
+        mock.expect_post()
+            .times(1)
+            .withf(|_, _, msg| {
+                msg.contains("<wsse:Username>admin</wsse:Username>")
+                    && msg.contains("PasswordDigest")
+                    && !msg.contains("secret-password")
+                    && msg.contains("<wsdl:GetScopes/>")
+            })
+            .returning(|_, _, _| {
+                Ok(parser::parse(
+                    r#"<Envelope><Body><GetScopesResponse><Scopes><ScopeItem>onvif://www.onvif.org/name/NVT</ScopeItem></Scopes></GetScopesResponse></Body></Envelope>"#,
+                )
+                .unwrap())
+            });
+        let credentials = OnvifCredentials {
+            username: "admin".to_string(),
+            password: "secret-password".to_string(),
+        };
+        assert_eq!(
+            vec!["onvif://www.onvif.org/name/NVT".to_string()],
+            inner_get_device_scopes("test-url", Some(&credentials), &mock)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inner_check_device_service() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockHttp::new();
+        let response = r#"<?xml version="1.0" encoding="UTF-8"?>
+<SOAP-ENV:Envelope xmlns:SOAP-ENV="http://www.w3.org/2003/05/soap-envelope" xmlns:tds="http://www.onvif.org/ver10/device/wsdl" xmlns:tt="http://www.onvif.org/ver10/schema">
+    <SOAP-ENV:Body>
+        <tds:GetSystemDateAndTimeResponse>
+            <tds:SystemDateAndTime>
+                <tt:DateTimeType>NTP</tt:DateTimeType>
+                <tt:DaylightSavings>false</tt:DaylightSavings>
+            </tds:SystemDateAndTime>
+        </tds:GetSystemDateAndTimeResponse>
+    </SOAP-ENV:Body>
+</SOAP-ENV:Envelope>"#;
+        configure_post(
+            &mut mock,
+            &"test_inner_check_device_service-url".to_string(),
+            &get_action(DEVICE_WSDL, "GetSystemDateAndTime"),
+            &GET_SYSTEM_DATE_AND_TIME_TEMPLATE.to_string(),
+            &response.to_string(),
+        );
+        assert!(inner_check_device_service(
+            &"test_inner_check_device_service-url".to_string(),
+            &mock
+        )
+        .await
+        .is_ok());
+
+        // Other web servers do not answer with a system date and time
+        let mut mock = MockHttp::new();
+        configure_post(
+            &mut mock,
+            &"test_inner_check_device_service-url".to_string(),
+            &get_action(DEVICE_WSDL, "GetSystemDateAndTime"),
+            &GET_SYSTEM_DATE_AND_TIME_TEMPLATE.to_string(),
+            &"<html><body>Router login</body></html>".to_string(),
+        );
+        assert!(inner_check_device_service(
+            &"test_inner_check_device_service-url".to_string(),
+            &mock
+        )
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_inner_get_device_information() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockHttp::new();
+        let response = r#"<?xml version="1.0" encoding="UTF-8"?>
+<SOAP-ENV:Envelope xmlns:SOAP-ENV="http://www.w3.org/2003/05/soap-envelope" xmlns:tds="http://www.onvif.org/ver10/device/wsdl">
+    <SOAP-ENV:Body>
+        <tds:GetDeviceInformationResponse>
+            <tds:Manufacturer>Contoso</tds:Manufacturer>
+            <tds:Model>IPC-model</tds:Model>
+            <tds:FirmwareVersion>V4.11.0</tds:FirmwareVersion>
+            <tds:SerialNumber>00123456</tds:SerialNumber>
+            <tds:HardwareId>1419d68a-1dd2-11b2-a105-000000000000</tds:HardwareId>
+        </tds:GetDeviceInformationResponse>
+    </SOAP-ENV:Body>
+</SOAP-ENV:Envelope>"#;
+        configure_post(
+            &mut mock,
+            &"test_inner_get_device_information-url".to_string(),
+            &get_action(DEVICE_WSDL, "GetDeviceInformation"),
+            &GET_DEVICE_INFORMATION_TEMPLATE.to_string(),
+            &response.to_string(),
+        );
+        assert_eq!(
+            OnvifDeviceInformation {
+                manufacturer: "Contoso".to_string(),
+                model: "IPC-model".to_string(),
+                firmware_version: "V4.11.0".to_string(),
+                serial_number: "00123456".to_string(),
+                hardware_id: "1419d68a-1dd2-11b2-a105-000000000000".to_string(),
+            },
+            inner_get_device_information(
+                &"test_inner_get_device_information-url".to_string(),
+                None,
+                &mock
+            )
+            .await
+            .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inner_get_device_hostname() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockHttp::new();
+        let response = r#"<?xml version="1.0" encoding="UTF-8"?>
+<SOAP-ENV:Envelope xmlns:SOAP-ENV="http://www.w3.org/2003/05/soap-envelope" xmlns:tds="http://www.onvif.org/ver10/device/wsdl" xmlns:tt="http://www.onvif.org/ver10/schema">
+    <SOAP-ENV:Body>
+        <tds:GetHostnameResponse>
+            <tds:HostnameInformation>
+                <tt:FromDHCP>false</tt:FromDHCP>
+                <tt:Name>camera-lobby</tt:Name>
+            </tds:HostnameInformation>
+        </tds:GetHostnameResponse>
+    </SOAP-ENV:Body>
+</SOAP-ENV:Envelope>"#;
+        configure_post(
+            &mut mock,
+            &"test_inner_get_device_hostname-url".to_string(),
+            &get_action(DEVICE_WSDL, "GetHostname"),
+            &GET_HOSTNAME_TEMPLATE.to_string(),
+            &response.to_string(),
+        );
+        assert_eq!(
+            "camera-lobby".to_string(),
+            inner_get_device_hostname(
+                &"test_inner_get_device_hostname-url".to_string(),
+                None,
+                &mock
+            )
+            .await
+            .unwrap()
+        );
+    }
+
     #[test]
     fn test_http_handle_request_body_no_panic() {
         assert!(HttpRequest::handle_request_body("\r\n").is_err());
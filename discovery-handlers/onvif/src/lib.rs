@@ -1,3 +1,4 @@
+mod credentials;
 pub mod discovery_handler;
 mod discovery_impl;
 mod discovery_utils;
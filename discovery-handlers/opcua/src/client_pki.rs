@@ -0,0 +1,210 @@
+use k8s_openapi::api::core::v1::Secret;
+use log::error;
+use std::{
+    fs::{DirBuilder, OpenOptions},
+    io::Write,
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Key of the DER encoded certificate of the Discovery Handler's OPC UA Client in a Secret of client PKI
+pub const CLIENT_CERTIFICATE_KEY: &str = "client_certificate";
+/// Key of the PEM encoded private key of the Discovery Handler's OPC UA Client in a Secret of client PKI
+pub const CLIENT_PRIVATE_KEY_KEY: &str = "client_key_pem";
+/// Prefix of the keys of the DER encoded certificates of trusted OPC UA Servers in a Secret of client PKI
+pub const TRUSTED_CERTIFICATE_KEY_PREFIX: &str = "trusted_";
+
+/// Paths within a PKI directory at which the OPC UA Client looks for its own certificate and private key
+const OWN_CERTIFICATE_PATH: &str = "own/cert.der";
+const OWN_PRIVATE_KEY_PATH: &str = "private/private.pem";
+
+/// Certificate and private key with which the Discovery Handler's OPC UA Client opens secure channels, along with the
+/// certificates of the Servers it trusts, as read from a Secret
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientPki {
+    pub certificate: Vec<u8>,
+    pub private_key: Vec<u8>,
+    pub trusted_server_certificates: Vec<Vec<u8>>,
+}
+
+impl ClientPki {
+    /// Reads the client certificate, private key and trust list in a Secret. The trust list is made up of every entry
+    /// whose key starts with `trusted_`.
+    pub fn from_secret(secret: &Secret) -> Result<Self, anyhow::Error> {
+        let data = secret
+            .data
+            .as_ref()
+            .ok_or_else(|| anyhow::format_err!("Secret has no data"))?;
+        let get_entry = |key: &str| {
+            data.get(key)
+                .map(|value| value.0.clone())
+                .ok_or_else(|| anyhow::format_err!("Secret has no {} entry", key))
+        };
+        let mut trusted_keys: Vec<&String> = data
+            .keys()
+            .filter(|key| key.starts_with(TRUSTED_CERTIFICATE_KEY_PREFIX))
+            .collect();
+        trusted_keys.sort();
+        Ok(ClientPki {
+            certificate: get_entry(CLIENT_CERTIFICATE_KEY)?,
+            private_key: get_entry(CLIENT_PRIVATE_KEY_KEY)?,
+            trusted_server_certificates: trusted_keys
+                .into_iter()
+                .map(|key| data[key].0.clone())
+                .collect(),
+        })
+    }
+
+    /// Writes the client certificate and private key into a PKI directory, such as one made by
+    /// `create_pki_directory`, where the OPC UA Client reads them. The private key is created only readable by the
+    /// Discovery Handler's user, so it is never readable by others, even while it is being written.
+    pub fn write(&self, pki_directory: &Path) -> std::io::Result<()> {
+        let certificate_file = pki_directory.join(OWN_CERTIFICATE_PATH);
+        let private_key_file = pki_directory.join(OWN_PRIVATE_KEY_PATH);
+        for file in &[&certificate_file, &private_key_file] {
+            if let Some(directory) = file.parent() {
+                DirBuilder::new()
+                    .recursive(true)
+                    .mode(0o700)
+                    .create(directory)?;
+            }
+        }
+        std::fs::write(&certificate_file, &self.certificate)?;
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&private_key_file)?
+            .write_all(&self.private_key)
+    }
+}
+
+/// Number of PKI directories created by this process, which tells them apart
+static PKI_DIRECTORY_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Creates a new PKI directory that only the Discovery Handler's user can access. Creation fails rather than reusing
+/// a directory that already exists, so that nobody else can have created it or be able to read the private key
+/// written into it. The directory should be removed with `remove_pki_directory` once it is no longer used.
+pub fn create_pki_directory() -> std::io::Result<PathBuf> {
+    let pki_directory = std::env::temp_dir().join(format!(
+        "akri-opcua-discovery-pki-{}-{}",
+        std::process::id(),
+        PKI_DIRECTORY_COUNT.fetch_add(1, Ordering::SeqCst)
+    ));
+    DirBuilder::new().mode(0o700).create(&pki_directory)?;
+    Ok(pki_directory)
+}
+
+/// Removes a PKI directory along with the private key in it
+pub fn remove_pki_directory(pki_directory: &Path) {
+    if let Err(e) = std::fs::remove_dir_all(pki_directory) {
+        error!(
+            "remove_pki_directory - could not remove {:?}: {}",
+            pki_directory, e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::ByteString;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn secret(data: &[(&str, &str)]) -> Secret {
+        Secret {
+            data: Some(
+                data.iter()
+                    .map(|(key, value)| (key.to_string(), ByteString(value.as_bytes().to_vec())))
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_from_secret() {
+        let client_pki = ClientPki::from_secret(&secret(&[
+            ("client_certificate", "client certificate"),
+            ("client_key_pem", "client key"),
+            ("client_key", "pfx key used by brokers"),
+            ("trusted_server2", "server 2 certificate"),
+            ("trusted_server1", "server 1 certificate"),
+            ("ca_certificate", "ca certificate"),
+        ]))
+        .unwrap();
+        assert_eq!(client_pki.certificate, b"client certificate");
+        assert_eq!(client_pki.private_key, b"client key");
+        assert_eq!(
+            client_pki.trusted_server_certificates,
+            vec![
+                b"server 1 certificate".to_vec(),
+                b"server 2 certificate".to_vec()
+            ]
+        );
+
+        assert_eq!(
+            ClientPki::from_secret(&secret(&[("client_certificate", "client certificate")]))
+                .unwrap_err()
+                .to_string(),
+            "Secret has no client_key_pem entry"
+        );
+        assert!(ClientPki::from_secret(&Secret::default()).is_err());
+    }
+
+    #[test]
+    fn test_write() {
+        let pki_directory = tempfile::tempdir().unwrap();
+        let client_pki = ClientPki {
+            certificate: b"client certificate".to_vec(),
+            private_key: b"client key".to_vec(),
+            trusted_server_certificates: Vec::new(),
+        };
+        client_pki.write(pki_directory.path()).unwrap();
+        // Writing again replaces the files
+        client_pki.write(pki_directory.path()).unwrap();
+        assert_eq!(
+            std::fs::read(pki_directory.path().join("own/cert.der")).unwrap(),
+            b"client certificate"
+        );
+        let private_key_file = pki_directory.path().join("private/private.pem");
+        assert_eq!(std::fs::read(&private_key_file).unwrap(), b"client key");
+        assert_eq!(
+            std::fs::metadata(&private_key_file)
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777,
+            0o600
+        );
+    }
+
+    #[test]
+    fn test_create_pki_directory() {
+        let pki_directory = create_pki_directory().unwrap();
+        let other_pki_directory = create_pki_directory().unwrap();
+        assert_ne!(pki_directory, other_pki_directory);
+        assert_eq!(
+            std::fs::metadata(&pki_directory)
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777,
+            0o700
+        );
+
+        ClientPki {
+            certificate: b"client certificate".to_vec(),
+            private_key: b"client key".to_vec(),
+            trusted_server_certificates: Vec::new(),
+        }
+        .write(&pki_directory)
+        .unwrap();
+        remove_pki_directory(&pki_directory);
+        remove_pki_directory(&other_pki_directory);
+        assert!(!pki_directory.exists());
+        assert!(!other_pki_directory.exists());
+    }
+}
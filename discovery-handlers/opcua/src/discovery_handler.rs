@@ -1,26 +1,46 @@
-use super::{discovery_impl::do_standard_discovery, OPCUA_DISCOVERY_URL_LABEL};
+use super::{
+    client_pki::{create_pki_directory, remove_pki_directory, ClientPki},
+    discovery_impl::{do_standard_discovery, DiscoveredServer, NodeCondition, ServerFilters},
+    wrappers::opcua_client_wrapper::{DiscoverySecurity, NodeLocation},
+    OPCUA_APPLICATION_TYPE_LABEL, OPCUA_APPLICATION_URI_LABEL, OPCUA_DISCOVERY_URL_LABEL,
+    OPCUA_ENDPOINTS_LABEL, OPCUA_PRODUCT_URI_LABEL,
+};
+use ::url::Url;
 use akri_discovery_utils::{
     discovery::{
-        discovery_handler::{deserialize_discovery_details, DISCOVERED_DEVICES_CHANNEL_CAPACITY},
-        v0::{
-            discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, DiscoverResponse,
+        discovery_handler::deserialize_discovery_details,
+        polling::{
+            spawn_polling_discovery, spawn_polling_discovery_v1, PollingDiscoverer, PollingSettings,
         },
-        DiscoverStream,
+        v0::{discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest},
+        v1, DiscoverStream, V1DiscoverStream,
     },
     filtering::FilterList,
 };
+use akri_shared::k8s::{self, KubeInterface};
 use async_trait::async_trait;
 use log::{error, info, trace};
-use std::time::Duration;
+use opcua_client::prelude::{
+    MessageSecurityMode, NodeId, QualifiedName, SecurityPolicy, OPC_TCP_SCHEME,
+};
+use schemars::JsonSchema;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant},
+};
 use tokio::sync::mpsc;
-use tokio::time::delay_for;
 use tonic::{Response, Status};
 
 // TODO: make this configurable
 pub const DISCOVERY_INTERVAL_SECS: u64 = 10;
+/// Age after which the Secret of the client certificate is read again, so that renewed certificates and trust lists
+/// are picked up without reading it on every discovery
+pub const CLIENT_CERTIFICATE_REFRESH_SECS: u64 = 60;
 
 /// Methods for discovering OPC UA Servers
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum OpcuaDiscoveryMethod {
     Standard(StandardOpcuaDiscovery),
@@ -30,11 +50,92 @@ pub enum OpcuaDiscoveryMethod {
 /// Discovers OPC UA Servers and/or LocalDiscoveryServers at specified DiscoveryURLs.
 /// If the DiscoveryURL is for a LocalDiscoveryServer, it will discover all Servers
 /// that have registered with that LocalDiscoveryServer.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
 pub struct StandardOpcuaDiscovery {
     #[serde(default = "lds_discovery_url", skip_serializing_if = "Vec::is_empty")]
     pub discovery_urls: Vec<String>,
+    /// Security with which FindServers is called, for Servers that refuse unsecured discovery requests. If not set,
+    /// FindServers is called with security policy None.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security: Option<OpcuaDiscoverySecurity>,
+}
+
+/// Security policy, message security mode and client certificate with which the Discovery Handler opens secure
+/// channels to DiscoveryEndpoints, along with which Server certificates it trusts
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct OpcuaDiscoverySecurity {
+    #[serde(default)]
+    pub security_policy: OpcuaSecurityPolicy,
+    #[serde(default)]
+    pub message_security_mode: OpcuaMessageSecurityMode,
+    pub client_certificate_secret: OpcuaClientCertificateSecret,
+    /// Whether the certificate of every Server is trusted rather than only those in the Secret's trust list
+    #[serde(default)]
+    pub trust_server_certificates: bool,
+}
+
+/// Security policies with which secure channels can be opened
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+pub enum OpcuaSecurityPolicy {
+    Basic128Rsa15,
+    Basic256,
+    Basic256Sha256,
+    Aes128Sha256RsaOaep,
+}
+
+impl Default for OpcuaSecurityPolicy {
+    fn default() -> Self {
+        OpcuaSecurityPolicy::Basic256Sha256
+    }
+}
+
+impl OpcuaSecurityPolicy {
+    fn to_security_policy(self) -> SecurityPolicy {
+        match self {
+            OpcuaSecurityPolicy::Basic128Rsa15 => SecurityPolicy::Basic128Rsa15,
+            OpcuaSecurityPolicy::Basic256 => SecurityPolicy::Basic256,
+            OpcuaSecurityPolicy::Basic256Sha256 => SecurityPolicy::Basic256Sha256,
+            OpcuaSecurityPolicy::Aes128Sha256RsaOaep => SecurityPolicy::Aes128Sha256RsaOaep,
+        }
+    }
+}
+
+/// Message security modes of secure channels
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+pub enum OpcuaMessageSecurityMode {
+    Sign,
+    SignAndEncrypt,
+}
+
+impl Default for OpcuaMessageSecurityMode {
+    fn default() -> Self {
+        OpcuaMessageSecurityMode::SignAndEncrypt
+    }
+}
+
+impl OpcuaMessageSecurityMode {
+    fn to_message_security_mode(self) -> MessageSecurityMode {
+        match self {
+            OpcuaMessageSecurityMode::Sign => MessageSecurityMode::Sign,
+            OpcuaMessageSecurityMode::SignAndEncrypt => MessageSecurityMode::SignAndEncrypt,
+        }
+    }
+}
+
+/// Secret that holds the DER encoded `client_certificate` and PEM encoded `client_key_pem` of the Discovery
+/// Handler's OPC UA Client, along with the DER encoded certificates of trusted Servers under keys prefixed with
+/// `trusted_`. It can be the same Secret that holds the certificates of OPC UA brokers. Its namespace is required
+/// since Discovery Handlers are not told the namespace of the Configuration.
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct OpcuaClientCertificateSecret {
+    pub name: String,
+    pub namespace: String,
 }
 
 /// If no DiscoveryURLs are specified, uses the OPC UA default DiscoveryURL
@@ -47,14 +148,167 @@ fn lds_discovery_url() -> Vec<String> {
 /// CRD
 ///
 /// The OPC UA discovery handler is designed to support multiple methods
-/// for discovering OPC UA servers and stores a filter list for
-/// application names.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// for discovering OPC UA servers and stores filter lists for
+/// application names, application URIs and product URIs, along with the
+/// minimum message security mode that a server must offer an endpoint with
+/// and the nodes that must exist in its address space.
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
 pub struct OpcuaDiscoveryDetails {
     pub opcua_discovery_method: OpcuaDiscoveryMethod,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub application_names: Option<FilterList>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub application_uris: Option<FilterList>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product_uris: Option<FilterList>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum_security_mode: Option<OpcuaMessageSecurityMode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub node_filter: Vec<OpcuaNodeFilter>,
+}
+
+/// Node that must exist in the address space of an OPC UA Server for the Server to be discovered, located by either
+/// its `nodeId` (such as `ns=2;s=Machine.Model`) or its `browsePath` from the Objects folder (such as
+/// `2:Machine/2:Model`, where each browse name is prefixed with its namespace index unless it is 0). Its value is
+/// passed to brokers as the property `propertyName`, which defaults to `OPCUA_NODE_<index>`.
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct OpcuaNodeFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub browse_path: Option<String>,
+    /// Value, as a string, that the node must have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_value: Option<String>,
+    /// Name of the built-in data type, such as `String` or `UInt32`, that the node's value must have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub property_name: Option<String>,
+}
+
+impl OpcuaNodeFilter {
+    /// Returns the condition that a Server's node must meet, which is the index-th entry of the node filter
+    fn get_node_condition(&self, index: usize) -> Result<NodeCondition, anyhow::Error> {
+        let location = match (&self.node_id, &self.browse_path) {
+            (Some(node_id), None) => NodeLocation::NodeId(
+                NodeId::from_str(node_id)
+                    .map_err(|_| anyhow::format_err!("nodeId \"{}\" is not valid", node_id))?,
+            ),
+            (None, Some(browse_path)) => NodeLocation::BrowsePath(parse_browse_path(browse_path)?),
+            _ => {
+                return Err(anyhow::format_err!(
+                    "exactly one of nodeId and browsePath must be set"
+                ))
+            }
+        };
+        Ok(NodeCondition {
+            location,
+            expected_value: self.expected_value.clone(),
+            data_type: self.data_type.clone(),
+            property_name: self
+                .property_name
+                .clone()
+                .unwrap_or_else(|| format!("OPCUA_NODE_{}", index)),
+        })
+    }
+}
+
+/// Parses a browse path of `/` separated browse names, each optionally prefixed with a namespace index and `:`
+fn parse_browse_path(browse_path: &str) -> Result<Vec<QualifiedName>, anyhow::Error> {
+    browse_path
+        .trim_start_matches('/')
+        .split('/')
+        .map(|browse_name| {
+            let (namespace_index, name) = match browse_name.find(':') {
+                Some(separator) => match browse_name[..separator].parse::<u16>() {
+                    Ok(namespace_index) => (namespace_index, &browse_name[separator + 1..]),
+                    Err(_) => (0, browse_name),
+                },
+                None => (0, browse_name),
+            };
+            if name.is_empty() {
+                return Err(anyhow::format_err!(
+                    "browsePath \"{}\" has an empty browse name",
+                    browse_path
+                ));
+            }
+            Ok(QualifiedName::new(namespace_index, name))
+        })
+        .collect()
+}
+
+impl OpcuaDiscoveryDetails {
+    /// Returns the conditions of the node filter
+    fn get_node_conditions(&self) -> Result<Vec<NodeCondition>, anyhow::Error> {
+        self.node_filter
+            .iter()
+            .enumerate()
+            .map(|(index, node_filter)| {
+                node_filter
+                    .get_node_condition(index)
+                    .map_err(|e| anyhow::format_err!("nodeFilter[{}] is not valid: {}", index, e))
+            })
+            .collect()
+    }
+
+    /// Checks that every DiscoveryURL is an `opc.tcp` URL with a host and port and that every node of the node filter
+    /// is located by a valid NodeId or browse path. This is run by the Configuration webhook rather than by `discover`.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        self.get_node_conditions()?;
+        match &self.opcua_discovery_method {
+            OpcuaDiscoveryMethod::Standard(standard_opcua_discovery) => {
+                for (index, discovery_url) in
+                    standard_opcua_discovery.discovery_urls.iter().enumerate()
+                {
+                    validate_discovery_url(discovery_url).map_err(|e| {
+                        anyhow::format_err!(
+                            "opcuaDiscoveryMethod.standard.discoveryUrls[{}] \"{}\" is not valid: {}",
+                            index,
+                            discovery_url,
+                            e
+                        )
+                    })?;
+                }
+                if let Some(security) = &standard_opcua_discovery.security {
+                    if security.client_certificate_secret.name.is_empty() {
+                        return Err(anyhow::format_err!(
+                            "opcuaDiscoveryMethod.standard.security.clientCertificateSecret.name must not be empty"
+                        ));
+                    }
+                    if security.client_certificate_secret.namespace.is_empty() {
+                        return Err(anyhow::format_err!(
+                            "opcuaDiscoveryMethod.standard.security.clientCertificateSecret.namespace must not be empty"
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that a DiscoveryURL can be used to connect to an OPC UA Server or LocalDiscoveryServer
+fn validate_discovery_url(discovery_url: &str) -> Result<(), anyhow::Error> {
+    let url = Url::parse(discovery_url)?;
+    if url.scheme() != OPC_TCP_SCHEME {
+        return Err(anyhow::format_err!(
+            "scheme must be {} rather than {}",
+            OPC_TCP_SCHEME,
+            url.scheme()
+        ));
+    }
+    if url.host_str().is_none() {
+        return Err(anyhow::format_err!("missing host"));
+    }
+    if url.port().is_none() {
+        return Err(anyhow::format_err!("missing port"));
+    }
+    Ok(())
 }
 
 /// `DiscoveryHandlerImpl` discovers udev instances by parsing the udev rules in `discovery_handler_config.udev_rules`.
@@ -67,6 +321,66 @@ impl DiscoveryHandlerImpl {
     pub fn new(register_sender: Option<mpsc::Sender<()>>) -> Self {
         DiscoveryHandlerImpl { register_sender }
     }
+
+    /// Returns the discoverer that scans for the devices described by the discovery details of a Discover request,
+    /// along with how often it scans
+    fn discoverer(
+        discovery_details: &str,
+    ) -> Result<(impl PollingDiscoverer, PollingSettings), Status> {
+        let discovery_handler_config: OpcuaDiscoveryDetails =
+            deserialize_discovery_details(discovery_details)
+                .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
+        // Servers cannot be filtered on nodes that cannot be located, so an invalid node filter is rejected here.
+        // Everything else in `validate` is only enforced by the Configuration webhook.
+        let nodes = discovery_handler_config
+            .get_node_conditions()
+            .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
+        let filters = ServerFilters {
+            application_names: discovery_handler_config.application_names.clone(),
+            application_uris: discovery_handler_config.application_uris.clone(),
+            product_uris: discovery_handler_config.product_uris.clone(),
+            minimum_security_mode: discovery_handler_config
+                .minimum_security_mode
+                .map(OpcuaMessageSecurityMode::to_message_security_mode),
+            nodes,
+        };
+        let discovery_method = discovery_handler_config.opcua_discovery_method;
+        let (kube_interface, pki_directory) = match &discovery_method {
+            OpcuaDiscoveryMethod::Standard(standard_opcua_discovery) => {
+                match standard_opcua_discovery.security {
+                    Some(_) => {
+                        let pki_directory = create_pki_directory().map_err(|e| {
+                            tonic::Status::new(
+                                tonic::Code::Internal,
+                                format!("could not create PKI directory: {}", e),
+                            )
+                        })?;
+                        let kube_interface = k8s::try_create_kube_interface().map_err(|e| {
+                            tonic::Status::new(
+                                tonic::Code::FailedPrecondition,
+                                format!(
+                                    "could not create a Kubernetes client to read clientCertificateSecret: {}",
+                                    e
+                                ),
+                            )
+                        })?;
+                        (Some(kube_interface), Some(pki_directory))
+                    }
+                    None => (None, None),
+                }
+            }
+        };
+        Ok((
+            OpcuaDiscoverer {
+                discovery_method,
+                filters,
+                kube_interface,
+                pki_directory,
+                discovery_security: CachedDiscoverySecurity::default(),
+            },
+            PollingSettings::new(Duration::from_secs(DISCOVERY_INTERVAL_SECS)),
+        ))
+    }
 }
 
 #[async_trait]
@@ -77,86 +391,203 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
         request: tonic::Request<DiscoverRequest>,
     ) -> Result<Response<Self::DiscoverStream>, Status> {
         info!("discover - called for OPC UA protocol");
-        let register_sender = self.register_sender.clone();
-        let discover_request = request.get_ref();
-        let (mut discovered_devices_sender, discovered_devices_receiver) =
-            mpsc::channel(DISCOVERED_DEVICES_CHANNEL_CAPACITY);
-        let discovery_handler_config: OpcuaDiscoveryDetails =
-            deserialize_discovery_details(&discover_request.discovery_details)
-                .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
-        let mut previously_discovered_devices: Vec<Device> = Vec::new();
-        tokio::spawn(async move {
-            let discovery_method = discovery_handler_config.opcua_discovery_method.clone();
-            let application_names = discovery_handler_config.application_names.clone();
-            loop {
-                let discovery_urls: Vec<String> = match discovery_method.clone() {
-                    OpcuaDiscoveryMethod::Standard(standard_opcua_discovery) => {
-                        do_standard_discovery(
-                            standard_opcua_discovery.discovery_urls.clone(),
-                            application_names.clone(),
-                        )
-                    } // No other discovery methods implemented yet
+        let (discoverer, settings) = Self::discoverer(&request.get_ref().discovery_details)?;
+        Ok(Response::new(spawn_polling_discovery(
+            discoverer,
+            settings,
+            self.register_sender.clone(),
+        )))
+    }
+}
+
+#[async_trait]
+impl akri_discovery_utils::discovery::v1::discovery_handler_server::DiscoveryHandler
+    for DiscoveryHandlerImpl
+{
+    type DiscoverStream = V1DiscoverStream;
+    async fn discover(
+        &self,
+        request: tonic::Request<v1::DiscoverRequest>,
+    ) -> Result<Response<Self::DiscoverStream>, Status> {
+        info!("discover - called for OPC UA protocol over the v1 Discovery API");
+        let (discoverer, settings) = Self::discoverer(&request.get_ref().discovery_details)?;
+        Ok(Response::new(spawn_polling_discovery_v1(
+            discoverer,
+            settings,
+            self.register_sender.clone(),
+        )))
+    }
+}
+
+/// Discovers the OPC UA Servers that pass a Configuration's filters
+struct OpcuaDiscoverer<K> {
+    discovery_method: OpcuaDiscoveryMethod,
+    filters: ServerFilters,
+    kube_interface: Option<K>,
+    /// Private directory into which the client certificate and key are written when discovering over secure channels
+    pki_directory: Option<PathBuf>,
+    discovery_security: CachedDiscoverySecurity,
+}
+
+impl<K> Drop for OpcuaDiscoverer<K> {
+    fn drop(&mut self) {
+        if let Some(pki_directory) = &self.pki_directory {
+            remove_pki_directory(pki_directory);
+        }
+    }
+}
+
+#[async_trait]
+impl<K: KubeInterface + 'static> PollingDiscoverer for OpcuaDiscoverer<K> {
+    async fn discover_once(&mut self) -> Result<Vec<Device>, anyhow::Error> {
+        let discovered_servers: Vec<DiscoveredServer> = match &self.discovery_method {
+            OpcuaDiscoveryMethod::Standard(standard_opcua_discovery) => {
+                // Servers that require security cannot be discovered without the client certificate, so the previously
+                // discovered devices are kept rather than reported as gone if its Secret was never read.
+                let security = match (
+                    &standard_opcua_discovery.security,
+                    &self.kube_interface,
+                    &self.pki_directory,
+                ) {
+                    (Some(security), Some(kube_interface), Some(pki_directory)) => Some(
+                        self.discovery_security
+                            .get(security, pki_directory, kube_interface)
+                            .await?
+                            .clone(),
+                    ),
+                    _ => None,
                 };
+                do_standard_discovery(
+                    standard_opcua_discovery.discovery_urls.clone(),
+                    &self.filters,
+                    security,
+                )
+            } // No other discovery methods implemented yet
+        };
 
-                // Build DiscoveryResult for each server discovered
-                let discovered_devices = discovery_urls
-                    .into_iter()
-                    .map(|discovery_url| {
-                        let mut properties = std::collections::HashMap::new();
-                        trace!(
-                            "discover - found OPC UA server at DiscoveryURL {}",
-                            discovery_url
-                        );
-                        properties
-                            .insert(OPCUA_DISCOVERY_URL_LABEL.to_string(), discovery_url.clone());
-                        Device {
-                            id: discovery_url,
-                            properties,
-                            mounts: Vec::default(),
-                            device_specs: Vec::default(),
-                        }
-                    })
-                    .collect::<Vec<Device>>();
-                let mut changed_device_list = false;
-                let mut matching_device_count = 0;
-                discovered_devices.iter().for_each(|device| {
-                    if !previously_discovered_devices.contains(device) {
-                        changed_device_list = true;
-                    } else {
-                        matching_device_count += 1;
-                    }
-                });
-                if changed_device_list
-                    || matching_device_count != previously_discovered_devices.len()
-                {
-                    trace!("discover - for OPC UA, sending updated device list");
-                    previously_discovered_devices = discovered_devices.clone();
-                    if let Err(e) = discovered_devices_sender
-                        .send(Ok(DiscoverResponse {
-                            devices: discovered_devices,
-                        }))
-                        .await
-                    {
-                        error!(
-                            "discover - for OPC UA failed to send discovery response with error {}",
-                            e
-                        );
-                        if let Some(mut sender) = register_sender {
-                            sender.send(()).await.unwrap();
-                        }
-                        break;
-                    }
+        // Build DiscoveryResult for each server discovered
+        Ok(discovered_servers
+            .into_iter()
+            .map(|server| {
+                trace!(
+                    "discover_once - found OPC UA server at DiscoveryURL {}",
+                    server.discovery_url
+                );
+                Device {
+                    properties: get_server_properties(&server),
+                    id: server.discovery_url,
+                    mounts: Vec::default(),
+                    device_specs: Vec::default(),
+                }
+            })
+            .collect())
+    }
+}
+
+/// Returns the properties of a discovered Server that are passed to brokers, so that they can connect to one of its
+/// endpoints without calling GetEndpoints again
+fn get_server_properties(server: &DiscoveredServer) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    properties.insert(
+        OPCUA_DISCOVERY_URL_LABEL.to_string(),
+        server.discovery_url.clone(),
+    );
+    properties.insert(
+        OPCUA_APPLICATION_URI_LABEL.to_string(),
+        server.application_uri.clone(),
+    );
+    properties.insert(
+        OPCUA_PRODUCT_URI_LABEL.to_string(),
+        server.product_uri.clone(),
+    );
+    properties.insert(
+        OPCUA_APPLICATION_TYPE_LABEL.to_string(),
+        format!("{:?}", server.application_type),
+    );
+    properties.insert(
+        OPCUA_ENDPOINTS_LABEL.to_string(),
+        serde_json::to_string(&server.endpoints).unwrap(),
+    );
+    properties.extend(server.node_values.clone());
+    properties
+}
+
+/// Client certificate and trust list read from a Secret, which is read again once they are
+/// `CLIENT_CERTIFICATE_REFRESH_SECS` old
+#[derive(Default)]
+struct CachedDiscoverySecurity {
+    discovery_security: Option<DiscoverySecurity>,
+    read_at: Option<Instant>,
+}
+
+impl CachedDiscoverySecurity {
+    /// Returns the cached security, reading the Secret first if it is too old. If the Secret cannot be read, the
+    /// security that was last read is kept, and an error is only returned if it was never read.
+    async fn get(
+        &mut self,
+        security: &OpcuaDiscoverySecurity,
+        pki_directory: &Path,
+        kube_interface: &impl KubeInterface,
+    ) -> Result<&DiscoverySecurity, anyhow::Error> {
+        let refresh_interval = Duration::from_secs(CLIENT_CERTIFICATE_REFRESH_SECS);
+        if self
+            .read_at
+            .map_or(true, |read_at| read_at.elapsed() >= refresh_interval)
+        {
+            self.read_at = Some(Instant::now());
+            let secret = &security.client_certificate_secret;
+            match get_discovery_security(security, pki_directory, kube_interface).await {
+                Ok(discovery_security) => self.discovery_security = Some(discovery_security),
+                Err(e) if self.discovery_security.is_some() => error!(
+                    "get - could not load client certificate from Secret {} in namespace {}, keeping the one that was last read: {}",
+                    secret.name, secret.namespace, e
+                ),
+                Err(e) => {
+                    // Reads the Secret again on the next discovery rather than waiting for the refresh interval
+                    self.read_at = None;
+                    return Err(anyhow::format_err!(
+                        "could not load client certificate from Secret {} in namespace {}: {}",
+                        secret.name,
+                        secret.namespace,
+                        e
+                    ));
                 }
-                delay_for(Duration::from_secs(DISCOVERY_INTERVAL_SECS)).await;
             }
-        });
-        Ok(Response::new(discovered_devices_receiver))
+        }
+        Ok(self.discovery_security.as_ref().unwrap())
     }
 }
 
+/// Reads the client certificate, private key and trust list from a Secret and writes the certificate and key into a
+/// PKI directory, from which the OPC UA Client loads them
+async fn get_discovery_security(
+    security: &OpcuaDiscoverySecurity,
+    pki_directory: &Path,
+    kube_interface: &impl KubeInterface,
+) -> Result<DiscoverySecurity, anyhow::Error> {
+    let secret = kube_interface
+        .find_secret(
+            &security.client_certificate_secret.name,
+            &security.client_certificate_secret.namespace,
+        )
+        .await
+        .map_err(|e| anyhow::format_err!("{}", e))?;
+    let client_pki = ClientPki::from_secret(&secret)?;
+    client_pki.write(pki_directory)?;
+    Ok(DiscoverySecurity {
+        security_policy: security.security_policy.to_security_policy(),
+        message_security_mode: security.message_security_mode.to_message_security_mode(),
+        pki_dir: pki_directory.to_path_buf(),
+        trusted_server_certificates: client_pki.trusted_server_certificates,
+        trust_server_certificates: security.trust_server_certificates,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use akri_shared::k8s::MockKubeInterface;
+    use k8s_openapi::{api::core::v1::Secret, ByteString};
 
     #[test]
     fn test_deserialize_discovery_details_empty() {
@@ -189,4 +620,400 @@ mod tests {
         let expected_serialized = r#"{"opcuaDiscoveryMethod":{"standard":{"discoveryUrls":["opc.tcp://127.0.0.1:4855/"]}},"applicationNames":{"items":["Some application name"],"action":"Include"}}"#;
         assert_eq!(expected_serialized, serialized);
     }
+
+    #[test]
+    fn test_validate_discovery_details() {
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details("{}").unwrap();
+        assert!(dh_config.validate().is_ok());
+
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard:
+                discoveryUrls:
+                - opc.tcp://127.0.0.1:4855/
+                - http://127.0.0.1:4855/
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(&yaml).unwrap();
+        assert_eq!(
+            dh_config.validate().unwrap_err().to_string(),
+            "opcuaDiscoveryMethod.standard.discoveryUrls[1] \"http://127.0.0.1:4855/\" is not valid: scheme must be opc.tcp rather than http"
+        );
+
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard:
+                discoveryUrls:
+                - opc.tcp://127.0.0.1/
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(&yaml).unwrap();
+        assert!(dh_config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .ends_with("missing port"));
+
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard:
+                discoveryUrls:
+                - not a url
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(&yaml).unwrap();
+        assert!(dh_config.validate().is_err());
+
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard:
+                security:
+                  clientCertificateSecret:
+                    name: ""
+                    namespace: default
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(&yaml).unwrap();
+        assert_eq!(
+            dh_config.validate().unwrap_err().to_string(),
+            "opcuaDiscoveryMethod.standard.security.clientCertificateSecret.name must not be empty"
+        );
+
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard:
+                security:
+                  clientCertificateSecret:
+                    name: opcua-broker-credentials
+                    namespace: ""
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(&yaml).unwrap();
+        assert_eq!(
+            dh_config.validate().unwrap_err().to_string(),
+            "opcuaDiscoveryMethod.standard.security.clientCertificateSecret.namespace must not be empty"
+        );
+
+        // The namespace of the Secret is required
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard:
+                security:
+                  clientCertificateSecret:
+                    name: opcua-broker-credentials
+        "#;
+        assert!(deserialize_discovery_details::<OpcuaDiscoveryDetails>(&yaml).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_discovery_details_security() {
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard:
+                discoveryUrls:
+                - opc.tcp://127.0.0.1:4855/
+                security:
+                  clientCertificateSecret:
+                    name: opcua-broker-credentials
+                    namespace: akri
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(&yaml).unwrap();
+        assert!(dh_config.validate().is_ok());
+        let OpcuaDiscoveryMethod::Standard(standard_opcua_discovery) =
+            dh_config.opcua_discovery_method;
+        let security = standard_opcua_discovery.security.unwrap();
+        assert_eq!(
+            security.security_policy,
+            OpcuaSecurityPolicy::Basic256Sha256
+        );
+        assert_eq!(
+            security.message_security_mode,
+            OpcuaMessageSecurityMode::SignAndEncrypt
+        );
+        assert_eq!(security.client_certificate_secret.namespace, "akri");
+        assert!(!security.trust_server_certificates);
+
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard:
+                security:
+                  securityPolicy: Basic256
+                  messageSecurityMode: Sign
+                  clientCertificateSecret:
+                    name: opcua-broker-credentials
+                    namespace: default
+                  trustServerCertificates: true
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(&yaml).unwrap();
+        let serialized = serde_json::to_string(&dh_config).unwrap();
+        let expected_serialized = r#"{"opcuaDiscoveryMethod":{"standard":{"discoveryUrls":["opc.tcp://localhost:4840/"],"security":{"securityPolicy":"Basic256","messageSecurityMode":"Sign","clientCertificateSecret":{"name":"opcua-broker-credentials","namespace":"default"},"trustServerCertificates":true}}}}"#;
+        assert_eq!(expected_serialized, serialized);
+    }
+
+    #[test]
+    fn test_deserialize_discovery_details_server_filters() {
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard: {}
+            applicationUris:
+              items:
+              - urn:Mock OPC UA Server
+            productUris:
+              action: Exclude
+              items:
+              - urn:Mock Product
+            minimumSecurityMode: Sign
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(&yaml).unwrap();
+        let serialized = serde_json::to_string(&dh_config).unwrap();
+        let expected_serialized = r#"{"opcuaDiscoveryMethod":{"standard":{"discoveryUrls":["opc.tcp://localhost:4840/"]}},"applicationUris":{"items":["urn:Mock OPC UA Server"],"action":"Include"},"productUris":{"items":["urn:Mock Product"],"action":"Exclude"},"minimumSecurityMode":"Sign"}"#;
+        assert_eq!(expected_serialized, serialized);
+    }
+
+    #[test]
+    fn test_get_server_properties() {
+        let server = DiscoveredServer {
+            discovery_url: "opc.tcp://127.0.0.1:4855/".to_string(),
+            application_uri: "urn:Mock OPC UA Server".to_string(),
+            product_uri: "urn:Mock Product".to_string(),
+            application_type: opcua_client::prelude::ApplicationType::Server,
+            endpoints: vec![super::super::discovery_impl::DiscoveredEndpoint {
+                endpoint_url: "opc.tcp://127.0.0.1:4855/".to_string(),
+                security_policy_uri: "http://opcfoundation.org/UA/SecurityPolicy#Basic256Sha256"
+                    .to_string(),
+                security_mode: "SignAndEncrypt".to_string(),
+                security_level: 10,
+            }],
+            node_values: vec![("MACHINE_MODEL".to_string(), "XYZ-100".to_string())]
+                .into_iter()
+                .collect(),
+        };
+        let properties = get_server_properties(&server);
+        assert_eq!(properties.len(), 6);
+        assert_eq!(properties["MACHINE_MODEL"], "XYZ-100");
+        assert_eq!(
+            properties[OPCUA_DISCOVERY_URL_LABEL],
+            "opc.tcp://127.0.0.1:4855/"
+        );
+        assert_eq!(
+            properties[OPCUA_APPLICATION_URI_LABEL],
+            "urn:Mock OPC UA Server"
+        );
+        assert_eq!(properties[OPCUA_PRODUCT_URI_LABEL], "urn:Mock Product");
+        assert_eq!(properties[OPCUA_APPLICATION_TYPE_LABEL], "Server");
+        assert_eq!(
+            properties[OPCUA_ENDPOINTS_LABEL],
+            r#"[{"endpointUrl":"opc.tcp://127.0.0.1:4855/","securityPolicyUri":"http://opcfoundation.org/UA/SecurityPolicy#Basic256Sha256","securityMode":"SignAndEncrypt","securityLevel":10}]"#
+        );
+    }
+
+    #[test]
+    fn test_get_node_conditions() {
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard: {}
+            nodeFilter:
+            - browsePath: /2:Machine/2:Model
+              expectedValue: XYZ-100
+              propertyName: MACHINE_MODEL
+            - nodeId: ns=2;s=SerialNumber
+              dataType: UInt32
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(&yaml).unwrap();
+        assert!(dh_config.validate().is_ok());
+        assert_eq!(
+            dh_config.get_node_conditions().unwrap(),
+            vec![
+                NodeCondition {
+                    location: NodeLocation::BrowsePath(vec![
+                        QualifiedName::new(2, "Machine"),
+                        QualifiedName::new(2, "Model")
+                    ]),
+                    expected_value: Some("XYZ-100".to_string()),
+                    data_type: None,
+                    property_name: "MACHINE_MODEL".to_string(),
+                },
+                NodeCondition {
+                    location: NodeLocation::NodeId(NodeId::new(2, "SerialNumber")),
+                    expected_value: None,
+                    data_type: Some("UInt32".to_string()),
+                    property_name: "OPCUA_NODE_1".to_string(),
+                }
+            ]
+        );
+
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard: {}
+            nodeFilter:
+            - nodeId: ns=2;s=SerialNumber
+              browsePath: 2:SerialNumber
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(&yaml).unwrap();
+        assert_eq!(
+            dh_config.validate().unwrap_err().to_string(),
+            "nodeFilter[0] is not valid: exactly one of nodeId and browsePath must be set"
+        );
+
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard: {}
+            nodeFilter:
+            - browsePath: 2:Machine//Model
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(&yaml).unwrap();
+        assert!(dh_config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_browse_path() {
+        assert_eq!(
+            parse_browse_path("Server/ServerStatus/0:State").unwrap(),
+            vec![
+                QualifiedName::new(0, "Server"),
+                QualifiedName::new(0, "ServerStatus"),
+                QualifiedName::new(0, "State")
+            ]
+        );
+        // Browse names may contain colons that do not follow a namespace index
+        assert_eq!(
+            parse_browse_path("3:Line:1").unwrap(),
+            vec![QualifiedName::new(3, "Line:1")]
+        );
+        assert_eq!(
+            parse_browse_path("Machine:Model").unwrap(),
+            vec![QualifiedName::new(0, "Machine:Model")]
+        );
+        assert!(parse_browse_path("").is_err());
+    }
+
+    fn client_pki_secret() -> Secret {
+        Secret {
+            data: Some(
+                vec![
+                    (
+                        "client_certificate".to_string(),
+                        ByteString(b"client certificate".to_vec()),
+                    ),
+                    (
+                        "client_key_pem".to_string(),
+                        ByteString(b"client key".to_vec()),
+                    ),
+                    (
+                        "trusted_server".to_string(),
+                        ByteString(b"server certificate".to_vec()),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    fn discovery_security_of(yaml: &str) -> OpcuaDiscoverySecurity {
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        let OpcuaDiscoveryMethod::Standard(standard_opcua_discovery) =
+            dh_config.opcua_discovery_method;
+        standard_opcua_discovery.security.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_discovery_security() {
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard:
+                security:
+                  clientCertificateSecret:
+                    name: opcua-broker-credentials
+                    namespace: default
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(&yaml).unwrap();
+        let OpcuaDiscoveryMethod::Standard(standard_opcua_discovery) =
+            dh_config.opcua_discovery_method;
+        let security = standard_opcua_discovery.security.unwrap();
+        let pki_directory = tempfile::tempdir().unwrap();
+
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_secret()
+            .times(1)
+            .withf(|name, namespace| name == "opcua-broker-credentials" && namespace == "default")
+            .returning(|_, _| Ok(client_pki_secret()));
+        let discovery_security = get_discovery_security(&security, pki_directory.path(), &mock)
+            .await
+            .unwrap();
+        assert_eq!(
+            discovery_security,
+            DiscoverySecurity {
+                security_policy: SecurityPolicy::Basic256Sha256,
+                message_security_mode: MessageSecurityMode::SignAndEncrypt,
+                pki_dir: pki_directory.path().to_path_buf(),
+                trusted_server_certificates: vec![b"server certificate".to_vec()],
+                trust_server_certificates: false,
+            }
+        );
+        assert_eq!(
+            std::fs::read(pki_directory.path().join("own/cert.der")).unwrap(),
+            b"client certificate"
+        );
+
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_secret()
+            .times(1)
+            .returning(|_, _| Err("forbidden".into()));
+        assert!(
+            get_discovery_security(&security, pki_directory.path(), &mock)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cached_discovery_security() {
+        let security = discovery_security_of(
+            r#"
+            opcuaDiscoveryMethod:
+              standard:
+                security:
+                  clientCertificateSecret:
+                    name: opcua-broker-credentials
+                    namespace: default
+        "#,
+        );
+        let pki_directory = tempfile::tempdir().unwrap();
+
+        // An error is returned while the Secret was never read
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_secret()
+            .times(1)
+            .returning(|_, _| Err("forbidden".into()));
+        let mut cached_discovery_security = CachedDiscoverySecurity::default();
+        assert!(cached_discovery_security
+            .get(&security, pki_directory.path(), &mock)
+            .await
+            .is_err());
+
+        // The Secret is read once until it is too old
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_secret()
+            .times(1)
+            .returning(|_, _| Ok(client_pki_secret()));
+        for _ in 0..2 {
+            assert_eq!(
+                cached_discovery_security
+                    .get(&security, pki_directory.path(), &mock)
+                    .await
+                    .unwrap()
+                    .trusted_server_certificates,
+                vec![b"server certificate".to_vec()]
+            );
+        }
+
+        // The security that was last read is kept if the Secret cannot be read again
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_secret()
+            .times(1)
+            .returning(|_, _| Err("forbidden".into()));
+        cached_discovery_security.read_at =
+            Some(Instant::now() - Duration::from_secs(CLIENT_CERTIFICATE_REFRESH_SECS));
+        assert!(cached_discovery_security
+            .get(&security, pki_directory.path(), &mock)
+            .await
+            .is_ok());
+    }
 }
@@ -1,5 +1,7 @@
 use super::wrappers::{
-    opcua_client_wrapper::{create_opcua_discovery_client, OpcuaClient},
+    opcua_client_wrapper::{
+        create_opcua_discovery_client, DiscoverySecurity, NodeLocation, OpcuaClient,
+    },
     tcp_stream_wrapper::{TcpStream, TcpStreamImpl},
 };
 use ::url::Url;
@@ -7,6 +9,8 @@ use akri_discovery_utils::filtering::{should_include, FilterList};
 use log::{error, info, trace};
 use opcua_client::prelude::*;
 use std::{
+    cmp::Reverse,
+    collections::HashMap,
     net::{SocketAddr, ToSocketAddrs},
     time::Duration,
 };
@@ -15,6 +19,52 @@ use std::{
 /// Used when testing TCP connection before calling FindServers on the endpoint
 const TCP_CONNECTION_TEST_TIMEOUT_SECS: u64 = 3;
 
+/// Filters applied to the Servers found at the DiscoveryURLs of an OPC UA Configuration
+#[derive(Clone, Debug, Default)]
+pub struct ServerFilters {
+    pub application_names: Option<FilterList>,
+    pub application_uris: Option<FilterList>,
+    pub product_uris: Option<FilterList>,
+    /// Servers are only discovered if they have an endpoint with at least this message security mode
+    pub minimum_security_mode: Option<MessageSecurityMode>,
+    /// Servers are only discovered if every one of these nodes exists in their address space
+    pub nodes: Vec<NodeCondition>,
+}
+
+/// Node that must exist in the address space of a Server for it to be discovered, optionally with a value of a
+/// specific data type and/or value. Its value is passed to brokers as the property `property_name`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeCondition {
+    pub location: NodeLocation,
+    pub expected_value: Option<String>,
+    pub data_type: Option<String>,
+    pub property_name: String,
+}
+
+/// OPC UA Server that has been discovered, along with the endpoints it reported from GetEndpoints
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiscoveredServer {
+    pub discovery_url: String,
+    pub application_uri: String,
+    pub product_uri: String,
+    pub application_type: ApplicationType,
+    /// Endpoints of the Server, ordered from the most to the least secure according to their security level
+    pub endpoints: Vec<DiscoveredEndpoint>,
+    /// Values of the nodes of the `nodes` filter, keyed by their property names
+    pub node_values: HashMap<String, String>,
+}
+
+/// Endpoint of a discovered OPC UA Server, which is passed to brokers so that they can connect to a secure endpoint
+/// without calling GetEndpoints again
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredEndpoint {
+    pub endpoint_url: String,
+    pub security_policy_uri: String,
+    pub security_mode: String,
+    pub security_level: u8,
+}
+
 /// `standard` is the only `OpcuaDiscoveryMethod` currently implemented, which takes in a set of DiscoveryURLs and discovers all the servers at those DiscoveryURLs.
 ///
 /// Every OPC UA server/application has a DiscoveryEndpoint that Clients can access without establishing a session.
@@ -24,38 +74,53 @@ const TCP_CONNECTION_TEST_TIMEOUT_SECS: u64 = 3;
 /// provides mechanisms for Clients to obtain this list" (OPC UA Specification 12). A LocalDiscoveryServer is an implementation
 /// of an OPC UA DiscoveryServer.
 /// `do_standard_discovery` creates an OPC UA Discovery Client and calls get_discovery_urls, passing in the DiscoveryURLs provided
-/// in the OPC UA Configuration.
+/// in the OPC UA Configuration, and then calls GetEndpoints on each discovered Server and reads the nodes of the `nodes`
+/// filter. If `security` is set, the Client calls FindServers and reads nodes over a secure channel.
 pub fn do_standard_discovery(
     discovery_urls: Vec<String>,
-    filter_list: Option<FilterList>,
-) -> Vec<String> {
+    filters: &ServerFilters,
+    security: Option<DiscoverySecurity>,
+) -> Vec<DiscoveredServer> {
     info!(
         "do_standard_discovery - for DiscoveryUrls {:?}",
         discovery_urls
     );
-    let mut discovery_handler_client = create_opcua_discovery_client();
+    let mut discovery_handler_client = create_opcua_discovery_client(security);
     let tcp_stream = TcpStreamImpl {};
-    get_discovery_urls(
+    let servers = get_discovery_urls(
         &mut discovery_handler_client,
         discovery_urls,
-        filter_list,
-        tcp_stream,
+        filters,
+        &tcp_stream,
+    );
+    let servers = get_server_endpoints(
+        &mut discovery_handler_client,
+        servers,
+        filters.minimum_security_mode,
+        &tcp_stream,
+    );
+    get_node_values(
+        &mut discovery_handler_client,
+        servers,
+        &filters.nodes,
+        &tcp_stream,
     )
 }
 
 /// This calls FindServers on each DiscoveryURL provided in order to
 /// (1) verify the DiscoveryURL
 /// (2) discover other servers registered with a Local Discovery Server in the case that the DiscoveryURL is for an LDS
-/// (3) determine whether the application at that URL should be included according to `ApplicationType` and the `application_names` filter
+/// (3) determine whether the application at that URL should be included according to `ApplicationType` and the
+/// `application_names`, `application_uris` and `product_uris` filters
 fn get_discovery_urls(
     discovery_handler_client: &mut impl OpcuaClient,
     lds_urls: Vec<String>,
-    filter_list: Option<FilterList>,
-    tcp_stream: impl TcpStream,
-) -> Vec<String> {
-    let mut discovery_urls: Vec<String> = Vec::new();
+    filters: &ServerFilters,
+    tcp_stream: &impl TcpStream,
+) -> Vec<DiscoveredServer> {
+    let mut discovery_urls: Vec<DiscoveredServer> = Vec::new();
     lds_urls.iter().for_each(|url| {
-        if let Err(e) = test_tcp_connection(url, &tcp_stream) {
+        if let Err(e) = test_tcp_connection(url, tcp_stream) {
             error!(
                 "get_discovery_urls - failed to make tcp connection with url {} with error {:?}",
                 url, e
@@ -68,15 +133,20 @@ fn get_discovery_urls(
                         url,
                         applications.len()
                     );
-                    let mut servers_discovery_urls: Vec<String> = applications
+                    let mut servers_discovery_urls: Vec<DiscoveredServer> = applications
                         .iter()
                         .filter_map(|application| {
-                            get_discovery_url_from_application_description(
-                                application,
-                                filter_list.as_ref(),
-                            )
+                            get_discovery_url_from_application_description(application, filters)
+                                .map(|discovery_url| DiscoveredServer {
+                                    discovery_url,
+                                    application_uri: application.application_uri.to_string(),
+                                    product_uri: application.product_uri.to_string(),
+                                    application_type: application.application_type,
+                                    endpoints: Vec::new(),
+                                    node_values: HashMap::new(),
+                                })
                         })
-                        .collect::<Vec<String>>();
+                        .collect::<Vec<DiscoveredServer>>();
                     discovery_urls.append(&mut servers_discovery_urls);
                 }
                 Err(err) => {
@@ -89,10 +159,181 @@ fn get_discovery_urls(
         }
     });
     // Remove duplicates in the case that a server was registered with more than one LDS
-    discovery_urls.dedup();
+    discovery_urls.dedup_by(|a, b| a.discovery_url == b.discovery_url);
     discovery_urls
 }
 
+/// This calls GetEndpoints on each discovered Server to learn the endpoints, along with their security policies and
+/// modes, that brokers can connect to. Servers whose endpoints cannot be read are still discovered, without endpoints,
+/// unless a minimum security mode is required, in which case only Servers with an endpoint of at least that mode are
+/// kept.
+fn get_server_endpoints(
+    discovery_handler_client: &mut impl OpcuaClient,
+    servers: Vec<DiscoveredServer>,
+    minimum_security_mode: Option<MessageSecurityMode>,
+    tcp_stream: &impl TcpStream,
+) -> Vec<DiscoveredServer> {
+    servers
+        .into_iter()
+        .filter_map(|mut server| {
+            let endpoints = match test_tcp_connection(&server.discovery_url, tcp_stream) {
+                Ok(_) => discovery_handler_client
+                    .get_server_endpoints(&server.discovery_url)
+                    .map_err(|e| anyhow::format_err!("{:?}", e)),
+                Err(e) => Err(e),
+            };
+            let endpoints = endpoints.unwrap_or_else(|e| {
+                trace!(
+                    "get_server_endpoints - cannot get endpoints of Server at {}. Error {:?}",
+                    server.discovery_url,
+                    e
+                );
+                Vec::new()
+            });
+            if let Some(minimum_security_mode) = minimum_security_mode {
+                if !endpoints.iter().any(|endpoint| {
+                    get_security_mode_rank(endpoint.security_mode)
+                        >= get_security_mode_rank(minimum_security_mode)
+                }) {
+                    trace!(
+                        "get_server_endpoints - Server at {} has no endpoint with security mode {:?} or higher. Ignoring it.",
+                        server.discovery_url,
+                        minimum_security_mode
+                    );
+                    return None;
+                }
+            }
+            server.endpoints = get_discovered_endpoints(&endpoints);
+            Some(server)
+        })
+        .collect()
+}
+
+/// This connects to each discovered Server to read the nodes of the `nodes` filter, keeping only the Servers on which
+/// every node exists with the expected data type and value. The values of the nodes are stored to be passed to
+/// brokers. No session is created if there are no nodes to read.
+fn get_node_values(
+    discovery_handler_client: &mut impl OpcuaClient,
+    servers: Vec<DiscoveredServer>,
+    nodes: &[NodeCondition],
+    tcp_stream: &impl TcpStream,
+) -> Vec<DiscoveredServer> {
+    if nodes.is_empty() {
+        return servers;
+    }
+    let locations: Vec<NodeLocation> = nodes.iter().map(|node| node.location.clone()).collect();
+    servers
+        .into_iter()
+        .filter_map(|mut server| {
+            let values = match test_tcp_connection(&server.discovery_url, tcp_stream) {
+                Ok(_) => discovery_handler_client
+                    .read_nodes(&server.discovery_url, &locations)
+                    .map_err(|e| anyhow::format_err!("{:?}", e)),
+                Err(e) => Err(e),
+            };
+            let values = match values {
+                Ok(values) if values.len() == nodes.len() => values,
+                Ok(_) => return None,
+                Err(e) => {
+                    trace!(
+                        "get_node_values - cannot read nodes of Server at {}. Error {:?}",
+                        server.discovery_url,
+                        e
+                    );
+                    return None;
+                }
+            };
+            for (node, value) in nodes.iter().zip(values) {
+                let (data_type, value) = match value {
+                    Some(value) => get_variant_data_type_and_value(&value),
+                    None => {
+                        trace!(
+                            "get_node_values - Server at {} does not have node {:?}. Ignoring it.",
+                            server.discovery_url,
+                            node.location
+                        );
+                        return None;
+                    }
+                };
+                if node
+                    .data_type
+                    .as_ref()
+                    .map_or(false, |expected| expected != &data_type)
+                    || node
+                        .expected_value
+                        .as_ref()
+                        .map_or(false, |expected| expected != &value)
+                {
+                    trace!(
+                        "get_node_values - node {:?} of Server at {} has {} value {}, which does not match. Ignoring it.",
+                        node.location,
+                        server.discovery_url,
+                        data_type,
+                        value
+                    );
+                    return None;
+                }
+                server.node_values.insert(node.property_name.clone(), value);
+            }
+            Some(server)
+        })
+        .collect()
+}
+
+/// Returns the name of the data type of a value along with the value as a string, which is how values are compared
+/// with the `nodes` filter and passed to brokers
+fn get_variant_data_type_and_value(variant: &Variant) -> (String, String) {
+    let (data_type, value) = match variant {
+        Variant::Boolean(value) => ("Boolean", value.to_string()),
+        Variant::SByte(value) => ("SByte", value.to_string()),
+        Variant::Byte(value) => ("Byte", value.to_string()),
+        Variant::Int16(value) => ("Int16", value.to_string()),
+        Variant::UInt16(value) => ("UInt16", value.to_string()),
+        Variant::Int32(value) => ("Int32", value.to_string()),
+        Variant::UInt32(value) => ("UInt32", value.to_string()),
+        Variant::Int64(value) => ("Int64", value.to_string()),
+        Variant::UInt64(value) => ("UInt64", value.to_string()),
+        Variant::Float(value) => ("Float", value.to_string()),
+        Variant::Double(value) => ("Double", value.to_string()),
+        Variant::String(value) => ("String", value.to_string()),
+        Variant::LocalizedText(value) => ("LocalizedText", value.text.to_string()),
+        other => {
+            let value = format!("{:?}", other);
+            return (
+                value.split('(').next().unwrap_or_default().to_string(),
+                value,
+            );
+        }
+    };
+    (data_type.to_string(), value)
+}
+
+/// Converts the endpoints returned by GetEndpoints into the endpoints passed to brokers, ordered from the most to the
+/// least secure
+fn get_discovered_endpoints(endpoints: &[EndpointDescription]) -> Vec<DiscoveredEndpoint> {
+    let mut discovered_endpoints: Vec<DiscoveredEndpoint> = endpoints
+        .iter()
+        .map(|endpoint| DiscoveredEndpoint {
+            endpoint_url: endpoint.endpoint_url.to_string(),
+            security_policy_uri: endpoint.security_policy_uri.to_string(),
+            security_mode: format!("{:?}", endpoint.security_mode),
+            security_level: endpoint.security_level,
+        })
+        .collect();
+    discovered_endpoints.sort_by_key(|endpoint| Reverse(endpoint.security_level));
+    discovered_endpoints
+}
+
+/// Orders message security modes from the least to the most secure
+fn get_security_mode_rank(security_mode: MessageSecurityMode) -> u8 {
+    match security_mode {
+        MessageSecurityMode::None => 1,
+        MessageSecurityMode::Sign => 2,
+        MessageSecurityMode::SignAndEncrypt => 3,
+        _ => 0,
+    }
+}
+
 /// The Rust OPC UA implementation of FindServers does not use a timeout when connecting with a Server over TCP
 /// So, an unsuccessful attempt can take over 2 minutes.
 /// Therefore, this tests the connection using a timeout before calling FindServers on the DiscoveryURL.
@@ -109,12 +350,12 @@ fn test_tcp_connection(url: &str, tcp_stream: &impl TcpStream) -> Result<(), any
 
 /// This selects a DiscoveryURL from an application's `ApplicationDescription` so long as the Application passes the following criteria
 /// (1) it is `ApplicationType::Server` (not a DiscoveryServer, Client, ClientServer)
-/// (2) it passes the FilterList criteria for `application_name`
+/// (2) it passes the FilterList criteria for `application_name`, `application_uri` and `product_uri`
 /// Note: OPC UA Applications can have more than one DiscoveryURL, often to support different transport protocols.
 /// This function preferences tcp discovery URLs, as tcp endpoints support both application and communication layer security.
 fn get_discovery_url_from_application_description(
     server: &ApplicationDescription,
-    filter_list: Option<&FilterList>,
+    filters: &ServerFilters,
 ) -> Option<String> {
     trace!(
         "get_discovery_url_from_application - found server : {}",
@@ -127,12 +368,34 @@ fn get_discovery_url_from_application_description(
             server.application_type
         );
         None
-    } else if !should_include(filter_list, &server.application_name.text.to_string()) {
+    } else if !should_include(
+        filters.application_names.as_ref(),
+        &server.application_name.text.to_string(),
+    ) {
         trace!(
             "get_discovery_url_from_application - Application {} has been filtered out by application name",
             server.application_name.text.to_string()
         );
         None
+    } else if !should_include(
+        filters.application_uris.as_ref(),
+        &server.application_uri.to_string(),
+    ) {
+        trace!(
+            "get_discovery_url_from_application - Application {} has been filtered out by application URI",
+            server.application_uri
+        );
+        None
+    } else if !should_include(
+        filters.product_uris.as_ref(),
+        &server.product_uri.to_string(),
+    ) {
+        trace!(
+            "get_discovery_url_from_application - Application {} has been filtered out by product URI {}",
+            server.application_uri,
+            server.product_uri
+        );
+        None
     } else if let Some(ref server_discovery_urls) = server.discovery_urls {
         // TODO: could two different DiscoveryUrls be registered as localhost:<port> on different lds's?
         // Should this ensure that DiscoveryUrls are IP addresses instead of DNS?
@@ -186,6 +449,7 @@ mod tests {
         opcua_client_wrapper::MockOpcuaClient, tcp_stream_wrapper::MockTcpStream,
     };
     use super::*;
+    use akri_discovery_utils::filtering::FilterType;
     use mockall::Sequence;
 
     pub fn create_application_description(
@@ -276,11 +540,11 @@ mod tests {
         let discovery_urls = get_discovery_urls(
             &mut mock_client,
             vec![lds_url.to_string(), lds_url2.to_string()],
-            None,
-            mock_tcp_stream,
+            &ServerFilters::default(),
+            &mock_tcp_stream,
         );
         assert_eq!(discovery_urls.len(), 2);
-        assert_eq!(&discovery_urls[0], discovery_url);
+        assert_eq!(discovery_urls[0].discovery_url, discovery_url);
     }
 
     #[test]
@@ -315,11 +579,11 @@ mod tests {
         let discovery_urls = get_discovery_urls(
             &mut mock_client,
             vec![discovery_url.to_string(), discovery_url2.to_string()],
-            None,
-            mock_tcp_stream,
+            &ServerFilters::default(),
+            &mock_tcp_stream,
         );
         assert_eq!(discovery_urls.len(), 1);
-        assert_eq!(&discovery_urls[0], discovery_url2);
+        assert_eq!(discovery_urls[0].discovery_url, discovery_url2);
     }
 
     #[test]
@@ -361,8 +625,8 @@ mod tests {
         let discovery_urls = get_discovery_urls(
             &mut mock_client,
             vec![lds_url.to_string(), lds_url2.to_string()],
-            None,
-            mock_tcp_stream,
+            &ServerFilters::default(),
+            &mock_tcp_stream,
         );
         assert_eq!(discovery_urls.len(), 1);
     }
@@ -375,8 +639,8 @@ mod tests {
         assert!(get_discovery_urls(
             &mut mock_client,
             vec!["tcp://127.0.0.1:4855/".to_string()],
-            None,
-            mock_tcp_stream
+            &ServerFilters::default(),
+            &mock_tcp_stream
         )
         .is_empty())
     }
@@ -412,9 +676,286 @@ mod tests {
         let discovery_urls = get_discovery_urls(
             &mut mock_client,
             vec![discovery_url.to_string()],
-            None,
-            mock_tcp_stream,
+            &ServerFilters::default(),
+            &mock_tcp_stream,
         );
         assert!(discovery_urls.is_empty());
     }
+
+    #[test]
+    fn test_get_discovery_urls_filters() {
+        let lds_url = "opc.tcp://127.0.0.1:4840/";
+        let mut mock_client = MockOpcuaClient::new();
+        let mut mock_tcp_stream = MockTcpStream::new();
+        mock_tcp_stream
+            .expect_connect_timeout()
+            .returning(|_, _| Ok(()));
+        let mut server = create_application_description(
+            "urn:Mock OPC UA Server",
+            "Mock OPC UA Server",
+            ApplicationType::Server,
+            "opc.tcp://127.0.0.1:4855/",
+        );
+        server.product_uri = UAString::from("urn:Mock Product");
+        let mut server2 = create_application_description(
+            "urn:Mock OPC UA Server2",
+            "Mock OPC UA Server2",
+            ApplicationType::Server,
+            "opc.tcp://127.0.0.1:4866/",
+        );
+        server2.product_uri = UAString::from("urn:Other Product");
+        mock_client
+            .expect_find_servers()
+            .withf(move |url: &str| url == lds_url)
+            .returning(move |_| Ok(vec![server.clone(), server2.clone()]));
+
+        let filters = ServerFilters {
+            application_uris: Some(FilterList {
+                items: vec!["urn:Mock OPC UA Server2".to_string()],
+                action: FilterType::Exclude,
+            }),
+            ..Default::default()
+        };
+        let discovery_urls = get_discovery_urls(
+            &mut mock_client,
+            vec![lds_url.to_string()],
+            &filters,
+            &mock_tcp_stream,
+        );
+        assert_eq!(discovery_urls.len(), 1);
+        assert_eq!(discovery_urls[0].application_uri, "urn:Mock OPC UA Server");
+        assert_eq!(discovery_urls[0].product_uri, "urn:Mock Product");
+        assert_eq!(discovery_urls[0].application_type, ApplicationType::Server);
+
+        let filters = ServerFilters {
+            product_uris: Some(FilterList {
+                items: vec!["urn:Other Product".to_string()],
+                action: FilterType::Include,
+            }),
+            ..Default::default()
+        };
+        let discovery_urls = get_discovery_urls(
+            &mut mock_client,
+            vec![lds_url.to_string()],
+            &filters,
+            &mock_tcp_stream,
+        );
+        assert_eq!(discovery_urls.len(), 1);
+        assert_eq!(discovery_urls[0].discovery_url, "opc.tcp://127.0.0.1:4866/");
+    }
+
+    fn create_endpoint_description(
+        endpoint_url: &str,
+        security_policy: SecurityPolicy,
+        security_mode: MessageSecurityMode,
+        security_level: u8,
+    ) -> EndpointDescription {
+        EndpointDescription {
+            endpoint_url: UAString::from(endpoint_url),
+            server: create_application_description(
+                "urn:Mock OPC UA Server",
+                "Mock OPC UA Server",
+                ApplicationType::Server,
+                endpoint_url,
+            ),
+            server_certificate: ByteString::null(),
+            security_mode,
+            security_policy_uri: UAString::from(security_policy.to_uri()),
+            user_identity_tokens: None,
+            transport_profile_uri: UAString::from(""),
+            security_level,
+        }
+    }
+
+    fn create_discovered_server(discovery_url: &str) -> DiscoveredServer {
+        DiscoveredServer {
+            discovery_url: discovery_url.to_string(),
+            application_uri: "urn:Mock OPC UA Server".to_string(),
+            product_uri: String::new(),
+            application_type: ApplicationType::Server,
+            endpoints: Vec::new(),
+            node_values: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_get_server_endpoints() {
+        let secure_server_url = "opc.tcp://127.0.0.1:4855/";
+        let insecure_server_url = "opc.tcp://127.0.0.1:4866/";
+        let unreachable_server_url = "opc.tcp://127.0.0.1:4877/";
+        let mut mock_client = MockOpcuaClient::new();
+        let mut mock_tcp_stream = MockTcpStream::new();
+        let unreachable_socket_addr = get_socket_addr(unreachable_server_url).unwrap();
+        mock_tcp_stream
+            .expect_connect_timeout()
+            .returning(move |addr, _| {
+                if addr == &unreachable_socket_addr {
+                    Err(std::io::Error::from(std::io::ErrorKind::TimedOut))
+                } else {
+                    Ok(())
+                }
+            });
+        mock_client
+            .expect_get_server_endpoints()
+            .withf(move |url: &str| url == secure_server_url)
+            .returning(move |_| {
+                Ok(vec![
+                    create_endpoint_description(
+                        secure_server_url,
+                        SecurityPolicy::None,
+                        MessageSecurityMode::None,
+                        0,
+                    ),
+                    create_endpoint_description(
+                        secure_server_url,
+                        SecurityPolicy::Basic256Sha256,
+                        MessageSecurityMode::SignAndEncrypt,
+                        10,
+                    ),
+                ])
+            });
+        mock_client
+            .expect_get_server_endpoints()
+            .withf(move |url: &str| url == insecure_server_url)
+            .returning(move |_| {
+                Ok(vec![create_endpoint_description(
+                    insecure_server_url,
+                    SecurityPolicy::None,
+                    MessageSecurityMode::None,
+                    0,
+                )])
+            });
+        let servers = vec![
+            create_discovered_server(secure_server_url),
+            create_discovered_server(insecure_server_url),
+            create_discovered_server(unreachable_server_url),
+        ];
+
+        // Without a minimum security mode, every Server is kept, even if its endpoints cannot be read
+        let discovered_servers =
+            get_server_endpoints(&mut mock_client, servers.clone(), None, &mock_tcp_stream);
+        assert_eq!(discovered_servers.len(), 3);
+        assert_eq!(
+            discovered_servers[0].endpoints,
+            vec![
+                DiscoveredEndpoint {
+                    endpoint_url: secure_server_url.to_string(),
+                    security_policy_uri: SecurityPolicy::Basic256Sha256.to_uri().to_string(),
+                    security_mode: "SignAndEncrypt".to_string(),
+                    security_level: 10,
+                },
+                DiscoveredEndpoint {
+                    endpoint_url: secure_server_url.to_string(),
+                    security_policy_uri: SecurityPolicy::None.to_uri().to_string(),
+                    security_mode: "None".to_string(),
+                    security_level: 0,
+                }
+            ]
+        );
+        assert!(discovered_servers[2].endpoints.is_empty());
+
+        let discovered_servers = get_server_endpoints(
+            &mut mock_client,
+            servers,
+            Some(MessageSecurityMode::Sign),
+            &mock_tcp_stream,
+        );
+        assert_eq!(discovered_servers.len(), 1);
+        assert_eq!(discovered_servers[0].discovery_url, secure_server_url);
+    }
+
+    #[test]
+    fn test_get_node_values() {
+        let machine_url = "opc.tcp://127.0.0.1:4855/";
+        let other_machine_url = "opc.tcp://127.0.0.1:4866/";
+        let plain_server_url = "opc.tcp://127.0.0.1:4877/";
+        let mut mock_client = MockOpcuaClient::new();
+        let mut mock_tcp_stream = MockTcpStream::new();
+        mock_tcp_stream
+            .expect_connect_timeout()
+            .returning(|_, _| Ok(()));
+        let model_location = NodeLocation::BrowsePath(vec![
+            QualifiedName::new(2, "Machine"),
+            QualifiedName::new(2, "Model"),
+        ]);
+        let serial_number_location = NodeLocation::NodeId(NodeId::new(2, "SerialNumber"));
+        let expected_locations = vec![model_location.clone(), serial_number_location.clone()];
+        mock_client
+            .expect_read_nodes()
+            .withf(move |url: &str, nodes: &[NodeLocation]| {
+                url == machine_url && nodes == expected_locations.as_slice()
+            })
+            .returning(|_, _| {
+                Ok(vec![
+                    Some(Variant::from("XYZ-100")),
+                    Some(Variant::from(1234_u32)),
+                ])
+            });
+        mock_client
+            .expect_read_nodes()
+            .withf(move |url: &str, _: &[NodeLocation]| url == other_machine_url)
+            .returning(|_, _| {
+                Ok(vec![
+                    Some(Variant::from("XYZ-200")),
+                    Some(Variant::from(5678_u32)),
+                ])
+            });
+        mock_client
+            .expect_read_nodes()
+            .withf(move |url: &str, _: &[NodeLocation]| url == plain_server_url)
+            .returning(|_, _| Ok(vec![None, None]));
+        let servers = vec![
+            create_discovered_server(machine_url),
+            create_discovered_server(other_machine_url),
+            create_discovered_server(plain_server_url),
+        ];
+        let nodes = vec![
+            NodeCondition {
+                location: model_location,
+                expected_value: Some("XYZ-100".to_string()),
+                data_type: None,
+                property_name: "MACHINE_MODEL".to_string(),
+            },
+            NodeCondition {
+                location: serial_number_location,
+                expected_value: None,
+                data_type: Some("UInt32".to_string()),
+                property_name: "OPCUA_NODE_1".to_string(),
+            },
+        ];
+
+        let discovered_servers =
+            get_node_values(&mut mock_client, servers.clone(), &nodes, &mock_tcp_stream);
+        assert_eq!(discovered_servers.len(), 1);
+        assert_eq!(discovered_servers[0].discovery_url, machine_url);
+        assert_eq!(
+            discovered_servers[0].node_values["MACHINE_MODEL"],
+            "XYZ-100"
+        );
+        assert_eq!(discovered_servers[0].node_values["OPCUA_NODE_1"], "1234");
+
+        // No nodes are read if there is no node filter
+        let mut mock_client = MockOpcuaClient::new();
+        mock_client.expect_read_nodes().times(0);
+        assert_eq!(
+            get_node_values(&mut mock_client, servers.clone(), &[], &mock_tcp_stream),
+            servers
+        );
+    }
+
+    #[test]
+    fn test_get_variant_data_type_and_value() {
+        assert_eq!(
+            get_variant_data_type_and_value(&Variant::from(true)),
+            ("Boolean".to_string(), "true".to_string())
+        );
+        assert_eq!(
+            get_variant_data_type_and_value(&Variant::from(-1.5_f64)),
+            ("Double".to_string(), "-1.5".to_string())
+        );
+        assert_eq!(
+            get_variant_data_type_and_value(&Variant::from("XYZ-100")),
+            ("String".to_string(), "XYZ-100".to_string())
+        );
+    }
 }
@@ -1,6 +1,7 @@
 #[macro_use]
 extern crate serde_derive;
 
+mod client_pki;
 pub mod discovery_handler;
 mod discovery_impl;
 mod wrappers;
@@ -8,6 +9,15 @@ mod wrappers;
 /// Name of the environment variable that will be mounted into the OPC UA broker pods.
 /// Holds the DiscoveryURL for the OPC UA Server the broker is to connect to.
 pub const OPCUA_DISCOVERY_URL_LABEL: &str = "OPCUA_DISCOVERY_URL";
+/// Name of the environment variable that holds the ApplicationUri of the OPC UA Server
+pub const OPCUA_APPLICATION_URI_LABEL: &str = "OPCUA_APPLICATION_URI";
+/// Name of the environment variable that holds the ProductUri of the OPC UA Server
+pub const OPCUA_PRODUCT_URI_LABEL: &str = "OPCUA_PRODUCT_URI";
+/// Name of the environment variable that holds the ApplicationType of the OPC UA Server
+pub const OPCUA_APPLICATION_TYPE_LABEL: &str = "OPCUA_APPLICATION_TYPE";
+/// Name of the environment variable that holds a JSON array of the endpoints of the OPC UA Server, each with its
+/// `endpointUrl`, `securityPolicyUri`, `securityMode` and `securityLevel`, ordered from the most to the least secure
+pub const OPCUA_ENDPOINTS_LABEL: &str = "OPCUA_ENDPOINTS";
 /// Name that OPC UA discovery handlers use when registering with the Agent
 pub const DISCOVERY_HANDLER_NAME: &str = "opcua";
 /// Defines whether this discovery handler discovers local devices on nodes rather than ones visible to multiple nodes
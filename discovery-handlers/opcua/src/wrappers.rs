@@ -1,8 +1,13 @@
 /// Wrapper to enable mocking of OPC UA Client
 pub mod opcua_client_wrapper {
+    use log::{error, trace};
     #[cfg(test)]
     use mockall::{automock, predicate::*};
     use opcua_client::prelude::*;
+    use std::{
+        path::PathBuf,
+        sync::{Arc, RwLock},
+    };
 
     #[cfg_attr(test, automock)]
     pub trait OpcuaClient {
@@ -10,10 +15,45 @@ pub mod opcua_client_wrapper {
             &mut self,
             discovery_endpoint_url: &str,
         ) -> Result<Vec<ApplicationDescription>, StatusCode>;
+
+        fn get_server_endpoints(
+            &mut self,
+            discovery_endpoint_url: &str,
+        ) -> Result<Vec<EndpointDescription>, StatusCode>;
+
+        /// Returns the value of each node, or `None` if the node does not exist or its value cannot be read
+        fn read_nodes(
+            &mut self,
+            discovery_endpoint_url: &str,
+            nodes: &[NodeLocation],
+        ) -> Result<Vec<Option<Variant>>, StatusCode>;
+    }
+
+    /// Location of a node in the address space of a Server
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum NodeLocation {
+        NodeId(NodeId),
+        /// Browse names of the nodes on the path from the Objects folder to the node, following hierarchical
+        /// references
+        BrowsePath(Vec<QualifiedName>),
+    }
+
+    /// Security with which FindServers is called on DiscoveryEndpoints that refuse unsecured requests
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct DiscoverySecurity {
+        pub security_policy: SecurityPolicy,
+        pub message_security_mode: MessageSecurityMode,
+        /// Directory that holds the Client's certificate and private key
+        pub pki_dir: PathBuf,
+        /// DER encoded certificates of the Servers that are trusted
+        pub trusted_server_certificates: Vec<Vec<u8>>,
+        /// Whether every Server's certificate is trusted, in which case `trusted_server_certificates` is ignored
+        pub trust_server_certificates: bool,
     }
 
     pub struct OpcuaClientImpl {
         inner_opcua_client: Client,
+        security: Option<DiscoverySecurity>,
     }
 
     impl OpcuaClientImpl {
@@ -22,17 +62,145 @@ pub mod opcua_client_wrapper {
             application_uri: &str,
             create_sample_keypair: bool,
             session_retry_limit: i32,
+            security: Option<DiscoverySecurity>,
         ) -> Self {
+            let mut client_builder = ClientBuilder::new()
+                .application_name(application_name)
+                .application_uri(application_uri)
+                .create_sample_keypair(create_sample_keypair)
+                .session_retry_limit(session_retry_limit);
+            if let Some(security) = &security {
+                // Server certificates are checked against the trust list before a session is created, so the
+                // Client's own certificate store does not need to reject them
+                client_builder = client_builder
+                    .pki_dir(security.pki_dir.clone())
+                    .trust_server_certs(true);
+            }
             OpcuaClientImpl {
-                inner_opcua_client: ClientBuilder::new()
-                    .application_name(application_name)
-                    .application_uri(application_uri)
-                    .create_sample_keypair(create_sample_keypair)
-                    .session_retry_limit(session_retry_limit)
-                    .client()
-                    .unwrap(),
+                inner_opcua_client: client_builder.client().unwrap(),
+                security,
             }
         }
+
+        /// Creates a session with a Server using the configured security, or security policy None if there is none.
+        /// GetEndpoints is first called without security to learn the endpoints and certificate of the Server, which
+        /// must be trusted before a secure channel is opened.
+        fn create_session(
+            &mut self,
+            discovery_endpoint_url: &str,
+        ) -> Result<Arc<RwLock<Session>>, StatusCode> {
+            let endpoints = self
+                .inner_opcua_client
+                .get_server_endpoints_from_url(discovery_endpoint_url)?;
+            let mut endpoint = match &self.security {
+                Some(security) => {
+                    let endpoint = select_endpoint(
+                        &endpoints,
+                        security.security_policy,
+                        security.message_security_mode,
+                    )?;
+                    if !is_trusted(&endpoint, security) {
+                        error!(
+                            "create_session - certificate of Server {} at {} is not trusted",
+                            endpoint.server.application_uri, discovery_endpoint_url
+                        );
+                        return Err(StatusCode::BadCertificateUntrusted);
+                    }
+                    endpoint
+                }
+                None => {
+                    select_endpoint(&endpoints, SecurityPolicy::None, MessageSecurityMode::None)?
+                }
+            };
+            // Servers often advertise endpoints at host names that cannot be resolved from the cluster, so the
+            // channel is opened to the DiscoveryURL that was used to reach the Server
+            endpoint.endpoint_url = UAString::from(discovery_endpoint_url);
+            self.inner_opcua_client.new_session_from_info(endpoint)
+        }
+
+        /// Calls FindServers over a secure channel
+        fn find_servers_securely(
+            &mut self,
+            discovery_endpoint_url: &str,
+        ) -> Result<Vec<ApplicationDescription>, StatusCode> {
+            let session = self.create_session(discovery_endpoint_url)?;
+            let mut session = session.write().unwrap();
+            session.connect()?;
+            let result = session.find_servers(discovery_endpoint_url);
+            session.disconnect();
+            result
+        }
+    }
+
+    /// Resolves the browse paths of nodes to NodeIds and reads the values of the nodes
+    fn read_nodes_in_session(
+        session: &mut Session,
+        nodes: &[NodeLocation],
+    ) -> Result<Vec<Option<Variant>>, StatusCode> {
+        let mut node_ids = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            let node_id = match node {
+                NodeLocation::NodeId(node_id) => Some(node_id.clone()),
+                NodeLocation::BrowsePath(browse_names) => session
+                    .translate_browse_paths_to_node_ids(&[get_browse_path(browse_names)])?
+                    .into_iter()
+                    .next()
+                    .filter(|result| result.status_code.is_good())
+                    .and_then(|result| result.targets)
+                    .and_then(|targets| targets.into_iter().next())
+                    .map(|target| target.target_id.node_id),
+            };
+            node_ids.push(node_id);
+        }
+        let nodes_to_read: Vec<ReadValueId> = node_ids
+            .iter()
+            .flatten()
+            .map(|node_id| ReadValueId {
+                node_id: node_id.clone(),
+                attribute_id: AttributeId::Value as u32,
+                index_range: UAString::null(),
+                data_encoding: QualifiedName::null(),
+            })
+            .collect();
+        let mut data_values = if nodes_to_read.is_empty() {
+            Vec::new()
+        } else {
+            session.read(&nodes_to_read)?
+        }
+        .into_iter();
+        Ok(node_ids
+            .iter()
+            .map(|node_id| {
+                node_id.as_ref()?;
+                let data_value = data_values.next()?;
+                if data_value.status.map_or(true, |status| status.is_good()) {
+                    data_value.value
+                } else {
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Returns the browse path from the Objects folder through nodes with the browse names, following hierarchical
+    /// references
+    fn get_browse_path(browse_names: &[QualifiedName]) -> BrowsePath {
+        BrowsePath {
+            starting_node: ObjectId::ObjectsFolder.into(),
+            relative_path: RelativePath {
+                elements: Some(
+                    browse_names
+                        .iter()
+                        .map(|browse_name| RelativePathElement {
+                            reference_type_id: ReferenceTypeId::HierarchicalReferences.into(),
+                            is_inverse: false,
+                            include_subtypes: true,
+                            target_name: browse_name.clone(),
+                        })
+                        .collect(),
+                ),
+            },
+        }
     }
 
     impl OpcuaClient for OpcuaClientImpl {
@@ -40,12 +208,78 @@ pub mod opcua_client_wrapper {
             &mut self,
             discovery_endpoint_url: &str,
         ) -> Result<Vec<ApplicationDescription>, StatusCode> {
-            self.inner_opcua_client.find_servers(discovery_endpoint_url)
+            if self.security.is_some() {
+                self.find_servers_securely(discovery_endpoint_url)
+            } else {
+                self.inner_opcua_client.find_servers(discovery_endpoint_url)
+            }
+        }
+
+        fn get_server_endpoints(
+            &mut self,
+            discovery_endpoint_url: &str,
+        ) -> Result<Vec<EndpointDescription>, StatusCode> {
+            // GetEndpoints does not require security, so it is always called without it
+            self.inner_opcua_client
+                .get_server_endpoints_from_url(discovery_endpoint_url)
+        }
+
+        fn read_nodes(
+            &mut self,
+            discovery_endpoint_url: &str,
+            nodes: &[NodeLocation],
+        ) -> Result<Vec<Option<Variant>>, StatusCode> {
+            let session = self.create_session(discovery_endpoint_url)?;
+            let mut session = session.write().unwrap();
+            session.connect_and_activate()?;
+            let result = read_nodes_in_session(&mut session, nodes);
+            session.disconnect();
+            result
         }
     }
+
+    /// Returns the endpoint of a Server that uses the security policy and message security mode
+    fn select_endpoint(
+        endpoints: &[EndpointDescription],
+        security_policy: SecurityPolicy,
+        message_security_mode: MessageSecurityMode,
+    ) -> Result<EndpointDescription, StatusCode> {
+        let security_policy_uri = security_policy.to_uri();
+        endpoints
+            .iter()
+            .find(|endpoint| {
+                endpoint.security_policy_uri.as_ref() == security_policy_uri
+                    && endpoint.security_mode == message_security_mode
+            })
+            .cloned()
+            .ok_or_else(|| {
+                trace!(
+                    "select_endpoint - no endpoint uses security policy {} with mode {:?}",
+                    security_policy_uri,
+                    message_security_mode
+                );
+                StatusCode::BadSecurityPolicyRejected
+            })
+    }
+
+    /// Checks whether the certificate of an endpoint's Server is in the trust list
+    fn is_trusted(endpoint: &EndpointDescription, security: &DiscoverySecurity) -> bool {
+        if security.trust_server_certificates {
+            return true;
+        }
+        match &endpoint.server_certificate.value {
+            Some(certificate) => security
+                .trusted_server_certificates
+                .iter()
+                .any(|trusted_certificate| trusted_certificate == certificate),
+            None => false,
+        }
+    }
+
     /// Returns an OPC UA Client that will only be used to connect to OPC UA Server and Local Discovery Servers' DiscoveryEndpoints
-    pub fn create_opcua_discovery_client() -> impl OpcuaClient {
-        // No security is needed to connect to these DisoveryEndpoints; therefore, creating a keypair for the Client is unneccessary.
+    pub fn create_opcua_discovery_client(security: Option<DiscoverySecurity>) -> impl OpcuaClient {
+        // The Client's keypair is read from the PKI directory when security is used and otherwise unneccessary, so
+        // a sample keypair is never created.
         let create_sample_keypair = false;
         // Do not try to create a session again
         let session_retry_limit = 0;
@@ -54,8 +288,122 @@ pub mod opcua_client_wrapper {
             "urn:DiscoveryHandlerClient",
             create_sample_keypair,
             session_retry_limit,
+            security,
         )
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn security(trusted_server_certificates: Vec<Vec<u8>>) -> DiscoverySecurity {
+            DiscoverySecurity {
+                security_policy: SecurityPolicy::Basic256Sha256,
+                message_security_mode: MessageSecurityMode::SignAndEncrypt,
+                pki_dir: PathBuf::from("/tmp/pki"),
+                trusted_server_certificates,
+                trust_server_certificates: false,
+            }
+        }
+
+        fn endpoint(
+            security_policy: SecurityPolicy,
+            security_mode: MessageSecurityMode,
+            server_certificate: &[u8],
+        ) -> EndpointDescription {
+            EndpointDescription {
+                endpoint_url: UAString::from("opc.tcp://server:4840/"),
+                server: ApplicationDescription {
+                    application_uri: UAString::from("urn:server"),
+                    product_uri: UAString::from(""),
+                    application_name: LocalizedText::new("", "Server"),
+                    application_type: ApplicationType::Server,
+                    gateway_server_uri: UAString::from(""),
+                    discovery_profile_uri: UAString::from(""),
+                    discovery_urls: None,
+                },
+                server_certificate: ByteString::from(server_certificate),
+                security_mode,
+                security_policy_uri: UAString::from(security_policy.to_uri()),
+                user_identity_tokens: None,
+                transport_profile_uri: UAString::from(""),
+                security_level: 0,
+            }
+        }
+
+        #[test]
+        fn test_select_endpoint() {
+            let endpoints = vec![
+                endpoint(SecurityPolicy::None, MessageSecurityMode::None, b""),
+                endpoint(
+                    SecurityPolicy::Basic256Sha256,
+                    MessageSecurityMode::Sign,
+                    b"server",
+                ),
+                endpoint(
+                    SecurityPolicy::Basic256Sha256,
+                    MessageSecurityMode::SignAndEncrypt,
+                    b"server",
+                ),
+            ];
+            let selected = select_endpoint(
+                &endpoints,
+                SecurityPolicy::Basic256Sha256,
+                MessageSecurityMode::SignAndEncrypt,
+            )
+            .unwrap();
+            assert_eq!(selected.security_mode, MessageSecurityMode::SignAndEncrypt);
+            let selected =
+                select_endpoint(&endpoints, SecurityPolicy::None, MessageSecurityMode::None)
+                    .unwrap();
+            assert_eq!(selected.security_mode, MessageSecurityMode::None);
+            assert_eq!(
+                select_endpoint(
+                    &endpoints,
+                    SecurityPolicy::Basic256,
+                    MessageSecurityMode::SignAndEncrypt
+                )
+                .unwrap_err(),
+                StatusCode::BadSecurityPolicyRejected
+            );
+        }
+
+        #[test]
+        fn test_get_browse_path() {
+            let browse_path = get_browse_path(&[
+                QualifiedName::new(2, "Machine"),
+                QualifiedName::new(2, "Model"),
+            ]);
+            assert_eq!(
+                browse_path.starting_node,
+                NodeId::from(ObjectId::ObjectsFolder)
+            );
+            let elements = browse_path.relative_path.elements.unwrap();
+            assert_eq!(elements.len(), 2);
+            assert_eq!(elements[1].target_name, QualifiedName::new(2, "Model"));
+            assert!(elements[1].include_subtypes);
+        }
+
+        #[test]
+        fn test_is_trusted() {
+            let server_endpoint = endpoint(
+                SecurityPolicy::Basic256Sha256,
+                MessageSecurityMode::SignAndEncrypt,
+                b"server",
+            );
+            assert!(is_trusted(
+                &server_endpoint,
+                &security(vec![b"other".to_vec(), b"server".to_vec()])
+            ));
+            assert!(!is_trusted(
+                &server_endpoint,
+                &security(vec![b"other".to_vec()])
+            ));
+            let mut trust_all = security(Vec::new());
+            trust_all.trust_server_certificates = true;
+            assert!(is_trusted(&server_endpoint, &trust_all));
+        }
+    }
 }
 pub mod tcp_stream_wrapper {
     #[cfg(test)]
@@ -1,18 +1,21 @@
-use super::{discovery_impl::do_parse_and_find, wrappers::udev_enumerator};
+use super::{
+    discovery_impl::{do_parse_and_find, parse_udev_rule},
+    wrappers::udev_enumerator,
+};
 use akri_discovery_utils::discovery::{
-    discovery_handler::{deserialize_discovery_details, DISCOVERED_DEVICES_CHANNEL_CAPACITY},
-    v0::{
-        discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, DiscoverResponse,
-        Mount,
+    discovery_handler::deserialize_discovery_details,
+    polling::{
+        spawn_polling_discovery, spawn_polling_discovery_v1, PollingDiscoverer, PollingSettings,
     },
-    DiscoverStream,
+    v0::{discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, Mount},
+    v1, DiscoverStream, V1DiscoverStream,
 };
 use async_trait::async_trait;
 use log::{error, info, trace};
+use schemars::JsonSchema;
 use std::collections::HashSet;
 use std::time::Duration;
 use tokio::sync::mpsc;
-use tokio::time::delay_for;
 use tonic::{Response, Status};
 
 // TODO: make this configurable
@@ -20,12 +23,26 @@ pub const DISCOVERY_INTERVAL_SECS: u64 = 10;
 
 /// This defines the udev data stored in the Configuration
 /// CRD DiscoveryDetails
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
 pub struct UdevDiscoveryDetails {
     pub udev_rules: Vec<String>,
 }
 
+impl UdevDiscoveryDetails {
+    /// Checks that every udev rule can be parsed and only uses supported fields and match operations.
+    /// Used by the Configuration webhook; `discover` logs rules that it cannot use instead of rejecting them.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        for (index, rule) in self.udev_rules.iter().enumerate() {
+            parse_udev_rule(rule).map_err(|e| {
+                anyhow::format_err!("udevRules[{}] \"{}\" is not valid: {}", index, rule, e)
+            })?;
+        }
+        Ok(())
+    }
+}
+
 /// `DiscoveryHandlerImpl` discovers udev instances by parsing the udev rules in `discovery_handler_config.udev_rules`.
 pub struct DiscoveryHandlerImpl {
     register_sender: Option<mpsc::Sender<()>>,
@@ -35,6 +52,16 @@ impl DiscoveryHandlerImpl {
     pub fn new(register_sender: Option<mpsc::Sender<()>>) -> Self {
         DiscoveryHandlerImpl { register_sender }
     }
+
+    /// Returns the discoverer that scans for the devices described by the discovery details of a Discover request
+    fn discoverer(discovery_details: &str) -> Result<UdevDiscoverer, Status> {
+        let discovery_handler_config: UdevDiscoveryDetails =
+            deserialize_discovery_details(discovery_details)
+                .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
+        Ok(UdevDiscoverer {
+            udev_rules: discovery_handler_config.udev_rules,
+        })
+    }
 }
 
 #[async_trait]
@@ -45,83 +72,77 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
         request: tonic::Request<DiscoverRequest>,
     ) -> Result<Response<Self::DiscoverStream>, Status> {
         info!("discover - called for udev protocol");
-        let register_sender = self.register_sender.clone();
-        let discover_request = request.get_ref();
-        let (mut discovered_devices_sender, discovered_devices_receiver) =
-            mpsc::channel(DISCOVERED_DEVICES_CHANNEL_CAPACITY);
-        let discovery_handler_config: UdevDiscoveryDetails =
-            deserialize_discovery_details(&discover_request.discovery_details)
-                .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
-        let mut previously_discovered_devices: Vec<Device> = Vec::new();
-        tokio::spawn(async move {
-            let udev_rules = discovery_handler_config.udev_rules.clone();
-            loop {
-                trace!("discover - for udev rules {:?}", udev_rules);
-                let mut devpaths: HashSet<String> = HashSet::new();
-                udev_rules.iter().for_each(|rule| {
-                    let enumerator = udev_enumerator::create_enumerator();
-                    let paths = do_parse_and_find(enumerator, &rule).unwrap();
-                    paths.into_iter().for_each(|path| {
-                        devpaths.insert(path);
-                    });
-                });
-                trace!(
-                    "discover - mapping and returning devices at devpaths {:?}",
-                    devpaths
-                );
-                let discovered_devices = devpaths
-                    .into_iter()
-                    .map(|path| {
-                        let mut properties = std::collections::HashMap::new();
-                        properties.insert(super::UDEV_DEVNODE_LABEL_ID.to_string(), path.clone());
-                        let mount = Mount {
-                            container_path: path.clone(),
-                            host_path: path.clone(),
-                            read_only: true,
-                        };
-                        // TODO: use device spec
-                        Device {
-                            id: path,
-                            properties,
-                            mounts: vec![mount],
-                            device_specs: Vec::default(),
-                        }
-                    })
-                    .collect::<Vec<Device>>();
-                let mut changed_device_list = false;
-                let mut matching_device_count = 0;
-                discovered_devices.iter().for_each(|device| {
-                    if !previously_discovered_devices.contains(device) {
-                        changed_device_list = true;
-                    } else {
-                        matching_device_count += 1;
-                    }
-                });
-                if changed_device_list
-                    || matching_device_count != previously_discovered_devices.len()
-                {
-                    info!("discover - sending updated device list");
-                    previously_discovered_devices = discovered_devices.clone();
-                    if let Err(e) = discovered_devices_sender
-                        .send(Ok(DiscoverResponse {
-                            devices: discovered_devices,
-                        }))
-                        .await
-                    {
-                        error!(
-                            "discover - for udev failed to send discovery response with error {}",
-                            e
-                        );
-                        if let Some(mut sender) = register_sender {
-                            sender.send(()).await.unwrap();
-                        }
-                        break;
-                    }
-                }
-                delay_for(Duration::from_secs(DISCOVERY_INTERVAL_SECS)).await;
+        Ok(Response::new(spawn_polling_discovery(
+            Self::discoverer(&request.get_ref().discovery_details)?,
+            PollingSettings::new(Duration::from_secs(DISCOVERY_INTERVAL_SECS)),
+            self.register_sender.clone(),
+        )))
+    }
+}
+
+#[async_trait]
+impl akri_discovery_utils::discovery::v1::discovery_handler_server::DiscoveryHandler
+    for DiscoveryHandlerImpl
+{
+    type DiscoverStream = V1DiscoverStream;
+    async fn discover(
+        &self,
+        request: tonic::Request<v1::DiscoverRequest>,
+    ) -> Result<Response<Self::DiscoverStream>, Status> {
+        info!("discover - called for udev protocol over the v1 Discovery API");
+        Ok(Response::new(spawn_polling_discovery_v1(
+            Self::discoverer(&request.get_ref().discovery_details)?,
+            PollingSettings::new(Duration::from_secs(DISCOVERY_INTERVAL_SECS)),
+            self.register_sender.clone(),
+        )))
+    }
+}
+
+/// Scans for the devices that match any of a Configuration's udev rules
+struct UdevDiscoverer {
+    udev_rules: Vec<String>,
+}
+
+#[async_trait]
+impl PollingDiscoverer for UdevDiscoverer {
+    async fn discover_once(&mut self) -> Result<Vec<Device>, anyhow::Error> {
+        trace!("discover_once - for udev rules {:?}", self.udev_rules);
+        let mut devpaths: HashSet<String> = HashSet::new();
+        self.udev_rules.iter().for_each(|rule| {
+            let enumerator = udev_enumerator::create_enumerator();
+            match do_parse_and_find(enumerator, &rule) {
+                Ok(paths) => paths.into_iter().for_each(|path| {
+                    devpaths.insert(path);
+                }),
+                Err(e) => error!(
+                    "discover_once - failed to find devices for udev rule {} with error {}",
+                    rule, e
+                ),
             }
         });
-        Ok(Response::new(discovered_devices_receiver))
+        trace!(
+            "discover_once - mapping and returning devices at devpaths {:?}",
+            devpaths
+        );
+        Ok(devpaths
+            .into_iter()
+            .map(|path| {
+                let mut properties = std::collections::HashMap::new();
+                properties.insert(super::UDEV_DEVNODE_LABEL_ID.to_string(), path.clone());
+                let mount = Mount {
+                    container_path: path.clone(),
+                    host_path: path.clone(),
+                    read_only: true,
+                };
+                // TODO: use device spec
+                Device {
+                    id: path,
+                    properties,
+                    mounts: vec![mount],
+                    device_specs: Vec::default(),
+                }
+            })
+            .collect())
     }
 }
 
@@ -156,4 +177,37 @@ mod tests {
         assert_eq!(udev_dh_config.udev_rules.len(), 1);
         assert_eq!(&udev_dh_config.udev_rules[0], "KERNEL==\"video[0-9]*\"");
     }
+
+    #[test]
+    fn test_validate_discovery_details() {
+        let udev_dh_config = UdevDiscoveryDetails {
+            udev_rules: vec!["KERNEL==\"video[0-9]*\"".to_string()],
+        };
+        assert!(udev_dh_config.validate().is_ok());
+
+        // Unsupported field
+        let udev_dh_config = UdevDiscoveryDetails {
+            udev_rules: vec![
+                "KERNEL==\"video[0-9]*\"".to_string(),
+                "ACTION==\"add\"".to_string(),
+            ],
+        };
+        assert!(udev_dh_config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .starts_with(r#"udevRules[1] "ACTION=="add"" is not valid"#));
+
+        // Action operation
+        let udev_dh_config = UdevDiscoveryDetails {
+            udev_rules: vec!["KERNEL=\"video[0-9]*\"".to_string()],
+        };
+        assert!(udev_dh_config.validate().is_err());
+
+        // Unparsable rule
+        let udev_dh_config = UdevDiscoveryDetails {
+            udev_rules: vec!["KERNEL==video".to_string()],
+        };
+        assert!(udev_dh_config.validate().is_err());
+    }
 }
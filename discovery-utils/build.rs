@@ -1,6 +1,13 @@
 fn main() {
     tonic_build::configure()
         .out_dir("./src/discovery")
-        .compile(&["proto/discovery.proto"], &["proto"])
+        .compile(
+            &[
+                "proto/discovery.proto",
+                "proto/discovery_v1.proto",
+                "proto/health.proto",
+            ],
+            &["proto"],
+        )
         .expect("failed to compile protos");
 }
@@ -1,19 +1,31 @@
+/// Standard gRPC health checking API served alongside the Discovery API, which is auto-generated by `build.rs` from
+/// `proto/health.proto`
+#[path = "grpc.health.v1.rs"]
+pub mod grpc_health_v1;
 /// Akri's Discovery API code, which is auto-generated by `build.rs` from `proto/discovery.proto`
 pub mod v0;
+/// Version 1 of Akri's Discovery API, which streams changes to discovered devices rather than full device lists. It is
+/// auto-generated by `build.rs` from `proto/discovery_v1.proto`.
+pub mod v1;
 
 /// Definition of the DiscoverStream type expected for supported embedded Akri DiscoveryHandlers
 pub type DiscoverStream = tokio::sync::mpsc::Receiver<Result<v0::DiscoverResponse, tonic::Status>>;
+/// Stream of changes to discovered devices returned by Discovery Handlers that implement the v1 Discovery API
+pub type V1DiscoverStream =
+    tokio::sync::mpsc::Receiver<Result<v1::DiscoverResponse, tonic::Status>>;
 
 pub mod discovery_handler {
     use super::super::registration_client::{
         register_discovery_handler, register_discovery_handler_again,
     };
+    use super::super::security::get_tls_files;
     use super::{
-        server::run_discovery_server,
+        server::{run_versioned_discovery_server, DiffingDiscoveryHandler},
         v0::{
             discovery_handler_server::DiscoveryHandler,
             register_discovery_handler_request::EndpointType, RegisterDiscoveryHandlerRequest,
         },
+        v1::discovery_handler_server::DiscoveryHandler as V1DiscoveryHandler,
     };
     use log::trace;
     use tokio::sync::mpsc;
@@ -29,21 +41,46 @@ pub mod discovery_handler {
     /// in receiving.
     pub const DISCOVERED_DEVICES_CHANNEL_CAPACITY: usize = 4;
 
+    /// Serves a Discovery Handler that only implements the v0 `DiscoveryHandler` service and registers it with the
+    /// Agent. It is served over the v1 Discovery API too, by converting each full list of devices it sends into the
+    /// changes since the previous list.
     pub async fn run_discovery_handler(
         discovery_handler: impl DiscoveryHandler,
         register_receiver: mpsc::Receiver<()>,
         protocol_name: &str,
         shared: bool,
+        discovery_details_schema: Option<String>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        run_versioned_discovery_handler(
+            DiffingDiscoveryHandler::new(discovery_handler),
+            register_receiver,
+            protocol_name,
+            shared,
+            discovery_details_schema,
+        )
+        .await
+    }
+
+    /// Serves a Discovery Handler that implements both the v0 and v1 `DiscoveryHandler` services and registers it
+    /// with the Agent
+    pub async fn run_versioned_discovery_handler(
+        discovery_handler: impl DiscoveryHandler + V1DiscoveryHandler,
+        register_receiver: mpsc::Receiver<()>,
+        protocol_name: &str,
+        shared: bool,
+        discovery_details_schema: Option<String>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
         let mut use_uds = true;
         let mut endpoint: String = match std::env::var("POD_IP") {
             Ok(pod_ip) => {
-                trace!("run_discovery_handler - registering with Agent with IP endpoint");
+                trace!("run_versioned_discovery_handler - registering with Agent with IP endpoint");
                 use_uds = false;
                 format!("{}:{}", pod_ip, DISCOVERY_PORT)
             }
             Err(_) => {
-                trace!("run_discovery_handler - registering with Agent with uds endpoint");
+                trace!(
+                    "run_versioned_discovery_handler - registering with Agent with uds endpoint"
+                );
                 format!(
                     "{}/{}.sock",
                     std::env::var(super::super::DISCOVERY_HANDLERS_DIRECTORY_LABEL).unwrap(),
@@ -53,12 +90,17 @@ pub mod discovery_handler {
         };
         let endpoint_clone = endpoint.clone();
         let discovery_handle = tokio::spawn(async move {
-            run_discovery_server(discovery_handler, &endpoint_clone)
+            run_versioned_discovery_server(discovery_handler, &endpoint_clone)
                 .await
                 .unwrap();
         });
         let endpoint_type = if !use_uds {
-            endpoint.insert_str(0, "http://");
+            // The discovery server is served over TLS if certificates have been provided
+            if get_tls_files()?.is_some() {
+                endpoint.insert_str(0, "https://");
+            } else {
+                endpoint.insert_str(0, "http://");
+            }
             EndpointType::Network
         } else {
             EndpointType::Uds
@@ -68,6 +110,7 @@ pub mod discovery_handler {
             endpoint,
             endpoint_type: endpoint_type as i32,
             shared,
+            discovery_details_schema: discovery_details_schema.unwrap_or_default(),
         };
         register_discovery_handler(&register_request).await?;
         let registration_handle = tokio::spawn(async move {
@@ -91,6 +134,598 @@ pub mod discovery_handler {
         })?;
         Ok(discovery_handler_config)
     }
+
+    /// Generates the JSON Schema of a Discovery Handler's discovery details type `T`, to be advertised to the Agent in
+    /// `RegisterDiscoveryHandlerRequest.discovery_details_schema` so it can validate Configurations before discovery.
+    pub fn discovery_details_schema<T>() -> String
+    where
+        T: schemars::JsonSchema,
+    {
+        serde_json::to_string(&schemars::schema_for!(T))
+            .expect("JSON Schemas generated by schemars are always serializable")
+    }
+}
+
+/// Tracks the devices reported by a Discovery Handler so that only changes to them need to be sent over the v1
+/// Discovery API.
+pub mod delta {
+    use super::{v0::Device, v1::DiscoverResponse};
+    use std::collections::HashMap;
+
+    /// Number of responses containing only changes that are sent between full snapshots of discovered devices
+    pub const RESPONSES_PER_SNAPSHOT: u64 = 20;
+
+    /// Keeps the set of devices that has been sent to a client along with the sequence number of the next response.
+    #[derive(Default)]
+    pub struct DeviceDeltaTracker {
+        devices: HashMap<String, Device>,
+        next_sequence_number: u64,
+        responses_since_snapshot: u64,
+    }
+
+    impl DeviceDeltaTracker {
+        pub fn new() -> Self {
+            DeviceDeltaTracker::default()
+        }
+
+        /// Compares a full list of discovered devices to the devices previously sent and returns a response with the
+        /// devices that were added, updated, or removed. Returns None if nothing changed since the last response.
+        pub fn diff(&mut self, devices: Vec<Device>) -> Option<DiscoverResponse> {
+            let currently_visible_devices: HashMap<String, Device> = devices
+                .into_iter()
+                .map(|device| (device.id.clone(), device))
+                .collect();
+            let mut added = Vec::new();
+            let mut updated = Vec::new();
+            for (id, device) in &currently_visible_devices {
+                match self.devices.get(id) {
+                    None => added.push(device.clone()),
+                    Some(previous_device) if previous_device != device => {
+                        updated.push(device.clone())
+                    }
+                    _ => {}
+                }
+            }
+            let removed: Vec<String> = self
+                .devices
+                .keys()
+                .filter(|id| !currently_visible_devices.contains_key(*id))
+                .cloned()
+                .collect();
+            // The first response is always sent so that clients know discovery has started
+            if added.is_empty()
+                && updated.is_empty()
+                && removed.is_empty()
+                && self.next_sequence_number != 0
+            {
+                return None;
+            }
+            Some(self.changes(added, updated, removed))
+        }
+
+        /// Records changes to discovered devices and returns the response to send for them. The first response and
+        /// every `RESPONSES_PER_SNAPSHOT`th response after it are snapshots of all devices rather than just the changes.
+        /// Discovery Handlers that already know what changed can call this directly instead of `diff`.
+        pub fn changes(
+            &mut self,
+            added: Vec<Device>,
+            updated: Vec<Device>,
+            removed: Vec<String>,
+        ) -> DiscoverResponse {
+            for id in &removed {
+                self.devices.remove(id);
+            }
+            for device in added.iter().chain(updated.iter()) {
+                self.devices.insert(device.id.clone(), device.clone());
+            }
+            let sequence_number = self.next_sequence_number;
+            self.next_sequence_number += 1;
+            if sequence_number == 0 || self.responses_since_snapshot >= RESPONSES_PER_SNAPSHOT {
+                self.responses_since_snapshot = 0;
+                DiscoverResponse {
+                    sequence_number,
+                    snapshot: true,
+                    added: self.devices.values().cloned().collect(),
+                    updated: Vec::new(),
+                    removed: Vec::new(),
+                }
+            } else {
+                self.responses_since_snapshot += 1;
+                DiscoverResponse {
+                    sequence_number,
+                    snapshot: false,
+                    added,
+                    updated,
+                    removed,
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn device(id: &str, value: &str) -> Device {
+            let mut properties = HashMap::new();
+            properties.insert("KEY".to_string(), value.to_string());
+            Device {
+                id: id.to_string(),
+                properties,
+                mounts: Vec::default(),
+                device_specs: Vec::default(),
+            }
+        }
+
+        #[test]
+        fn test_diff() {
+            let mut tracker = DeviceDeltaTracker::new();
+            // First response is a snapshot, even if no devices are discovered
+            let response = tracker.diff(Vec::new()).unwrap();
+            assert!(response.snapshot);
+            assert_eq!(response.sequence_number, 0);
+            assert!(response.added.is_empty());
+            // Nothing changed
+            assert!(tracker.diff(Vec::new()).is_none());
+            let response = tracker
+                .diff(vec![device("device1", "a"), device("device2", "a")])
+                .unwrap();
+            assert!(!response.snapshot);
+            assert_eq!(response.sequence_number, 1);
+            assert_eq!(response.added.len(), 2);
+            assert!(tracker
+                .diff(vec![device("device1", "a"), device("device2", "a")])
+                .is_none());
+            let response = tracker
+                .diff(vec![device("device1", "b"), device("device3", "a")])
+                .unwrap();
+            assert_eq!(response.sequence_number, 2);
+            assert_eq!(response.added, vec![device("device3", "a")]);
+            assert_eq!(response.updated, vec![device("device1", "b")]);
+            assert_eq!(response.removed, vec!["device2".to_string()]);
+        }
+
+        #[test]
+        fn test_changes_sends_periodic_snapshots() {
+            let mut tracker = DeviceDeltaTracker::new();
+            assert!(
+                tracker
+                    .changes(vec![device("device1", "a")], Vec::new(), Vec::new())
+                    .snapshot
+            );
+            for i in 0..RESPONSES_PER_SNAPSHOT {
+                let response = tracker.changes(
+                    vec![device(&format!("device{}", i + 2), "a")],
+                    Vec::new(),
+                    Vec::new(),
+                );
+                assert!(!response.snapshot);
+                assert_eq!(response.added.len(), 1);
+            }
+            let response = tracker.changes(Vec::new(), Vec::new(), vec!["device1".to_string()]);
+            assert!(response.snapshot);
+            assert_eq!(response.sequence_number, RESPONSES_PER_SNAPSHOT + 1);
+            assert_eq!(response.added.len() as u64, RESPONSES_PER_SNAPSHOT);
+            assert!(response.removed.is_empty());
+        }
+    }
+}
+
+/// Runs the discovery loop of Discovery Handlers that find devices by periodically scanning for them, so that they only
+/// need to implement a single scan.
+pub mod polling {
+    use super::super::health::{Heartbeat, HEALTH_REPORTER};
+    use super::{
+        delta::DeviceDeltaTracker,
+        discovery_handler::DISCOVERED_DEVICES_CHANNEL_CAPACITY,
+        v0::{Device, DiscoverResponse},
+        v1, DiscoverStream, V1DiscoverStream,
+    };
+    use async_trait::async_trait;
+    use futures::future;
+    use log::{error, trace};
+    use std::{
+        task::Poll,
+        time::{Duration, Instant},
+    };
+    use tokio::{sync::mpsc, time::delay_for};
+
+    /// Default multiple of the polling interval that is waited at most between scans after consecutive failed scans
+    pub const DEFAULT_MAX_BACKOFF_INTERVALS: u32 = 8;
+
+    /// A Discovery Handler's scan for devices, which `spawn_polling_discovery` repeats until the Agent stops listening
+    #[async_trait]
+    pub trait PollingDiscoverer: Send + 'static {
+        /// Scans once for devices and returns all the devices that are currently visible. An error means that the
+        /// visible devices could not be determined, in which case the previously discovered devices are kept.
+        async fn discover_once(&mut self) -> Result<Vec<Device>, anyhow::Error>;
+
+        /// Waits until the next scan is due. Discoverers that are notified of changes to devices can override this to
+        /// scan again early.
+        async fn wait_for_next_scan(&mut self, interval: Duration) {
+            delay_for(interval).await;
+        }
+    }
+
+    /// Timing of a polling discovery loop
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct PollingSettings {
+        /// Time waited between scans
+        pub interval: Duration,
+        /// Longest time waited between scans while they keep failing
+        pub max_backoff: Duration,
+        /// Longest time a scan is expected to take, which is allowed for when reporting the health of the loop
+        pub max_scan_duration: Duration,
+    }
+
+    impl PollingSettings {
+        pub fn new(interval: Duration) -> Self {
+            PollingSettings {
+                interval,
+                max_backoff: interval * DEFAULT_MAX_BACKOFF_INTERVALS,
+                max_scan_duration: Duration::default(),
+            }
+        }
+
+        /// Returns the time waited after a number of consecutive failed scans, which doubles with every failure
+        pub fn backoff(&self, failed_scans: u32) -> Duration {
+            let backoff = self
+                .interval
+                .checked_mul(2u32.saturating_pow(failed_scans.saturating_sub(1)))
+                .unwrap_or(self.max_backoff);
+            backoff.min(self.max_backoff).max(self.interval)
+        }
+    }
+
+    /// Starts scanning for devices in the background and returns the stream over which the devices are sent whenever
+    /// they change. Scanning stops once the Agent has closed the stream, which is checked before every scan, after
+    /// which the Discovery Handler is told to register again over `register_sender`.
+    pub fn spawn_polling_discovery_v1(
+        discoverer: impl PollingDiscoverer,
+        settings: PollingSettings,
+        register_sender: Option<mpsc::Sender<()>>,
+    ) -> V1DiscoverStream {
+        let (discovered_devices_sender, discovered_devices_receiver) =
+            mpsc::channel(DISCOVERED_DEVICES_CHANNEL_CAPACITY);
+        tokio::spawn(async move {
+            run_polling_discovery_v1(
+                discoverer,
+                settings,
+                discovered_devices_sender,
+                register_sender,
+            )
+            .await;
+        });
+        discovered_devices_receiver
+    }
+
+    /// Like `spawn_polling_discovery_v1`, but sends the full list of devices whenever they change
+    pub fn spawn_polling_discovery(
+        discoverer: impl PollingDiscoverer,
+        settings: PollingSettings,
+        register_sender: Option<mpsc::Sender<()>>,
+    ) -> DiscoverStream {
+        let (discovered_devices_sender, discovered_devices_receiver) =
+            mpsc::channel(DISCOVERED_DEVICES_CHANNEL_CAPACITY);
+        tokio::spawn(async move {
+            run_polling_discovery(
+                discoverer,
+                settings,
+                discovered_devices_sender,
+                register_sender,
+            )
+            .await;
+        });
+        discovered_devices_receiver
+    }
+
+    /// Repeatedly scans for devices, sending the full list of devices whenever a device is added or removed or its
+    /// properties change. The first list is always sent so that the Agent knows discovery has started.
+    pub async fn run_polling_discovery(
+        discoverer: impl PollingDiscoverer,
+        settings: PollingSettings,
+        discovered_devices_sender: mpsc::Sender<Result<DiscoverResponse, tonic::Status>>,
+        register_sender: Option<mpsc::Sender<()>>,
+    ) {
+        run_polling_loop(
+            discoverer,
+            settings,
+            discovered_devices_sender,
+            register_sender,
+            |device_tracker, devices| {
+                device_tracker
+                    .diff(devices.clone())
+                    .map(|_| DiscoverResponse { devices })
+            },
+        )
+        .await
+    }
+
+    /// Repeatedly scans for devices, sending the devices that were added, updated, or removed whenever there are any,
+    /// along with periodic snapshots of all devices. The first response is always a snapshot so that the Agent knows
+    /// discovery has started.
+    pub async fn run_polling_discovery_v1(
+        discoverer: impl PollingDiscoverer,
+        settings: PollingSettings,
+        discovered_devices_sender: mpsc::Sender<Result<v1::DiscoverResponse, tonic::Status>>,
+        register_sender: Option<mpsc::Sender<()>>,
+    ) {
+        run_polling_loop(
+            discoverer,
+            settings,
+            discovered_devices_sender,
+            register_sender,
+            |device_tracker, devices| device_tracker.diff(devices),
+        )
+        .await
+    }
+
+    /// Repeatedly scans for devices and sends the response that `get_response` makes from the devices of each scan,
+    /// unless it returns None because the devices did not change
+    async fn run_polling_loop<R>(
+        mut discoverer: impl PollingDiscoverer,
+        settings: PollingSettings,
+        mut discovered_devices_sender: mpsc::Sender<Result<R, tonic::Status>>,
+        register_sender: Option<mpsc::Sender<()>>,
+        mut get_response: impl FnMut(&mut DeviceDeltaTracker, Vec<Device>) -> Option<R>,
+    ) {
+        let heartbeat = HEALTH_REPORTER.heartbeat(settings.interval + settings.max_scan_duration);
+        let mut device_tracker = DeviceDeltaTracker::new();
+        let mut failed_scans = 0;
+        loop {
+            heartbeat.beat();
+            // Devices are only sent when they change, so the Agent closing the stream is checked for separately
+            if is_stream_closed(&mut discovered_devices_sender).await {
+                trace!("run_polling_loop - stream closed by the Agent");
+                break;
+            }
+            match discoverer.discover_once().await {
+                Ok(devices) => {
+                    failed_scans = 0;
+                    if let Some(response) = get_response(&mut device_tracker, devices) {
+                        trace!("run_polling_loop - sending changed devices");
+                        if discovered_devices_sender.send(Ok(response)).await.is_err() {
+                            error!("run_polling_loop - failed to send discovery response, as the stream was closed");
+                            break;
+                        }
+                    }
+                }
+                Err(e) => {
+                    failed_scans += 1;
+                    error!(
+                        "run_polling_loop - scan failed {} time(s) in a row, keeping previously discovered devices: {}",
+                        failed_scans, e
+                    );
+                }
+            }
+            if failed_scans == 0 {
+                discoverer.wait_for_next_scan(settings.interval).await;
+            } else {
+                back_off(
+                    &heartbeat,
+                    settings.backoff(failed_scans),
+                    settings.interval,
+                )
+                .await;
+            }
+        }
+        if let Some(mut sender) = register_sender {
+            if let Err(e) = sender.send(()).await {
+                error!(
+                    "run_polling_loop - failed to signal to register again with error {}",
+                    e
+                );
+            }
+        }
+    }
+
+    /// Returns whether the Agent has closed the stream that devices are sent over, without sending anything
+    async fn is_stream_closed<T>(sender: &mut mpsc::Sender<T>) -> bool {
+        future::poll_fn(|cx| match sender.poll_ready(cx) {
+            Poll::Ready(Ok(())) => {
+                // Gives back the capacity reserved by the check
+                sender.disarm();
+                Poll::Ready(false)
+            }
+            Poll::Ready(Err(_)) => Poll::Ready(true),
+            // The Agent has not read the previous devices yet
+            Poll::Pending => Poll::Ready(false),
+        })
+        .await
+    }
+
+    /// Waits before scanning again after a failed scan. The loop is not stuck while it waits, so it keeps beating.
+    async fn back_off(heartbeat: &Heartbeat, backoff: Duration, interval: Duration) {
+        let resume = Instant::now() + backoff;
+        loop {
+            let remaining = resume.saturating_duration_since(Instant::now());
+            if remaining == Duration::default() {
+                break;
+            }
+            delay_for(remaining.min(interval)).await;
+            heartbeat.beat();
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::collections::{HashMap, VecDeque};
+
+        /// Returns the results of a list of scans in order, and no devices once they run out
+        struct MockDiscoverer {
+            scans: VecDeque<Result<Vec<Device>, anyhow::Error>>,
+        }
+
+        #[async_trait]
+        impl PollingDiscoverer for MockDiscoverer {
+            async fn discover_once(&mut self) -> Result<Vec<Device>, anyhow::Error> {
+                self.scans.pop_front().unwrap_or_else(|| Ok(Vec::new()))
+            }
+        }
+
+        fn device(id: &str, value: &str) -> Device {
+            let mut properties = HashMap::new();
+            properties.insert("KEY".to_string(), value.to_string());
+            Device {
+                id: id.to_string(),
+                properties,
+                mounts: Vec::default(),
+                device_specs: Vec::default(),
+            }
+        }
+
+        #[test]
+        fn test_backoff() {
+            let settings = PollingSettings::new(Duration::from_secs(10));
+            assert_eq!(settings.max_backoff, Duration::from_secs(80));
+            assert_eq!(settings.backoff(0), Duration::from_secs(10));
+            assert_eq!(settings.backoff(1), Duration::from_secs(10));
+            assert_eq!(settings.backoff(2), Duration::from_secs(20));
+            assert_eq!(settings.backoff(4), Duration::from_secs(80));
+            assert_eq!(settings.backoff(5), Duration::from_secs(80));
+            assert_eq!(settings.backoff(u32::MAX), Duration::from_secs(80));
+        }
+
+        #[tokio::test]
+        async fn test_polling_discovery_sends_changes() {
+            let discoverer = MockDiscoverer {
+                scans: vec![
+                    Ok(vec![device("device1", "a")]),
+                    // Unchanged
+                    Ok(vec![device("device1", "a")]),
+                    // Failed scans keep the devices
+                    Err(anyhow::format_err!("scan failed")),
+                    Ok(vec![device("device2", "a"), device("device1", "a")]),
+                    // Unchanged, in a different order
+                    Ok(vec![device("device1", "a"), device("device2", "a")]),
+                    // Changed properties
+                    Ok(vec![device("device1", "b"), device("device2", "a")]),
+                ]
+                .into_iter()
+                .collect(),
+            };
+            let mut stream = spawn_polling_discovery(
+                discoverer,
+                PollingSettings::new(Duration::from_millis(1)),
+                None,
+            );
+            assert_eq!(
+                next_devices(&mut stream).await,
+                vec![device("device1", "a")]
+            );
+            assert_eq!(
+                next_devices(&mut stream).await,
+                vec![device("device2", "a"), device("device1", "a")]
+            );
+            assert_eq!(
+                next_devices(&mut stream).await,
+                vec![device("device1", "b"), device("device2", "a")]
+            );
+            // Every device is gone once the scans run out
+            assert!(next_devices(&mut stream).await.is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_polling_discovery_v1_sends_changes() {
+            let discoverer = MockDiscoverer {
+                scans: vec![
+                    Ok(vec![device("device1", "a")]),
+                    // Unchanged
+                    Ok(vec![device("device1", "a")]),
+                    Ok(vec![device("device1", "b"), device("device2", "a")]),
+                ]
+                .into_iter()
+                .collect(),
+            };
+            let mut stream = spawn_polling_discovery_v1(
+                discoverer,
+                PollingSettings::new(Duration::from_millis(1)),
+                None,
+            );
+            let response = stream.recv().await.unwrap().unwrap();
+            assert!(response.snapshot);
+            assert_eq!(response.sequence_number, 0);
+            assert_eq!(response.added, vec![device("device1", "a")]);
+            let response = stream.recv().await.unwrap().unwrap();
+            assert!(!response.snapshot);
+            assert_eq!(response.sequence_number, 1);
+            assert_eq!(response.added, vec![device("device2", "a")]);
+            assert_eq!(response.updated, vec![device("device1", "b")]);
+            // Every device is gone once the scans run out
+            let response = stream.recv().await.unwrap().unwrap();
+            assert_eq!(response.sequence_number, 2);
+            assert!(response.added.is_empty());
+            assert_eq!(response.removed.len(), 2);
+        }
+
+        #[tokio::test]
+        async fn test_polling_discovery_stops_when_stream_closed() {
+            // Finds a device on every other scan, so that there is always a change to send
+            struct FlappingDiscoverer {
+                found: bool,
+            }
+
+            #[async_trait]
+            impl PollingDiscoverer for FlappingDiscoverer {
+                async fn discover_once(&mut self) -> Result<Vec<Device>, anyhow::Error> {
+                    self.found = !self.found;
+                    if self.found {
+                        Ok(vec![device("device1", "a")])
+                    } else {
+                        Ok(Vec::new())
+                    }
+                }
+            }
+
+            let (register_sender, mut register_receiver) = mpsc::channel(1);
+            let mut stream = spawn_polling_discovery(
+                FlappingDiscoverer { found: false },
+                PollingSettings::new(Duration::from_millis(1)),
+                Some(register_sender),
+            );
+            assert_eq!(next_devices(&mut stream).await.len(), 1);
+            drop(stream);
+            assert!(register_receiver.recv().await.is_some());
+        }
+
+        #[tokio::test]
+        async fn test_polling_discovery_stops_when_stream_closed_without_changes() {
+            let (register_sender, mut register_receiver) = mpsc::channel(1);
+            let mut stream = spawn_polling_discovery(
+                MockDiscoverer {
+                    scans: VecDeque::new(),
+                },
+                PollingSettings::new(Duration::from_millis(1)),
+                Some(register_sender),
+            );
+            assert!(next_devices(&mut stream).await.is_empty());
+            drop(stream);
+            assert!(register_receiver.recv().await.is_some());
+        }
+
+        #[tokio::test]
+        async fn test_polling_discovery_stops_when_register_receiver_closed() {
+            let (register_sender, register_receiver) = mpsc::channel(1);
+            drop(register_receiver);
+            let (discovered_devices_sender, discovered_devices_receiver) = mpsc::channel(1);
+            drop(discovered_devices_receiver);
+            // Returns rather than panicking when the Discovery Handler is no longer listening for registrations
+            run_polling_discovery(
+                MockDiscoverer {
+                    scans: VecDeque::new(),
+                },
+                PollingSettings::new(Duration::from_millis(1)),
+                discovered_devices_sender,
+                Some(register_sender),
+            )
+            .await;
+        }
+
+        async fn next_devices(stream: &mut DiscoverStream) -> Vec<Device> {
+            stream.recv().await.unwrap().unwrap().devices
+        }
+    }
 }
 
 #[cfg(any(feature = "mock-discovery-handler", test))]
@@ -187,19 +822,144 @@ pub mod mock_discovery_handler {
 }
 
 pub mod server {
-    use super::v0::discovery_handler_server::{DiscoveryHandler, DiscoveryHandlerServer};
+    use super::super::{
+        health::{HealthService, HEALTH_REPORTER},
+        security::{get_tls_files, server_tls_config},
+    };
+    use super::{
+        delta::DeviceDeltaTracker,
+        discovery_handler::DISCOVERED_DEVICES_CHANNEL_CAPACITY,
+        grpc_health_v1::health_server::HealthServer,
+        v0::{
+            self,
+            discovery_handler_server::{DiscoveryHandler, DiscoveryHandlerServer},
+        },
+        v1::{
+            self,
+            discovery_handler_server::{
+                DiscoveryHandler as V1DiscoveryHandler,
+                DiscoveryHandlerServer as V1DiscoveryHandlerServer,
+            },
+        },
+    };
     use akri_shared::uds::unix_stream;
-    use futures::stream::TryStreamExt;
-    use log::info;
-    use std::path::Path;
-    use tokio::net::UnixListener;
-    use tonic::transport::Server;
+    use async_trait::async_trait;
+    use futures::stream::{StreamExt, TryStreamExt};
+    use log::{info, trace};
+    use std::{path::Path, sync::Arc};
+    use tokio::{net::UnixListener, sync::mpsc};
+    use tonic::{transport::Server, Request, Response, Status};
+
+    /// Serves a Discovery Handler that implements both the v0 and v1 `DiscoveryHandler` services, so that the same
+    /// instance can be added to the server once per Discovery API version.
+    pub struct VersionedDiscoveryHandler<T>(Arc<T>);
+
+    impl<T> VersionedDiscoveryHandler<T> {
+        pub fn new(discovery_handler: T) -> Self {
+            VersionedDiscoveryHandler(Arc::new(discovery_handler))
+        }
+    }
+
+    impl<T> Clone for VersionedDiscoveryHandler<T> {
+        fn clone(&self) -> Self {
+            VersionedDiscoveryHandler(self.0.clone())
+        }
+    }
+
+    #[async_trait]
+    impl<T: DiscoveryHandler + V1DiscoveryHandler> DiscoveryHandler for VersionedDiscoveryHandler<T> {
+        type DiscoverStream = <T as DiscoveryHandler>::DiscoverStream;
+        async fn discover(
+            &self,
+            request: Request<v0::DiscoverRequest>,
+        ) -> Result<Response<Self::DiscoverStream>, Status> {
+            DiscoveryHandler::discover(&*self.0, request).await
+        }
+    }
+
+    #[async_trait]
+    impl<T: DiscoveryHandler + V1DiscoveryHandler> V1DiscoveryHandler for VersionedDiscoveryHandler<T> {
+        type DiscoverStream = <T as V1DiscoveryHandler>::DiscoverStream;
+        async fn discover(
+            &self,
+            request: Request<v1::DiscoverRequest>,
+        ) -> Result<Response<Self::DiscoverStream>, Status> {
+            V1DiscoveryHandler::discover(&*self.0, request).await
+        }
+    }
+
+    /// Adds the v1 `DiscoveryHandler` service to a Discovery Handler that only implements the v0 service. The full
+    /// device lists it sends are converted to v1 responses by sending only the changes between consecutive lists.
+    /// Discovery Handlers that already know which devices changed should implement the v1 service themselves instead.
+    pub struct DiffingDiscoveryHandler<T>(T);
+
+    impl<T> DiffingDiscoveryHandler<T> {
+        pub fn new(discovery_handler: T) -> Self {
+            DiffingDiscoveryHandler(discovery_handler)
+        }
+    }
+
+    #[async_trait]
+    impl<T: DiscoveryHandler> DiscoveryHandler for DiffingDiscoveryHandler<T> {
+        type DiscoverStream = T::DiscoverStream;
+        async fn discover(
+            &self,
+            request: Request<v0::DiscoverRequest>,
+        ) -> Result<Response<Self::DiscoverStream>, Status> {
+            self.0.discover(request).await
+        }
+    }
+
+    #[async_trait]
+    impl<T: DiscoveryHandler> V1DiscoveryHandler for DiffingDiscoveryHandler<T> {
+        type DiscoverStream = mpsc::Receiver<Result<v1::DiscoverResponse, Status>>;
+        async fn discover(
+            &self,
+            request: Request<v1::DiscoverRequest>,
+        ) -> Result<Response<Self::DiscoverStream>, Status> {
+            let v0_request = Request::new(v0::DiscoverRequest {
+                discovery_details: request.into_inner().discovery_details,
+            });
+            let mut v0_stream = Box::pin(self.0.discover(v0_request).await?.into_inner());
+            let (mut discovered_devices_sender, discovered_devices_receiver) =
+                mpsc::channel(DISCOVERED_DEVICES_CHANNEL_CAPACITY);
+            tokio::spawn(async move {
+                let mut delta_tracker = DeviceDeltaTracker::new();
+                while let Some(result) = v0_stream.next().await {
+                    let response = match result {
+                        Ok(response) => match delta_tracker.diff(response.devices) {
+                            Some(response) => Ok(response),
+                            None => continue,
+                        },
+                        Err(status) => Err(status),
+                    };
+                    // Dropping the v0 stream lets the Discovery Handler know the client has disconnected
+                    if discovered_devices_sender.send(response).await.is_err() {
+                        trace!("discover - v1 client dropped its end of the stream");
+                        break;
+                    }
+                }
+            });
+            Ok(Response::new(discovered_devices_receiver))
+        }
+    }
 
     pub async fn run_discovery_server(
         discovery_handler: impl DiscoveryHandler,
         discovery_endpoint: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
-        internal_run_discovery_server(
+        run_versioned_discovery_server(
+            DiffingDiscoveryHandler::new(discovery_handler),
+            discovery_endpoint,
+        )
+        .await
+    }
+
+    pub async fn run_versioned_discovery_server(
+        discovery_handler: impl DiscoveryHandler + V1DiscoveryHandler,
+        discovery_endpoint: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        internal_run_versioned_discovery_server(
             discovery_handler,
             discovery_endpoint,
             &std::env::var(super::super::DISCOVERY_HANDLERS_DIRECTORY_LABEL).unwrap(),
@@ -207,34 +967,66 @@ pub mod server {
         .await
     }
 
-    /// Creates a DiscoveryHandlerServer for the given Discovery Handler at the specified endpoint Verifies the endpoint
-    /// by checking that it is in the discovery handler directory if it is UDS or that it is a valid IP address and
-    /// port.
+    /// Serves a Discovery Handler that only implements the v0 `DiscoveryHandler` service over both Discovery APIs.
+    /// See `internal_run_versioned_discovery_server`.
     pub async fn internal_run_discovery_server(
         discovery_handler: impl DiscoveryHandler,
         discovery_endpoint: &str,
         discovery_handler_directory: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
-        info!("internal_run_discovery_server - entered");
+        internal_run_versioned_discovery_server(
+            DiffingDiscoveryHandler::new(discovery_handler),
+            discovery_endpoint,
+            discovery_handler_directory,
+        )
+        .await
+    }
 
+    /// Creates a DiscoveryHandlerServer for the given Discovery Handler at the specified endpoint Verifies the endpoint
+    /// by checking that it is in the discovery handler directory if it is UDS or that it is a valid IP address and
+    /// port. Servers at an IP address use mTLS if `DISCOVERY_HANDLER_TLS_DIRECTORY` is set. The Discovery Handler is
+    /// served over both the v0 and v1 Discovery APIs, alongside the standard gRPC health service, which reports
+    /// whether the discovery loops of the Discovery Handler are still making progress.
+    pub async fn internal_run_versioned_discovery_server(
+        discovery_handler: impl DiscoveryHandler + V1DiscoveryHandler,
+        discovery_endpoint: &str,
+        discovery_handler_directory: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        info!("internal_run_versioned_discovery_server - entered");
+        let discovery_handler = VersionedDiscoveryHandler::new(discovery_handler);
         if discovery_endpoint.starts_with(discovery_handler_directory) {
             tokio::fs::create_dir_all(Path::new(&discovery_endpoint[..]).parent().unwrap()).await?;
             // Delete socket if it already exists
             std::fs::remove_file(discovery_endpoint).unwrap_or(());
             let mut uds = UnixListener::bind(discovery_endpoint)?;
             Server::builder()
-                .add_service(DiscoveryHandlerServer::new(discovery_handler))
+                .add_service(DiscoveryHandlerServer::new(discovery_handler.clone()))
+                .add_service(V1DiscoveryHandlerServer::new(discovery_handler))
+                .add_service(HealthServer::new(HealthService::new(
+                    HEALTH_REPORTER.clone(),
+                )))
                 .serve_with_incoming(uds.incoming().map_ok(unix_stream::UnixStream))
                 .await?;
             std::fs::remove_file(discovery_endpoint).unwrap_or(());
         } else {
             let addr = discovery_endpoint.parse()?;
-            Server::builder()
-                .add_service(DiscoveryHandlerServer::new(discovery_handler))
+            let server = match get_tls_files()? {
+                Some(tls_files) => {
+                    info!("internal_run_versioned_discovery_server - serving over mTLS");
+                    Server::builder().tls_config(server_tls_config(&tls_files))
+                }
+                None => Server::builder(),
+            };
+            server
+                .add_service(DiscoveryHandlerServer::new(discovery_handler.clone()))
+                .add_service(V1DiscoveryHandlerServer::new(discovery_handler))
+                .add_service(HealthServer::new(HealthService::new(
+                    HEALTH_REPORTER.clone(),
+                )))
                 .serve(addr)
                 .await?;
         }
-        info!("internal_run_discovery_server - finished");
+        info!("internal_run_versioned_discovery_server - finished");
         Ok(())
     }
 
@@ -285,6 +1077,45 @@ pub mod server {
             assert!(stream.message().await.unwrap().unwrap().devices.is_empty());
         }
 
+        #[tokio::test]
+        async fn test_run_discovery_server_uds_v1() {
+            let (discovery_handler_dir, discovery_handler_socket) =
+                get_mock_discovery_handler_dir_and_endpoint("protocol.sock");
+            let device = v0::Device {
+                id: "device1".to_string(),
+                properties: std::collections::HashMap::new(),
+                mounts: Vec::default(),
+                device_specs: Vec::default(),
+            };
+            let _handle: tokio::task::JoinHandle<()> = run_mock_discovery_handler(
+                &discovery_handler_dir,
+                &discovery_handler_socket,
+                false,
+                vec![device.clone()],
+            )
+            .await;
+            let channel = Endpoint::try_from("dummy://[::]:50051")
+                .unwrap()
+                .connect_with_connector(tower::service_fn(move |_: Uri| {
+                    UnixStream::connect(discovery_handler_socket.clone())
+                }))
+                .await
+                .unwrap();
+            let mut discovery_handler_client =
+                v1::discovery_handler_client::DiscoveryHandlerClient::new(channel);
+            let mut stream = discovery_handler_client
+                .discover(Request::new(v1::DiscoverRequest {
+                    discovery_details: String::new(),
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            let response = stream.message().await.unwrap().unwrap();
+            assert!(response.snapshot);
+            assert_eq!(response.sequence_number, 0);
+            assert_eq!(response.added, vec![device]);
+        }
+
         // Test when improper socket path or IP address is given as an endpoint
         #[tokio::test]
         async fn test_run_discovery_server_error_invalid_ip_addr() {
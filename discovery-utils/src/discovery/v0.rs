@@ -16,6 +16,10 @@ pub struct RegisterDiscoveryHandlerRequest {
     /// or can only be ever be discovered by a single node (e.g. a local USB device)
     #[prost(bool, tag = "4")]
     pub shared: bool,
+    /// Optional JSON Schema describing the discovery details this `DiscoveryHandler` expects
+    /// in a Configuration. If set, the Agent validates Configurations against it before discovery.
+    #[prost(string, tag = "5")]
+    pub discovery_details_schema: std::string::String,
 }
 pub mod register_discovery_handler_request {
     /// Specifies the type of endpoint.
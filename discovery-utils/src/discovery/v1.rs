@@ -0,0 +1,203 @@
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiscoverRequest {
+    /// String containing all the details (such as filtering options)
+    /// the `DiscoveryHandler` needs to find a set of devices.
+    #[prost(string, tag = "1")]
+    pub discovery_details: std::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiscoverResponse {
+    /// Incremented by one for every response sent on a stream, starting at 0. A client that sees a gap in
+    /// sequence numbers ignores changes until the next snapshot.
+    #[prost(uint64, tag = "1")]
+    pub sequence_number: u64,
+    /// If true, `added` contains the full list of discovered devices, and any device not in it
+    /// should be considered removed. The first response on a stream is always a snapshot and
+    /// snapshots are periodically resent so that clients can recover from missed changes.
+    #[prost(bool, tag = "2")]
+    pub snapshot: bool,
+    /// Devices that were newly discovered
+    #[prost(message, repeated, tag = "3")]
+    pub added: ::std::vec::Vec<super::v0::Device>,
+    /// Previously discovered devices whose properties, mounts, or device specs changed
+    #[prost(message, repeated, tag = "4")]
+    pub updated: ::std::vec::Vec<super::v0::Device>,
+    /// Ids of previously discovered devices that are no longer visible
+    #[prost(string, repeated, tag = "5")]
+    pub removed: ::std::vec::Vec<std::string::String>,
+}
+#[doc = r" Generated client implementations."]
+pub mod discovery_handler_client {
+    #![allow(unused_variables, dead_code, missing_docs)]
+    use tonic::codegen::*;
+    #[doc = " Version 1 of the `DiscoveryHandler` service. Rather than sending the full list of discovered devices"]
+    #[doc = " every time discovery results change, a `DiscoveryHandler` sends only the devices that were added, updated,"]
+    #[doc = " or removed. The Akri Agent falls back to `v0.DiscoveryHandler` if a `DiscoveryHandler` does not serve this version."]
+    pub struct DiscoveryHandlerClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl DiscoveryHandlerClient<tonic::transport::Channel> {
+        #[doc = r" Attempt to create a new client by connecting to a given endpoint."]
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: std::convert::TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> DiscoveryHandlerClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::ResponseBody: Body + HttpBody + Send + 'static,
+        T::Error: Into<StdError>,
+        <T::ResponseBody as HttpBody>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_interceptor(inner: T, interceptor: impl Into<tonic::Interceptor>) -> Self {
+            let inner = tonic::client::Grpc::with_interceptor(inner, interceptor);
+            Self { inner }
+        }
+        pub async fn discover(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DiscoverRequest>,
+        ) -> Result<tonic::Response<tonic::codec::Streaming<super::DiscoverResponse>>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/v1.DiscoveryHandler/Discover");
+            self.inner
+                .server_streaming(request.into_request(), path, codec)
+                .await
+        }
+    }
+    impl<T: Clone> Clone for DiscoveryHandlerClient<T> {
+        fn clone(&self) -> Self {
+            Self {
+                inner: self.inner.clone(),
+            }
+        }
+    }
+}
+#[doc = r" Generated server implementations."]
+pub mod discovery_handler_server {
+    #![allow(unused_variables, dead_code, missing_docs)]
+    use tonic::codegen::*;
+    #[doc = "Generated trait containing gRPC methods that should be implemented for use with DiscoveryHandlerServer."]
+    #[async_trait]
+    pub trait DiscoveryHandler: Send + Sync + 'static {
+        #[doc = "Server streaming response type for the Discover method."]
+        type DiscoverStream: Stream<Item = Result<super::DiscoverResponse, tonic::Status>>
+            + Send
+            + Sync
+            + 'static;
+        async fn discover(
+            &self,
+            request: tonic::Request<super::DiscoverRequest>,
+        ) -> Result<tonic::Response<Self::DiscoverStream>, tonic::Status>;
+    }
+    #[doc = " Version 1 of the `DiscoveryHandler` service. Rather than sending the full list of discovered devices"]
+    #[doc = " every time discovery results change, a `DiscoveryHandler` sends only the devices that were added, updated,"]
+    #[doc = " or removed. The Akri Agent falls back to `v0.DiscoveryHandler` if a `DiscoveryHandler` does not serve this version."]
+    #[derive(Debug)]
+    #[doc(hidden)]
+    pub struct DiscoveryHandlerServer<T: DiscoveryHandler> {
+        inner: _Inner<T>,
+    }
+    struct _Inner<T>(Arc<T>, Option<tonic::Interceptor>);
+    impl<T: DiscoveryHandler> DiscoveryHandlerServer<T> {
+        pub fn new(inner: T) -> Self {
+            let inner = Arc::new(inner);
+            let inner = _Inner(inner, None);
+            Self { inner }
+        }
+        pub fn with_interceptor(inner: T, interceptor: impl Into<tonic::Interceptor>) -> Self {
+            let inner = Arc::new(inner);
+            let inner = _Inner(inner, Some(interceptor.into()));
+            Self { inner }
+        }
+    }
+    impl<T: DiscoveryHandler> Service<http::Request<HyperBody>> for DiscoveryHandlerServer<T> {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = Never;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<HyperBody>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/v1.DiscoveryHandler/Discover" => {
+                    struct DiscoverSvc<T: DiscoveryHandler>(pub Arc<T>);
+                    impl<T: DiscoveryHandler>
+                        tonic::server::ServerStreamingService<super::DiscoverRequest>
+                        for DiscoverSvc<T>
+                    {
+                        type Response = super::DiscoverResponse;
+                        type ResponseStream = T::DiscoverStream;
+                        type Future =
+                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DiscoverRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { inner.discover(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let interceptor = inner.1;
+                        let inner = inner.0;
+                        let method = DiscoverSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = if let Some(interceptor) = interceptor {
+                            tonic::server::Grpc::with_interceptor(codec, interceptor)
+                        } else {
+                            tonic::server::Grpc::new(codec)
+                        };
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", "12")
+                        .body(tonic::body::BoxBody::empty())
+                        .unwrap())
+                }),
+            }
+        }
+    }
+    impl<T: DiscoveryHandler> Clone for DiscoveryHandlerServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self { inner }
+        }
+    }
+    impl<T: DiscoveryHandler> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone(), self.1.clone())
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: DiscoveryHandler> tonic::transport::NamedService for DiscoveryHandlerServer<T> {
+        const NAME: &'static str = "v1.DiscoveryHandler";
+    }
+}
@@ -1,5 +1,7 @@
+use schemars::JsonSchema;
+
 /// This defines the types of supported filters
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub enum FilterType {
     /// If the filter type is Exclude, any items NOT found in the
     /// list are accepted
@@ -19,8 +21,9 @@ fn default_action() -> FilterType {
 /// The items list can either define the only acceptable
 /// items (Include) or can define the only unacceptable items
 /// (Exclude)
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
 pub struct FilterList {
     /// This defines a list of items that will be evaluated as part
     /// of the filtering process
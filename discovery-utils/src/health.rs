@@ -0,0 +1,199 @@
+use super::discovery::grpc_health_v1::{
+    health_check_response::ServingStatus, health_server::Health, HealthCheckRequest,
+    HealthCheckResponse,
+};
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{sync::mpsc, time::delay_for};
+use tonic::{Request, Response, Status};
+
+/// Number of heartbeats a discovery loop can miss before its Discovery Handler is reported as not serving
+pub const MISSED_HEARTBEATS_BEFORE_NOT_SERVING: u32 = 6;
+
+/// Interval at which the serving status is sent to clients watching the health of a Discovery Handler
+pub const HEALTH_WATCH_INTERVAL_SECS: u64 = 5;
+
+/// Names of the services whose health can be checked. An empty name refers to the health of the server as a whole.
+const HEALTH_CHECKED_SERVICE_NAMES: [&str; 3] = ["", "v0.DiscoveryHandler", "v1.DiscoveryHandler"];
+
+lazy_static! {
+    /// Tracks the heartbeats of all the discovery loops running in this process. It is served by the health service
+    /// started by `run_discovery_server`.
+    pub static ref HEALTH_REPORTER: HealthReporter = HealthReporter::default();
+}
+
+struct HeartbeatState {
+    last_heartbeat: Instant,
+    timeout: Duration,
+}
+
+/// Reports a Discovery Handler as not serving when any of its discovery loops stops making progress, such as when it is
+/// stuck scanning for devices, even if its connection with the Agent is still open.
+#[derive(Clone, Default)]
+pub struct HealthReporter {
+    heartbeats: Arc<Mutex<HashMap<u64, HeartbeatState>>>,
+    next_heartbeat_id: Arc<AtomicU64>,
+}
+
+impl HealthReporter {
+    /// Starts tracking a discovery loop that calls `Heartbeat::beat` about every `interval`. The loop stops being
+    /// tracked once the returned `Heartbeat` is dropped.
+    pub fn heartbeat(&self, interval: Duration) -> Heartbeat {
+        let id = self.next_heartbeat_id.fetch_add(1, Ordering::SeqCst);
+        self.heartbeats.lock().unwrap().insert(
+            id,
+            HeartbeatState {
+                last_heartbeat: Instant::now(),
+                timeout: interval * MISSED_HEARTBEATS_BEFORE_NOT_SERVING,
+            },
+        );
+        Heartbeat {
+            id,
+            heartbeats: self.heartbeats.clone(),
+        }
+    }
+
+    /// Returns `NotServing` if any discovery loop has missed too many heartbeats and `Serving` otherwise
+    pub fn status(&self) -> ServingStatus {
+        let missed_heartbeats = self
+            .heartbeats
+            .lock()
+            .unwrap()
+            .values()
+            .any(|state| state.last_heartbeat.elapsed() > state.timeout);
+        if missed_heartbeats {
+            ServingStatus::NotServing
+        } else {
+            ServingStatus::Serving
+        }
+    }
+}
+
+/// Handle a discovery loop uses to report that it is still making progress
+pub struct Heartbeat {
+    id: u64,
+    heartbeats: Arc<Mutex<HashMap<u64, HeartbeatState>>>,
+}
+
+impl Heartbeat {
+    pub fn beat(&self) {
+        if let Some(state) = self.heartbeats.lock().unwrap().get_mut(&self.id) {
+            state.last_heartbeat = Instant::now();
+        }
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.heartbeats.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Implementation of the standard gRPC `Health` service that serves the status of a `HealthReporter`
+pub struct HealthService {
+    health_reporter: HealthReporter,
+}
+
+impl HealthService {
+    pub fn new(health_reporter: HealthReporter) -> Self {
+        HealthService { health_reporter }
+    }
+}
+
+#[async_trait]
+impl Health for HealthService {
+    async fn check(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        let service = &request.get_ref().service;
+        if !HEALTH_CHECKED_SERVICE_NAMES.contains(&service.as_str()) {
+            return Err(Status::not_found(format!("unknown service {}", service)));
+        }
+        Ok(Response::new(HealthCheckResponse {
+            status: self.health_reporter.status() as i32,
+        }))
+    }
+
+    type WatchStream = mpsc::Receiver<Result<HealthCheckResponse, Status>>;
+
+    async fn watch(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let known_service =
+            HEALTH_CHECKED_SERVICE_NAMES.contains(&request.get_ref().service.as_str());
+        let health_reporter = self.health_reporter.clone();
+        let (mut status_sender, status_receiver) = mpsc::channel(1);
+        tokio::spawn(async move {
+            // The status is resent periodically so that the task stops soon after the client goes away
+            loop {
+                let status = if known_service {
+                    health_reporter.status()
+                } else {
+                    ServingStatus::ServiceUnknown
+                };
+                if status_sender
+                    .send(Ok(HealthCheckResponse {
+                        status: status as i32,
+                    }))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+                delay_for(Duration::from_secs(HEALTH_WATCH_INTERVAL_SECS)).await;
+            }
+        });
+        Ok(Response::new(status_receiver))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_reporter_status() {
+        let health_reporter = HealthReporter::default();
+        // Serving when no discovery is running
+        assert_eq!(health_reporter.status(), ServingStatus::Serving);
+        let interval = Duration::from_millis(10);
+        let heartbeat = health_reporter.heartbeat(interval);
+        heartbeat.beat();
+        std::thread::sleep(interval * (MISSED_HEARTBEATS_BEFORE_NOT_SERVING + 1));
+        assert_eq!(health_reporter.status(), ServingStatus::NotServing);
+        heartbeat.beat();
+        assert_eq!(health_reporter.status(), ServingStatus::Serving);
+        // A stuck discovery loop that has stopped no longer affects the status
+        std::thread::sleep(interval * (MISSED_HEARTBEATS_BEFORE_NOT_SERVING + 1));
+        drop(heartbeat);
+        assert_eq!(health_reporter.status(), ServingStatus::Serving);
+    }
+
+    #[tokio::test]
+    async fn test_health_service_check() {
+        let health_service = HealthService::new(HealthReporter::default());
+        let response = health_service
+            .check(Request::new(HealthCheckRequest {
+                service: "v0.DiscoveryHandler".to_string(),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(response.get_ref().status, ServingStatus::Serving as i32);
+        let status = health_service
+            .check(Request::new(HealthCheckRequest {
+                service: "unknown".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+}
@@ -1,7 +1,11 @@
 pub mod discovery;
 pub mod filtering;
+pub mod health;
 pub mod registration_client;
+pub mod security;
 
+#[macro_use]
+extern crate lazy_static;
 #[macro_use]
 extern crate serde_derive;
 
@@ -1,5 +1,6 @@
-use super::discovery::v0::{
-    registration_client::RegistrationClient, RegisterDiscoveryHandlerRequest,
+use super::{
+    discovery::v0::{registration_client::RegistrationClient, RegisterDiscoveryHandlerRequest},
+    security::{get_registration_token, REGISTRATION_TOKEN_METADATA_KEY},
 };
 use log::{info, trace};
 use std::convert::TryFrom;
@@ -21,7 +22,14 @@ pub async fn register_discovery_handler(
             .await
         {
             let mut client = RegistrationClient::new(channel);
-            let request = Request::new(register_request.clone());
+            let mut request = Request::new(register_request.clone());
+            // Present the registration token, if the Agent requires one
+            if let Some(token) = get_registration_token()? {
+                request.metadata_mut().insert(
+                    REGISTRATION_TOKEN_METADATA_KEY,
+                    format!("Bearer {}", token).parse()?,
+                );
+            }
             client.register_discovery_handler(request).await?;
             break;
         }
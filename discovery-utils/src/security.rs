@@ -0,0 +1,174 @@
+use std::path::Path;
+use tonic::{
+    metadata::MetadataMap,
+    transport::{Certificate, ClientTlsConfig, Identity, ServerTlsConfig},
+    Status,
+};
+
+/// Name of the environment variable that holds the directory containing the certificates used for mTLS between the
+/// Agent and network based Discovery Handlers. The directory is expected to be a mounted `kubernetes.io/tls` Secret
+/// (such as one issued by cert-manager) containing `tls.crt`, `tls.key`, and `ca.crt`.
+pub const DISCOVERY_HANDLER_TLS_DIRECTORY_LABEL: &str = "DISCOVERY_HANDLER_TLS_DIRECTORY";
+
+/// Name of the environment variable that optionally overrides the domain name the Agent expects in the certificates
+/// of network based Discovery Handlers. If not set, the host of the registered endpoint is used.
+pub const DISCOVERY_HANDLER_TLS_DOMAIN_NAME_LABEL: &str = "DISCOVERY_HANDLER_TLS_DOMAIN_NAME";
+
+/// Name of the environment variable that holds the path to a file containing the token Discovery Handlers must
+/// present when registering with the Agent. Usually a key of a mounted Secret.
+pub const REGISTRATION_TOKEN_PATH_LABEL: &str = "DISCOVERY_HANDLER_REGISTRATION_TOKEN_PATH";
+
+/// Metadata key under which a Discovery Handler sends its registration token as a bearer token
+pub const REGISTRATION_TOKEN_METADATA_KEY: &str = "authorization";
+
+/// Name of the certificate file in the TLS directory
+pub const TLS_CERT_FILE_NAME: &str = "tls.crt";
+
+/// Name of the private key file in the TLS directory
+pub const TLS_KEY_FILE_NAME: &str = "tls.key";
+
+/// Name of the certificate authority file in the TLS directory
+pub const CA_CERT_FILE_NAME: &str = "ca.crt";
+
+/// PEM encoded certificates used to secure a connection between the Agent and a network based Discovery Handler
+#[derive(Clone, Debug)]
+pub struct TlsFiles {
+    /// Certificate presented by this side of the connection
+    pub cert: Vec<u8>,
+    /// Private key of `cert`
+    pub key: Vec<u8>,
+    /// Certificate authority used to verify the other side of the connection
+    pub ca: Vec<u8>,
+}
+
+impl TlsFiles {
+    /// Reads the certificate, key, and certificate authority from a directory
+    pub fn read(directory: &Path) -> Result<Self, anyhow::Error> {
+        let read = |file_name: &str| {
+            std::fs::read(directory.join(file_name)).map_err(|e| {
+                anyhow::format_err!(
+                    "could not read {} from TLS directory {:?} with error {}",
+                    file_name,
+                    directory,
+                    e
+                )
+            })
+        };
+        Ok(TlsFiles {
+            cert: read(TLS_CERT_FILE_NAME)?,
+            key: read(TLS_KEY_FILE_NAME)?,
+            ca: read(CA_CERT_FILE_NAME)?,
+        })
+    }
+}
+
+/// Returns the certificates in the directory specified by `DISCOVERY_HANDLER_TLS_DIRECTORY` or None if TLS has not
+/// been configured. They are read on every call, so rotated certificates are picked up by new connections.
+pub fn get_tls_files() -> Result<Option<TlsFiles>, anyhow::Error> {
+    match std::env::var(DISCOVERY_HANDLER_TLS_DIRECTORY_LABEL) {
+        Ok(directory) => Ok(Some(TlsFiles::read(Path::new(&directory))?)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Creates the TLS configuration for a Discovery Handler server, which requires the Agent to present a certificate
+/// signed by the certificate authority.
+pub fn server_tls_config(tls_files: &TlsFiles) -> ServerTlsConfig {
+    ServerTlsConfig::new()
+        .identity(Identity::from_pem(&tls_files.cert, &tls_files.key))
+        .client_ca_root(Certificate::from_pem(&tls_files.ca))
+}
+
+/// Creates the TLS configuration the Agent uses to connect to a Discovery Handler server
+pub fn client_tls_config(tls_files: &TlsFiles, domain_name: Option<&str>) -> ClientTlsConfig {
+    let tls_config = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(&tls_files.ca))
+        .identity(Identity::from_pem(&tls_files.cert, &tls_files.key));
+    match domain_name {
+        Some(domain_name) => tls_config.domain_name(domain_name),
+        None => tls_config,
+    }
+}
+
+/// Returns the token read from the file specified by `DISCOVERY_HANDLER_REGISTRATION_TOKEN_PATH` or None if no token
+/// has been configured.
+pub fn get_registration_token() -> Result<Option<String>, anyhow::Error> {
+    match std::env::var(REGISTRATION_TOKEN_PATH_LABEL) {
+        Ok(path) => {
+            let token = std::fs::read_to_string(&path).map_err(|e| {
+                anyhow::format_err!(
+                    "could not read registration token from {} with error {}",
+                    path,
+                    e
+                )
+            })?;
+            Ok(Some(token.trim().to_string()))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+/// Checks that request metadata contains the expected registration token as a bearer token
+pub fn check_registration_token(
+    metadata: &MetadataMap,
+    expected_token: &str,
+) -> Result<(), Status> {
+    let presented_token = metadata
+        .get(REGISTRATION_TOKEN_METADATA_KEY)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| Status::unauthenticated("missing registration token"))?;
+    // Compare every byte so the time taken does not reveal how much of the token matched
+    let matches = presented_token.len() == expected_token.len()
+        && presented_token
+            .bytes()
+            .zip(expected_token.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0;
+    if matches {
+        Ok(())
+    } else {
+        Err(Status::unauthenticated("invalid registration token"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::Builder;
+
+    #[test]
+    fn test_tls_files_read() {
+        let tls_dir = Builder::new().prefix("tls").tempdir().unwrap();
+        std::fs::write(tls_dir.path().join(TLS_CERT_FILE_NAME), "cert").unwrap();
+        std::fs::write(tls_dir.path().join(TLS_KEY_FILE_NAME), "key").unwrap();
+        // Missing certificate authority should be an error
+        assert!(TlsFiles::read(tls_dir.path()).is_err());
+        std::fs::write(tls_dir.path().join(CA_CERT_FILE_NAME), "ca").unwrap();
+        let tls_files = TlsFiles::read(tls_dir.path()).unwrap();
+        assert_eq!(tls_files.cert, b"cert".to_vec());
+        assert_eq!(tls_files.key, b"key".to_vec());
+        assert_eq!(tls_files.ca, b"ca".to_vec());
+    }
+
+    #[test]
+    fn test_check_registration_token() {
+        let mut metadata = MetadataMap::new();
+        assert_eq!(
+            check_registration_token(&metadata, "secret")
+                .unwrap_err()
+                .code(),
+            tonic::Code::Unauthenticated
+        );
+        metadata.insert(
+            REGISTRATION_TOKEN_METADATA_KEY,
+            "Bearer wrong!".parse().unwrap(),
+        );
+        assert!(check_registration_token(&metadata, "secret").is_err());
+        metadata.insert(
+            REGISTRATION_TOKEN_METADATA_KEY,
+            "Bearer secret".parse().unwrap(),
+        );
+        assert!(check_registration_token(&metadata, "secret").is_ok());
+    }
+}
@@ -10,14 +10,15 @@ use super::API_NAMESPACE;
 use super::API_VERSION;
 use k8s_openapi::api::core::v1::PodSpec;
 use k8s_openapi::api::core::v1::ServiceSpec;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 use kube::{
-    api::{ListParams, Object, ObjectList, RawApi, Void},
+    api::{ListParams, Object, ObjectList, PatchParams, RawApi},
     client::APIClient,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-pub type KubeAkriConfig = Object<Configuration, Void>;
-pub type KubeAkriConfigList = ObjectList<Object<Configuration, Void>>;
+pub type KubeAkriConfig = Object<Configuration, ConfigurationStatus>;
+pub type KubeAkriConfigList = ObjectList<Object<Configuration, ConfigurationStatus>>;
 
 /// This specifies which `DiscoveryHandler` should be used for discovery
 /// and any details that need to be sent to the `DiscoveryHandler`.
@@ -30,6 +31,43 @@ pub struct DiscoveryHandlerInfo {
     pub discovery_details: String,
 }
 
+/// This specifies which properties of a device are set as labels and
+/// annotations on its Instance, so that Instances can be selected by them.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyMetadata {
+    /// Maps names of device properties to the keys of the labels that their values are set as
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    /// Maps names of device properties to the keys of the annotations that their values are set as
+    #[serde(default)]
+    pub annotations: HashMap<String, String>,
+    /// Whether the labels and annotations are also set on the broker Pods and Instance Services of an Instance
+    #[serde(default)]
+    pub propagate_to_brokers: bool,
+}
+
+/// This specifies a Secret that holds a credential for each device, such as
+/// the password of each camera, and how the credential of a device is
+/// delivered to the brokers that use it. Each broker is only given the
+/// credential of its own device.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceCredentials {
+    /// Name of the Secret, which must be in the Configuration's namespace
+    pub secret_name: String,
+    /// Device property whose value is the key of a device's entry in the Secret, such as
+    /// `ONVIF_DEVICE_MAC_ADDRESS`. Entries are keyed by device id when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_property: Option<String>,
+    /// Path of the file in broker containers that the credential is mounted as
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mount_path: Option<String>,
+    /// Name of the environment variable in broker containers that the credential is set as
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_var: Option<String>,
+}
+
 /// Defines the information in the Akri Configuration CRD
 ///
 /// A Configuration is the primary method for users to describe anticipated
@@ -76,6 +114,151 @@ pub struct Configuration {
     /// that represent the discovered resources.
     #[serde(default)]
     pub broker_properties: HashMap<String, String>,
+
+    /// This defines the path in broker containers at which the
+    /// broker properties are also mounted as files: a
+    /// `properties.json` with all of them plus one file per
+    /// property. Unlike environment variables, the files are
+    /// refreshed when the properties of a shared device's
+    /// Instance change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broker_properties_mount_path: Option<String>,
+
+    /// This defines how the names of Instances are built from the
+    /// properties of the devices they represent, such as
+    /// `{{ONVIF_DEVICE_MAC_ADDRESS}}`. The rendered template is
+    /// made a valid DNS-1123 label, appended to the
+    /// Configuration's name and followed by a digest of the
+    /// device's id. Devices that lack a referenced property are
+    /// named with the digest alone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_name_template: Option<String>,
+
+    /// This defines the number of bytes of the digest of a
+    /// device's id that is used to name its Instance. Each
+    /// byte adds two hexadecimal characters to the name.
+    #[serde(default = "default_instance_digest_length")]
+    pub instance_digest_length: usize,
+
+    /// This defines which properties of discovered devices
+    /// are set as labels and annotations on their Instances
+    /// and, optionally, on their broker Pods and Services.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub property_metadata: Option<PropertyMetadata>,
+
+    /// This defines a Secret with a credential for each
+    /// discovered device, which is given only to the brokers
+    /// that use that device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_credentials: Option<DeviceCredentials>,
+}
+
+/// Returns the labels and annotations that `PropertyMetadata` derives from the properties of a device. Properties that
+/// the device does not have are skipped. Label keys and values and annotation keys are made valid, while annotation
+/// values are used as they are.
+pub fn get_property_labels_and_annotations(
+    property_metadata: &PropertyMetadata,
+    properties: &HashMap<String, String>,
+) -> (BTreeMap<String, String>, BTreeMap<String, String>) {
+    let labels = property_metadata
+        .labels
+        .iter()
+        .filter_map(|(property, key)| {
+            let value = properties.get(property)?;
+            Some((to_label_key(key)?, to_label_value(value)))
+        })
+        .collect();
+    let annotations = property_metadata
+        .annotations
+        .iter()
+        .filter_map(|(property, key)| {
+            let value = properties.get(property)?;
+            Some((to_label_key(key)?, value.clone()))
+        })
+        .collect();
+    (labels, annotations)
+}
+
+/// Adds the labels and annotations that `PropertyMetadata` derives from the properties of a device to the metadata of
+/// a broker Pod or Service if they are to be propagated to brokers. Labels and annotations that are already set, such
+/// as the ones Akri uses to select broker Pods, are never replaced.
+pub fn add_property_labels_and_annotations(
+    metadata: &mut ObjectMeta,
+    property_metadata: &PropertyMetadata,
+    properties: &HashMap<String, String>,
+) {
+    if !property_metadata.propagate_to_brokers {
+        return;
+    }
+    let (labels, annotations) = get_property_labels_and_annotations(property_metadata, properties);
+    let existing_labels = metadata.labels.get_or_insert_with(BTreeMap::new);
+    for (key, value) in labels {
+        existing_labels.entry(key).or_insert(value);
+    }
+    let existing_annotations = metadata.annotations.get_or_insert_with(BTreeMap::new);
+    for (key, value) in annotations {
+        existing_annotations.entry(key).or_insert(value);
+    }
+}
+
+/// Makes a string a valid label or annotation key, which is a name optionally prefixed with a DNS subdomain and '/'.
+/// Returns None if nothing is left of the name.
+pub fn to_label_key(key: &str) -> Option<String> {
+    let (prefix, name) = match key.rfind('/') {
+        Some(index) => (&key[..index], &key[index + 1..]),
+        None => ("", key),
+    };
+    let name = to_label_value(name);
+    if name.is_empty() {
+        return None;
+    }
+    let prefix: String = prefix
+        .to_ascii_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .split('.')
+        .map(|segment| segment.trim_matches('-'))
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<&str>>()
+        .join(".");
+    if prefix.is_empty() {
+        Some(name)
+    } else {
+        Some(format!(
+            "{}/{}",
+            prefix
+                .chars()
+                .take(253)
+                .collect::<String>()
+                .trim_end_matches(|c: char| c == '.' || c == '-'),
+            name
+        ))
+    }
+}
+
+/// Makes a string a valid label value by replacing characters other than letters, digits, '-', '_' and '.' with '-',
+/// cutting it to 63 characters and removing characters other than letters and digits from both ends
+pub fn to_label_value(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .take(63)
+        .collect::<String>()
+        .trim_matches(|c: char| !c.is_ascii_alphanumeric())
+        .to_string()
 }
 
 /// Get Configurations for a given namespace
@@ -181,10 +364,89 @@ pub async fn find_configuration(
     }
 }
 
+/// Defines the status of an Akri Configuration, which is written by Agents
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigurationStatus {
+    /// This describes why the Configuration's discovery details are not
+    /// valid for its `DiscoveryHandler`, if they are not. It is not
+    /// skipped when `None` so that updating the status clears it.
+    #[serde(default)]
+    pub discovery_details_error: Option<String>,
+}
+
+/// Update the status of a Configuration with given name and namespace
+///
+/// Example:
+///
+/// ```no_run
+/// use akri_shared::akri::configuration;
+/// use kube::client::APIClient;
+/// use kube::config;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let api_client = APIClient::new(config::incluster_config().unwrap());
+/// configuration::update_configuration_status(
+///     &configuration::ConfigurationStatus {
+///         discovery_details_error: None,
+///     },
+///     "dcc-1",
+///     "default",
+///     &api_client).await.unwrap();
+/// # }
+/// ```
+pub async fn update_configuration_status(
+    status: &ConfigurationStatus,
+    name: &str,
+    namespace: &str,
+    kube_client: &APIClient,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    log::trace!("update_configuration_status enter");
+    let akri_config_type = RawApi::customResource(API_CONFIGURATIONS)
+        .group(API_NAMESPACE)
+        .version(API_VERSION)
+        .within(&namespace);
+    let status_patch = serde_json::to_vec(&serde_json::json!({ "status": status }))?;
+
+    log::trace!("update_configuration_status kube_client.request::<KubeAkriConfig>(akri_config_type.patch_status(...)?).await?");
+    match kube_client
+        .request::<KubeAkriConfig>(akri_config_type.patch_status(
+            &name,
+            &PatchParams::default(),
+            status_patch,
+        )?)
+        .await
+    {
+        Ok(_config_modified) => {
+            log::trace!("update_configuration_status return");
+            Ok(())
+        }
+        Err(kube::Error::Api(ae)) => {
+            log::trace!(
+                "update_configuration_status kube_client.request returned kube error: {:?}",
+                ae
+            );
+            Err(ae.into())
+        }
+        Err(e) => {
+            log::trace!(
+                "update_configuration_status kube_client.request error: {:?}",
+                e
+            );
+            Err(e.into())
+        }
+    }
+}
+
 fn default_capacity() -> i32 {
     1
 }
 
+fn default_instance_digest_length() -> usize {
+    3
+}
+
 #[cfg(test)]
 mod crd_serialization_tests {
     use super::super::super::os::file;
@@ -228,6 +490,14 @@ mod crd_serialization_tests {
         assert_eq!(None, deserialized.instance_service_spec);
         assert_eq!(None, deserialized.configuration_service_spec);
         assert_eq!(0, deserialized.broker_properties.len());
+        assert_eq!(None, deserialized.instance_name_template);
+        assert_eq!(
+            default_instance_digest_length(),
+            deserialized.instance_digest_length
+        );
+        assert_eq!(None, deserialized.property_metadata);
+        assert_eq!(None, deserialized.broker_properties_mount_path);
+        assert_eq!(None, deserialized.device_credentials);
     }
 
     #[test]
@@ -243,7 +513,7 @@ mod crd_serialization_tests {
         assert_eq!(0, deserialized.broker_properties.len());
 
         let serialized = serde_json::to_string(&deserialized).unwrap();
-        let expected_deserialized = r#"{"discoveryHandler":{"name":"random","discoveryDetails":""},"capacity":4,"brokerProperties":{}}"#;
+        let expected_deserialized = r#"{"discoveryHandler":{"name":"random","discoveryDetails":""},"capacity":4,"brokerProperties":{},"instanceDigestLength":3}"#;
         assert_eq!(expected_deserialized, serialized);
     }
 
@@ -333,4 +603,81 @@ mod crd_serialization_tests {
         assert_ne!(None, deserialized.configuration_service_spec);
         assert_eq!(2, deserialized.broker_properties.len());
     }
+
+    #[test]
+    fn test_property_labels_and_annotations() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let json = r#"{
+            "labels": {
+                "ONVIF_DEVICE_MODEL": "example.com/model",
+                "ONVIF_DEVICE_SCOPES": "Example.COM/scope",
+                "MISSING": "example.com/missing"
+            },
+            "annotations": { "ONVIF_DEVICE_SCOPES": "example.com/scopes" },
+            "propagateToBrokers": true
+        }"#;
+        let property_metadata: PropertyMetadata = serde_json::from_str(json).unwrap();
+        let properties: HashMap<String, String> = vec![
+            ("ONVIF_DEVICE_MODEL", "Camera Model 2000"),
+            (
+                "ONVIF_DEVICE_SCOPES",
+                "onvif://www.onvif.org/location/building-7",
+            ),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        let (labels, annotations) =
+            get_property_labels_and_annotations(&property_metadata, &properties);
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels["example.com/model"], "Camera-Model-2000");
+        assert_eq!(
+            labels["example.com/scope"],
+            "onvif---www.onvif.org-location-building-7"
+        );
+        assert_eq!(
+            annotations["example.com/scopes"],
+            "onvif://www.onvif.org/location/building-7"
+        );
+
+        // Labels that are already set are kept
+        let mut metadata = ObjectMeta::default();
+        let mut existing_labels = BTreeMap::new();
+        existing_labels.insert("example.com/model".to_string(), "kept".to_string());
+        metadata.labels = Some(existing_labels);
+        add_property_labels_and_annotations(&mut metadata, &property_metadata, &properties);
+        assert_eq!(
+            metadata.labels.as_ref().unwrap()["example.com/model"],
+            "kept"
+        );
+        assert_eq!(metadata.labels.as_ref().unwrap().len(), 2);
+        assert_eq!(metadata.annotations.as_ref().unwrap().len(), 1);
+
+        // Nothing is added to brokers unless it is to be propagated
+        let property_metadata = PropertyMetadata {
+            propagate_to_brokers: false,
+            ..property_metadata
+        };
+        let mut metadata = ObjectMeta::default();
+        add_property_labels_and_annotations(&mut metadata, &property_metadata, &properties);
+        assert_eq!(metadata, ObjectMeta::default());
+    }
+
+    #[test]
+    fn test_to_label_key_and_value() {
+        assert_eq!(to_label_key("model"), Some("model".to_string()));
+        assert_eq!(
+            to_label_key("Example.com/Model Name"),
+            Some("example.com/Model-Name".to_string())
+        );
+        assert_eq!(
+            to_label_key("-my_org-.io/scope"),
+            Some("my-org.io/scope".to_string())
+        );
+        assert_eq!(to_label_key("example.com/"), None);
+        assert_eq!(to_label_value("_value with spaces_"), "value-with-spaces");
+        assert_eq!(to_label_value(&"a".repeat(100)).len(), 63);
+        assert_eq!(to_label_value("///"), "");
+    }
 }
@@ -1,4 +1,4 @@
-use super::{API_INSTANCES, API_NAMESPACE, API_VERSION};
+use super::{AKRI_CORDONED_ANNOTATION_NAME, API_INSTANCES, API_NAMESPACE, API_VERSION};
 use kube::{
     api::{
         DeleteParams, ListParams, Object, ObjectList, ObjectMeta, OwnerReference, PatchParams,
@@ -6,11 +6,18 @@ use kube::{
     },
     client::APIClient,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 pub type KubeAkriInstance = Object<Instance, Void>;
 pub type KubeAkriInstanceList = ObjectList<Object<Instance, Void>>;
 
+/// Maximum length of an Instance name that is built from an `instanceNameTemplate`. Instance names are also the value
+/// of the `akri.sh/instance` label of broker Pods, which cannot be longer than a DNS-1123 label.
+pub const MAX_INSTANCE_NAME_LENGTH: usize = 63;
+
+/// Maximum number of bytes of the digest of a device's id that is used in an Instance name
+pub const MAX_INSTANCE_DIGEST_LENGTH: usize = 16;
+
 /// Defines the information in the Instance CRD
 ///
 /// An Instance is a specific instance described by
@@ -182,6 +189,8 @@ pub async fn find_instance(
 ///     "default",
 ///     "config-1",
 ///     "abcdefgh-ijkl-mnop-qrst-uvwxyz012345",
+///     &std::collections::BTreeMap::new(),
+///     &std::collections::BTreeMap::new(),
 ///     &api_client).await.unwrap();
 /// # }
 /// ```
@@ -191,6 +200,8 @@ pub async fn create_instance(
     namespace: &str,
     owner_config_name: &str,
     owner_config_uid: &str,
+    labels: &BTreeMap<String, String>,
+    annotations: &BTreeMap<String, String>,
     kube_client: &APIClient,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
     log::trace!("create_instance enter");
@@ -202,6 +213,8 @@ pub async fn create_instance(
     let kube_instance = KubeAkriInstance {
         metadata: ObjectMeta {
             name: name.to_string(),
+            labels: labels.clone(),
+            annotations: annotations.clone(),
             ownerReferences: vec![OwnerReference {
                 apiVersion: format!("{}/{}", API_NAMESPACE, API_VERSION),
                 kind: "Configuration".to_string(),
@@ -378,6 +391,136 @@ pub async fn update_instance(
     }
 }
 
+/// Returns whether an Instance has been cordoned, in which case no new broker Pods are scheduled to it
+pub fn is_cordoned(instance: &KubeAkriInstance) -> bool {
+    instance
+        .metadata
+        .annotations
+        .get(AKRI_CORDONED_ANNOTATION_NAME)
+        .map_or(false, |cordoned| cordoned == "true")
+}
+
+/// Returns the names of the device properties that an `instanceNameTemplate` references as `{{NAME}}`, or why the
+/// template cannot be rendered
+pub fn get_instance_name_template_properties(template: &str) -> Result<Vec<String>, String> {
+    let mut properties = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after_start = &rest[start + 2..];
+        let end = after_start
+            .find("}}")
+            .ok_or_else(|| format!("\"{}\" has a {{{{ that is not closed", template))?;
+        let property = after_start[..end].trim();
+        if property.is_empty() {
+            return Err(format!(
+                "\"{}\" references an empty property name",
+                template
+            ));
+        }
+        properties.push(property.to_string());
+        rest = &after_start[end + 2..];
+    }
+    Ok(properties)
+}
+
+/// Renders an `instanceNameTemplate` by replacing each `{{NAME}}` with the value that `lookup` returns for the
+/// property and makes the result a valid DNS-1123 label. Returns None if a property is not found or nothing is left of
+/// the rendered template.
+pub fn render_instance_name_template(
+    template: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after_start = &rest[start + 2..];
+        let end = after_start.find("}}")?;
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(&lookup(after_start[..end].trim())?);
+        rest = &after_start[end + 2..];
+    }
+    rendered.push_str(rest);
+    let label = to_dns_label(&rendered, MAX_INSTANCE_NAME_LENGTH);
+    if label.is_empty() {
+        None
+    } else {
+        Some(label)
+    }
+}
+
+/// Makes a string a valid DNS-1123 label of at most `max_length` characters by lowercasing it, replacing every run of
+/// characters other than letters and digits with a single '-' and removing '-' from both ends.
+pub fn to_dns_label(value: &str, max_length: usize) -> String {
+    let mut label = String::new();
+    for c in value.chars().map(|c| c.to_ascii_lowercase()) {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() {
+            label.push(c);
+        } else if !label.is_empty() && !label.ends_with('-') {
+            label.push('-');
+        }
+    }
+    label
+        .chars()
+        .take(max_length)
+        .collect::<String>()
+        .trim_end_matches('-')
+        .to_string()
+}
+
+/// Cordon or uncordon Instance by setting or removing its `akri.sh/cordoned` annotation
+///
+/// Example:
+///
+/// ```no_run
+/// use akri_shared::akri::instance;
+/// use kube::client::APIClient;
+/// use kube::config;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let api_client = APIClient::new(config::incluster_config().unwrap());
+/// instance::cordon_instance(
+///     "instance-1",
+///     "default",
+///     true,
+///     &api_client).await.unwrap();
+/// # }
+/// ```
+pub async fn cordon_instance(
+    name: &str,
+    namespace: &str,
+    cordoned: bool,
+    kube_client: &APIClient,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    log::trace!("cordon_instance enter");
+    let akri_instance_type = RawApi::customResource(API_INSTANCES)
+        .group(API_NAMESPACE)
+        .version(API_VERSION)
+        .within(&namespace);
+
+    // Merge patch that sets the annotation or, with a null value, removes it
+    let patch = serde_json::json!({
+        "metadata": {
+            "annotations": {
+                AKRI_CORDONED_ANNOTATION_NAME: if cordoned { Some("true") } else { None }
+            }
+        }
+    });
+    log::trace!("cordon_instance patch: {}", patch);
+    let patch_request =
+        akri_instance_type.patch(name, &PatchParams::default(), serde_json::to_vec(&patch)?)?;
+    match kube_client.request::<KubeAkriInstance>(patch_request).await {
+        Ok(_instance_modified) => {
+            log::trace!("cordon_instance return");
+            Ok(())
+        }
+        Err(e) => {
+            log::trace!("cordon_instance kube_client.request error: {:?}", e);
+            Err(e.into())
+        }
+    }
+}
+
 fn default_shared() -> bool {
     false
 }
@@ -471,4 +614,77 @@ mod crd_serializeation_tests {
             let _ = serde_json::to_string(&deserialized).unwrap();
         }
     }
+
+    #[test]
+    fn test_is_cordoned() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let instance_json = |annotations: &str| {
+            format!(
+                r#"{{"apiVersion":"akri.sh/v0","kind":"Instance","metadata":{{"name":"foo","namespace":"default","annotations":{}}},"spec":{{"configurationName":"foo"}}}}"#,
+                annotations
+            )
+        };
+        let instance: KubeAkriInstance = serde_json::from_str(&instance_json("{}")).unwrap();
+        assert!(!is_cordoned(&instance));
+        let instance: KubeAkriInstance =
+            serde_json::from_str(&instance_json(r#"{"akri.sh/cordoned":"true"}"#)).unwrap();
+        assert!(is_cordoned(&instance));
+        let instance: KubeAkriInstance =
+            serde_json::from_str(&instance_json(r#"{"akri.sh/cordoned":"false"}"#)).unwrap();
+        assert!(!is_cordoned(&instance));
+    }
+
+    #[test]
+    fn test_instance_name_template_properties() {
+        assert_eq!(
+            get_instance_name_template_properties("cam-{{ONVIF_DEVICE_MAC_ADDRESS}}").unwrap(),
+            vec!["ONVIF_DEVICE_MAC_ADDRESS".to_string()]
+        );
+        assert_eq!(
+            get_instance_name_template_properties("{{ A }}-{{B}}").unwrap(),
+            vec!["A".to_string(), "B".to_string()]
+        );
+        assert!(get_instance_name_template_properties("static")
+            .unwrap()
+            .is_empty());
+        assert!(get_instance_name_template_properties("{{A").is_err());
+        assert!(get_instance_name_template_properties("{{ }}").is_err());
+    }
+
+    #[test]
+    fn test_render_instance_name_template() {
+        let properties: HashMap<String, String> = vec![
+            ("MAC".to_string(), "AA:BB:CC:00:11:22".to_string()),
+            ("SERIAL".to_string(), "Logitech_Webcam_C270_#1".to_string()),
+            ("EMPTY".to_string(), "".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let lookup = |name: &str| properties.get(name).cloned();
+        assert_eq!(
+            render_instance_name_template("{{MAC}}", lookup),
+            Some("aa-bb-cc-00-11-22".to_string())
+        );
+        assert_eq!(
+            render_instance_name_template("usb-{{SERIAL}}", lookup),
+            Some("usb-logitech-webcam-c270-1".to_string())
+        );
+        assert_eq!(render_instance_name_template("{{MISSING}}", lookup), None);
+        assert_eq!(render_instance_name_template("{{EMPTY}}", lookup), None);
+        assert_eq!(render_instance_name_template("{{MAC", lookup), None);
+        assert_eq!(
+            render_instance_name_template(&"x".repeat(100), lookup)
+                .unwrap()
+                .len(),
+            MAX_INSTANCE_NAME_LENGTH
+        );
+    }
+
+    #[test]
+    fn test_to_dns_label() {
+        assert_eq!(to_dns_label("--Foo..Bar__", 63), "foo-bar");
+        assert_eq!(to_dns_label("abc-def", 4), "abc");
+        assert_eq!(to_dns_label("ÄÖ", 63), "");
+    }
 }
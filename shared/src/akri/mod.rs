@@ -10,6 +10,11 @@ pub const API_INSTANCES: &str = "instances";
 pub const AKRI_PREFIX: &str = "akri.sh";
 /// Container Annotation name used to store slot name
 pub const AKRI_SLOT_ANNOTATION_NAME: &str = "akri.agent.slot";
+/// Instance Annotation that stops new broker Pods from being scheduled to an Instance
+pub const AKRI_CORDONED_ANNOTATION_NAME: &str = "akri.sh/cordoned";
+/// Instance Annotation that records the id of the device that an Instance represents, so that a device whose Instance
+/// name is taken by another device's Instance can be given a different name
+pub const AKRI_DEVICE_ID_ANNOTATION_NAME: &str = "akri.sh/device-id";
 
 pub mod configuration;
 pub mod instance;
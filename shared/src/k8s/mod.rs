@@ -1,6 +1,6 @@
 use super::akri::{
     configuration,
-    configuration::{KubeAkriConfig, KubeAkriConfigList},
+    configuration::{ConfigurationStatus, KubeAkriConfig, KubeAkriConfigList},
     instance,
     instance::{Instance, KubeAkriInstance, KubeAkriInstanceList},
     retry::{random_delay, MAX_INSTANCE_UPDATE_TRIES},
@@ -9,7 +9,7 @@ use super::akri::{
 use async_trait::async_trait;
 use futures::executor::block_on;
 use k8s_openapi::api::core::v1::{
-    NodeSpec, NodeStatus, Pod, PodSpec, PodStatus, Service, ServiceSpec, ServiceStatus,
+    NodeSpec, NodeStatus, Pod, PodSpec, PodStatus, Secret, Service, ServiceSpec, ServiceStatus,
 };
 use kube::{
     api::{Object, ObjectList},
@@ -17,9 +17,11 @@ use kube::{
     config,
 };
 use mockall::{automock, predicate::*};
+use std::collections::BTreeMap;
 
 pub mod node;
 pub mod pod;
+pub mod secret;
 pub mod service;
 
 pub const NODE_SELECTOR_OP_IN: &str = "In";
@@ -151,6 +153,12 @@ pub trait KubeInterface: Send + Sync {
         namespace: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>;
 
+    async fn find_secret(
+        &self,
+        name: &str,
+        namespace: &str,
+    ) -> Result<Secret, Box<dyn std::error::Error + Send + Sync + 'static>>;
+
     async fn find_configuration(
         &self,
         name: &str,
@@ -159,6 +167,12 @@ pub trait KubeInterface: Send + Sync {
     async fn get_configurations(
         &self,
     ) -> Result<KubeAkriConfigList, Box<dyn std::error::Error + Send + Sync + 'static>>;
+    async fn update_configuration_status(
+        &self,
+        status: &ConfigurationStatus,
+        name: &str,
+        namespace: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>;
 
     async fn find_instance(
         &self,
@@ -175,6 +189,8 @@ pub trait KubeInterface: Send + Sync {
         namespace: &str,
         owner_config_name: &str,
         owner_config_uid: &str,
+        labels: &BTreeMap<String, String>,
+        annotations: &BTreeMap<String, String>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>;
     async fn delete_instance(
         &self,
@@ -187,6 +203,12 @@ pub trait KubeInterface: Send + Sync {
         name: &str,
         namespace: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>;
+    async fn cordon_instance(
+        &self,
+        name: &str,
+        namespace: &str,
+        cordoned: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>;
 }
 
 /// Create new KubeInetrace implementation
@@ -194,6 +216,12 @@ pub fn create_kube_interface() -> impl KubeInterface {
     KubeImpl::new()
 }
 
+/// Create new KubeInterface implementation, returning an error rather than panicking if no Kubernetes config can be
+/// loaded, such as when a Discovery Handler that only needs Kubernetes for some Configurations runs outside a cluster
+pub fn try_create_kube_interface() -> Result<impl KubeInterface, kube::Error> {
+    KubeImpl::try_new()
+}
+
 #[derive(Clone)]
 struct KubeImpl {
     kube_configuration: kube::config::Configuration,
@@ -202,18 +230,23 @@ struct KubeImpl {
 impl KubeImpl {
     /// Create new instance of KubeImpl
     fn new() -> Self {
-        KubeImpl {
+        KubeImpl::try_new().unwrap()
+    }
+
+    /// Create new instance of KubeImpl, returning an error if no Kubernetes config can be loaded
+    fn try_new() -> Result<Self, kube::Error> {
+        Ok(KubeImpl {
             kube_configuration: match std::env::var("KUBERNETES_PORT") {
                 Ok(_val) => {
                     log::trace!("Loading in-cluster config");
-                    config::incluster_config().unwrap() // pub fn incluster_config() -> Result<Configuration> {
+                    config::incluster_config()? // pub fn incluster_config() -> Result<Configuration> {
                 }
                 Err(_e) => {
                     log::trace!("Loading config file");
-                    block_on(config::load_kube_config()).unwrap() // pub async fn load_kube_config() -> Result<Configuration>
+                    block_on(config::load_kube_config())? // pub async fn load_kube_config() -> Result<Configuration>
                 }
             },
-        }
+        })
     }
 }
 
@@ -434,6 +467,28 @@ impl KubeInterface for KubeImpl {
         service::update_service(svc_to_update, name, namespace, self.get_kube_client()).await
     }
 
+    /// Get Kubernetes Secret with given name and namespace
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use akri_shared::k8s;
+    /// use akri_shared::k8s::KubeInterface;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let kube = k8s::create_kube_interface();
+    /// let secret = kube.find_secret("onvif-credentials", "default").await.unwrap();
+    /// # }
+    /// ```
+    async fn find_secret(
+        &self,
+        name: &str,
+        namespace: &str,
+    ) -> Result<Secret, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        secret::find_secret(name, namespace, self.get_kube_client()).await
+    }
+
     // Get Akri Configuration with given name and namespace
     ///
     /// Example:
@@ -474,6 +529,36 @@ impl KubeInterface for KubeImpl {
     ) -> Result<KubeAkriConfigList, Box<dyn std::error::Error + Send + Sync + 'static>> {
         configuration::get_configurations(&self.get_kube_client()).await
     }
+    // Update the status of an Akri Configuration with given name and namespace
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use akri_shared::k8s;
+    /// use akri_shared::k8s::KubeInterface;
+    /// use akri_shared::akri::configuration::ConfigurationStatus;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let kube = k8s::create_kube_interface();
+    /// kube.update_configuration_status(
+    ///     &ConfigurationStatus {
+    ///         discovery_details_error: Some("missing field `udevRules`".to_string()),
+    ///     },
+    ///     "dcc-1",
+    ///     "dcc-namespace"
+    /// ).await.unwrap();
+    /// # }
+    /// ```
+    async fn update_configuration_status(
+        &self,
+        status: &ConfigurationStatus,
+        name: &str,
+        namespace: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        configuration::update_configuration_status(status, name, namespace, &self.get_kube_client())
+            .await
+    }
 
     // Get Akri Instance with given name and namespace
     ///
@@ -538,7 +623,9 @@ impl KubeInterface for KubeImpl {
     ///     "instance-1",
     ///     "instance-namespace",
     ///     "config-1",
-    ///     "abcdefgh-ijkl-mnop-qrst-uvwxyz012345"
+    ///     "abcdefgh-ijkl-mnop-qrst-uvwxyz012345",
+    ///     &std::collections::BTreeMap::new(),
+    ///     &std::collections::BTreeMap::new(),
     /// ).await.unwrap();
     /// # }
     /// ```
@@ -549,6 +636,8 @@ impl KubeInterface for KubeImpl {
         namespace: &str,
         owner_config_name: &str,
         owner_config_uid: &str,
+        labels: &BTreeMap<String, String>,
+        annotations: &BTreeMap<String, String>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
         instance::create_instance(
             instance_to_create,
@@ -556,6 +645,8 @@ impl KubeInterface for KubeImpl {
             namespace,
             owner_config_name,
             owner_config_uid,
+            labels,
+            annotations,
             &self.get_kube_client(),
         )
         .await
@@ -618,6 +709,32 @@ impl KubeInterface for KubeImpl {
         instance::update_instance(instance_to_update, name, namespace, &self.get_kube_client())
             .await
     }
+    /// Cordon or uncordon Akri Instance
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use akri_shared::k8s;
+    /// use akri_shared::k8s::KubeInterface;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let kube = k8s::create_kube_interface();
+    /// kube.cordon_instance(
+    ///     "instance-1",
+    ///     "instance-namespace",
+    ///     true
+    /// ).await.unwrap();
+    /// # }
+    /// ```
+    async fn cordon_instance(
+        &self,
+        name: &str,
+        namespace: &str,
+        cordoned: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        instance::cordon_instance(name, namespace, cordoned, &self.get_kube_client()).await
+    }
 }
 
 /// This deletes an Instance unless it has already been deleted by another node
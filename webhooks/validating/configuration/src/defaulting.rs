@@ -0,0 +1,411 @@
+use akri_debug_echo::discovery_handler::DebugEchoDiscoveryDetails;
+use akri_discovery_utils::discovery::discovery_handler::deserialize_discovery_details;
+use akri_onvif::discovery_handler::OnvifDiscoveryDetails;
+use akri_opcua::discovery_handler::OpcuaDiscoveryDetails;
+use akri_shared::{akri::configuration::Configuration, k8s::RESOURCE_REQUIREMENTS_KEY};
+use akri_udev::discovery_handler::UdevDiscoveryDetails;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+
+/// Label that records which Discovery Handler a Configuration uses, so that Configurations can be selected by it
+pub const DISCOVERY_HANDLER_LABEL_NAME: &str = "akri.sh/discovery-handler";
+/// Kubernetes' recommended label for the application that an object is a part of
+pub const PART_OF_LABEL_NAME: &str = "app.kubernetes.io/part-of";
+/// Value of the `app.kubernetes.io/part-of` label set on Configurations
+pub const PART_OF_LABEL_VALUE: &str = "akri";
+/// Quantity of the Akri resource limited by the first broker container when no container requests it
+pub const DEFAULT_RESOURCE_LIMIT: &str = "1";
+
+/// Creates the JSON patch (RFC 6902) operations that explicitly set every value of a Configuration that would
+/// otherwise be defaulted by the Agent, Controller or Discovery Handler, so that the stored Configuration is the one
+/// that is acted upon. Values set by the user are never changed. discoveryDetails that the Discovery Handler cannot
+/// parse are left for the validating webhook to reject.
+pub fn default_configuration(raw: &Value, config: &Configuration) -> Vec<Value> {
+    let mut patch = Vec::new();
+    default_labels(raw, config, &mut patch);
+    let spec = &raw["spec"];
+    if spec.get("capacity").is_none() {
+        patch.push(add("/spec/capacity", json!(config.capacity)));
+    }
+    if spec.get("instanceDigestLength").is_none() {
+        patch.push(add(
+            "/spec/instanceDigestLength",
+            json!(config.instance_digest_length),
+        ));
+    }
+    if let Ok(Some(discovery_details)) = default_discovery_details(
+        &config.discovery_handler.name,
+        &config.discovery_handler.discovery_details,
+    ) {
+        let op = if spec["discoveryHandler"].get("discoveryDetails").is_some() {
+            replace
+        } else {
+            add
+        };
+        patch.push(op(
+            "/spec/discoveryHandler/discoveryDetails",
+            json!(discovery_details),
+        ));
+    }
+    default_broker_resources(spec, &mut patch);
+    patch
+}
+
+fn add(path: &str, value: Value) -> Value {
+    json!({ "op": "add", "path": path, "value": value })
+}
+
+fn replace(path: &str, value: Value) -> Value {
+    json!({ "op": "replace", "path": path, "value": value })
+}
+
+/// Escapes a key so it can be used as a segment of a JSON pointer (RFC 6901)
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn default_labels(raw: &Value, config: &Configuration, patch: &mut Vec<Value>) {
+    let standard_labels = vec![
+        (PART_OF_LABEL_NAME, PART_OF_LABEL_VALUE),
+        (
+            DISCOVERY_HANDLER_LABEL_NAME,
+            config.discovery_handler.name.as_str(),
+        ),
+    ];
+    match raw["metadata"].get("labels").and_then(Value::as_object) {
+        Some(labels) => {
+            for (name, value) in standard_labels {
+                if !labels.contains_key(name) {
+                    patch.push(add(
+                        &format!("/metadata/labels/{}", escape(name)),
+                        json!(value),
+                    ));
+                }
+            }
+        }
+        None => {
+            let labels: serde_json::Map<String, Value> = standard_labels
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), json!(value)))
+                .collect();
+            patch.push(add("/metadata/labels", Value::Object(labels)));
+        }
+    }
+}
+
+/// Returns the discoveryDetails with the defaults of Akri's built-in Discovery Handlers filled in or `None` if no
+/// defaults are missing. Discovery Handlers that are not built into Akri are left untouched.
+fn default_discovery_details(
+    discovery_handler_name: &str,
+    discovery_details: &str,
+) -> Result<Option<String>, anyhow::Error> {
+    match discovery_handler_name {
+        akri_debug_echo::DISCOVERY_HANDLER_NAME => {
+            fill_in_defaults::<DebugEchoDiscoveryDetails>(discovery_details)
+        }
+        akri_onvif::DISCOVERY_HANDLER_NAME => {
+            fill_in_defaults::<OnvifDiscoveryDetails>(discovery_details)
+        }
+        akri_opcua::DISCOVERY_HANDLER_NAME => {
+            fill_in_defaults::<OpcuaDiscoveryDetails>(discovery_details)
+        }
+        akri_udev::DISCOVERY_HANDLER_NAME => {
+            fill_in_defaults::<UdevDiscoveryDetails>(discovery_details)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Appends the top-level fields that the Discovery Handler's deserialization defaults to the discoveryDetails as
+/// written by the user, so that their comments and formatting are kept. Fields set by the user, including ones whose
+/// nested fields are defaulted, are kept as is. Returns `None` if no fields are missing or if the details cannot be
+/// extended by appending to them, for example because they are written in flow style.
+fn fill_in_defaults<T: DeserializeOwned + Serialize>(
+    discovery_details: &str,
+) -> Result<Option<String>, anyhow::Error> {
+    let defaulted = serde_yaml::to_value(deserialize_discovery_details::<T>(discovery_details)?)?;
+    let details = if discovery_details.trim().is_empty() {
+        serde_yaml::Mapping::new()
+    } else {
+        match serde_yaml::from_str(discovery_details)? {
+            serde_yaml::Value::Mapping(details) => details,
+            serde_yaml::Value::Null => serde_yaml::Mapping::new(),
+            _ => return Ok(None),
+        }
+    };
+    let missing: serde_yaml::Mapping = match defaulted.as_mapping() {
+        Some(defaulted) => defaulted
+            .iter()
+            .filter(|(key, _)| !details.contains_key(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect(),
+        None => return Ok(None),
+    };
+    if missing.is_empty() {
+        return Ok(None);
+    }
+    let missing_yaml = format!(
+        "{}\n",
+        serde_yaml::to_string(&missing)?
+            .trim_start_matches("---\n")
+            .trim_end()
+    );
+    let defaulted_details = if details.is_empty() {
+        missing_yaml
+    } else if discovery_details.ends_with('\n') {
+        format!("{}{}", discovery_details, missing_yaml)
+    } else {
+        format!("{}\n{}", discovery_details, missing_yaml)
+    };
+    let mut expected = details;
+    expected.extend(missing);
+    match serde_yaml::from_str::<serde_yaml::Value>(&defaulted_details) {
+        Ok(serde_yaml::Value::Mapping(appended)) if appended == expected => {
+            Ok(Some(defaulted_details))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Makes broker containers that request the Akri resource in `resources.requests` also limit it by the same quantity,
+/// as Kubernetes requires for extended resources. If no container requests the resource at all, the first container
+/// gets a limit of `DEFAULT_RESOURCE_LIMIT`, as in the Configurations of the Helm chart.
+fn default_broker_resources(spec: &Value, patch: &mut Vec<Value>) {
+    let containers = match spec["brokerPodSpec"]["containers"].as_array() {
+        Some(containers) if !containers.is_empty() => containers,
+        _ => return,
+    };
+    let mut resource_found = false;
+    for (index, container) in containers.iter().enumerate() {
+        let resources = &container["resources"];
+        if resources["limits"].get(RESOURCE_REQUIREMENTS_KEY).is_some() {
+            resource_found = true;
+        } else if let Some(requested) = resources["requests"].get(RESOURCE_REQUIREMENTS_KEY) {
+            add_resource_limit(index, container, requested.clone(), patch);
+            resource_found = true;
+        }
+    }
+    if !resource_found {
+        add_resource_limit(0, &containers[0], json!(DEFAULT_RESOURCE_LIMIT), patch);
+    }
+}
+
+fn add_resource_limit(index: usize, container: &Value, quantity: Value, patch: &mut Vec<Value>) {
+    let path = format!("/spec/brokerPodSpec/containers/{}/resources", index);
+    let mut limit = serde_json::Map::new();
+    limit.insert(RESOURCE_REQUIREMENTS_KEY.to_string(), quantity.clone());
+    let limit = Value::Object(limit);
+    if container.get("resources").is_none() {
+        patch.push(add(&path, json!({ "limits": limit })));
+    } else if container["resources"].get("limits").is_none() {
+        patch.push(add(&format!("{}/limits", path), limit));
+    } else {
+        patch.push(add(
+            &format!("{}/limits/{}", path, escape(RESOURCE_REQUIREMENTS_KEY)),
+            quantity,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default(raw: Value) -> Vec<Value> {
+        let config: Configuration =
+            serde_json::from_value(raw["spec"].clone()).expect("Configuration JSON");
+        default_configuration(&raw, &config)
+    }
+
+    #[test]
+    fn test_default_configuration_nothing_to_default() {
+        let raw = json!({
+            "metadata": {
+                "name": "config",
+                "labels": {
+                    "app.kubernetes.io/part-of": "akri",
+                    "akri.sh/discovery-handler": "debugEcho"
+                }
+            },
+            "spec": {
+                "discoveryHandler": {
+                    "name": "debugEcho",
+                    "discoveryDetails": "descriptions: [\"foo0\"]"
+                },
+                "capacity": 5,
+                "instanceDigestLength": 3,
+                "brokerPodSpec": {
+                    "containers": [{
+                        "name": "broker",
+                        "image": "image",
+                        "resources": { "limits": { "{{PLACEHOLDER}}": "1" } }
+                    }]
+                }
+            }
+        });
+        assert!(default(raw).is_empty());
+    }
+
+    #[test]
+    fn test_default_configuration_labels_and_capacity() {
+        let raw = json!({
+            "metadata": { "name": "config" },
+            "spec": { "discoveryHandler": { "name": "custom" } }
+        });
+        assert_eq!(
+            default(raw),
+            vec![
+                json!({ "op": "add", "path": "/metadata/labels", "value": {
+                    "app.kubernetes.io/part-of": "akri",
+                    "akri.sh/discovery-handler": "custom"
+                }}),
+                json!({ "op": "add", "path": "/spec/capacity", "value": 1 }),
+                json!({ "op": "add", "path": "/spec/instanceDigestLength", "value": 3 }),
+            ]
+        );
+
+        let raw = json!({
+            "metadata": { "name": "config", "labels": { "app.kubernetes.io/part-of": "mine" } },
+            "spec": { "discoveryHandler": { "name": "custom" }, "capacity": 2, "instanceDigestLength": 4 }
+        });
+        assert_eq!(
+            default(raw),
+            vec![
+                json!({ "op": "add", "path": "/metadata/labels/akri.sh~1discovery-handler", "value": "custom" })
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_configuration_discovery_details() {
+        let raw = json!({
+            "metadata": { "name": "config", "labels": {
+                "app.kubernetes.io/part-of": "akri",
+                "akri.sh/discovery-handler": "onvif"
+            }},
+            "spec": {
+                "discoveryHandler": { "name": "onvif", "discoveryDetails": "ipAddresses:\n  items:\n  - 10.0.0.1 # lobby camera\n" },
+                "capacity": 1,
+                "instanceDigestLength": 3
+            }
+        });
+        let patch = default(raw);
+        assert_eq!(patch.len(), 1);
+        assert_eq!(patch[0]["op"], "replace");
+        assert_eq!(patch[0]["path"], "/spec/discoveryHandler/discoveryDetails");
+        // The user's details are kept as written and the defaults are appended to them
+        assert!(patch[0]["value"]
+            .as_str()
+            .unwrap()
+            .starts_with("ipAddresses:\n  items:\n  - 10.0.0.1 # lobby camera\n"));
+        let details: OnvifDiscoveryDetails =
+            deserialize_discovery_details(patch[0]["value"].as_str().unwrap()).unwrap();
+        assert_eq!(details.discovery_timeout_seconds, 1);
+        assert_eq!(details.ip_addresses.unwrap().items, vec!["10.0.0.1"]);
+
+        // OPC UA defaults to discovering via the LDS on the local host
+        let raw = json!({
+            "metadata": { "name": "config", "labels": {
+                "app.kubernetes.io/part-of": "akri",
+                "akri.sh/discovery-handler": "opcua"
+            }},
+            "spec": {
+                "discoveryHandler": { "name": "opcua", "discoveryDetails": "applicationNames:\n  action: Include\n  items: [\"app\"]\n" },
+                "capacity": 1,
+                "instanceDigestLength": 3
+            }
+        });
+        let patch = default(raw);
+        assert_eq!(patch.len(), 1);
+        assert_eq!(patch[0]["op"], "replace");
+        assert!(patch[0]["value"]
+            .as_str()
+            .unwrap()
+            .contains("opc.tcp://localhost:4840/"));
+
+        // Details in flow style cannot be appended to, so they are left as written
+        let raw = json!({
+            "metadata": { "name": "config", "labels": {
+                "app.kubernetes.io/part-of": "akri",
+                "akri.sh/discovery-handler": "onvif"
+            }},
+            "spec": {
+                "discoveryHandler": { "name": "onvif", "discoveryDetails": "{ipAddresses: {items: [10.0.0.1]}}" },
+                "capacity": 1,
+                "instanceDigestLength": 3
+            }
+        });
+        assert!(default(raw).is_empty());
+    }
+
+    #[test]
+    fn test_default_configuration_broker_resources() {
+        let raw = json!({
+            "metadata": { "name": "config", "labels": {
+                "app.kubernetes.io/part-of": "akri",
+                "akri.sh/discovery-handler": "custom"
+            }},
+            "spec": {
+                "discoveryHandler": { "name": "custom" },
+                "capacity": 1,
+                "instanceDigestLength": 3,
+                "brokerPodSpec": {
+                    "containers": [
+                        { "name": "broker", "image": "image" },
+                        { "name": "sidecar", "image": "image", "resources": { "requests": { "{{PLACEHOLDER}}": "2" } } }
+                    ]
+                }
+            }
+        });
+        assert_eq!(
+            default(raw.clone()),
+            vec![json!({
+                "op": "add",
+                "path": "/spec/brokerPodSpec/containers/1/resources/limits",
+                "value": { "{{PLACEHOLDER}}": "2" }
+            })]
+        );
+    }
+
+    #[test]
+    fn test_default_configuration_broker_resources_not_requested() {
+        let raw = json!({
+            "metadata": { "name": "config", "labels": {
+                "app.kubernetes.io/part-of": "akri",
+                "akri.sh/discovery-handler": "custom"
+            }},
+            "spec": {
+                "discoveryHandler": { "name": "custom" },
+                "capacity": 1,
+                "instanceDigestLength": 3,
+                "brokerPodSpec": {
+                    "containers": [
+                        { "name": "broker", "image": "image", "resources": { "limits": { "memory": "64Mi" } } },
+                        { "name": "sidecar", "image": "image" }
+                    ]
+                }
+            }
+        });
+        // The first container limits the resource, keeping its other limits
+        assert_eq!(
+            default(raw.clone()),
+            vec![json!({
+                "op": "add",
+                "path": "/spec/brokerPodSpec/containers/0/resources/limits/{{PLACEHOLDER}}",
+                "value": "1"
+            })]
+        );
+
+        let mut raw = raw;
+        raw["spec"]["brokerPodSpec"]["containers"][0] =
+            json!({ "name": "broker", "image": "image" });
+        assert_eq!(
+            default(raw),
+            vec![json!({
+                "op": "add",
+                "path": "/spec/brokerPodSpec/containers/0/resources",
+                "value": { "limits": { "{{PLACEHOLDER}}": "1" } }
+            })]
+        );
+    }
+}
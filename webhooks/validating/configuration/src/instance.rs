@@ -0,0 +1,311 @@
+use akri_shared::{
+    akri::instance::KubeAkriInstance,
+    k8s::{KubeInterface, ERROR_NOT_FOUND},
+};
+use std::collections::{HashMap, HashSet};
+
+/// Checks a change to an Instance. Only the `instance_writers` (the Agent's and Controller's service accounts) may
+/// change which nodes can access an Instance and which nodes have claimed its slots, and no change may leave the
+/// slots in a state that the Agent cannot allocate from.
+pub async fn validate_instance(
+    operation: &str,
+    username: Option<&str>,
+    instance: &KubeAkriInstance,
+    old_instance: Option<&KubeAkriInstance>,
+    instance_writers: &[String],
+    kube_interface: &dyn KubeInterface,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    let old_spec = old_instance.map(|old_instance| &old_instance.spec);
+    let spec = &instance.spec;
+    let nodes_changed = old_spec.map_or(!spec.nodes.is_empty(), |old_spec| {
+        old_spec.nodes != spec.nodes
+    });
+    let device_usage_changed =
+        old_spec.map_or(true, |old_spec| old_spec.device_usage != spec.device_usage);
+    let device_usage_claimed = old_spec.map_or(
+        spec.device_usage.values().any(|node| !node.is_empty()),
+        |_| device_usage_changed,
+    );
+    let is_instance_writer = username.map_or(false, |username| {
+        instance_writers.iter().any(|writer| writer == username)
+    });
+    if !is_instance_writer {
+        if nodes_changed {
+            errors.push(format!(
+                "{} is not allowed to change nodes, which is managed by the Akri Agent and Controller",
+                username.unwrap_or("unknown user")
+            ));
+        }
+        if device_usage_claimed {
+            errors.push(format!(
+                "{} is not allowed to change deviceUsage, which is managed by the Akri Agent and Controller",
+                username.unwrap_or("unknown user")
+            ));
+        }
+    }
+    if let Some(old_spec) = old_spec {
+        errors.extend(validate_device_usage_transition(
+            &old_spec.device_usage,
+            &spec.device_usage,
+        ));
+    }
+
+    // The Configuration only needs to be looked up when the Instance is created or moved to another Configuration
+    // or its slots change
+    let configuration_changed = old_spec.map_or(true, |old_spec| {
+        old_spec.configuration_name != spec.configuration_name
+    });
+    if operation == "CREATE" || configuration_changed || device_usage_changed {
+        let instance_name = instance.metadata.name.as_str();
+        let namespace = instance.metadata.namespace.as_deref().unwrap_or("default");
+        match kube_interface
+            .find_configuration(&spec.configuration_name, namespace)
+            .await
+        {
+            Ok(config) => {
+                if device_usage_changed {
+                    errors.extend(validate_slots(
+                        instance_name,
+                        &spec.device_usage,
+                        config.spec.capacity,
+                    ));
+                }
+            }
+            Err(e) => match e.downcast_ref::<kube::Error>() {
+                Some(kube::Error::Api(ae)) if ae.code == ERROR_NOT_FOUND => {
+                    errors.push(format!(
+                        "configurationName {} does not name a Configuration in namespace {}",
+                        spec.configuration_name, namespace
+                    ));
+                }
+                _ => {
+                    errors.push(format!(
+                        "Configuration {} could not be retrieved to validate the Instance: {}",
+                        spec.configuration_name, e
+                    ));
+                }
+            },
+        }
+    }
+    errors
+}
+
+/// Checks that a slot that is claimed by a node is not claimed by another node without first being released
+fn validate_device_usage_transition(
+    old_device_usage: &HashMap<String, String>,
+    device_usage: &HashMap<String, String>,
+) -> Vec<String> {
+    let mut slots: Vec<&String> = device_usage.keys().collect();
+    slots.sort();
+    slots
+        .into_iter()
+        .filter_map(|slot| {
+            let node = &device_usage[slot];
+            match old_device_usage.get(slot) {
+                Some(old_node) if !old_node.is_empty() && !node.is_empty() && old_node != node => {
+                    Some(format!(
+                        "deviceUsage slot {} is claimed by node {} and cannot be claimed by node {} before it is released",
+                        slot, old_node, node
+                    ))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Checks that there is exactly one slot for each unit of the Configuration's capacity and that slots are named
+/// the way the Agent names them (`<Instance name>-<slot number>`)
+fn validate_slots(
+    instance_name: &str,
+    device_usage: &HashMap<String, String>,
+    capacity: i32,
+) -> Vec<String> {
+    let expected_slots: HashSet<String> = (0..capacity)
+        .map(|slot| format!("{}-{}", instance_name, slot))
+        .collect();
+    let slots: HashSet<String> = device_usage.keys().cloned().collect();
+    if slots == expected_slots {
+        return Vec::new();
+    }
+    let mut unexpected_slots: Vec<&String> = slots.difference(&expected_slots).collect();
+    unexpected_slots.sort();
+    let mut missing_slots: Vec<&String> = expected_slots.difference(&slots).collect();
+    missing_slots.sort();
+    vec![format!(
+        "deviceUsage must have one slot for each of the Configuration's capacity of {}, unexpected slots: {:?}, missing slots: {:?}",
+        capacity, unexpected_slots, missing_slots
+    )]
+}
+
+/// Parses the Instance of an AdmissionRequest
+pub fn parse_instance(raw: &serde_json::Value) -> Result<KubeAkriInstance, String> {
+    serde_json::from_value(raw.clone())
+        .map_err(|e| format!("Could not parse as Akri Instance: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use akri_shared::{akri::configuration::KubeAkriConfig, k8s::MockKubeInterface};
+
+    const AGENT: &str = "system:serviceaccount:default:akri-agent-sa";
+    const USER: &str = "admin";
+
+    fn instance(nodes: &[&str], device_usage: &[(&str, &str)]) -> KubeAkriInstance {
+        let device_usage: HashMap<String, String> = device_usage
+            .iter()
+            .map(|(slot, node)| (slot.to_string(), node.to_string()))
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "apiVersion": "akri.sh/v0",
+            "kind": "Instance",
+            "metadata": { "name": "config-a-b494b6", "namespace": "default" },
+            "spec": {
+                "configurationName": "config-a",
+                "shared": true,
+                "nodes": nodes,
+                "deviceUsage": device_usage
+            }
+        }))
+        .unwrap()
+    }
+
+    fn mock_find_configuration(mock: &mut MockKubeInterface, capacity: i32) {
+        mock.expect_find_configuration()
+            .returning(move |name, namespace| {
+                let config: KubeAkriConfig = serde_json::from_value(serde_json::json!({
+                    "apiVersion": "akri.sh/v0",
+                    "kind": "Configuration",
+                    "metadata": { "name": name, "namespace": namespace },
+                    "spec": {
+                        "discoveryHandler": { "name": "debugEcho" },
+                        "capacity": capacity
+                    }
+                }))
+                .unwrap();
+                Ok(config)
+            });
+    }
+
+    fn writers() -> Vec<String> {
+        vec![AGENT.to_string()]
+    }
+
+    #[actix_rt::test]
+    async fn test_validate_instance_agent_claims_slot() {
+        let mut mock = MockKubeInterface::new();
+        mock_find_configuration(&mut mock, 2);
+        let old = instance(
+            &["node-a"],
+            &[("config-a-b494b6-0", ""), ("config-a-b494b6-1", "")],
+        );
+        let new = instance(
+            &["node-a"],
+            &[("config-a-b494b6-0", "node-a"), ("config-a-b494b6-1", "")],
+        );
+        let errors =
+            validate_instance("UPDATE", Some(AGENT), &new, Some(&old), &writers(), &mock).await;
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[actix_rt::test]
+    async fn test_validate_instance_user_changes() {
+        let mut mock = MockKubeInterface::new();
+        mock_find_configuration(&mut mock, 2);
+        let old = instance(
+            &["node-a"],
+            &[("config-a-b494b6-0", ""), ("config-a-b494b6-1", "")],
+        );
+        let new = instance(
+            &["node-a", "node-b"],
+            &[("config-a-b494b6-0", "node-b"), ("config-a-b494b6-1", "")],
+        );
+        let errors =
+            validate_instance("UPDATE", Some(USER), &new, Some(&old), &writers(), &mock).await;
+        assert_eq!(
+            errors,
+            vec![
+                "admin is not allowed to change nodes, which is managed by the Akri Agent and Controller".to_string(),
+                "admin is not allowed to change deviceUsage, which is managed by the Akri Agent and Controller".to_string(),
+            ]
+        );
+
+        // Changes to other fields are allowed
+        let mut new = old.clone();
+        new.spec
+            .broker_properties
+            .insert("KEY".to_string(), "VALUE".to_string());
+        let errors =
+            validate_instance("UPDATE", Some(USER), &new, Some(&old), &writers(), &mock).await;
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[actix_rt::test]
+    async fn test_validate_instance_slot_overwritten() {
+        let mut mock = MockKubeInterface::new();
+        mock_find_configuration(&mut mock, 2);
+        let old = instance(
+            &["node-a", "node-b"],
+            &[("config-a-b494b6-0", "node-a"), ("config-a-b494b6-1", "")],
+        );
+        let new = instance(
+            &["node-a", "node-b"],
+            &[("config-a-b494b6-0", "node-b"), ("config-a-b494b6-1", "")],
+        );
+        let errors =
+            validate_instance("UPDATE", Some(AGENT), &new, Some(&old), &writers(), &mock).await;
+        assert_eq!(
+            errors,
+            vec!["deviceUsage slot config-a-b494b6-0 is claimed by node node-a and cannot be claimed by node node-b before it is released".to_string()]
+        );
+
+        // Releasing a slot is allowed
+        let new = instance(
+            &["node-a", "node-b"],
+            &[("config-a-b494b6-0", ""), ("config-a-b494b6-1", "")],
+        );
+        let errors =
+            validate_instance("UPDATE", Some(AGENT), &new, Some(&old), &writers(), &mock).await;
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[actix_rt::test]
+    async fn test_validate_instance_slots_do_not_match_capacity() {
+        let mut mock = MockKubeInterface::new();
+        mock_find_configuration(&mut mock, 2);
+        let new = instance(
+            &["node-a"],
+            &[("config-a-b494b6-0", ""), ("config-a-b494b6-5", "")],
+        );
+        let errors = validate_instance("CREATE", Some(AGENT), &new, None, &writers(), &mock).await;
+        assert_eq!(
+            errors,
+            vec!["deviceUsage must have one slot for each of the Configuration's capacity of 2, unexpected slots: [\"config-a-b494b6-5\"], missing slots: [\"config-a-b494b6-1\"]".to_string()]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_validate_instance_configuration_not_found() {
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_configuration().returning(|_, _| {
+            Err(kube::Error::Api(kube::ErrorResponse {
+                status: "Failure".to_string(),
+                message: "configurations.akri.sh \"config-a\" not found".to_string(),
+                reason: "NotFound".to_string(),
+                code: ERROR_NOT_FOUND,
+            })
+            .into())
+        });
+        let new = instance(&["node-a"], &[("config-a-b494b6-0", "")]);
+        let errors = validate_instance("CREATE", Some(AGENT), &new, None, &writers(), &mock).await;
+        assert_eq!(
+            errors,
+            vec![
+                "configurationName config-a does not name a Configuration in namespace default"
+                    .to_string()
+            ]
+        );
+    }
+}
@@ -1,21 +1,67 @@
-use actix_web::{post, web, App, HttpResponse, HttpServer, Responder};
-use akri_shared::akri::configuration::KubeAkriConfig;
+#[macro_use]
+extern crate lazy_static;
+mod defaulting;
+mod instance;
+mod semantics;
+mod tls;
+
+use actix_web::{get, post, web, App, HttpResponse, HttpServer, Responder};
+use akri_debug_echo::discovery_handler::DebugEchoDiscoveryDetails;
+use akri_discovery_utils::discovery::discovery_handler::deserialize_discovery_details;
+use akri_onvif::discovery_handler::OnvifDiscoveryDetails;
+use akri_opcua::discovery_handler::OpcuaDiscoveryDetails;
+use akri_shared::{
+    akri::{
+        configuration::{DiscoveryHandlerInfo, KubeAkriConfig},
+        metrics::run_metrics_server,
+    },
+    k8s::KubeInterface,
+};
+use akri_udev::discovery_handler::UdevDiscoveryDetails;
 use clap::Arg;
-use k8s_openapi::apimachinery::pkg::runtime::RawExtension;
+use defaulting::default_configuration;
+use log::{error, info, trace, warn};
 use openapi::models::{
     V1AdmissionRequest as AdmissionRequest, V1AdmissionResponse as AdmissionResponse,
     V1AdmissionReview as AdmissionReview, V1Status as Status,
 };
-use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
+use prometheus::{HistogramVec, IntCounterVec};
+use semantics::validate_semantics;
 use serde_json::{json, Value};
+use std::{sync::Arc, time::Instant};
+use tls::{get_builder, reload_certificate_periodically, ReloadableCertificate};
+
+/// Largest AdmissionReview accepted, which fits any object that etcd can store
+const MAX_ADMISSION_REVIEW_BYTES: usize = 3 * 1024 * 1024;
+
+lazy_static! {
+    // Reports the number of AdmissionReviews handled, grouped by endpoint and whether the object was allowed
+    pub static ref WEBHOOK_REQUEST_COUNT_METRIC: IntCounterVec = prometheus::register_int_counter_vec!("akri_webhook_request_count", "Akri Webhook AdmissionReview count", &["endpoint", "allowed"]).unwrap();
+    // Reports the time taken to handle AdmissionReviews, grouped by endpoint
+    pub static ref WEBHOOK_REQUEST_LATENCY_METRIC: HistogramVec = prometheus::register_histogram_vec!("akri_webhook_request_latency_seconds", "Akri Webhook AdmissionReview latency in seconds", &["endpoint"]).unwrap();
+}
 
-fn get_builder(key: &str, crt: &str) -> SslAcceptorBuilder {
-    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
-    builder.set_private_key_file(key, SslFiletype::PEM).unwrap();
-    builder.set_certificate_chain_file(crt).unwrap();
+/// Environment variable set to the namespace of the webhook's Pod, in which Akri's service accounts are
+const POD_NAMESPACE_LABEL: &str = "POD_NAMESPACE";
+/// Service accounts that are allowed to change the nodes and deviceUsage of Instances if `--instance-writers` is not
+/// set, which are in the namespace that Akri is installed in
+const DEFAULT_INSTANCE_WRITER_SERVICE_ACCOUNTS: [&str; 2] = ["akri-agent-sa", "akri-controller-sa"];
 
-    builder
+/// Returns the users that are allowed to change the nodes and deviceUsage of Instances if `--instance-writers` is not
+/// set, which are the Agent's and Controller's service accounts in `namespace`
+fn get_default_instance_writers(namespace: &str) -> Vec<String> {
+    DEFAULT_INSTANCE_WRITER_SERVICE_ACCOUNTS
+        .iter()
+        .map(|service_account| format!("system:serviceaccount:{}:{}", namespace, service_account))
+        .collect()
 }
+
+/// State shared by the workers that serve `/validate-instance`
+struct InstanceWebhook {
+    instance_writers: Vec<String>,
+    kube_interface: Arc<dyn KubeInterface>,
+}
+
 fn check(
     v: &serde_json::Value,
     deserialized: &serde_json::Value,
@@ -96,108 +142,314 @@ fn check(
     }
 }
 
-fn filter_configuration(mut v: Value) -> Value {
-    let metadata = v["metadata"].as_object_mut().unwrap();
+fn filter_configuration(mut v: Value) -> Result<Value, String> {
+    let metadata = v["metadata"]
+        .as_object_mut()
+        .ok_or_else(|| "Configuration has no metadata".to_string())?;
     metadata.remove("creationTimestamp");
     metadata.remove("deletionTimestamp");
     metadata.remove("managedFields");
 
-    let generation = metadata.get_mut("generation").unwrap();
-    *generation = json!(generation.as_f64().unwrap());
+    if let Some(generation) = metadata.get_mut("generation") {
+        let as_f64 = generation
+            .as_f64()
+            .ok_or_else(|| format!("metadata.generation {} is not a number", generation))?;
+        *generation = json!(as_f64);
+    }
 
-    v
+    Ok(v)
+}
+
+/// Checks the discoveryDetails of Configurations that use one of Akri's built-in Discovery Handlers the same way
+/// the Discovery Handler would upon receiving a discover request. Discovery Handlers that are not built into Akri are
+/// not checked.
+fn validate_discovery_details(
+    discovery_handler: &DiscoveryHandlerInfo,
+) -> Result<(), anyhow::Error> {
+    let discovery_details = &discovery_handler.discovery_details;
+    match discovery_handler.name.as_str() {
+        akri_debug_echo::DISCOVERY_HANDLER_NAME => {
+            deserialize_discovery_details::<DebugEchoDiscoveryDetails>(discovery_details)?
+                .validate()
+        }
+        akri_onvif::DISCOVERY_HANDLER_NAME => {
+            deserialize_discovery_details::<OnvifDiscoveryDetails>(discovery_details)?.validate()
+        }
+        akri_opcua::DISCOVERY_HANDLER_NAME => {
+            deserialize_discovery_details::<OpcuaDiscoveryDetails>(discovery_details)?.validate()
+        }
+        akri_udev::DISCOVERY_HANDLER_NAME => {
+            deserialize_discovery_details::<UdevDiscoveryDetails>(discovery_details)?.validate()
+        }
+        _ => Ok(()),
+    }
+}
+
+fn deny(rqst: &AdmissionRequest, message: String) -> AdmissionResponse {
+    AdmissionResponse {
+        allowed: false,
+        audit_annotations: None,
+        patch: None,
+        patch_type: None,
+        status: Some(Status {
+            api_version: None,
+            code: None,
+            details: None,
+            kind: None,
+            message: Some(message),
+            metadata: None,
+            reason: None,
+            status: None,
+        }),
+        uid: rqst.uid.to_owned(),
+        warnings: None,
+    }
 }
 
 fn validate_configuration(rqst: &AdmissionRequest) -> AdmissionResponse {
-    println!("Validating Configuration");
     match &rqst.object {
         Some(raw) => {
-            let x: RawExtension = serde_json::from_value(raw.clone())
-                .expect("Could not parse as Kubernetes RawExtension");
-            let y = serde_json::to_string(&x).unwrap();
-            let config: KubeAkriConfig =
-                serde_json::from_str(y.as_str()).expect("Could not parse as Akri Configuration");
-            let reserialized = serde_json::to_string(&config).unwrap();
-            let deserialized: Value = serde_json::from_str(&reserialized).expect("untyped JSON");
-            println!(
+            let config: KubeAkriConfig = match serde_json::from_value(raw.clone()) {
+                Ok(config) => config,
+                Err(e) => {
+                    return deny(
+                        rqst,
+                        format!("Could not parse as Akri Configuration: {}", e),
+                    )
+                }
+            };
+            let deserialized: Value = match serde_json::to_value(&config) {
+                Ok(deserialized) => deserialized,
+                Err(e) => return deny(rqst, format!("Could not reserialize Configuration: {}", e)),
+            };
+            trace!(
                 "validate_configuration - deserialized Configuration: {:?}",
                 deserialized
             );
-            let val: Value = filter_configuration(raw.clone());
-            println!(
+            let val: Value = match filter_configuration(raw.clone()) {
+                Ok(val) => val,
+                Err(e) => return deny(rqst, e),
+            };
+            trace!(
                 "validate_configuration - expected deserialized format: {:?}",
                 val
             );
 
             // Do they match?
-            match check(&val, &deserialized) {
-                Ok(_) => AdmissionResponse::new(true, rqst.uid.to_owned()),
-                Err(e) => AdmissionResponse {
-                    allowed: false,
-                    audit_annotations: None,
-                    patch: None,
-                    patch_type: None,
-                    status: Some(Status {
-                        api_version: None,
-                        code: None,
-                        details: None,
-                        kind: None,
-                        message: Some(e.to_string()),
-                        metadata: None,
-                        reason: None,
-                        status: None,
-                    }),
-                    uid: rqst.uid.to_owned(),
-                    warnings: None,
-                },
+            if let Err(e) = check(&val, &deserialized) {
+                return deny(rqst, e.to_string());
+            }
+
+            // Is the Configuration consistent and are the discoveryDetails usable by the Discovery Handler?
+            let mut violations = validate_semantics(&config.spec);
+            if let Err(e) = validate_discovery_details(&config.spec.discovery_handler) {
+                violations.errors.insert(
+                    0,
+                    format!(
+                        "discoveryDetails for {} Discovery Handler are not valid: {}",
+                        config.spec.discovery_handler.name, e
+                    ),
+                );
+            }
+            let warnings = if violations.warnings.is_empty() {
+                None
+            } else {
+                Some(violations.warnings)
+            };
+            if violations.errors.is_empty() {
+                AdmissionResponse {
+                    warnings,
+                    ..AdmissionResponse::new(true, rqst.uid.to_owned())
+                }
+            } else {
+                AdmissionResponse {
+                    warnings,
+                    ..deny(rqst, violations.errors.join("; "))
+                }
             }
         }
-        None => AdmissionResponse {
-            allowed: false,
-            audit_annotations: None,
-            patch: None,
-            patch_type: None,
-            status: Some(Status {
-                api_version: None,
-                code: None,
-                details: None,
-                kind: None,
-                message: Some("AdmissionRequest object contains no data".to_owned()),
-                metadata: None,
-                reason: None,
-                status: None,
-            }),
-            uid: rqst.uid.to_owned(),
-            warnings: None,
+        None => deny(rqst, "AdmissionRequest object contains no data".to_owned()),
+    }
+}
+
+fn mutate_configuration(rqst: &AdmissionRequest) -> AdmissionResponse {
+    let raw = match &rqst.object {
+        Some(raw) => raw,
+        None => return deny(rqst, "AdmissionRequest object contains no data".to_owned()),
+    };
+    // Configurations that cannot be parsed are admitted unchanged so that the validating webhook can reject them
+    // with a precise message
+    let config: KubeAkriConfig = match serde_json::from_value(raw.clone()) {
+        Ok(config) => config,
+        Err(e) => {
+            warn!(
+                "mutate_configuration - could not parse Configuration, not defaulting it: {}",
+                e
+            );
+            return AdmissionResponse::new(true, rqst.uid.to_owned());
+        }
+    };
+    let patch = default_configuration(raw, &config.spec);
+    trace!("mutate_configuration - JSON patch: {:?}", patch);
+    if patch.is_empty() {
+        return AdmissionResponse::new(true, rqst.uid.to_owned());
+    }
+    AdmissionResponse {
+        patch: Some(base64::encode(Value::Array(patch).to_string())),
+        patch_type: Some("JSONPatch".to_owned()),
+        ..AdmissionResponse::new(true, rqst.uid.to_owned())
+    }
+}
+
+/// Wraps the response to an AdmissionRequest in an AdmissionReview, logging the outcome as a single line of
+/// `key=value` pairs and recording it in the Webhook's metrics
+fn admission_review(
+    endpoint: &str,
+    rqst: &AdmissionRequest,
+    resp: AdmissionResponse,
+    start: Instant,
+) -> HttpResponse {
+    let duration = start.elapsed();
+    let allowed = resp.allowed;
+    info!(
+        "endpoint={} uid={} kind={:?} namespace={:?} name={:?} operation={} user={:?} allowed={} duration_ms={} message={:?}",
+        endpoint,
+        rqst.uid,
+        rqst.kind.kind,
+        rqst.namespace,
+        rqst.name,
+        rqst.operation,
+        rqst.user_info.username,
+        allowed,
+        duration.as_millis(),
+        resp.status
+            .as_ref()
+            .and_then(|status| status.message.as_deref())
+            .unwrap_or_default()
+    );
+    WEBHOOK_REQUEST_COUNT_METRIC
+        .with_label_values(&[endpoint, &allowed.to_string()])
+        .inc();
+    WEBHOOK_REQUEST_LATENCY_METRIC
+        .with_label_values(&[endpoint])
+        .observe(duration.as_secs_f64());
+    let resp: AdmissionReview = AdmissionReview {
+        api_version: Some("admission.k8s.io/v1".to_owned()),
+        kind: Some("AdmissionReview".to_owned()),
+        request: None,
+        response: Some(resp),
+    };
+    match serde_json::to_string(&resp) {
+        Ok(body) => HttpResponse::Ok().body(body),
+        Err(e) => {
+            error!(
+                "endpoint={} uid={} could not serialize AdmissionReview: {}",
+                endpoint, rqst.uid, e
+            );
+            HttpResponse::InternalServerError().body("")
+        }
+    }
+}
+
+/// Responds to an AdmissionReview that contains no AdmissionRequest
+fn empty_admission_review(endpoint: &str) -> HttpResponse {
+    warn!(
+        "endpoint={} received an AdmissionReview without request",
+        endpoint
+    );
+    WEBHOOK_REQUEST_COUNT_METRIC
+        .with_label_values(&[endpoint, "error"])
+        .inc();
+    HttpResponse::BadRequest().body("")
+}
+
+#[post("/mutate")]
+async fn mutate(rqst: web::Json<AdmissionReview>) -> impl Responder {
+    let start = Instant::now();
+    match &rqst.request {
+        Some(rqst) => admission_review("/mutate", rqst, mutate_configuration(rqst), start),
+        None => empty_admission_review("/mutate"),
+    }
+}
+
+async fn validate_instance_request(
+    rqst: &AdmissionRequest,
+    state: &InstanceWebhook,
+) -> AdmissionResponse {
+    let instance = match rqst.object.as_ref().map(instance::parse_instance) {
+        Some(Ok(instance)) => instance,
+        Some(Err(e)) => return deny(rqst, e),
+        None => return deny(rqst, "AdmissionRequest object contains no data".to_owned()),
+    };
+    let old_instance = match rqst.old_object.as_ref().filter(|old| !old.is_null()) {
+        Some(old) => match instance::parse_instance(old) {
+            Ok(old_instance) => Some(old_instance),
+            Err(e) => return deny(rqst, e),
         },
+        None => None,
+    };
+    let errors = instance::validate_instance(
+        &rqst.operation,
+        rqst.user_info.username.as_deref(),
+        &instance,
+        old_instance.as_ref(),
+        &state.instance_writers,
+        state.kube_interface.as_ref(),
+    )
+    .await;
+    if errors.is_empty() {
+        AdmissionResponse::new(true, rqst.uid.to_owned())
+    } else {
+        deny(rqst, errors.join("; "))
+    }
+}
+
+#[post("/validate-instance")]
+async fn validate_instance(
+    rqst: web::Json<AdmissionReview>,
+    state: web::Data<InstanceWebhook>,
+) -> impl Responder {
+    let start = Instant::now();
+    match &rqst.request {
+        Some(rqst) => admission_review(
+            "/validate-instance",
+            rqst,
+            validate_instance_request(rqst, &state).await,
+            start,
+        ),
+        None => empty_admission_review("/validate-instance"),
     }
 }
 
 #[post("/validate")]
 async fn validate(rqst: web::Json<AdmissionReview>) -> impl Responder {
-    println!("Handler invoked");
+    let start = Instant::now();
     match &rqst.request {
-        Some(rqst) => {
-            println!("Handler received: AdmissionRequest");
-            let resp = validate_configuration(&rqst);
-            let resp: AdmissionReview = AdmissionReview {
-                api_version: Some("admission.k8s.io/v1".to_owned()),
-                kind: Some("AdmissionReview".to_owned()),
-                request: None,
-                response: Some(resp),
-            };
-            let body = serde_json::to_string(&resp).expect("Valid AdmissionReview");
-            return HttpResponse::Ok().body(body);
-        }
-        None => {
-            println!("Handler received: Nothing");
-            return HttpResponse::BadRequest().body("");
-        }
+        Some(rqst) => admission_review("/validate", rqst, validate_configuration(rqst), start),
+        None => empty_admission_review("/validate"),
     }
 }
 
+#[get("/healthz")]
+async fn healthz() -> impl Responder {
+    HttpResponse::Ok().body("ok")
+}
+
+/// Accepts AdmissionReviews as large as any object that the API server sends and responds to ones that cannot be
+/// parsed with a Bad Request
+fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(MAX_ADMISSION_REVIEW_BYTES)
+        .error_handler(|e, _| {
+            warn!("could not parse AdmissionReview: {}", e);
+            let resp = HttpResponse::BadRequest().body(e.to_string());
+            actix_web::error::InternalError::from_response(e, resp).into()
+        })
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    env_logger::try_init().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
     let matches = clap::App::new("Akri Webhook")
         .arg(
             Arg::new("crt_file")
@@ -220,6 +472,15 @@ async fn main() -> std::io::Result<()> {
                 .required(true)
                 .about("port"),
         )
+        .arg(
+            Arg::new("instance_writers")
+                .long("instance-writers")
+                .takes_value(true)
+                .required(false)
+                .about(
+                    "Comma separated users that may change the nodes and deviceUsage of Instances. Defaults to the Agent's and Controller's service accounts in the namespace in POD_NAMESPACE",
+                ),
+        )
         .get_matches();
 
     let crt_file = matches.value_of("crt_file").expect("TLS certificate file");
@@ -229,16 +490,66 @@ async fn main() -> std::io::Result<()> {
         .value_of("port")
         .unwrap_or("8443")
         .parse::<u16>()
-        .expect("valid port [0-65535]");
+        .map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("port must be in [0-65535]: {}", e),
+            )
+        })?;
 
     let endpoint = format!("0.0.0.0:{}", port);
-    println!("Started Webhook server: {}", endpoint);
+    info!("Started Webhook server: {}", endpoint);
 
-    let builder = get_builder(key_file, crt_file);
-    HttpServer::new(|| App::new().service(validate))
-        .bind_openssl(endpoint, builder)?
-        .run()
-        .await
+    let instance_writers = match matches.value_of("instance_writers") {
+        Some(instance_writers) => instance_writers
+            .split(',')
+            .map(|writer| writer.trim().to_string())
+            .filter(|writer| !writer.is_empty())
+            .collect(),
+        None => {
+            let namespace = std::env::var(POD_NAMESPACE_LABEL).map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "--instance-writers must be set if {} is not",
+                        POD_NAMESPACE_LABEL
+                    ),
+                )
+            })?;
+            get_default_instance_writers(&namespace)
+        }
+    };
+    let instance_webhook = web::Data::new(InstanceWebhook {
+        instance_writers,
+        kube_interface: Arc::new(akri_shared::k8s::create_kube_interface()),
+    });
+
+    let certificate = ReloadableCertificate::new(key_file, crt_file)
+        .map(Arc::new)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    let builder = get_builder(certificate.clone())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    actix_rt::spawn(reload_certificate_periodically(certificate));
+
+    // Start server for prometheus metrics
+    actix_rt::spawn(async {
+        if let Err(e) = run_metrics_server().await {
+            error!("main - metrics server failed: {}", e);
+        }
+    });
+
+    HttpServer::new(move || {
+        App::new()
+            .app_data(instance_webhook.clone())
+            .app_data(json_config())
+            .service(healthz)
+            .service(validate)
+            .service(mutate)
+            .service(validate_instance)
+    })
+    .bind_openssl(endpoint, builder)?
+    .run()
+    .await
 }
 
 #[cfg(test)]
@@ -318,6 +629,7 @@ mod tests {
                         ]
                     },
                     "capacity": 1,
+                    "instanceDigestLength": 3,
                     "brokerProperties": {}
                 }
             },
@@ -686,7 +998,7 @@ mod tests {
         let t: Object<Void, Void> = serde_json::from_str(METADATA).expect("Valid Metadata");
         let reserialized = serde_json::to_string(&t).expect("bytes");
         let deserialized: Value = serde_json::from_str(&reserialized).expect("untyped JSON");
-        let v = filter_configuration(deserialized);
+        let v = filter_configuration(deserialized).unwrap();
         assert_eq!(v["metadata"].get("creationTimestamp"), None);
     }
 
@@ -695,7 +1007,7 @@ mod tests {
         let t: Object<Void, Void> = serde_json::from_str(METADATA).expect("Valid Metadata");
         let reserialized = serde_json::to_string(&t).expect("bytes");
         let deserialized: Value = serde_json::from_str(&reserialized).expect("untyped JSON");
-        let v = filter_configuration(deserialized);
+        let v = filter_configuration(deserialized).unwrap();
         assert_eq!(v["metadata"].get("deletionTimestamp"), None);
     }
 
@@ -704,7 +1016,7 @@ mod tests {
         let t: Object<Void, Void> = serde_json::from_str(METADATA).expect("Valid Metadata");
         let reserialized = serde_json::to_string(&t).expect("bytes");
         let deserialized: Value = serde_json::from_str(&reserialized).expect("untyped JSON");
-        let v = filter_configuration(deserialized);
+        let v = filter_configuration(deserialized).unwrap();
         assert_eq!(v["metadata"].get("managedFields"), None);
     }
 
@@ -713,7 +1025,7 @@ mod tests {
         let t: Object<Void, Void> = serde_json::from_str(METADATA).expect("Valid Metadata");
         let reserialized = serde_json::to_string(&t).expect("bytes");
         let deserialized: Value = serde_json::from_str(&reserialized).expect("untyped JSON");
-        let v = filter_configuration(deserialized);
+        let v = filter_configuration(deserialized).unwrap();
         assert!(v["metadata"].get("generation").unwrap().is_f64());
     }
 
@@ -743,6 +1055,279 @@ mod tests {
         assert_eq!(resp.allowed, true);
     }
 
+    fn admission_request_with_discovery_handler(
+        name: &str,
+        discovery_details: &str,
+    ) -> AdmissionRequest {
+        let mut review: Value = serde_json::from_str(VALID).expect("v1.AdmissionReview JSON");
+        review["request"]["object"]["spec"]["discoveryHandler"] = json!({
+            "name": name,
+            "discoveryDetails": discovery_details,
+        });
+        let review: AdmissionReview =
+            serde_json::from_value(review).expect("v1.AdmissionReview JSON");
+        review.request.expect("v1.AdmissionRequest JSON")
+    }
+
+    fn denial_message(resp: &AdmissionResponse) -> String {
+        resp.status
+            .as_ref()
+            .and_then(|status| status.message.clone())
+            .expect("denial message")
+    }
+
+    #[test]
+    fn test_validate_configuration_discovery_details_valid() {
+        let discovery_handlers = vec![
+            ("debugEcho", "descriptions:\n- \"foo0\"\n"),
+            ("onvif", "ipAddresses:\n  items:\n  - 10.0.0.1\n"),
+            (
+                "opcua",
+                "opcuaDiscoveryMethod:\n  standard:\n    discoveryUrls:\n    - opc.tcp://127.0.0.1:4840/\n",
+            ),
+            ("udev", "udevRules:\n- 'KERNEL==\"video[0-9]*\"'\n"),
+            ("custom", "anything: goes"),
+        ];
+        for (name, discovery_details) in discovery_handlers {
+            let rqst = admission_request_with_discovery_handler(name, discovery_details);
+            let resp = validate_configuration(&rqst);
+            assert_eq!(resp.allowed, true, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_validate_configuration_discovery_details_invalid() {
+        let discovery_handlers = vec![
+            (
+                "debugEcho",
+                "descriptions: []\n",
+                "discoveryDetails for debugEcho Discovery Handler are not valid: descriptions must list at least one device",
+            ),
+            (
+                "onvif",
+                "macAddresses:\n  items:\n  - not-a-mac\n",
+                "discoveryDetails for onvif Discovery Handler are not valid: macAddresses.items[0] \"not-a-mac\"",
+            ),
+            (
+                "opcua",
+                "opcuaDiscoveryMethod:\n  standard:\n    discoveryUrls:\n    - http://127.0.0.1:4840/\n",
+                "discoveryDetails for opcua Discovery Handler are not valid: opcuaDiscoveryMethod.standard.discoveryUrls[0]",
+            ),
+            (
+                "udev",
+                "udevRules:\n- 'ACTION==\"add\"'\n",
+                "discoveryDetails for udev Discovery Handler are not valid: udevRules[0]",
+            ),
+            (
+                "udev",
+                "udevRulez: []\n",
+                "discoveryDetails for udev Discovery Handler are not valid: ",
+            ),
+        ];
+        for (name, discovery_details, expected_message) in discovery_handlers {
+            let rqst = admission_request_with_discovery_handler(name, discovery_details);
+            let resp = validate_configuration(&rqst);
+            assert_eq!(resp.allowed, false, "{}", name);
+            assert!(
+                denial_message(&resp).starts_with(expected_message),
+                "{}",
+                denial_message(&resp)
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_configuration_semantics() {
+        let mut review: Value = serde_json::from_str(VALID).expect("v1.AdmissionReview JSON");
+        let spec = &mut review["request"]["object"]["spec"];
+        spec["capacity"] = json!(0);
+        spec["brokerProperties"] = json!({ "dotted.name": "1", "1NVALID": "2" });
+        spec["brokerPodSpec"]["containers"][0]["resources"]["limits"]["{{PLACEHOLDER}}"] =
+            json!("2");
+        spec["discoveryHandler"]["discoveryDetails"] = json!("descriptions: []");
+        let review: AdmissionReview =
+            serde_json::from_value(review).expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        let resp = validate_configuration(&rqst);
+        assert_eq!(resp.allowed, false);
+        // All violations are returned at once
+        assert_eq!(
+            denial_message(&resp),
+            "discoveryDetails for debugEcho Discovery Handler are not valid: descriptions must list at least one device; \
+            capacity must be at least 1 but is 0; \
+            brokerProperties key \"1NVALID\" is not a valid environment variable name"
+        );
+        assert_eq!(resp.warnings.expect("warnings").len(), 2);
+    }
+
+    #[test]
+    fn test_validate_configuration_warnings() {
+        let mut review: Value = serde_json::from_str(VALID).expect("v1.AdmissionReview JSON");
+        review["request"]["object"]["spec"]["brokerProperties"] = json!({ "dotted.name": "1" });
+        let review: AdmissionReview =
+            serde_json::from_value(review).expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        let resp = validate_configuration(&rqst);
+        assert_eq!(resp.allowed, true);
+        assert_eq!(resp.warnings.expect("warnings").len(), 1);
+    }
+
+    #[test]
+    fn test_mutate_configuration() {
+        let mut review: Value = serde_json::from_str(VALID).expect("v1.AdmissionReview JSON");
+        review["request"]["object"]["spec"]
+            .as_object_mut()
+            .unwrap()
+            .remove("capacity");
+        let review: AdmissionReview =
+            serde_json::from_value(review).expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        let resp = mutate_configuration(&rqst);
+        assert_eq!(resp.allowed, true);
+        assert_eq!(resp.patch_type, Some("JSONPatch".to_owned()));
+        let patch: Value =
+            serde_json::from_slice(&base64::decode(resp.patch.unwrap()).unwrap()).unwrap();
+        assert_eq!(
+            patch,
+            json!([
+                {
+                    "op": "add",
+                    "path": "/metadata/labels",
+                    "value": {
+                        "app.kubernetes.io/part-of": "akri",
+                        "akri.sh/discovery-handler": "debugEcho"
+                    }
+                },
+                { "op": "add", "path": "/spec/capacity", "value": 1 }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_mutate_configuration_unparsable() {
+        let mut review: Value = serde_json::from_str(VALID).expect("v1.AdmissionReview JSON");
+        review["request"]["object"]["spec"]["capacity"] = json!("one");
+        let review: AdmissionReview =
+            serde_json::from_value(review).expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        let resp = mutate_configuration(&rqst);
+        assert_eq!(resp.allowed, true);
+        assert_eq!(resp.patch, None);
+    }
+
+    #[actix_rt::test]
+    async fn test_mutate() {
+        let mut app = test::init_service(App::new().service(mutate)).await;
+        let valid: AdmissionReview = serde_json::from_str(VALID).expect("v1.AdmissionReview JSON");
+        let rqst = test::TestRequest::post()
+            .uri("/mutate")
+            .set_json(&valid)
+            .to_request();
+        let resp = test::call_service(&mut app, rqst).await;
+        assert_eq!(resp.status().is_success(), true);
+    }
+
+    #[test]
+    fn test_get_default_instance_writers() {
+        assert_eq!(
+            get_default_instance_writers("akri"),
+            vec![
+                "system:serviceaccount:akri:akri-agent-sa".to_string(),
+                "system:serviceaccount:akri:akri-controller-sa".to_string()
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_validate_instance_create() {
+        let mut mock = akri_shared::k8s::MockKubeInterface::new();
+        mock.expect_find_configuration()
+            .returning(|name, namespace| {
+                Ok(serde_json::from_value(json!({
+                    "apiVersion": "akri.sh/v0",
+                    "kind": "Configuration",
+                    "metadata": { "name": name, "namespace": namespace },
+                    "spec": { "discoveryHandler": { "name": "debugEcho" }, "capacity": 1 }
+                }))
+                .unwrap())
+            });
+        let state = InstanceWebhook {
+            instance_writers: vec!["system:serviceaccount:default:akri-agent-sa".to_string()],
+            kube_interface: Arc::new(mock),
+        };
+        let mut review: Value = serde_json::from_str(VALID).expect("v1.AdmissionReview JSON");
+        review["request"]["userInfo"]["username"] =
+            json!("system:serviceaccount:default:akri-agent-sa");
+        review["request"]["object"] = json!({
+            "apiVersion": "akri.sh/v0",
+            "kind": "Instance",
+            "metadata": { "name": "config-a-b494b6", "namespace": "default" },
+            "spec": {
+                "configurationName": "config-a",
+                "nodes": ["node-a"],
+                "deviceUsage": { "config-a-b494b6-0": "node-a" }
+            }
+        });
+        let review: AdmissionReview =
+            serde_json::from_value(review).expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        let resp = validate_instance_request(&rqst, &state).await;
+        assert_eq!(resp.allowed, true);
+
+        let mut rqst = rqst;
+        rqst.user_info.username = Some("admin".to_string());
+        let resp = validate_instance_request(&rqst, &state).await;
+        assert_eq!(resp.allowed, false);
+    }
+
+    #[test]
+    fn test_validate_configuration_unparsable() {
+        let mut review: Value = serde_json::from_str(VALID).expect("v1.AdmissionReview JSON");
+        review["request"]["object"]["spec"]["capacity"] = json!("one");
+        let review: AdmissionReview =
+            serde_json::from_value(review).expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        let resp = validate_configuration(&rqst);
+        assert_eq!(resp.allowed, false);
+        assert!(denial_message(&resp).starts_with("Could not parse as Akri Configuration"));
+
+        let mut review: Value = serde_json::from_str(VALID).expect("v1.AdmissionReview JSON");
+        review["request"]["object"]["metadata"]["generation"] = json!("one");
+        let review: AdmissionReview =
+            serde_json::from_value(review).expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        let resp = validate_configuration(&rqst);
+        assert_eq!(resp.allowed, false);
+    }
+
+    #[actix_rt::test]
+    async fn test_healthz() {
+        let mut app = test::init_service(App::new().service(healthz)).await;
+        let rqst = test::TestRequest::get().uri("/healthz").to_request();
+        let resp = test::call_service(&mut app, rqst).await;
+        assert_eq!(resp.status().is_success(), true);
+    }
+
+    #[actix_rt::test]
+    async fn test_validate_malformed() {
+        let mut app =
+            test::init_service(App::new().app_data(json_config()).service(validate)).await;
+        let rqst = test::TestRequest::post()
+            .uri("/validate")
+            .header("content-type", "application/json")
+            .set_payload("{ not json")
+            .to_request();
+        let resp = test::call_service(&mut app, rqst).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+        let rqst = test::TestRequest::post()
+            .uri("/validate")
+            .set_json(&json!({ "kind": "AdmissionReview", "apiVersion": "admission.k8s.io/v1" }))
+            .to_request();
+        let resp = test::call_service(&mut app, rqst).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
     #[actix_rt::test]
     async fn test_validate_valid() {
         let mut app = test::init_service(App::new().service(validate)).await;
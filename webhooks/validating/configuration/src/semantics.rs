@@ -0,0 +1,574 @@
+use akri_shared::{
+    akri::{
+        configuration::{to_label_key, Configuration},
+        instance::{get_instance_name_template_properties, MAX_INSTANCE_DIGEST_LENGTH},
+    },
+    k8s::{
+        pod::{
+            AKRI_CONFIGURATION_LABEL_NAME, AKRI_INSTANCE_LABEL_NAME, AKRI_TARGET_NODE_LABEL_NAME,
+            APP_LABEL_ID, CONTROLLER_LABEL_ID,
+        },
+        RESOURCE_REQUIREMENTS_KEY,
+    },
+};
+use k8s_openapi::api::core::v1::{Container, ServiceSpec};
+
+/// Labels that Akri sets on broker Pods. A Service selector that uses one of these keys either has its value
+/// overwritten by Akri or never matches a broker Pod.
+const AKRI_BROKER_LABELS: [&str; 5] = [
+    APP_LABEL_ID,
+    CONTROLLER_LABEL_ID,
+    AKRI_CONFIGURATION_LABEL_NAME,
+    AKRI_INSTANCE_LABEL_NAME,
+    AKRI_TARGET_NODE_LABEL_NAME,
+];
+
+/// Problems found in a Configuration. `errors` cause the Configuration to be rejected while `warnings` are returned
+/// to the user alongside an admitted Configuration.
+#[derive(Debug, Default, PartialEq)]
+pub struct Violations {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Checks the invariants between the fields of a Configuration that cannot be expressed in the CRD's schema and that
+/// would otherwise only surface as broker Pods and Services that silently do not work.
+pub fn validate_semantics(config: &Configuration) -> Violations {
+    let mut violations = Violations::default();
+    if config.capacity < 1 {
+        violations.errors.push(format!(
+            "capacity must be at least 1 but is {}",
+            config.capacity
+        ));
+    }
+    match &config.broker_pod_spec {
+        Some(broker_pod_spec) => {
+            validate_broker_containers(
+                &broker_pod_spec.containers,
+                "brokerPodSpec.containers",
+                &mut violations,
+            );
+            if let Some(init_containers) = &broker_pod_spec.init_containers {
+                for (index, container) in init_containers.iter().enumerate() {
+                    let (in_limits, in_requests) = references_placeholder(container);
+                    if in_limits || in_requests {
+                        violations.errors.push(format!(
+                            "brokerPodSpec.initContainers[{}] requests the {} resource, which is only replaced in containers",
+                            index, RESOURCE_REQUIREMENTS_KEY
+                        ));
+                    }
+                }
+            }
+        }
+        None => {
+            if config.instance_service_spec.is_some() {
+                violations.errors.push(
+                    "instanceServiceSpec must not be set without a brokerPodSpec".to_string(),
+                );
+            }
+            if config.configuration_service_spec.is_some() {
+                violations.errors.push(
+                    "configurationServiceSpec must not be set without a brokerPodSpec".to_string(),
+                );
+            }
+        }
+    }
+    if let Some(instance_service_spec) = &config.instance_service_spec {
+        validate_service_selector(
+            instance_service_spec,
+            "instanceServiceSpec",
+            &mut violations,
+        );
+    }
+    if let Some(configuration_service_spec) = &config.configuration_service_spec {
+        validate_service_selector(
+            configuration_service_spec,
+            "configurationServiceSpec",
+            &mut violations,
+        );
+    }
+    let mut property_names: Vec<&String> = config.broker_properties.keys().collect();
+    property_names.sort();
+    for name in property_names {
+        if !is_env_var_name(name) {
+            violations.errors.push(format!(
+                "brokerProperties key \"{}\" is not a valid environment variable name",
+                name
+            ));
+        } else if !is_c_identifier(name) {
+            violations.warnings.push(format!(
+                "brokerProperties key \"{}\" contains '-' or '.', so it cannot be read as an environment variable by most shells",
+                name
+            ));
+        }
+    }
+    if let Some(mount_path) = &config.broker_properties_mount_path {
+        if !mount_path.starts_with('/') || mount_path == "/" {
+            violations.errors.push(format!(
+                "brokerPropertiesMountPath \"{}\" must be an absolute path other than /",
+                mount_path
+            ));
+        }
+    }
+    if let Some(device_credentials) = &config.device_credentials {
+        if device_credentials.secret_name.is_empty() {
+            violations
+                .errors
+                .push("deviceCredentials.secretName must not be empty".to_string());
+        }
+        if device_credentials.mount_path.is_none() && device_credentials.env_var.is_none() {
+            violations.errors.push(
+                "deviceCredentials must set mountPath or envVar, or brokers are not given their credentials"
+                    .to_string(),
+            );
+        }
+        if let Some(mount_path) = &device_credentials.mount_path {
+            if !mount_path.starts_with('/') || mount_path.ends_with('/') {
+                violations.errors.push(format!(
+                    "deviceCredentials.mountPath \"{}\" must be the absolute path of a file",
+                    mount_path
+                ));
+            }
+        }
+        if let Some(env_var) = &device_credentials.env_var {
+            if !is_env_var_name(env_var) {
+                violations.errors.push(format!(
+                    "deviceCredentials.envVar \"{}\" is not a valid environment variable name",
+                    env_var
+                ));
+            } else if config.broker_properties.contains_key(env_var) {
+                violations.warnings.push(format!(
+                    "deviceCredentials.envVar {} replaces the brokerProperties entry of the same name",
+                    env_var
+                ));
+            }
+        }
+    }
+    if !(1..=MAX_INSTANCE_DIGEST_LENGTH).contains(&config.instance_digest_length) {
+        violations.errors.push(format!(
+            "instanceDigestLength must be between 1 and {} but is {}",
+            MAX_INSTANCE_DIGEST_LENGTH, config.instance_digest_length
+        ));
+    }
+    if let Some(template) = &config.instance_name_template {
+        match get_instance_name_template_properties(template) {
+            Ok(properties) if properties.is_empty() => violations.warnings.push(format!(
+                "instanceNameTemplate \"{}\" does not reference any device property, so every Instance after the first is named with a digest of its device's id",
+                template
+            )),
+            Ok(_) => {}
+            Err(e) => violations
+                .errors
+                .push(format!("instanceNameTemplate {}", e)),
+        }
+    }
+    if let Some(property_metadata) = &config.property_metadata {
+        for (field, keys) in &[
+            ("labels", &property_metadata.labels),
+            ("annotations", &property_metadata.annotations),
+        ] {
+            let mut properties: Vec<&String> = keys.keys().collect();
+            properties.sort();
+            for property in properties {
+                let key = &keys[property];
+                match to_label_key(key) {
+                    None => violations.errors.push(format!(
+                        "propertyMetadata.{} key \"{}\" for property {} is not a valid key",
+                        field, key, property
+                    )),
+                    Some(sanitized_key) if &sanitized_key != key => {
+                        violations.warnings.push(format!(
+                            "propertyMetadata.{} key \"{}\" for property {} is not a valid key and is set as \"{}\" instead",
+                            field, key, property, sanitized_key
+                        ))
+                    }
+                    Some(sanitized_key) => {
+                        if property_metadata.propagate_to_brokers
+                            && *field == "labels"
+                            && AKRI_BROKER_LABELS.contains(&sanitized_key.as_str())
+                        {
+                            violations.errors.push(format!(
+                                "propertyMetadata.labels key {} for property {} is a label that Akri sets on broker Pods",
+                                key, property
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    violations
+}
+
+/// Checks that at least one container requests the `{{PLACEHOLDER}}` resource in its limits, which is what the
+/// Controller replaces with the Instance's resource name so that the broker is scheduled to a node that can use
+/// the device.
+fn validate_broker_containers(containers: &[Container], field: &str, violations: &mut Violations) {
+    let mut placeholder_limit_found = false;
+    for (index, container) in containers.iter().enumerate() {
+        let (in_limits, in_requests) = references_placeholder(container);
+        if in_requests && !in_limits {
+            violations.errors.push(format!(
+                "{}[{}].resources.requests contains {} but resources.limits does not",
+                field, index, RESOURCE_REQUIREMENTS_KEY
+            ));
+        }
+        if in_limits {
+            placeholder_limit_found = true;
+            let quantity = container
+                .resources
+                .as_ref()
+                .and_then(|resources| resources.limits.as_ref())
+                .and_then(|limits| limits.get(RESOURCE_REQUIREMENTS_KEY))
+                .map(|quantity| quantity.0.as_str())
+                .unwrap_or_default();
+            if quantity != "1" {
+                violations.warnings.push(format!(
+                    "{}[{}].resources.limits[{}] is \"{}\", so each broker uses more than one slot of a device",
+                    field, index, RESOURCE_REQUIREMENTS_KEY, quantity
+                ));
+            }
+        }
+    }
+    if !placeholder_limit_found {
+        violations.errors.push(format!(
+            "{} must request the {} resource in the resources.limits of at least one container",
+            field, RESOURCE_REQUIREMENTS_KEY
+        ));
+    }
+}
+
+/// Returns whether the `{{PLACEHOLDER}}` resource is in a container's limits and requests respectively
+fn references_placeholder(container: &Container) -> (bool, bool) {
+    match &container.resources {
+        Some(resources) => (
+            resources.limits.as_ref().map_or(false, |limits| {
+                limits.contains_key(RESOURCE_REQUIREMENTS_KEY)
+            }),
+            resources.requests.as_ref().map_or(false, |requests| {
+                requests.contains_key(RESOURCE_REQUIREMENTS_KEY)
+            }),
+        ),
+        None => (false, false),
+    }
+}
+
+fn validate_service_selector(service_spec: &ServiceSpec, field: &str, violations: &mut Violations) {
+    if let Some(selector) = &service_spec.selector {
+        for key in selector.keys() {
+            if AKRI_BROKER_LABELS.contains(&key.as_str()) {
+                violations.errors.push(format!(
+                    "{}.selector must not use the label {}, which Akri sets on broker Pods",
+                    field, key
+                ));
+            }
+        }
+    }
+}
+
+/// Returns whether a name is accepted by Kubernetes as the name of a container's environment variable
+fn is_env_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first)
+            if first.is_ascii_alphabetic() || first == '_' || first == '-' || first == '.' =>
+        {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+/// Returns whether a name is a C identifier, which is what shells accept as an environment variable name
+fn is_c_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use akri_shared::akri::configuration::DeviceCredentials;
+
+    fn configuration(spec: &str) -> Configuration {
+        serde_yaml::from_str(spec).expect("Configuration YAML")
+    }
+
+    const BROKER_POD_SPEC: &str = r#"
+        discoveryHandler:
+          name: debugEcho
+          discoveryDetails: "descriptions: [\"foo0\"]"
+        brokerPodSpec:
+          containers:
+          - name: broker
+            image: image
+            resources:
+              limits:
+                "{{PLACEHOLDER}}": "1"
+    "#;
+
+    #[test]
+    fn test_validate_semantics_valid() {
+        let config = configuration(BROKER_POD_SPEC);
+        assert_eq!(validate_semantics(&config), Violations::default());
+
+        // A Configuration without a broker is also valid
+        let config = configuration(
+            r#"
+            discoveryHandler:
+              name: debugEcho
+            brokerProperties:
+              RESOLUTION_WIDTH: "800"
+            "#,
+        );
+        assert_eq!(validate_semantics(&config), Violations::default());
+    }
+
+    #[test]
+    fn test_validate_semantics_capacity() {
+        let mut config = configuration(BROKER_POD_SPEC);
+        config.capacity = 0;
+        assert_eq!(
+            validate_semantics(&config).errors,
+            vec!["capacity must be at least 1 but is 0".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_validate_semantics_placeholder() {
+        let config = configuration(
+            r#"
+            discoveryHandler:
+              name: debugEcho
+            brokerPodSpec:
+              containers:
+              - name: broker
+                image: image
+                resources:
+                  requests:
+                    "{{PLACEHOLDER}}": "1"
+              initContainers:
+              - name: init
+                image: image
+                resources:
+                  limits:
+                    "{{PLACEHOLDER}}": "1"
+            "#,
+        );
+        assert_eq!(
+            validate_semantics(&config).errors,
+            vec![
+                "brokerPodSpec.containers[0].resources.requests contains {{PLACEHOLDER}} but resources.limits does not".to_string(),
+                "brokerPodSpec.containers must request the {{PLACEHOLDER}} resource in the resources.limits of at least one container".to_string(),
+                "brokerPodSpec.initContainers[0] requests the {{PLACEHOLDER}} resource, which is only replaced in containers".to_string(),
+            ]
+        );
+
+        let mut config = configuration(BROKER_POD_SPEC);
+        config.broker_pod_spec.as_mut().unwrap().containers[0]
+            .resources
+            .as_mut()
+            .unwrap()
+            .limits
+            .as_mut()
+            .unwrap()
+            .insert(
+                RESOURCE_REQUIREMENTS_KEY.to_string(),
+                k8s_openapi::apimachinery::pkg::api::resource::Quantity("2".to_string()),
+            );
+        let violations = validate_semantics(&config);
+        assert!(violations.errors.is_empty());
+        assert_eq!(violations.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_validate_semantics_services() {
+        let config = configuration(
+            r#"
+            discoveryHandler:
+              name: debugEcho
+            instanceServiceSpec:
+              ports:
+              - port: 80
+            configurationServiceSpec:
+              ports:
+              - port: 80
+            "#,
+        );
+        assert_eq!(
+            validate_semantics(&config).errors,
+            vec![
+                "instanceServiceSpec must not be set without a brokerPodSpec".to_string(),
+                "configurationServiceSpec must not be set without a brokerPodSpec".to_string(),
+            ]
+        );
+
+        let mut config = configuration(BROKER_POD_SPEC);
+        config.configuration_service_spec = Some(ServiceSpec {
+            selector: Some(
+                vec![
+                    ("tier".to_string(), "frontend".to_string()),
+                    (AKRI_INSTANCE_LABEL_NAME.to_string(), "instance".to_string()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ..Default::default()
+        });
+        assert_eq!(
+            validate_semantics(&config).errors,
+            vec!["configurationServiceSpec.selector must not use the label akri.sh/instance, which Akri sets on broker Pods".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_validate_semantics_broker_properties() {
+        let mut config = configuration(BROKER_POD_SPEC);
+        for (name, value) in &[
+            ("VALID_NAME", "1"),
+            ("dotted.name", "2"),
+            ("1_STARTS_WITH_DIGIT", "3"),
+            ("HAS SPACE", "4"),
+            ("", "5"),
+        ] {
+            config
+                .broker_properties
+                .insert(name.to_string(), value.to_string());
+        }
+        let violations = validate_semantics(&config);
+        assert_eq!(
+            violations.errors,
+            vec![
+                "brokerProperties key \"\" is not a valid environment variable name".to_string(),
+                "brokerProperties key \"1_STARTS_WITH_DIGIT\" is not a valid environment variable name".to_string(),
+                "brokerProperties key \"HAS SPACE\" is not a valid environment variable name".to_string(),
+            ]
+        );
+        assert_eq!(violations.warnings.len(), 1);
+        assert!(violations.warnings[0].contains("dotted.name"));
+    }
+
+    #[test]
+    fn test_validate_semantics_broker_properties_mount_path() {
+        let mut config = configuration(BROKER_POD_SPEC);
+        config.broker_properties_mount_path = Some("/etc/akri/properties".to_string());
+        assert_eq!(validate_semantics(&config), Violations::default());
+
+        for mount_path in &["etc/akri/properties", "/", ""] {
+            config.broker_properties_mount_path = Some(mount_path.to_string());
+            assert_eq!(
+                validate_semantics(&config).errors,
+                vec![format!(
+                    "brokerPropertiesMountPath \"{}\" must be an absolute path other than /",
+                    mount_path
+                )]
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_semantics_device_credentials() {
+        let mut config = configuration(BROKER_POD_SPEC);
+        config.device_credentials = Some(DeviceCredentials {
+            secret_name: "onvif-credentials".to_string(),
+            key_property: Some("ONVIF_DEVICE_MAC_ADDRESS".to_string()),
+            mount_path: Some("/etc/akri/credential".to_string()),
+            env_var: Some("DEVICE_PASSWORD".to_string()),
+        });
+        assert_eq!(validate_semantics(&config), Violations::default());
+
+        config.device_credentials = Some(DeviceCredentials::default());
+        assert_eq!(
+            validate_semantics(&config).errors,
+            vec![
+                "deviceCredentials.secretName must not be empty".to_string(),
+                "deviceCredentials must set mountPath or envVar, or brokers are not given their credentials".to_string(),
+            ]
+        );
+
+        config.device_credentials = Some(DeviceCredentials {
+            secret_name: "onvif-credentials".to_string(),
+            key_property: None,
+            mount_path: Some("/etc/akri/".to_string()),
+            env_var: Some("1_PASSWORD".to_string()),
+        });
+        assert_eq!(
+            validate_semantics(&config).errors,
+            vec![
+                "deviceCredentials.mountPath \"/etc/akri/\" must be the absolute path of a file"
+                    .to_string(),
+                "deviceCredentials.envVar \"1_PASSWORD\" is not a valid environment variable name"
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_semantics_instance_names() {
+        let mut config = configuration(BROKER_POD_SPEC);
+        config.instance_name_template = Some("cam-{{ONVIF_DEVICE_MAC_ADDRESS}}".to_string());
+        assert_eq!(validate_semantics(&config), Violations::default());
+
+        config.instance_digest_length = 0;
+        config.instance_name_template = Some("cam-{{ONVIF_DEVICE_MAC_ADDRESS".to_string());
+        assert_eq!(
+            validate_semantics(&config).errors,
+            vec![
+                "instanceDigestLength must be between 1 and 16 but is 0".to_string(),
+                "instanceNameTemplate \"cam-{{ONVIF_DEVICE_MAC_ADDRESS\" has a {{ that is not closed".to_string(),
+            ]
+        );
+
+        config.instance_digest_length = 4;
+        config.instance_name_template = Some("camera".to_string());
+        let violations = validate_semantics(&config);
+        assert!(violations.errors.is_empty());
+        assert_eq!(violations.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_validate_semantics_property_metadata() {
+        let config = configuration(&format!(
+            "{}{}",
+            BROKER_POD_SPEC,
+            r#"
+        propertyMetadata:
+          labels:
+            MODEL: example.com/model
+            LOCATION: Example.com/Location Scope
+            INSTANCE: akri.sh/instance
+          annotations:
+            SCOPES: "/"
+          propagateToBrokers: true
+            "#
+        ));
+        let violations = validate_semantics(&config);
+        assert_eq!(
+            violations.errors,
+            vec![
+                "propertyMetadata.labels key akri.sh/instance for property INSTANCE is a label that Akri sets on broker Pods".to_string(),
+                "propertyMetadata.annotations key \"/\" for property SCOPES is not a valid key".to_string(),
+            ]
+        );
+        assert_eq!(
+            violations.warnings,
+            vec!["propertyMetadata.labels key \"Example.com/Location Scope\" for property LOCATION is not a valid key and is set as \"example.com/Location-Scope\" instead".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_is_env_var_name() {
+        assert!(is_env_var_name("FOO_BAR"));
+        assert!(is_env_var_name("_foo"));
+        assert!(is_env_var_name("foo-bar.baz"));
+        assert!(!is_env_var_name("0FOO"));
+        assert!(!is_env_var_name("FOO=BAR"));
+        assert!(is_c_identifier("FOO_1"));
+        assert!(!is_c_identifier("foo-bar"));
+    }
+}
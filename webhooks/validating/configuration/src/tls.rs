@@ -0,0 +1,182 @@
+use log::{error, info, trace};
+use openssl::ssl::{SniError, SslAcceptor, SslAcceptorBuilder, SslContext, SslFiletype, SslMethod};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Length of time to wait between checks of whether the TLS certificate and private key have been rotated
+pub const CERTIFICATE_RELOAD_INTERVAL_SECS: u64 = 30;
+
+/// TLS certificate and private key that are served by the Webhook and reloaded when they change on disk, such as
+/// when cert-manager rotates the certificate in the Secret that is mounted into the Webhook's Pod.
+pub struct ReloadableCertificate {
+    key_file: String,
+    crt_file: String,
+    files: RwLock<(Vec<u8>, Vec<u8>)>,
+    context: RwLock<SslContext>,
+}
+
+impl ReloadableCertificate {
+    pub fn new(
+        key_file: &str,
+        crt_file: &str,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let files = read_files(key_file, crt_file)?;
+        let context = build_context(key_file, crt_file)?;
+        Ok(ReloadableCertificate {
+            key_file: key_file.to_string(),
+            crt_file: crt_file.to_string(),
+            files: RwLock::new(files),
+            context: RwLock::new(context),
+        })
+    }
+
+    /// Loads the certificate and private key again if either changed on disk, returning whether they were reloaded.
+    /// The previous certificate keeps being served if the new one cannot be loaded, for example because only one of
+    /// the files has been updated so far.
+    pub fn reload_if_changed(
+        &self,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let files = read_files(&self.key_file, &self.crt_file)?;
+        if *self.files.read().map_err(|e| e.to_string())? == files {
+            return Ok(false);
+        }
+        let context = build_context(&self.key_file, &self.crt_file)?;
+        *self.context.write().map_err(|e| e.to_string())? = context;
+        *self.files.write().map_err(|e| e.to_string())? = files;
+        Ok(true)
+    }
+
+    fn current_context(&self) -> Option<SslContext> {
+        self.context.read().ok().map(|context| context.clone())
+    }
+}
+
+fn read_files(
+    key_file: &str,
+    crt_file: &str,
+) -> Result<(Vec<u8>, Vec<u8>), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    Ok((std::fs::read(key_file)?, std::fs::read(crt_file)?))
+}
+
+fn build_acceptor(
+    key_file: &str,
+    crt_file: &str,
+) -> Result<SslAcceptorBuilder, openssl::error::ErrorStack> {
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+    builder.set_private_key_file(key_file, SslFiletype::PEM)?;
+    builder.set_certificate_chain_file(crt_file)?;
+    builder.check_private_key()?;
+    Ok(builder)
+}
+
+fn build_context(key_file: &str, crt_file: &str) -> Result<SslContext, openssl::error::ErrorStack> {
+    Ok(build_acceptor(key_file, crt_file)?.build().into_context())
+}
+
+/// Creates the TLS acceptor for the Webhook's server. Each connection is switched to the latest certificate when the
+/// client sends the server name (which the Kubernetes API server always does), so rotated certificates are served
+/// without restarting the Webhook.
+pub fn get_builder(
+    certificate: Arc<ReloadableCertificate>,
+) -> Result<SslAcceptorBuilder, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut builder = build_acceptor(&certificate.key_file, &certificate.crt_file)?;
+    builder.set_servername_callback(move |ssl, _alert| match certificate.current_context() {
+        Some(context) => ssl.set_ssl_context(&context).map_err(|e| {
+            error!(
+                "get_builder - could not switch to the latest certificate: {}",
+                e
+            );
+            SniError::ALERT_FATAL
+        }),
+        None => Err(SniError::ALERT_FATAL),
+    });
+    Ok(builder)
+}
+
+/// Periodically reloads the Webhook's certificate and private key if they changed on disk
+pub async fn reload_certificate_periodically(certificate: Arc<ReloadableCertificate>) {
+    loop {
+        actix_rt::time::delay_for(Duration::from_secs(CERTIFICATE_RELOAD_INTERVAL_SECS)).await;
+        match certificate.reload_if_changed() {
+            Ok(true) => info!(
+                "reload_certificate_periodically - reloaded certificate {}",
+                certificate.crt_file
+            ),
+            Ok(false) => trace!("reload_certificate_periodically - certificate unchanged"),
+            Err(e) => error!(
+                "reload_certificate_periodically - could not reload certificate {}, still serving the previous one: {}",
+                certificate.crt_file, e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::{
+        asn1::Asn1Time, bn::BigNum, hash::MessageDigest, pkey::PKey, rsa::Rsa,
+        x509::X509NameBuilder, x509::X509,
+    };
+
+    /// Writes a new self-signed certificate and its private key to the given files
+    fn write_self_signed_certificate(
+        key_file: &std::path::Path,
+        crt_file: &std::path::Path,
+        serial: u32,
+    ) {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "akri-webhook-configuration.default.svc")
+            .unwrap();
+        let name = name.build();
+        let mut crt = X509::builder().unwrap();
+        crt.set_version(2).unwrap();
+        crt.set_serial_number(&BigNum::from_u32(serial).unwrap().to_asn1_integer().unwrap())
+            .unwrap();
+        crt.set_subject_name(&name).unwrap();
+        crt.set_issuer_name(&name).unwrap();
+        crt.set_pubkey(&key).unwrap();
+        crt.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        crt.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        crt.sign(&key, MessageDigest::sha256()).unwrap();
+        std::fs::write(key_file, key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+        std::fs::write(crt_file, crt.build().to_pem().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_reload_if_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_file = dir.path().join("tls.key");
+        let crt_file = dir.path().join("tls.crt");
+        write_self_signed_certificate(&key_file, &crt_file, 1);
+        let certificate = Arc::new(
+            ReloadableCertificate::new(key_file.to_str().unwrap(), crt_file.to_str().unwrap())
+                .unwrap(),
+        );
+        assert!(get_builder(certificate.clone()).is_ok());
+        assert!(!certificate.reload_if_changed().unwrap());
+
+        // Rotated certificate is loaded
+        write_self_signed_certificate(&key_file, &crt_file, 2);
+        assert!(certificate.reload_if_changed().unwrap());
+        assert!(!certificate.reload_if_changed().unwrap());
+
+        // Previous certificate is kept when the new key does not match the new certificate
+        let other_key_file = dir.path().join("other.key");
+        let other_crt_file = dir.path().join("other.crt");
+        write_self_signed_certificate(&other_key_file, &other_crt_file, 3);
+        std::fs::copy(&other_key_file, &key_file).unwrap();
+        assert!(certificate.reload_if_changed().is_err());
+        assert!(certificate.current_context().is_some());
+    }
+
+    #[test]
+    fn test_new_missing_files() {
+        assert!(
+            ReloadableCertificate::new("/nonexistent/tls.key", "/nonexistent/tls.crt").is_err()
+        );
+    }
+}